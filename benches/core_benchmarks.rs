@@ -11,6 +11,72 @@ fn bench_id_generation(c: &mut Criterion) {
             black_box(TraceId::new());
         })
     });
+
+    // 对比批量生成与逐个生成1000个ID
+    let mut group = c.benchmark_group("generate_1000");
+    group.bench_function("TraceId::new", |b| {
+        b.iter(|| {
+            let ids: Vec<TraceId> = (0..1000).map(|_| TraceId::new()).collect();
+            black_box(ids);
+        })
+    });
+    group.bench_function("TraceId::new_batch", |b| {
+        b.iter(|| {
+            black_box(TraceId::new_batch(black_box(1000)));
+        })
+    });
+    group.finish();
+}
+
+/// 基准测试：生成字符串形式的ID（对比写入调用方缓冲区与分配 `String`）
+fn bench_hex_generation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("generate_hex");
+    group.bench_function("TraceId::new", |b| {
+        b.iter(|| {
+            black_box(TraceId::new());
+        })
+    });
+    group.bench_function("TraceId::new().to_string()", |b| {
+        b.iter(|| {
+            black_box(TraceId::new().to_string());
+        })
+    });
+    group.bench_function("TraceId::generate_into", |b| {
+        let mut buf = [0u8; 32];
+        b.iter(|| {
+            black_box(TraceId::generate_into(black_box(&mut buf)).len());
+        })
+    });
+    group.finish();
+}
+
+/// 基准测试：多线程并发生成（每次迭代每个线程生成1000个ID）
+fn bench_concurrent_generation(c: &mut Criterion) {
+    const PER_THREAD: usize = 1000;
+
+    let mut group = c.benchmark_group("concurrent_new_1000");
+    for threads in [1, 4, 8, 16] {
+        group.bench_function(format!("{threads}_threads"), |b| {
+            b.iter_custom(|iters| {
+                let barrier = std::sync::Barrier::new(threads);
+                let start = std::time::Instant::now();
+                std::thread::scope(|scope| {
+                    for _ in 0..threads {
+                        scope.spawn(|| {
+                            barrier.wait();
+                            for _ in 0..iters {
+                                for _ in 0..PER_THREAD {
+                                    black_box(TraceId::new());
+                                }
+                            }
+                        });
+                    }
+                });
+                start.elapsed()
+            })
+        });
+    }
+    group.finish();
 }
 
 /// 基准测试：ID 验证
@@ -54,8 +120,115 @@ fn bench_id_validation(c: &mut Criterion) {
     group.finish();
 }
 
+/// 基准测试：复制追踪ID（对比 `Arc<str>` 与 `String` 表示的克隆开销）
+///
+/// `TraceId` 是 `Copy` 的定长值，复制即49字节的内存拷贝；`Arc<str>` 的克隆是一次原子引用计数递增
+/// （多线程共享时还会争用同一缓存行），`String` 的克隆需要堆分配
+fn bench_clone(c: &mut Criterion) {
+    let trace_id = TraceId::new();
+    let shared: std::sync::Arc<str> = std::sync::Arc::from(trace_id.as_str());
+    let owned = trace_id.to_string();
+
+    // 每次迭代克隆1000份并保留到迭代结束，模拟请求路径上多处持有追踪ID
+    let mut group = c.benchmark_group("clone_1000");
+    group.bench_function("TraceId (Copy)", |b| {
+        b.iter(|| {
+            let copies: Vec<TraceId> = (0..1000).map(|_| *black_box(&trace_id)).collect();
+            black_box(copies)
+        })
+    });
+    group.bench_function("Arc<str>", |b| {
+        b.iter(|| {
+            let copies: Vec<std::sync::Arc<str>> = (0..1000)
+                .map(|_| std::sync::Arc::clone(black_box(&shared)))
+                .collect();
+            black_box(copies)
+        })
+    });
+    group.bench_function("String", |b| {
+        b.iter(|| {
+            let copies: Vec<String> = (0..1000).map(|_| black_box(&owned).clone()).collect();
+            black_box(copies)
+        })
+    });
+    group.finish();
+}
+
+/// 基准测试：转换为HTTP头部值
+#[cfg(feature = "http")]
+fn bench_header_value(c: &mut Criterion) {
+    let trace_id = TraceId::new();
+
+    let mut group = c.benchmark_group("HeaderValue");
+    group.bench_function("as_str().parse()", |b| {
+        b.iter(|| black_box(black_box(&trace_id).as_str().parse::<http::HeaderValue>()))
+    });
+    group.bench_function("to_header_value()", |b| {
+        b.iter(|| black_box(black_box(&trace_id).to_header_value()))
+    });
+    group.finish();
+}
+
+/// 未启用 `http` 特性时跳过
+#[cfg(not(feature = "http"))]
+fn bench_header_value(_c: &mut Criterion) {}
+
+/// 基准测试：经过 `TraceIdLayer` 的单个请求（对比是否写入请求扩展）
+#[cfg(feature = "tower")]
+fn bench_request_path(c: &mut Criterion) {
+    use http::{Request, Response};
+    use std::convert::Infallible;
+    use tower::{service_fn, Layer, ServiceExt};
+    use trace_id::{TraceIdConfig, TraceIdLayer, TRACE_ID_HEADER};
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+
+    let mut group = c.benchmark_group("request_path");
+    for enable_extensions in [true, false] {
+        let config = TraceIdConfig::builder()
+            .enable_span(false)
+            .enable_extensions(enable_extensions)
+            .build();
+        let service =
+            TraceIdLayer::with_config(config).layer(service_fn(|_req: Request<()>| async {
+                Ok::<_, Infallible>(Response::new(()))
+            }));
+
+        let name = if enable_extensions {
+            "extensions"
+        } else {
+            "no_extensions"
+        };
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let request = Request::builder()
+                    .header(TRACE_ID_HEADER, "0af7651916cd43dd8448eb211c80319c")
+                    .body(())
+                    .unwrap();
+                black_box(runtime.block_on(service.clone().oneshot(request)))
+            })
+        });
+    }
+    group.finish();
+}
+
+/// 未启用 `tower` 特性时跳过
+#[cfg(not(feature = "tower"))]
+fn bench_request_path(_c: &mut Criterion) {}
+
 // 注册基准测试组
-criterion_group!(benches, bench_id_generation, bench_id_validation);
+criterion_group!(
+    benches,
+    bench_id_generation,
+    bench_hex_generation,
+    bench_concurrent_generation,
+    bench_id_validation,
+    bench_clone,
+    bench_header_value,
+    bench_request_path
+);
 
 // 运行基准测试
 criterion_main!(benches);
@@ -0,0 +1,52 @@
+//! 字节/十六进制/字符串转换的模糊测试
+//!
+//! 运行：`cargo +nightly fuzz run conversions`
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use trace_id::TraceId;
+
+fuzz_target!(|data: &[u8]| {
+    // 任意输入作为字符串：所有校验入口的结论一致，通过校验的输入原样往返
+    if let Ok(input) = std::str::from_utf8(data) {
+        let validated = TraceId::from_string_validated(input);
+        assert_eq!(input.parse::<TraceId>().ok(), validated);
+        assert_eq!(TraceId::is_valid(input), validated.is_some());
+
+        if let Some(trace_id) = validated {
+            assert_eq!(trace_id.as_str(), input);
+            assert_eq!(TraceId::from_bytes(*trace_id.as_bytes()).as_str(), input);
+            assert_eq!(TraceId::from_u128(trace_id.to_u128()), Some(trace_id));
+            assert_eq!(
+                TraceId::from_string_validated_ci(&input.to_ascii_uppercase()),
+                Some(trace_id)
+            );
+        }
+
+        if let Some(short) = TraceId::from_string_validated_short(input) {
+            assert_eq!(short.as_str(), input);
+        }
+    }
+
+    // 任意字节作为头部值：与字符串解析结论一致
+    assert_eq!(
+        TraceId::is_valid_bytes(data),
+        std::str::from_utf8(data).is_ok_and(TraceId::is_valid)
+    );
+    if let Ok(value) = http::HeaderValue::from_bytes(data) {
+        let from_header = TraceId::from_header_value(&value);
+        assert_eq!(from_header.is_some(), TraceId::is_valid_bytes(data));
+    }
+
+    // 任意16字节：字节与整数形式往返
+    if let Ok(bytes) = <[u8; 16]>::try_from(data.get(..16).unwrap_or_default()) {
+        let trace_id = TraceId::from_bytes(bytes);
+        assert_eq!(trace_id.as_bytes(), &bytes);
+        assert_eq!(trace_id.to_u128(), u128::from_be_bytes(bytes));
+        assert_eq!(
+            TraceId::from_string_validated(trace_id.as_str()),
+            TraceId::from_u128(trace_id.to_u128())
+        );
+    }
+});
@@ -0,0 +1,40 @@
+//! 时钟抽象
+//!
+//! `TraceId::new()` 使用系统时钟生成48位毫秒时间戳，
+//! 通过 `Clock` trait 可以注入固定或可控的时钟，用于确定性测试或模拟时钟偏移。
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 毫秒级时钟源
+pub trait Clock {
+    /// 返回自 Unix 纪元以来的毫秒数
+    ///
+    /// 仅低48位会写入追踪ID
+    fn now_millis(&self) -> u64;
+}
+
+/// 系统时钟（默认时钟源）
+///
+/// 基于 `SystemTime::now()`，系统时间早于 Unix 纪元时返回0
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    #[inline]
+    fn now_millis(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64
+    }
+}
+
+impl<F> Clock for F
+where
+    F: Fn() -> u64,
+{
+    #[inline]
+    fn now_millis(&self) -> u64 {
+        self()
+    }
+}
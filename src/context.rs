@@ -1,34 +1,136 @@
 //! 追踪ID上下文管理
 //!
 //! 使用 `tokio::task_local` 提供与Web框架无关的追踪ID上下文管理。
+//! 启用 `runtime-agnostic` 特性后改用不依赖 tokio 类型的 `thread_local` 后端，
+//! 适用于 async-std、smol 等运行时，两种后端的传递语义见 `store` 模块。
+//! `spawn_traced`、`spawn_traced_blocking`（`spawn_blocking_traced`）始终使用 tokio 运行时创建任务。
+//!
+//! 对于没有tokio运行时的同步代码（`std::thread`、`rayon` 工作线程等），
+//! 提供基于 `thread_local` 的 `with_trace_id_sync`。两者同时存在时，
+//! 异步任务上下文优先：`get_trace_id()` 先查询 task_local，未设置时再查询 thread_local。
+//! 注意 thread_local 绑定的是线程而不是任务，不应在同步作用域内 `.await`
+//! 或跨线程转移工作，否则其他任务可能观察到该追踪ID。
 
+use crate::correlation::CorrelationFields;
+use crate::sampler::TRACE_FLAG_SAMPLED;
+use crate::trace_context::{SpanId, TraceContext, TraceState};
 use crate::trace_id::TraceId;
-use tokio::task_local;
+use std::cell::Cell;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU8, Ordering};
+use tokio::task::JoinHandle;
+
+mod store;
+
+use store::{context_local, ContextKey};
 
-// 使用tokio的task_local来存储当前请求的trace_id
-task_local! {
+// 当前请求的trace_id（默认存储在tokio的task_local中，见 `store` 模块）
+context_local! {
     static CURRENT_TRACE_ID: TraceId;
 }
 
+// 存储从上游 `traceparent` 解析出的完整上下文（可选）
+context_local! {
+    static CURRENT_TRACE_CONTEXT: TraceContext;
+}
+
+// 存储从上游 `tracestate` 解析出的厂商键值对（可选）
+context_local! {
+    static CURRENT_TRACE_STATE: TraceState;
+}
+
+// 存储本服务的采样决策对应的 trace-flags（可选）
+context_local! {
+    static CURRENT_TRACE_FLAGS: u8;
+}
+
+// 本服务为当前请求生成的跨度ID（可选）
+context_local! {
+    static CURRENT_SPAN_ID: SpanId;
+}
+
+// 与追踪ID一起传递的关联字段（可选）
+context_local! {
+    static CURRENT_CORRELATION: CorrelationFields;
+}
+
+// 同步代码路径使用的线程局部追踪ID（由 `with_trace_id_sync` 设置）
+thread_local! {
+    static SYNC_TRACE_ID: Cell<Option<TraceId>> = const { Cell::new(None) };
+}
+
+// 不在追踪上下文中时的日志级别（0表示静默），默认为WARN
+static MISSING_CONTEXT_LOG_LEVEL: AtomicU8 = AtomicU8::new(level_to_u8(Some(tracing::Level::WARN)));
+
+/// 设置 `get_trace_id()` 在追踪上下文之外被调用时的日志级别
+///
+/// 默认为 `WARN`，以便Web服务尽早发现遗漏的上下文；
+/// 对于本就运行在请求作用域之外的批处理任务，可以降低级别或传入None静默生成。
+///
+/// # 参数
+/// * `level` - 日志级别，None表示不记录日志
+pub fn set_missing_context_log_level(level: Option<tracing::Level>) {
+    MISSING_CONTEXT_LOG_LEVEL.store(level_to_u8(level), Ordering::Relaxed);
+}
+
+const fn level_to_u8(level: Option<tracing::Level>) -> u8 {
+    match level {
+        None => 0,
+        Some(tracing::Level::ERROR) => 1,
+        Some(tracing::Level::WARN) => 2,
+        Some(tracing::Level::INFO) => 3,
+        Some(tracing::Level::DEBUG) => 4,
+        Some(tracing::Level::TRACE) => 5,
+    }
+}
+
 /// 获取当前追踪ID
 ///
 /// 从当前异步任务的上下文中获取trace_id。
-/// 如果当前不在追踪上下文中，则记录一个警告并生成一个新的trace_id。
+/// 如果当前不在追踪上下文中，则记录日志（级别由 `set_missing_context_log_level` 控制，默认为警告）
+/// 并生成一个新的trace_id。
+/// 需要区分"不在追踪上下文中"的场景请使用 `get_trace_id_opt()`。
 ///
 /// # 返回
 /// 当前请求的追踪ID
 pub fn get_trace_id() -> TraceId {
+    get_trace_id_opt().unwrap_or_else(|| {
+        // 如果不在追踪上下文中，按配置的级别记录日志并生成新的trace_id
+        const MESSAGE: &str = "TraceId not found in task-local context. Generating a new one. This might indicate a logic error where a function is called outside of a traced request scope.";
+        match MISSING_CONTEXT_LOG_LEVEL.load(Ordering::Relaxed) {
+            1 => tracing::error!("{MESSAGE}"),
+            2 => tracing::warn!("{MESSAGE}"),
+            3 => tracing::info!("{MESSAGE}"),
+            4 => tracing::debug!("{MESSAGE}"),
+            5 => tracing::trace!("{MESSAGE}"),
+            _ => {}
+        }
+        TraceId::new()
+    })
+}
+
+/// 获取当前追踪ID（不生成回退ID）
+///
+/// 与 `get_trace_id()` 不同，不在追踪上下文中时直接返回None，
+/// 既不记录日志也不生成新的追踪ID，便于库代码自行决定如何处理。
+/// 异步任务上下文优先，其次是 `with_trace_id_sync` 设置的线程局部上下文。
+///
+/// # 返回
+/// 当前请求的追踪ID，不在追踪上下文中时返回None
+pub fn get_trace_id_opt() -> Option<TraceId> {
     CURRENT_TRACE_ID
-        .try_with(|trace_id| trace_id.clone())
-        .unwrap_or_else(|_| {
-            // 如果不在追踪上下文中，记录警告并生成新的trace_id
-            tracing::warn!("TraceId not found in task-local context. Generating a new one. This might indicate a logic error where a function is called outside of a traced request scope.");
-            TraceId::new()
-        })
+        .read(|trace_id| *trace_id)
+        .or_else(|| SYNC_TRACE_ID.with(Cell::get))
 }
 
 /// 在指定的追踪上下文中执行异步操作
 ///
+/// `TraceId` 是 `Copy` 的定长值（16字节原始数据、32字节十六进制缓存与位宽标记，无堆分配），
+/// 按值传入与 `get_trace_id()` 按值返回都只是一次内存拷贝，
+/// 在多层中间件中反复以同一ID进入作用域无需 `Arc` 或借用版本。
+///
 /// # 参数
 /// * `trace_id` - 要设置的追踪ID
 /// * `future` - 要执行的异步操作
@@ -39,7 +141,491 @@ pub async fn with_trace_id<F, T>(trace_id: TraceId, future: F) -> T
 where
     F: std::future::Future<Output = T>,
 {
-    CURRENT_TRACE_ID.scope(trace_id, future).await
+    // 登记守卫随 future 一起丢弃，提前取消或 panic 时同样会移除
+    #[cfg(feature = "registry")]
+    let _registration = crate::registry::register(trace_id);
+
+    CURRENT_TRACE_ID.scoped(trace_id, future).await
+}
+
+/// 在指定的追踪上下文中执行同步闭包
+///
+/// 适用于没有tokio运行时的代码路径（`std::thread`、`rayon` 工作线程等）。
+/// 追踪ID保存在线程局部存储中，闭包返回（或panic）后恢复之前的值，支持嵌套。
+/// 如果当前同时处于异步 `with_trace_id` 作用域内，`get_trace_id()` 仍返回异步上下文中的ID。
+///
+/// # 参数
+/// * `trace_id` - 要设置的追踪ID
+/// * `f` - 要执行的同步闭包
+///
+/// # 返回
+/// 闭包的返回值
+pub fn with_trace_id_sync<F, T>(trace_id: TraceId, f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    // 作用域结束时恢复之前的值，即使闭包panic也能正确恢复
+    let _guard = enter_trace_id(trace_id);
+    f()
+}
+
+/// 以守卫的形式进入追踪上下文，适用于不便改写为闭包或 future 的命令式代码
+///
+/// 与 `tracing` 的 `Span::enter` 类似：持有返回的守卫期间 `get_trace_id()` 返回该ID，
+/// 守卫丢弃时恢复进入之前的值。与 `with_trace_id_sync` 相同，追踪ID保存在线程局部存储中：
+/// - 守卫绑定当前线程，因此没有实现 `Send`；嵌套进入时应按相反顺序丢弃
+/// - 处于异步 `with_trace_id` 作用域内时，`get_trace_id()` 仍优先返回 task_local 中的ID，
+///   守卫无法覆盖中间件设置的上下文
+/// - 不要在持有守卫时 `.await`：任务挂起后同一线程上的其他任务会观察到该追踪ID，
+///   恢复时也可能已被调度到其他线程。异步代码请使用 `with_trace_id`
+///
+/// # 参数
+/// * `trace_id` - 要设置的追踪ID
+///
+/// # 返回
+/// 丢弃时恢复之前追踪ID的守卫
+///
+/// # 示例
+/// ```
+/// use trace_id::{enter_trace_id, get_trace_id_opt, TraceId};
+///
+/// let trace_id = TraceId::new();
+/// {
+///     let _guard = enter_trace_id(trace_id);
+///     assert_eq!(get_trace_id_opt(), Some(trace_id));
+/// }
+/// assert_eq!(get_trace_id_opt(), None);
+/// ```
+pub fn enter_trace_id(trace_id: TraceId) -> TraceIdGuard {
+    #[cfg(feature = "registry")]
+    let registration = crate::registry::register(trace_id);
+
+    let previous = SYNC_TRACE_ID.with(|current| current.replace(Some(trace_id)));
+    TraceIdGuard {
+        previous,
+        #[cfg(feature = "registry")]
+        _registration: registration,
+        _not_send: PhantomData,
+    }
+}
+
+/// `enter_trace_id` 返回的守卫，丢弃时恢复进入之前的追踪ID
+#[must_use = "dropping the guard immediately leaves the trace context"]
+pub struct TraceIdGuard {
+    previous: Option<TraceId>,
+    #[cfg(feature = "registry")]
+    _registration: crate::registry::Registration,
+    /// 线程局部上下文只能在进入时的线程上恢复
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for TraceIdGuard {
+    fn drop(&mut self) {
+        SYNC_TRACE_ID.with(|current| current.set(self.previous));
+    }
+}
+
+/// 在新的tokio任务中执行异步操作，并继承当前追踪ID
+///
+/// `task_local` 上下文不会自动传递到 `tokio::spawn` 创建的任务中，
+/// 该函数捕获当前追踪ID并在新任务中通过 `with_trace_id` 重新建立上下文，
+/// 存在关联字段时一并传递。不在追踪上下文中时等同于 `tokio::spawn`。
+///
+/// # 参数
+/// * `future` - 要执行的异步操作
+///
+/// # 返回
+/// 与 `tokio::spawn` 相同的 `JoinHandle`
+pub fn spawn_traced<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    tokio::spawn(scope_captured(
+        get_trace_id_opt(),
+        get_correlation(),
+        future,
+    ))
+}
+
+/// 绑定长连接（WebSocket 等）的处理函数，使整个连接期间都处于升级请求的追踪上下文中
+///
+/// 请求级中间件只为升级请求本身设置上下文，而 axum 的 `WebSocketUpgrade::on_upgrade`
+/// 等会在新任务中执行连接处理函数，`task_local` 上下文不会自动传递。
+/// 该函数在调用时（升级请求的处理器内）捕获当前追踪ID与关联字段，
+/// 返回的回调在连接建立后通过 `with_trace_id` 执行 `callback` 返回的 future，
+/// 连接上处理的每条消息中 `get_trace_id()` 都返回同一个ID。
+/// 不在追踪上下文中时回调行为与 `callback` 相同。
+///
+/// # 参数
+/// * `callback` - 接收连接（如 `WebSocket`）并返回连接处理 future 的函数
+///
+/// # 返回
+/// 可直接传给 `on_upgrade` 的回调
+///
+/// # 示例
+/// ```ignore
+/// use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+/// use axum::response::Response;
+/// use trace_id::{bind_trace_id_for_connection, get_trace_id};
+///
+/// async fn ws_handler(ws: WebSocketUpgrade) -> Response {
+///     ws.on_upgrade(bind_trace_id_for_connection(handle_socket))
+/// }
+///
+/// async fn handle_socket(mut socket: WebSocket) {
+///     while let Some(Ok(message)) = socket.recv().await {
+///         // 每条消息都使用升级请求的追踪ID
+///         tracing::info!(trace_id = %get_trace_id(), "received {message:?}");
+///         if socket.send(message).await.is_err() {
+///             break;
+///         }
+///     }
+/// }
+/// ```
+pub fn bind_trace_id_for_connection<C, T, F>(
+    callback: C,
+) -> impl FnOnce(T) -> Pin<Box<dyn Future<Output = F::Output> + Send>>
+where
+    C: FnOnce(T) -> F,
+    F: Future + Send + 'static,
+{
+    let trace_id = get_trace_id_opt();
+    let correlation = get_correlation();
+    move |connection| Box::pin(scope_captured(trace_id, correlation, callback(connection)))
+}
+
+/// 在捕获的追踪ID与关联字段中执行异步操作，未捕获到的部分不设置
+async fn scope_captured<F>(
+    trace_id: Option<TraceId>,
+    correlation: Option<CorrelationFields>,
+    future: F,
+) -> F::Output
+where
+    F: Future,
+{
+    let future = async move {
+        match correlation {
+            Some(fields) => with_correlation(fields, future).await,
+            None => future.await,
+        }
+    };
+
+    match trace_id {
+        Some(trace_id) => with_trace_id(trace_id, future).await,
+        None => future.await,
+    }
+}
+
+/// 在阻塞线程池中执行同步闭包，并继承当前追踪ID
+///
+/// `tokio::task::spawn_blocking` 的替代：`task_local` 上下文不会传递到阻塞线程，
+/// 该函数捕获当前追踪ID，闭包通过 `with_trace_id_sync` 在线程局部上下文中执行，
+/// 其中的 `get_trace_id()`（如数据库调用的日志）返回调用方的追踪ID；
+/// 与 `spawn_traced` 相同，存在关联字段时一并传递（`get_correlation()` 在闭包中可用）。
+/// 闭包结束后阻塞线程恢复原来的上下文，复用该线程的其他任务不会读到残留的ID。
+/// 不在追踪上下文中时等同于 `tokio::task::spawn_blocking`。
+///
+/// # 参数
+/// * `f` - 要执行的同步闭包
+///
+/// # 返回
+/// 与 `tokio::task::spawn_blocking` 相同的 `JoinHandle`
+pub fn spawn_traced_blocking<F, T>(f: F) -> JoinHandle<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let trace_id = get_trace_id_opt();
+    let correlation = get_correlation();
+    tokio::task::spawn_blocking(move || scope_captured_sync(trace_id, correlation, f))
+}
+
+/// `spawn_traced_blocking` 的别名，命名与 `tokio::task::spawn_blocking` 对应
+///
+/// # 参数
+/// * `f` - 要执行的同步闭包
+///
+/// # 返回
+/// 与 `tokio::task::spawn_blocking` 相同的 `JoinHandle`
+///
+/// # 示例
+/// ```
+/// use trace_id::{get_trace_id, spawn_blocking_traced, with_trace_id, TraceId};
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let trace_id = TraceId::new();
+/// let inner = with_trace_id(trace_id, async {
+///     spawn_blocking_traced(get_trace_id).await.unwrap()
+/// })
+/// .await;
+/// assert_eq!(inner, trace_id);
+/// # }
+/// ```
+#[inline]
+pub fn spawn_blocking_traced<F, R>(f: F) -> JoinHandle<R>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    spawn_traced_blocking(f)
+}
+
+/// `scope_captured` 的同步版本：追踪ID进入线程局部上下文，关联字段在闭包执行期间设置
+fn scope_captured_sync<R>(
+    trace_id: Option<TraceId>,
+    correlation: Option<CorrelationFields>,
+    f: impl FnOnce() -> R,
+) -> R {
+    let f = move || match correlation {
+        Some(fields) => CURRENT_CORRELATION.scoped_sync(fields, f),
+        None => f(),
+    };
+
+    match trace_id {
+        Some(trace_id) => with_trace_id_sync(trace_id, f),
+        None => f(),
+    }
+}
+
+/// 获取当前的 W3C TraceContext
+///
+/// 仅当当前异步任务处于 `with_trace_context` 作用域内时返回上下文，
+/// 否则返回None（不会生成新的上下文）。
+///
+/// # 返回
+/// 当前请求的上游追踪上下文
+pub fn get_trace_context() -> Option<TraceContext> {
+    CURRENT_TRACE_CONTEXT.read(|ctx| ctx.clone())
+}
+
+/// 在指定的 W3C TraceContext 中执行异步操作
+///
+/// 同时设置追踪ID上下文，作用域内 `get_trace_id()` 返回 `ctx.trace_id`。
+///
+/// # 参数
+/// * `ctx` - 要设置的追踪上下文
+/// * `future` - 要执行的异步操作
+///
+/// # 返回
+/// 异步操作的结果
+pub async fn with_trace_context<F, T>(ctx: TraceContext, future: F) -> T
+where
+    F: std::future::Future<Output = T>,
+{
+    let trace_id = ctx.trace_id;
+    CURRENT_TRACE_CONTEXT
+        .scoped(ctx, with_trace_id(trace_id, future))
+        .await
+}
+
+/// 获取当前的 W3C `tracestate`
+///
+/// 仅当当前异步任务处于 `with_trace_state` 作用域内时返回，否则返回None。
+/// 下游中间件可以在克隆后追加自己的成员，再通过 `with_trace_state` 传递下去。
+///
+/// # 返回
+/// 当前请求的厂商键值对列表
+pub fn get_trace_state() -> Option<TraceState> {
+    CURRENT_TRACE_STATE.read(|state| state.clone())
+}
+
+/// 在指定的 W3C `tracestate` 中执行异步操作
+///
+/// # 参数
+/// * `state` - 要设置的厂商键值对列表
+/// * `future` - 要执行的异步操作
+///
+/// # 返回
+/// 异步操作的结果
+pub async fn with_trace_state<F, T>(state: TraceState, future: F) -> T
+where
+    F: std::future::Future<Output = T>,
+{
+    CURRENT_TRACE_STATE.scoped(state, future).await
+}
+
+/// 获取当前的 trace-flags
+///
+/// 优先返回 `with_trace_flags` 设置的值（例如中间件的采样决策），
+/// 其次返回上游 `traceparent` 中的 trace-flags；均不存在时返回None。
+/// 向下游传播时应使用该值，使上下游的采样决策保持一致。
+///
+/// # 返回
+/// 当前请求的 trace-flags
+pub fn get_trace_flags() -> Option<u8> {
+    CURRENT_TRACE_FLAGS
+        .read(|flags| *flags)
+        .or_else(|| CURRENT_TRACE_CONTEXT.read(|ctx| ctx.flags))
+}
+
+/// 在指定的 trace-flags 中执行异步操作
+///
+/// # 参数
+/// * `flags` - 要设置的 trace-flags（如 `TRACE_FLAG_SAMPLED`）
+/// * `future` - 要执行的异步操作
+///
+/// # 返回
+/// 异步操作的结果
+pub async fn with_trace_flags<F, T>(flags: u8, future: F) -> T
+where
+    F: std::future::Future<Output = T>,
+{
+    CURRENT_TRACE_FLAGS.scoped(flags, future).await
+}
+
+/// 当前请求是否已采样
+///
+/// 按 `get_trace_flags()` 的 sampled 位判断。不存在 trace-flags（未配置采样器且没有上游
+/// `traceparent`）时视为已采样，与中间件写入 `traceparent` 时的默认值一致
+///
+/// # 返回
+/// 下游是否应记录本次请求
+pub fn is_sampled() -> bool {
+    get_trace_flags().is_none_or(|flags| flags & TRACE_FLAG_SAMPLED != 0)
+}
+
+/// 组合指定追踪ID向下游传播的 W3C 上下文
+///
+/// parent-id 为本服务的跨度ID（不存在时生成新的跨度ID），
+/// trace-flags 取 `get_trace_flags()`，不存在时视为已采样
+#[cfg_attr(not(feature = "http"), allow(dead_code))]
+pub(crate) fn outbound_trace_context(trace_id: TraceId) -> TraceContext {
+    TraceContext {
+        trace_id,
+        parent_id: get_span_id().unwrap_or_default(),
+        flags: get_trace_flags().unwrap_or(TRACE_FLAG_SAMPLED),
+    }
+}
+
+/// 获取当前的跨度ID
+///
+/// 中间件为每个请求生成的跨度ID，与 `get_trace_id()` 一起即可在日志中还原调用树；
+/// 上游 `traceparent` 中的 parent-id 请通过 `get_trace_context()` 获取。
+/// 每一跳沿用入站的追踪ID并生成新的跨度ID，出站请求（`inject_traceparent()`、
+/// `TraceIdMiddleware::with_traceparent()`）以它作为下游的 parent-id。
+/// 仅当当前异步任务处于 `with_span_id` 作用域内时返回，否则返回None。
+///
+/// # 返回
+/// 当前请求的跨度ID
+pub fn get_span_id() -> Option<SpanId> {
+    CURRENT_SPAN_ID.read(|span_id| span_id.clone())
+}
+
+/// 在指定的跨度ID中执行异步操作
+///
+/// # 参数
+/// * `span_id` - 要设置的跨度ID
+/// * `future` - 要执行的异步操作
+///
+/// # 返回
+/// 异步操作的结果
+pub async fn with_span_id<F, T>(span_id: SpanId, future: F) -> T
+where
+    F: std::future::Future<Output = T>,
+{
+    CURRENT_SPAN_ID.scoped(span_id, future).await
+}
+
+/// 获取当前的关联字段
+///
+/// 仅当当前异步任务处于 `with_correlation` 作用域内时返回（克隆整个集合），否则返回None。
+/// 只需要单个字段时请使用 `get_correlation_field()`。
+///
+/// # 返回
+/// 当前请求的关联字段
+pub fn get_correlation() -> Option<CorrelationFields> {
+    CURRENT_CORRELATION.read(|fields| fields.clone())
+}
+
+/// 获取当前关联字段中的单个值
+///
+/// # 参数
+/// * `key` - 字段名
+///
+/// # 返回
+/// 处于 `with_correlation` 作用域内且字段存在时返回Some(值)，否则返回None
+pub fn get_correlation_field(key: &str) -> Option<String> {
+    CURRENT_CORRELATION
+        .read(|fields| fields.get(key).map(str::to_owned))
+        .flatten()
+}
+
+/// 在指定的关联字段中执行异步操作
+///
+/// 与追踪ID相互独立，通常嵌套在 `with_trace_id` 之内使用；
+/// 嵌套调用时内层完全替换外层的字段集合，需要追加时先 `get_correlation()` 再插入。
+///
+/// # 参数
+/// * `fields` - 要设置的关联字段
+/// * `future` - 要执行的异步操作
+///
+/// # 返回
+/// 异步操作的结果
+pub async fn with_correlation<F, T>(fields: CorrelationFields, future: F) -> T
+where
+    F: std::future::Future<Output = T>,
+{
+    CURRENT_CORRELATION.scoped(fields, future).await
+}
+
+/// 当前异步任务中追踪上下文的快照
+///
+/// 用于在请求 future 之外（例如响应体的 `poll_frame`）重新进入同一上下文：
+/// `capture` 在作用域内读取各 task_local，`enter` 在同步闭包执行期间逐一恢复
+#[cfg(feature = "tower")]
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ContextSnapshot {
+    trace_id: Option<TraceId>,
+    trace_context: Option<TraceContext>,
+    trace_state: Option<TraceState>,
+    trace_flags: Option<u8>,
+    span_id: Option<SpanId>,
+    correlation: Option<CorrelationFields>,
+}
+
+#[cfg(feature = "tower")]
+impl ContextSnapshot {
+    /// 捕获当前异步任务的追踪上下文，未设置的部分为None
+    pub(crate) fn capture() -> Self {
+        Self {
+            trace_id: CURRENT_TRACE_ID.read(|trace_id| *trace_id),
+            trace_context: get_trace_context(),
+            trace_state: get_trace_state(),
+            trace_flags: CURRENT_TRACE_FLAGS.read(|flags| *flags),
+            span_id: get_span_id(),
+            correlation: get_correlation(),
+        }
+    }
+
+    /// 在快照的上下文中执行同步闭包，闭包返回（或panic）后恢复原来的上下文
+    pub(crate) fn enter<R>(&self, f: impl FnOnce() -> R) -> R {
+        sync_scope(&CURRENT_TRACE_ID, self.trace_id.as_ref(), || {
+            sync_scope(&CURRENT_TRACE_CONTEXT, self.trace_context.as_ref(), || {
+                sync_scope(&CURRENT_TRACE_STATE, self.trace_state.as_ref(), || {
+                    sync_scope(&CURRENT_TRACE_FLAGS, self.trace_flags.as_ref(), || {
+                        sync_scope(&CURRENT_SPAN_ID, self.span_id.as_ref(), || {
+                            sync_scope(&CURRENT_CORRELATION, self.correlation.as_ref(), f)
+                        })
+                    })
+                })
+            })
+        })
+    }
+}
+
+/// 值存在时在上下文作用域中执行同步闭包，否则直接执行
+#[cfg(feature = "tower")]
+fn sync_scope<T: Clone + 'static, R>(
+    key: &'static impl ContextKey<T>,
+    value: Option<&T>,
+    f: impl FnOnce() -> R,
+) -> R {
+    match value {
+        Some(value) => key.scoped_sync(value.clone(), f),
+        None => f(),
+    }
 }
 
 #[cfg(test)]
@@ -65,12 +651,353 @@ mod tests {
         assert_ne!(trace_id1, trace_id2, "连续调用应生成不同的ID");
     }
 
+    /// 验证缺失上下文日志级别的配置与静默模式
+    #[test]
+    fn test_missing_context_log_level() {
+        use std::sync::{atomic::AtomicUsize, Arc};
+        use tracing_subscriber::layer::{Context as LayerContext, SubscriberExt};
+
+        /// 统计事件数量的订阅层
+        struct CountEvents(Arc<AtomicUsize>);
+
+        impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for CountEvents {
+            fn on_event(&self, _event: &tracing::Event<'_>, _ctx: LayerContext<'_, S>) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let events = Arc::new(AtomicUsize::new(0));
+        let subscriber = tracing_subscriber::registry().with(CountEvents(events.clone()));
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        get_trace_id();
+        assert_eq!(events.load(Ordering::Relaxed), 1, "默认应记录日志");
+
+        set_missing_context_log_level(None);
+        let trace_id = get_trace_id();
+        assert!(TraceId::from_string_validated(trace_id.as_str()).is_some());
+        assert_eq!(events.load(Ordering::Relaxed), 1, "静默模式不应记录日志");
+
+        set_missing_context_log_level(Some(tracing::Level::DEBUG));
+        get_trace_id();
+        assert_eq!(events.load(Ordering::Relaxed), 2);
+
+        set_missing_context_log_level(Some(tracing::Level::WARN));
+    }
+
+    /// 验证trace-flags的优先级：显式设置 > 上游上下文
+    #[tokio::test]
+    async fn test_with_trace_flags() {
+        assert!(get_trace_flags().is_none());
+
+        let ctx =
+            TraceContext::parse("00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01").unwrap();
+        with_trace_context(ctx, async {
+            assert_eq!(get_trace_flags(), Some(0x01));
+            with_trace_flags(0x00, async {
+                assert_eq!(get_trace_flags(), Some(0x00));
+            })
+            .await;
+        })
+        .await;
+
+        with_trace_flags(0x01, async {
+            assert_eq!(get_trace_flags(), Some(0x01));
+        })
+        .await;
+    }
+
+    /// 验证采样判断：`-00` 未采样，`-01` 与缺失时视为已采样
+    #[tokio::test]
+    async fn test_is_sampled() {
+        assert!(is_sampled());
+
+        for (traceparent, sampled) in [
+            (
+                "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-00",
+                false,
+            ),
+            (
+                "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+                true,
+            ),
+        ] {
+            let ctx = TraceContext::parse(traceparent).unwrap();
+            with_trace_context(ctx.clone(), async {
+                assert_eq!(is_sampled(), sampled);
+
+                // 向下游传播时沿用 trace-id 与 flags，parent-id 为本服务的跨度ID
+                let span_id = SpanId::new();
+                let outbound = with_span_id(span_id.clone(), async {
+                    outbound_trace_context(ctx.trace_id)
+                })
+                .await;
+                assert_eq!(outbound.trace_id, ctx.trace_id);
+                assert_eq!(outbound.parent_id, span_id);
+                assert_eq!(outbound.is_sampled(), sampled);
+            })
+            .await;
+        }
+
+        // 采样器的决策优先于上游 flags
+        with_trace_flags(0x00, async { assert!(!is_sampled()) }).await;
+    }
+
+    /// 验证守卫丢弃后恢复之前的追踪ID，且异步上下文优先
+    #[tokio::test]
+    async fn test_enter_trace_id() {
+        let outer = TraceId::new();
+        let inner = TraceId::new();
+
+        let guard = enter_trace_id(outer);
+        assert_eq!(get_trace_id_opt(), Some(outer));
+        {
+            let _inner_guard = enter_trace_id(inner);
+            assert_eq!(get_trace_id_opt(), Some(inner));
+        }
+        assert_eq!(get_trace_id_opt(), Some(outer));
+        drop(guard);
+        assert_eq!(get_trace_id_opt(), None);
+
+        let task_id = TraceId::new();
+        with_trace_id(task_id, async {
+            let _guard = enter_trace_id(outer);
+            assert_eq!(get_trace_id_opt(), Some(task_id));
+        })
+        .await;
+    }
+
+    /// 验证跨度ID只在作用域内可见
+    #[tokio::test]
+    async fn test_with_span_id() {
+        assert!(get_span_id().is_none());
+
+        let span_id = SpanId::new();
+        let current = with_span_id(span_id.clone(), async { get_span_id() }).await;
+        assert_eq!(current, Some(span_id));
+    }
+
+    /// 验证以同一ID嵌套进入作用域时，ID按值拷贝且互不影响
+    #[tokio::test]
+    async fn test_nested_scopes_copy_trace_id() {
+        let trace_id = TraceId::new();
+        with_trace_id(trace_id, async move {
+            with_trace_id(trace_id, async move {
+                assert_eq!(get_trace_id(), trace_id);
+            })
+            .await;
+            assert_eq!(get_trace_id(), trace_id);
+        })
+        .await;
+        assert!(std::mem::size_of::<TraceId>() <= 49);
+    }
+
+    /// 验证get_trace_id_opt在上下文内外的行为
+    #[tokio::test]
+    async fn test_get_trace_id_opt() {
+        assert!(get_trace_id_opt().is_none(), "作用域外应返回None");
+
+        let trace_id = TraceId::new();
+        with_trace_id(trace_id, async {
+            assert_eq!(get_trace_id_opt(), Some(trace_id));
+        })
+        .await;
+
+        assert!(get_trace_id_opt().is_none(), "上下文不应泄漏到作用域之外");
+    }
+
+    /// 验证同步作用域的设置、嵌套恢复与跨线程隔离
+    #[test]
+    fn test_with_trace_id_sync() {
+        assert!(get_trace_id_opt().is_none());
+
+        let outer = TraceId::new();
+        let inner = TraceId::new();
+        with_trace_id_sync(outer, || {
+            assert_eq!(get_trace_id(), outer);
+
+            with_trace_id_sync(inner, || assert_eq!(get_trace_id(), inner));
+            assert_eq!(get_trace_id(), outer, "嵌套作用域结束后应恢复外层ID");
+
+            // 其他线程不会继承线程局部上下文
+            std::thread::spawn(|| assert!(get_trace_id_opt().is_none()))
+                .join()
+                .unwrap();
+        });
+
+        assert!(get_trace_id_opt().is_none(), "作用域结束后应清除");
+
+        // 闭包panic后同样恢复
+        let result = std::panic::catch_unwind(|| with_trace_id_sync(outer, || panic!()));
+        assert!(result.is_err());
+        assert!(get_trace_id_opt().is_none());
+    }
+
+    /// 验证异步上下文优先于同步线程局部上下文
+    #[tokio::test]
+    async fn test_task_local_takes_precedence_over_sync() {
+        let sync_id = TraceId::new();
+        let async_id = TraceId::new();
+
+        with_trace_id(async_id, async {
+            with_trace_id_sync(sync_id, || assert_eq!(get_trace_id(), async_id));
+        })
+        .await;
+
+        with_trace_id_sync(sync_id, || assert_eq!(get_trace_id(), sync_id));
+    }
+
+    /// 验证spawn_traced与spawn_traced_blocking将追踪ID传递到新任务
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_spawn_traced() {
+        let trace_id = TraceId::new();
+
+        let (spawned, blocking, untraced) = with_trace_id(trace_id, async {
+            let spawned = spawn_traced(async { get_trace_id_opt() });
+            let blocking = spawn_traced_blocking(get_trace_id_opt);
+            let untraced = tokio::spawn(async { get_trace_id_opt() });
+            (
+                spawned.await.unwrap(),
+                blocking.await.unwrap(),
+                untraced.await.unwrap(),
+            )
+        })
+        .await;
+
+        assert_eq!(spawned, Some(trace_id));
+        assert_eq!(blocking, Some(trace_id));
+        assert!(untraced.is_none(), "普通tokio::spawn不会继承上下文");
+
+        // 不在追踪上下文中时不设置任何ID
+        assert!(spawn_traced(async { get_trace_id_opt() })
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    /// 验证阻塞闭包中的 get_trace_id() 与调用方一致，嵌套调用时同样传递
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_spawn_traced_blocking() {
+        let trace_id = TraceId::new();
+
+        let (inner, nested) = with_trace_id(trace_id, async move {
+            spawn_traced_blocking(move || {
+                // 在阻塞线程中从线程局部上下文读取，再次卸载时继续传递
+                let nested = tokio::runtime::Handle::current()
+                    .block_on(spawn_traced_blocking(get_trace_id))
+                    .unwrap();
+                (get_trace_id(), nested)
+            })
+            .await
+            .unwrap()
+        })
+        .await;
+
+        assert_eq!(inner, trace_id);
+        assert_eq!(nested, trace_id);
+
+        // 不在追踪上下文中时不设置任何ID
+        assert!(spawn_traced_blocking(get_trace_id_opt)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    /// 验证spawn_blocking_traced与spawn_traced一样传递关联字段
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_spawn_blocking_traced_correlation() {
+        let trace_id = TraceId::new();
+        let mut fields = CorrelationFields::new();
+        fields.insert("tenant_id", "acme");
+
+        let (inner, tenant) = with_trace_id(
+            trace_id,
+            with_correlation(fields, async {
+                spawn_blocking_traced(|| (get_trace_id(), get_correlation_field("tenant_id")))
+                    .await
+                    .unwrap()
+            }),
+        )
+        .await;
+
+        assert_eq!(inner, trace_id);
+        assert_eq!(tenant.as_deref(), Some("acme"));
+
+        // 阻塞线程复用时不会残留关联字段
+        assert!(spawn_blocking_traced(get_correlation)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    /// 验证绑定后的连接处理函数在新任务中处理的每条消息都使用升级请求的追踪ID
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_bind_trace_id_for_connection() {
+        let trace_id = TraceId::new();
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel::<u32>();
+
+        let handle_connection = |mut messages: tokio::sync::mpsc::UnboundedReceiver<u32>| async move {
+            let mut seen = Vec::new();
+            while let Some(message) = messages.recv().await {
+                tokio::task::yield_now().await;
+                seen.push((message, get_trace_id_opt()));
+            }
+            seen
+        };
+
+        // 与 on_upgrade 相同：在处理器中绑定，之后在上下文之外的新任务中执行
+        let callback = with_trace_id(trace_id, async {
+            bind_trace_id_for_connection(handle_connection)
+        })
+        .await;
+        let connection = tokio::spawn(callback(receiver));
+        for message in 0..3 {
+            sender.send(message).unwrap();
+        }
+        drop(sender);
+
+        assert_eq!(
+            connection.await.unwrap(),
+            [0, 1, 2].map(|message| (message, Some(trace_id)))
+        );
+
+        // 不在追踪上下文中时不设置任何ID
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        sender.send(0).unwrap();
+        drop(sender);
+        let seen = bind_trace_id_for_connection(handle_connection)(receiver).await;
+        assert_eq!(seen, [(0, None)]);
+    }
+
+    #[tokio::test]
+    async fn test_with_correlation() {
+        assert!(get_correlation().is_none());
+        assert!(get_correlation_field("tenant-id").is_none());
+
+        let fields: CorrelationFields = [("tenant-id", "acme")].into_iter().collect();
+        let trace_id = TraceId::new();
+        let (tenant, spawned) = with_trace_id(
+            trace_id,
+            with_correlation(fields, async {
+                let spawned = spawn_traced(async {
+                    (get_trace_id_opt(), get_correlation_field("tenant-id"))
+                });
+                (get_correlation_field("tenant-id"), spawned.await.unwrap())
+            }),
+        )
+        .await;
+
+        assert_eq!(tenant.as_deref(), Some("acme"));
+        // spawn_traced 同时传递追踪ID与关联字段
+        assert_eq!(spawned, (Some(trace_id), Some("acme".to_string())));
+    }
+
     /// 改进测试：验证with_trace_id在整个异步作用域内（包括await点之后）都保持上下文
     #[tokio::test]
     async fn test_with_trace_id_context_persistence() {
         let expected_trace_id = TraceId::new(); // 使用有效的ID
 
-        let result = with_trace_id(expected_trace_id.clone(), async {
+        let result = with_trace_id(expected_trace_id, async {
             // 在await之前检查
             let current1 = get_trace_id();
             assert_eq!(current1, expected_trace_id, "ID在await之前应匹配");
@@ -99,12 +1026,12 @@ mod tests {
         let outer_id = TraceId::new();
         let inner_id = TraceId::new();
 
-        with_trace_id(outer_id.clone(), async {
+        with_trace_id(outer_id, async {
             // 验证外层上下文
             assert_eq!(get_trace_id(), outer_id, "应处于外层上下文");
 
             // 进入内层上下文
-            with_trace_id(inner_id.clone(), async {
+            with_trace_id(inner_id, async {
                 // 验证内层上下文覆盖了外层
                 assert_eq!(get_trace_id(), inner_id, "应处于内层上下文");
             })
@@ -124,7 +1051,7 @@ mod tests {
 
         for _ in 0..NUM_TASKS {
             let trace_id = TraceId::new();
-            let trace_id_clone = trace_id.clone();
+            let trace_id_clone = trace_id;
 
             let handle = tokio::spawn(async move {
                 with_trace_id(trace_id_clone, async move {
@@ -145,4 +1072,51 @@ mod tests {
             handle.await.unwrap();
         }
     }
+
+    /// 验证with_trace_context同时设置追踪上下文和追踪ID
+    #[tokio::test]
+    async fn test_with_trace_context() {
+        use crate::trace_context::SpanId;
+
+        assert!(get_trace_context().is_none(), "作用域外不应存在上下文");
+
+        let ctx = TraceContext {
+            trace_id: TraceId::new(),
+            parent_id: SpanId::new(),
+            flags: 1,
+        };
+
+        with_trace_context(ctx.clone(), async {
+            assert_eq!(get_trace_context(), Some(ctx.clone()));
+            assert_eq!(get_trace_id(), ctx.trace_id);
+        })
+        .await;
+
+        assert!(get_trace_context().is_none(), "上下文不应泄漏到作用域之外");
+    }
+
+    /// 验证with_trace_state的作用域与嵌套覆盖
+    #[tokio::test]
+    async fn test_with_trace_state() {
+        assert!(get_trace_state().is_none(), "作用域外不应存在tracestate");
+
+        let state = TraceState::parse("congo=t61rcWkgMzE").unwrap();
+        with_trace_state(state.clone(), async {
+            assert_eq!(get_trace_state(), Some(state.clone()));
+
+            // 下游追加自己的成员
+            let mut updated = get_trace_state().unwrap();
+            updated.insert("acme", "1");
+            with_trace_state(updated, async {
+                assert_eq!(
+                    get_trace_state().unwrap().to_header(),
+                    "acme=1,congo=t61rcWkgMzE"
+                );
+            })
+            .await;
+
+            assert_eq!(get_trace_state(), Some(state.clone()));
+        })
+        .await;
+    }
 }
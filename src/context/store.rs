@@ -0,0 +1,229 @@
+//! 上下文存储后端
+//!
+//! `context` 模块中的各项上下文（追踪ID、`traceparent`、关联字段等）通过 `ContextKey`
+//! 读取与设置，具体存储由特性决定：
+//!
+//! - 默认使用 `tokio::task_local`。`scoped` 返回的 future 每次被 poll 时设置值，poll 返回后恢复，
+//!   因此值跟随 future 跨越 `.await`，同一线程上交替执行的其他任务不会观察到该值
+//! - 启用 `runtime-agnostic` 特性后使用本模块基于 `thread_local` 的实现，不依赖 tokio 的任何类型，
+//!   适用于 async-std、smol 等运行时。同样只在 poll 期间设置值并在返回后恢复，
+//!   值不会泄漏到其他任务，也不会残留在工作线程上
+//!
+//! 两种后端都只作用于被 `scoped` 包装的 future 本身：在其中新建的任务（无论使用哪个运行时的 spawn）
+//! 不会继承上下文，需要像 `spawn_traced` 那样捕获后重新建立；在 poll 之外执行的代码
+//! （如运行时回调、`Drop`）也读取不到上下文
+
+use std::future::Future;
+
+/// 上下文存储的键，对应一项可在作用域内设置的上下文值
+pub(super) trait ContextKey<T: 'static> {
+    /// 读取当前值，不在作用域内时返回None
+    fn read<R>(&'static self, f: impl FnOnce(&T) -> R) -> Option<R>;
+
+    /// 在同步闭包执行期间设置值，闭包返回（或panic）后恢复原来的值
+    fn scoped_sync<R>(&'static self, value: T, f: impl FnOnce() -> R) -> R;
+
+    /// 在 future 执行期间设置值，每次 poll 时进入、poll 返回后恢复
+    fn scoped<F: Future>(&'static self, value: T, future: F) -> impl Future<Output = F::Output>;
+}
+
+/// 声明上下文存储的键，按特性选择后端
+macro_rules! context_local {
+    ($(#[$attr:meta])* static $name:ident: $t:ty;) => {
+        #[cfg(not(feature = "runtime-agnostic"))]
+        ::tokio::task_local! {
+            $(#[$attr])* static $name: $t;
+        }
+
+        #[cfg(feature = "runtime-agnostic")]
+        $(#[$attr])*
+        static $name: $crate::context::store::ThreadLocalKey<$t> = {
+            ::std::thread_local! {
+                static SLOT: ::std::cell::RefCell<Option<$t>> = const { ::std::cell::RefCell::new(None) };
+            }
+            $crate::context::store::ThreadLocalKey::new(&SLOT)
+        };
+    };
+}
+
+pub(super) use context_local;
+
+#[cfg(not(feature = "runtime-agnostic"))]
+impl<T: 'static> ContextKey<T> for tokio::task::LocalKey<T> {
+    fn read<R>(&'static self, f: impl FnOnce(&T) -> R) -> Option<R> {
+        self.try_with(f).ok()
+    }
+
+    fn scoped_sync<R>(&'static self, value: T, f: impl FnOnce() -> R) -> R {
+        tokio::task::LocalKey::sync_scope(self, value, f)
+    }
+
+    fn scoped<F: Future>(&'static self, value: T, future: F) -> impl Future<Output = F::Output> {
+        tokio::task::LocalKey::scope(self, value, future)
+    }
+}
+
+#[cfg(feature = "runtime-agnostic")]
+pub(crate) use thread_local_backend::ThreadLocalKey;
+
+/// 基于 `thread_local` 的后端（`runtime-agnostic` 特性）
+#[cfg(feature = "runtime-agnostic")]
+mod thread_local_backend {
+    use super::ContextKey;
+    use std::cell::RefCell;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+    use std::thread::LocalKey;
+
+    /// 线程局部存储中的上下文槽位，None表示不在作用域内
+    type Slot<T> = RefCell<Option<T>>;
+
+    /// 基于 `thread_local` 的上下文键，由 `context_local!` 创建
+    pub(crate) struct ThreadLocalKey<T: 'static> {
+        slot: &'static LocalKey<Slot<T>>,
+    }
+
+    impl<T: 'static> ThreadLocalKey<T> {
+        pub(crate) const fn new(slot: &'static LocalKey<Slot<T>>) -> Self {
+            Self { slot }
+        }
+    }
+
+    /// 与槽位交换值的守卫：创建时换入，丢弃时换回，即使发生panic也能恢复
+    struct Swapped<'a, T: 'static> {
+        slot: &'static LocalKey<Slot<T>>,
+        value: &'a mut Option<T>,
+    }
+
+    impl<'a, T: 'static> Swapped<'a, T> {
+        fn enter(slot: &'static LocalKey<Slot<T>>, value: &'a mut Option<T>) -> Self {
+            slot.with(|current| std::mem::swap(&mut *current.borrow_mut(), value));
+            Self { slot, value }
+        }
+    }
+
+    impl<T: 'static> Drop for Swapped<'_, T> {
+        fn drop(&mut self) {
+            // 线程退出时槽位可能已被销毁，此时无需恢复
+            let _ = self
+                .slot
+                .try_with(|current| std::mem::swap(&mut *current.borrow_mut(), self.value));
+        }
+    }
+
+    impl<T: 'static> ContextKey<T> for ThreadLocalKey<T> {
+        fn read<R>(&'static self, f: impl FnOnce(&T) -> R) -> Option<R> {
+            self.slot
+                .try_with(|current| current.borrow().as_ref().map(f))
+                .ok()
+                .flatten()
+        }
+
+        fn scoped_sync<R>(&'static self, value: T, f: impl FnOnce() -> R) -> R {
+            let mut value = Some(value);
+            let _swapped = Swapped::enter(self.slot, &mut value);
+            f()
+        }
+
+        fn scoped<F: Future>(
+            &'static self,
+            value: T,
+            future: F,
+        ) -> impl Future<Output = F::Output> {
+            ScopedFuture {
+                slot: self.slot,
+                value: Some(value),
+                future,
+            }
+        }
+    }
+
+    pin_project_lite::pin_project! {
+        /// 每次 poll 时在槽位中设置值的 future
+        struct ScopedFuture<T: 'static, F> {
+            slot: &'static LocalKey<Slot<T>>,
+            value: Option<T>,
+            #[pin]
+            future: F,
+        }
+    }
+
+    impl<T: 'static, F: Future> Future for ScopedFuture<T, F> {
+        type Output = F::Output;
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            let this = self.project();
+            let _swapped = Swapped::enter(this.slot, this.value);
+            this.future.poll(cx)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    context_local! {
+        static VALUE: u32;
+    }
+
+    #[test]
+    fn test_sync_scope() {
+        assert_eq!(VALUE.read(|value| *value), None);
+        VALUE.scoped_sync(1, || {
+            assert_eq!(VALUE.read(|value| *value), Some(1));
+            VALUE.scoped_sync(2, || assert_eq!(VALUE.read(|value| *value), Some(2)));
+            assert_eq!(VALUE.read(|value| *value), Some(1));
+        });
+        assert_eq!(VALUE.read(|value| *value), None);
+
+        // panic 后同样恢复
+        let result = std::panic::catch_unwind(|| VALUE.scoped_sync(3, || panic!("scope")));
+        assert!(result.is_err());
+        assert_eq!(VALUE.read(|value| *value), None);
+    }
+
+    #[test]
+    fn test_scope_without_tokio_runtime() {
+        // 不依赖 tokio 运行时：使用最简单的执行器驱动 future
+        let future = VALUE.scoped(7, async {
+            let before = VALUE.read(|value| *value);
+            YieldOnce(false).await;
+            (before, VALUE.read(|value| *value))
+        });
+        let mut future = std::pin::pin!(future);
+        let waker = std::task::Waker::noop();
+        let mut cx = std::task::Context::from_waker(waker);
+
+        // 第一次 poll 挂起后，值不会残留在线程上
+        assert!(future.as_mut().poll(&mut cx).is_pending());
+        assert_eq!(VALUE.read(|value| *value), None);
+
+        // 恢复执行时重新进入
+        assert_eq!(
+            future.as_mut().poll(&mut cx),
+            std::task::Poll::Ready((Some(7), Some(7)))
+        );
+        assert_eq!(VALUE.read(|value| *value), None);
+    }
+
+    /// 第一次 poll 时挂起一次的 future
+    struct YieldOnce(bool);
+
+    impl Future for YieldOnce {
+        type Output = ();
+
+        fn poll(
+            mut self: std::pin::Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<()> {
+            if self.0 {
+                return std::task::Poll::Ready(());
+            }
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            std::task::Poll::Pending
+        }
+    }
+}
@@ -0,0 +1,116 @@
+//! 关联字段
+//!
+//! 与追踪ID一起在异步任务上下文中传递的少量字符串键值对（租户ID、用户ID、请求ID等），
+//! 通过 `with_correlation` / `get_correlation_field` 访问。
+
+use std::collections::BTreeMap;
+
+/// 关联字段集合
+///
+/// 键按字典序存储，迭代顺序稳定。字段应保持少量且短小：
+/// 通过 `spawn_traced` 传递到新任务时会整体克隆
+///
+/// # 示例
+/// ```
+/// use trace_id::CorrelationFields;
+///
+/// let mut fields = CorrelationFields::new();
+/// fields.insert("tenant-id", "acme");
+/// assert_eq!(fields.get("tenant-id"), Some("acme"));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CorrelationFields {
+    fields: BTreeMap<String, String>,
+}
+
+impl CorrelationFields {
+    /// 创建空的字段集合
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 插入字段，键已存在时覆盖
+    ///
+    /// # 参数
+    /// * `key` - 字段名
+    /// * `value` - 字段值
+    ///
+    /// # 返回
+    /// 被覆盖的旧值
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<String>) -> Option<String> {
+        self.fields.insert(key.into(), value.into())
+    }
+
+    /// 获取字段值
+    ///
+    /// # 参数
+    /// * `key` - 字段名
+    ///
+    /// # 返回
+    /// 字段存在时返回Some(值)，否则返回None
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.fields.get(key).map(String::as_str)
+    }
+
+    /// 移除字段
+    ///
+    /// # 参数
+    /// * `key` - 字段名
+    ///
+    /// # 返回
+    /// 被移除的值
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        self.fields.remove(key)
+    }
+
+    /// 按键的字典序遍历所有字段
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.fields
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// 字段数量
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    /// 是否没有任何字段
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for CorrelationFields {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self {
+            fields: iter
+                .into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fields() {
+        let mut fields: CorrelationFields = [("user-id", "42"), ("tenant-id", "acme")]
+            .into_iter()
+            .collect();
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields.get("user-id"), Some("42"));
+        assert_eq!(fields.get("request-id"), None);
+
+        // 覆盖与移除
+        assert_eq!(fields.insert("user-id", "43"), Some("42".to_string()));
+        assert_eq!(fields.remove("tenant-id"), Some("acme".to_string()));
+        assert_eq!(fields.iter().collect::<Vec<_>>(), [("user-id", "43")]);
+
+        fields.remove("user-id");
+        assert!(fields.is_empty());
+    }
+}
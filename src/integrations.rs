@@ -1 +1,53 @@
+#[cfg(feature = "http")]
+pub mod b3;
+
+#[cfg(feature = "http")]
+pub mod datadog;
+
+#[cfg(feature = "http")]
+pub mod xray;
+
+#[cfg(feature = "http")]
+pub mod extensions;
+
+#[cfg(feature = "tower")]
+pub mod tower;
+
+#[cfg(feature = "tower")]
+pub mod catch_panic;
+
+#[cfg(feature = "tower")]
+pub mod streaming;
+
+#[cfg(feature = "tower")]
+pub mod trailers;
+
+#[cfg(feature = "axum")]
 pub mod axum;
+
+#[cfg(feature = "warp")]
+pub mod warp;
+
+#[cfg(feature = "poem")]
+pub mod poem;
+
+#[cfg(feature = "salvo")]
+pub mod salvo;
+
+#[cfg(feature = "rocket")]
+pub mod rocket;
+
+#[cfg(feature = "tonic")]
+pub mod tonic;
+
+#[cfg(feature = "tracing-layer")]
+pub mod tracing_layer;
+
+#[cfg(feature = "reqwest")]
+pub mod reqwest;
+
+#[cfg(feature = "otel")]
+pub mod otel;
+
+#[cfg(feature = "log")]
+pub mod log;
@@ -1,346 +1,221 @@
 //! Axum框架的追踪ID中间件
+//!
+//! 中间件层复用通用的 tower 实现，本模块提供 Axum 提取器，
+//! 以及用于 `Path`/`Query` 参数的 `TraceIdParam`
 
-use crate::{context, trace_id::TraceId, TRACE_ID_HEADER};
-use axum::{
-    extract::{FromRequestParts, Request},
-    http::{request::Parts, HeaderMap},
-    response::Response,
+use crate::{
+    context, trace_context::TraceContext, trace_id::TraceId, TraceIdRejection, TRACE_ID_HEADER,
 };
-use std::convert::Infallible;
-use std::{
-    sync::Arc,
-    task::{Context, Poll},
+use axum::{
+    extract::FromRequestParts,
+    http::{request::Parts, StatusCode},
+    response::{IntoResponse, Response},
 };
-use tower::{Layer, Service};
-use tracing::Instrument;
-
-/// 用于生成追踪ID的函数签名
-type Generator = Arc<dyn Fn() -> String + Send + Sync>;
-
-/// 追踪ID中间件配置选项
-#[derive(Clone, Debug)]
-pub struct TraceIdConfig {
-    /// 是否启用 tracing span（默认启用）
-    pub enable_span: bool,
-    /// 是否启用响应头（默认启用）
-    pub enable_response_header: bool,
-}
+use std::{convert::Infallible, fmt};
 
-impl Default for TraceIdConfig {
-    fn default() -> Self {
-        Self {
-            enable_span: true,
-            enable_response_header: true,
-        }
-    }
-}
+// -- TraceId Extractor --
 
-/// 高性能追踪中间件层
+/// Axum 提取器，用于在 handler 函数签名中直接获取 TraceId
 ///
-/// 支持性能优化配置，只负责trace_id的提取、生成和传递
-#[derive(Clone)]
-pub struct TraceIdLayer {
-    generator: Option<Generator>,
-    config: TraceIdConfig,
-}
-
-impl TraceIdLayer {
-    /// 创建新的追踪ID层，使用默认配置和高性能生成器
-    pub fn new() -> Self {
-        Self {
-            generator: None,
-            config: TraceIdConfig::default(),
-        }
-    }
-
-    /// 创建高性能模式的追踪ID层
-    ///
-    /// 禁用 tracing span 以获得最佳性能
-    pub fn new_high_performance() -> Self {
-        Self {
-            generator: None,
-            config: TraceIdConfig {
-                enable_span: false,
-                enable_response_header: true,
-            },
-        }
-    }
-
-    /// 使用自定义配置创建追踪ID层
-    pub fn with_config(config: TraceIdConfig) -> Self {
-        Self {
-            generator: None,
-            config,
-        }
-    }
+/// # 示例
+/// ```no_run
+/// use axum::{routing::get, Router};
+/// use trace_id::{TraceId, TraceIdLayer};
+///
+/// async fn my_handler(trace_id: TraceId) -> String {
+///     tracing::info!(trace_id = %trace_id, "Handler started");
+///     format!("Hello! Your trace ID is: {}", trace_id)
+/// }
+///
+/// let app: Router = Router::new()
+///     .route("/", get(my_handler))
+///     .layer(TraceIdLayer::new());
+/// ```
+impl<S> FromRequestParts<S> for TraceId
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
 
-    /// 使用自定义的生成器创建追踪ID层
-    ///
-    /// # 参数
-    /// * `generator` - 一个返回String的函数，用于生成ID
-    ///
-    /// # 示例
-    /// ```
-    /// use trace_id::TraceIdLayer;
+    /// 从请求中提取 TraceId
     ///
-    /// // 使用nanoid作为生成器
-    /// // let layer = TraceIdLayer::new().with_generator(|| nanoid::nanoid!());
-    /// ```
-    pub fn with_generator<F>(mut self, generator: F) -> Self
-    where
-        F: Fn() -> String + Send + Sync + 'static,
-    {
-        self.generator = Some(Arc::new(generator));
-        self
+    /// 这个提取器会调用 `context::get_trace_id()` 来获取当前请求的追踪ID。
+    /// 由于 TraceIdLayer 中间件已经设置了追踪上下文，这个提取器永远不会失败。
+    async fn from_request_parts(_parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(context::get_trace_id())
     }
 }
 
-impl Default for TraceIdLayer {
-    fn default() -> Self {
-        Self::new()
-    }
-}
+// -- TraceContext Extractor --
 
-impl<S> Layer<S> for TraceIdLayer {
-    type Service = TraceIdService<S>;
+/// Axum 提取器，获取本次请求向下游传播所需的完整追踪上下文
+///
+/// 与 `get_trace_context()` 返回上游 `traceparent` 不同，提取的是当前这一跳的上下文：
+/// - `trace_id`：当前追踪ID，与 `TraceId` 提取器相同
+/// - `parent_id`：中间件为本次请求生成的跨度ID（`get_span_id()`），下游据此关联到本服务
+/// - `flags`：当前的 trace-flags（`get_trace_flags()`），均不存在时视为已采样
+///
+/// 其 `to_header()` 即为出站请求应携带的 `traceparent`，与中间件写入响应的值一致，
+/// 无需再次读取请求头。与 `TraceId` 提取器相同，该提取器永远不会失败。
+///
+/// # 示例
+/// ```no_run
+/// use axum::{routing::get, Router};
+/// use trace_id::{TraceContext, TraceIdLayer};
+///
+/// async fn my_handler(ctx: TraceContext) -> String {
+///     // 调用下游服务时携带 `traceparent: <ctx.to_header()>`
+///     ctx.to_header()
+/// }
+///
+/// let app: Router = Router::new()
+///     .route("/", get(my_handler))
+///     .layer(TraceIdLayer::new());
+/// ```
+impl<S> FromRequestParts<S> for TraceContext
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
 
-    fn layer(&self, inner: S) -> Self::Service {
-        TraceIdService {
-            inner,
-            generator: self.generator.clone(),
-            config: self.config.clone(),
-        }
+    /// 从当前追踪上下文中组合 TraceContext
+    ///
+    /// 不在中间件作用域内时（没有跨度ID）生成新的跨度ID
+    async fn from_request_parts(_parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(context::outbound_trace_context(context::get_trace_id()))
     }
 }
 
-/// 高性能追踪ID服务
-#[derive(Clone)]
-pub struct TraceIdService<S> {
-    inner: S,
-    generator: Option<Generator>,
-    config: TraceIdConfig,
-}
+// -- RequiredTraceId Extractor --
 
-impl<S> Service<Request> for TraceIdService<S>
+/// 严格模式的 Axum 提取器：要求客户端提供有效的追踪ID
+///
+/// 与 `TraceId` 提取器不同，请求中没有有效的 `x-trace-id` 请求头时不会生成新ID，
+/// 而是以 `400 Bad Request` 拒绝请求。
+///
+/// # 示例
+/// ```no_run
+/// use axum::{routing::post, Router};
+/// use trace_id::{RequiredTraceId, TraceIdLayer};
+///
+/// async fn webhook(RequiredTraceId(trace_id): RequiredTraceId) -> String {
+///     format!("Accepted: {}", trace_id)
+/// }
+///
+/// let app: Router = Router::new()
+///     .route("/webhook", post(webhook))
+///     .layer(TraceIdLayer::new());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RequiredTraceId(pub TraceId);
+
+impl<S> FromRequestParts<S> for RequiredTraceId
 where
-    S: Service<Request, Response = Response> + Send + 'static,
-    S::Future: Send + 'static,
+    S: Send + Sync,
 {
-    type Response = S::Response;
-    type Error = S::Error;
-    type Future = std::pin::Pin<
-        Box<dyn std::future::Future<Output = Result<Self::Response, Self::Error>> + Send>,
-    >;
-
-    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        self.inner.poll_ready(cx)
+    type Rejection = MissingTraceId;
+
+    /// 从请求头中提取并校验客户端提供的追踪ID
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .headers
+            .get(TRACE_ID_HEADER)
+            .and_then(TraceId::from_header_value)
+            .map(RequiredTraceId)
+            .ok_or(MissingTraceId)
     }
+}
 
-    fn call(&mut self, mut req: Request) -> Self::Future {
-        // 从请求头中获取或生成追踪ID
-        let trace_id = extract_or_generate_trace_id(req.headers(), self.generator.as_deref());
-
-        // 提取请求信息用于span（在req被移动之前）
-        let method = req.method().clone();
-        let uri = req.uri().clone();
-
-        // 将追踪ID添加到请求扩展中（用于向后兼容）
-        req.extensions_mut().insert(trace_id.clone());
-
-        let future = self.inner.call(req);
-
-        let config = self.config.clone();
-
-        Box::pin(async move {
-            // 根据配置决定是否创建 span
-            if config.enable_span {
-                let span = tracing::info_span!(
-                    "request",
-                    trace_id = %trace_id.as_str(),
-                    method = %method,
-                    uri = %uri
-                );
-
-                // 在span和task_local上下文中执行请求处理
-                async move {
-                    context::with_trace_id(trace_id.clone(), async move {
-                        let mut response = future.await?;
-
-                        // 根据配置决定是否添加响应头
-                        if config.enable_response_header {
-                            if let Ok(header_value) = trace_id.as_str().parse() {
-                                response.headers_mut().insert(TRACE_ID_HEADER, header_value);
-                            }
-                        }
-
-                        Ok(response)
-                    })
-                    .await
-                }
-                .instrument(span)
-                .await
-            } else {
-                // 高性能模式：跳过 span 创建
-                context::with_trace_id(trace_id.clone(), async move {
-                    let mut response = future.await?;
-
-                    // 根据配置决定是否添加响应头
-                    if config.enable_response_header {
-                        if let Ok(header_value) = trace_id.as_str().parse() {
-                            response.headers_mut().insert(TRACE_ID_HEADER, header_value);
-                        }
-                    }
-
-                    Ok(response)
-                })
-                .await
-            }
-        })
+/// `RequiredTraceId` 的拒绝类型：请求中没有有效的 `x-trace-id`
+///
+/// 转换为 `400 Bad Request` 响应，响应体为错误说明
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MissingTraceId;
+
+impl fmt::Display for MissingTraceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "missing or invalid `{TRACE_ID_HEADER}` header: expected 32 lowercase hex characters"
+        )
     }
 }
 
-/// 从请求头中提取或生成新的追踪ID（高性能版本）
-fn extract_or_generate_trace_id(
-    headers: &HeaderMap,
-    generator: Option<&(dyn Fn() -> String + Send + Sync)>,
-) -> TraceId {
-    // 快速路径：直接从头部提取
-    if let Some(header_value) = headers.get(TRACE_ID_HEADER) {
-        if let Ok(id_str) = header_value.to_str() {
-            // 使用快速验证提升性能
-            if is_valid_trace_id_fast(id_str) {
-                return TraceId::from_string_unchecked(id_str);
-            } else if let Some(trace_id) = TraceId::from_string_validated(id_str) {
-                return trace_id;
-            }
-        }
-    }
+impl std::error::Error for MissingTraceId {}
 
-    // 生成新的追踪ID
-    if let Some(generator_fn) = generator {
-        let generated_id = generator_fn();
-        TraceId::from_string_validated(&generated_id).unwrap_or_default()
-    } else {
-        TraceId::new()
+impl IntoResponse for MissingTraceId {
+    fn into_response(self) -> Response {
+        (StatusCode::BAD_REQUEST, self.to_string()).into_response()
     }
 }
 
-/// 快速验证追踪ID格式（避免详细检查）
+/// 转换为 `400 Bad Request` 响应，响应体为错误说明
 ///
-/// 只接受符合 W3C TraceContext 规范的格式，其他格式需要完整验证
-fn is_valid_trace_id_fast(id: &str) -> bool {
-    // W3C TraceContext 规范：恰好32个字符的小写十六进制
-    id.len() == 32
-        && id
-            .bytes()
-            .all(|b| b.is_ascii_hexdigit() && !b.is_ascii_uppercase())
+/// 可在 `map_response` 中用它替换 `MissingIdPolicy::Reject` 产生的空响应体
+impl IntoResponse for TraceIdRejection {
+    fn into_response(self) -> Response {
+        (self.status(), self.to_string()).into_response()
+    }
 }
 
-// -- TraceId Extractor --
+// -- TraceIdParam --
 
-/// Axum 提取器，用于在 handler 函数签名中直接获取 TraceId
+/// 路径或查询参数中的追踪ID
+///
+/// 按 `FromStr`（W3C TraceContext 规则）反序列化，可用于 `Path<TraceIdParam>`、
+/// `Query<...>` 等基于 serde 的提取器；格式无效时由对应提取器以 `400 Bad Request` 拒绝，
+/// 响应体包含 `TraceIdParseError` 描述的具体原因。适用于在URL中携带追踪ID的 REST API。
 ///
 /// # 示例
 /// ```no_run
-/// use axum::{routing::get, Router};
-/// use trace_id::{TraceId, TraceIdLayer};
+/// use axum::{extract::Path, routing::get, Router};
+/// use trace_id::TraceIdParam;
 ///
-/// async fn my_handler(trace_id: TraceId) -> String {
-///     tracing::info!(trace_id = %trace_id, "Handler started");
-///     format!("Hello! Your trace ID is: {}", trace_id)
+/// async fn get_trace(Path(TraceIdParam(trace_id)): Path<TraceIdParam>) -> String {
+///     format!("Looking up trace {trace_id}")
 /// }
 ///
-/// let app: Router = Router::new()
-///     .route("/", get(my_handler))
-///     .layer(TraceIdLayer::new());
+/// let app: Router = Router::new().route("/traces/{trace_id}", get(get_trace));
 /// ```
-impl<S> FromRequestParts<S> for TraceId
-where
-    S: Send + Sync,
-{
-    type Rejection = Infallible;
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TraceIdParam(pub TraceId);
 
-    /// 从请求中提取 TraceId
-    ///
-    /// 这个提取器会调用 `context::get_trace_id()` 来获取当前请求的追踪ID。
-    /// 由于 TraceIdLayer 中间件已经设置了追踪上下文，这个提取器永远不会失败。
-    async fn from_request_parts(_parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
-        Ok(context::get_trace_id())
+impl<'de> serde::Deserialize<'de> for TraceIdParam {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let id = String::deserialize(deserializer)?;
+        id.parse().map(Self).map_err(serde::de::Error::custom)
+    }
+}
+
+impl From<TraceIdParam> for TraceId {
+    fn from(param: TraceIdParam) -> Self {
+        param.0
+    }
+}
+
+impl fmt::Display for TraceIdParam {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{
+        TraceIdConfig, TraceIdLayer, TRACEPARENT_HEADER, TRACESTATE_HEADER, TRACE_ID_HEADER,
+    };
     use axum::{
         body::Body,
-        http::{HeaderValue, Request, StatusCode},
+        http::{Request, StatusCode},
         routing::get,
         Router,
     };
     use tower::ServiceExt;
 
-    // --- 辅助函数测试 ---
-    mod id_extraction {
-        use super::*;
-
-        fn default_generator() -> Option<&'static (dyn Fn() -> String + Send + Sync)> {
-            None
-        }
-
-        #[test]
-        fn test_extract_trace_id_from_headers() {
-            let mut headers = HeaderMap::new();
-            let valid_trace_id = "0af7651916cd43dd8448eb211c80319c";
-            headers.insert(TRACE_ID_HEADER, HeaderValue::from_static(valid_trace_id));
-
-            let trace_id = extract_or_generate_trace_id(&headers, default_generator());
-            assert_eq!(trace_id.as_str(), valid_trace_id);
-        }
-
-        #[test]
-        fn test_generate_trace_id_when_missing() {
-            let headers = HeaderMap::new();
-            let trace_id = extract_or_generate_trace_id(&headers, default_generator());
-            assert_eq!(trace_id.as_str().len(), 32);
-            assert!(TraceId::from_string_validated(trace_id.as_str()).is_some());
-        }
-
-        #[test]
-        fn test_extract_with_invalid_header() {
-            let mut headers = HeaderMap::new();
-            headers.insert(TRACE_ID_HEADER, HeaderValue::from_static(""));
-            let trace_id = extract_or_generate_trace_id(&headers, default_generator());
-            assert_ne!(trace_id.as_str(), "");
-            assert_eq!(trace_id.as_str().len(), 32);
-
-            let mut headers = HeaderMap::new();
-            let long_id = "a".repeat(129);
-            headers.insert(TRACE_ID_HEADER, HeaderValue::from_str(&long_id).unwrap());
-            let trace_id = extract_or_generate_trace_id(&headers, default_generator());
-            assert_ne!(trace_id.as_str(), long_id);
-        }
-
-        #[test]
-        fn test_with_custom_generator() {
-            let headers = HeaderMap::new();
-            let custom_id = "0af7651916cd43dd8448eb211c80319c";
-            let generator = || custom_id.to_string();
-            let trace_id = extract_or_generate_trace_id(&headers, Some(&generator));
-            assert_eq!(trace_id.as_str(), custom_id);
-        }
-
-        #[test]
-        fn test_custom_generator_fallback() {
-            let headers = HeaderMap::new();
-            let invalid_id = "this-is-not-a-valid-id";
-            let generator = || invalid_id.to_string();
-            let trace_id = extract_or_generate_trace_id(&headers, Some(&generator));
-            assert_ne!(trace_id.as_str(), invalid_id);
-            assert_eq!(trace_id.as_str().len(), 32);
-        }
-    }
-
     // --- 提取器测试 ---
     #[tokio::test]
     async fn test_trace_id_extractor() {
@@ -351,7 +226,7 @@ mod tests {
             .into_parts();
         let test_trace_id = TraceId::new();
 
-        crate::context::with_trace_id(test_trace_id.clone(), async move {
+        crate::context::with_trace_id(test_trace_id, async move {
             let extracted_trace_id = TraceId::from_request_parts(&mut parts, &())
                 .await
                 .expect("TraceId extraction should never fail");
@@ -360,6 +235,195 @@ mod tests {
         .await;
     }
 
+    #[tokio::test]
+    async fn test_trace_context_extractor() {
+        use crate::{sampler::AlwaysOff, SpanId};
+
+        async fn handler(ctx: TraceContext) -> String {
+            assert_eq!(ctx.trace_id, crate::context::get_trace_id());
+            assert_eq!(Some(&ctx.parent_id), crate::context::get_span_id().as_ref());
+            ctx.to_header()
+        }
+
+        let get_traceparent = |app: Router, traceparent: Option<&str>| {
+            let mut request = Request::builder().uri("/");
+            if let Some(value) = traceparent {
+                request = request.header(TRACEPARENT_HEADER, value);
+            }
+            let request = request.body(Body::empty()).unwrap();
+            async move {
+                let response = app.oneshot(request).await.unwrap();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                TraceContext::parse(std::str::from_utf8(&body).unwrap()).unwrap()
+            }
+        };
+
+        // 上游 traceparent：沿用 trace-id 与 flags，parent-id 替换为本次请求的跨度ID
+        let upstream =
+            TraceContext::parse("00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-00").unwrap();
+        let config = TraceIdConfig {
+            enable_traceparent: true,
+            ..TraceIdConfig::default()
+        };
+        let app = Router::new()
+            .route("/", get(handler))
+            .layer(TraceIdLayer::with_config(config));
+        let ctx = get_traceparent(app.clone(), Some(&upstream.to_header())).await;
+        assert_eq!(ctx.trace_id, upstream.trace_id);
+        assert_ne!(ctx.parent_id, upstream.parent_id);
+        assert_eq!(ctx.flags, upstream.flags);
+
+        // 没有上游上下文时默认为已采样
+        let ctx = get_traceparent(app, None).await;
+        assert!(ctx.is_sampled());
+
+        // 采样器的决策体现在 flags 中
+        let app = Router::new()
+            .route("/", get(handler))
+            .layer(TraceIdLayer::new().with_sampler(AlwaysOff));
+        let ctx = get_traceparent(app, None).await;
+        assert!(!ctx.is_sampled());
+
+        // 中间件之外同样不会失败
+        let (mut parts, _body) = Request::builder().body(()).unwrap().into_parts();
+        let ctx = TraceContext::from_request_parts(&mut parts, &())
+            .await
+            .unwrap();
+        assert!(ctx.is_sampled());
+        assert!(SpanId::from_string_validated(ctx.parent_id.as_str()).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_required_trace_id_extractor() {
+        async fn handler(RequiredTraceId(trace_id): RequiredTraceId) -> String {
+            trace_id.to_string()
+        }
+
+        let app = Router::new()
+            .route("/", get(handler))
+            .layer(TraceIdLayer::new());
+
+        let valid_id = "0af7651916cd43dd8448eb211c80319c";
+        let request = Request::builder()
+            .uri("/")
+            .header(TRACE_ID_HEADER, valid_id)
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], valid_id.as_bytes());
+
+        // 缺失或无效时返回400，而不是生成新ID
+        for header in [None, Some("invalid")] {
+            let mut request = Request::builder().uri("/");
+            if let Some(value) = header {
+                request = request.header(TRACE_ID_HEADER, value);
+            }
+            let response = app
+                .clone()
+                .oneshot(request.body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert_eq!(&body[..], MissingTraceId.to_string().as_bytes());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_trace_id_rejection_response() {
+        use crate::MissingIdPolicy;
+
+        // 用 `map_response` 把中间件的空400响应替换为带错误说明的响应
+        let config = TraceIdConfig::builder()
+            .missing_id_policy(MissingIdPolicy::Reject)
+            .build();
+        let app = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(TraceIdLayer::with_config(config))
+            .layer(axum::middleware::map_response(|response: Response| async {
+                match response.extensions().get::<TraceIdRejection>() {
+                    Some(rejection) => rejection.into_response(),
+                    None => response,
+                }
+            }));
+
+        for (header, rejection) in [
+            (None, TraceIdRejection::Missing),
+            (Some("invalid"), TraceIdRejection::Invalid),
+        ] {
+            let mut request = Request::builder().uri("/");
+            if let Some(value) = header {
+                request = request.header(TRACE_ID_HEADER, value);
+            }
+            let response = app
+                .clone()
+                .oneshot(request.body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert_eq!(&body[..], rejection.to_string().as_bytes());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_trace_id_param() {
+        use crate::TraceIdParseError;
+        use axum::extract::{Path, Query};
+        use std::collections::HashMap;
+
+        async fn by_path(Path(TraceIdParam(trace_id)): Path<TraceIdParam>) -> String {
+            trace_id.to_string()
+        }
+
+        async fn by_query(Query(params): Query<HashMap<String, TraceIdParam>>) -> String {
+            params["trace_id"].to_string()
+        }
+
+        let app = Router::new()
+            .route("/traces/{trace_id}", get(by_path))
+            .route("/search", get(by_query));
+        let get_body = |uri: String| {
+            let app = app.clone();
+            async move {
+                let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+                let response = app.oneshot(request).await.unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                (status, String::from_utf8(body.to_vec()).unwrap())
+            }
+        };
+
+        let valid_id = "0af7651916cd43dd8448eb211c80319c";
+        for uri in [
+            format!("/traces/{valid_id}"),
+            format!("/search?trace_id={valid_id}"),
+        ] {
+            assert_eq!(get_body(uri).await, (StatusCode::OK, valid_id.to_string()));
+        }
+
+        // 格式无效时返回400，响应体包含解析失败的原因
+        let (status, body) = get_body("/traces/0af7651916cd43dd".to_string()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains(&TraceIdParseError::WrongLength(16).to_string()));
+
+        let (status, body) = get_body(format!("/search?trace_id={}", "0".repeat(32))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains(&TraceIdParseError::AllZeros.to_string()));
+    }
+
     // --- 中间件/服务测试 ---
     mod layer_behavior {
         use super::*;
@@ -428,6 +492,7 @@ mod tests {
             let config = TraceIdConfig {
                 enable_span: true,
                 enable_response_header: false,
+                ..TraceIdConfig::default()
             };
             let app = Router::new()
                 .route("/", get(handler))
@@ -437,5 +502,107 @@ mod tests {
             assert_eq!(response.status(), StatusCode::OK);
             assert!(response.headers().get(TRACE_ID_HEADER).is_none());
         }
+
+        #[tokio::test]
+        async fn test_traceparent_propagation() {
+            async fn context_handler() -> String {
+                crate::context::get_trace_context()
+                    .map(|ctx| ctx.to_header())
+                    .unwrap_or_default()
+            }
+
+            let traceparent = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+            let config = TraceIdConfig {
+                enable_traceparent: true,
+                ..TraceIdConfig::default()
+            };
+            let app = Router::new()
+                .route("/", get(context_handler))
+                .layer(TraceIdLayer::with_config(config));
+
+            // 场景1: 有效的 traceparent 优先于 x-trace-id
+            let request = Request::builder()
+                .uri("/")
+                .header(TRACEPARENT_HEADER, traceparent)
+                .header(TRACE_ID_HEADER, "1234567890abcdef1234567890abcdef")
+                .body(Body::empty())
+                .unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(
+                response.headers().get(TRACE_ID_HEADER).unwrap(),
+                "0af7651916cd43dd8448eb211c80319c"
+            );
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert_eq!(&body[..], traceparent.as_bytes());
+
+            // 场景2: 无效的 traceparent 回退到 x-trace-id，且不设置上下文
+            let request = Request::builder()
+                .uri("/")
+                .header(TRACEPARENT_HEADER, "invalid")
+                .header(TRACE_ID_HEADER, "1234567890abcdef1234567890abcdef")
+                .body(Body::empty())
+                .unwrap();
+            let response = app.oneshot(request).await.unwrap();
+            assert_eq!(
+                response.headers().get(TRACE_ID_HEADER).unwrap(),
+                "1234567890abcdef1234567890abcdef"
+            );
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert!(body.is_empty());
+        }
+
+        #[tokio::test]
+        async fn test_tracestate_propagation() {
+            async fn state_handler() -> String {
+                crate::context::get_trace_state()
+                    .map(|state| state.to_header())
+                    .unwrap_or_default()
+            }
+
+            let tracestate = "congo=t61rcWkgMzE,rojo=00f067aa0ba902b7";
+            let config = TraceIdConfig {
+                enable_tracestate: true,
+                echo_tracestate: true,
+                ..TraceIdConfig::default()
+            };
+            let app = Router::new()
+                .route("/", get(state_handler))
+                .layer(TraceIdLayer::with_config(config));
+
+            let request = Request::builder()
+                .uri("/")
+                .header(TRACESTATE_HEADER, tracestate)
+                .body(Body::empty())
+                .unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(
+                response.headers().get(TRACESTATE_HEADER).unwrap(),
+                tracestate
+            );
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert_eq!(&body[..], tracestate.as_bytes());
+
+            // 默认配置下不读取也不回写 tracestate
+            let app = Router::new()
+                .route("/", get(state_handler))
+                .layer(TraceIdLayer::new());
+            let request = Request::builder()
+                .uri("/")
+                .header(TRACESTATE_HEADER, tracestate)
+                .body(Body::empty())
+                .unwrap();
+            let response = app.oneshot(request).await.unwrap();
+            assert!(response.headers().get(TRACESTATE_HEADER).is_none());
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert!(body.is_empty());
+        }
     }
 }
@@ -0,0 +1,197 @@
+//! B3（Zipkin）头部传播支持
+//!
+//! 支持 B3 单头部格式（`b3: {trace-id}-{span-id}-{sampled}-{parent-span-id}`）
+//! 与多头部格式（`X-B3-TraceId`、`X-B3-SpanId`、`X-B3-Sampled`）。
+//! B3 的 trace-id 可以是64位（16个十六进制字符）或128位（32个十六进制字符），
+//! 64位的情况会在左侧补零扩展为32个字符。
+
+use crate::{trace_context::SpanId, trace_id::TraceId};
+use http::{HeaderMap, HeaderValue};
+
+/// B3 单头部名称
+pub const B3_SINGLE_HEADER: &str = "b3";
+
+/// B3 多头部格式：追踪ID头部名称
+pub const B3_TRACE_ID_HEADER: &str = "x-b3-traceid";
+
+/// B3 多头部格式：跨度ID头部名称
+pub const B3_SPAN_ID_HEADER: &str = "x-b3-spanid";
+
+/// B3 多头部格式：采样标志头部名称
+pub const B3_SAMPLED_HEADER: &str = "x-b3-sampled";
+
+/// 从请求头中提取 B3 追踪ID
+///
+/// 优先读取单头部 `b3`，不存在或无效时再读取多头部 `X-B3-TraceId`
+///
+/// # 参数
+/// * `headers` - 请求头
+///
+/// # 返回
+/// 如果存在有效的 B3 追踪ID则返回Some(TraceId)，否则返回None
+pub fn extract(headers: &HeaderMap) -> Option<TraceId> {
+    extract_single(headers).or_else(|| extract_multi(headers))
+}
+
+/// 从单头部 `b3` 中提取追踪ID
+///
+/// 仅包含采样标志的单头部（如 `b3: 0`）不携带追踪ID，返回None
+pub fn extract_single(headers: &HeaderMap) -> Option<TraceId> {
+    let value = headers.get(B3_SINGLE_HEADER)?.to_str().ok()?;
+    let (trace_id, _) = value.split_once('-')?;
+    parse_trace_id(trace_id)
+}
+
+/// 从多头部 `X-B3-TraceId` 中提取追踪ID
+pub fn extract_multi(headers: &HeaderMap) -> Option<TraceId> {
+    let value = headers.get(B3_TRACE_ID_HEADER)?.to_str().ok()?;
+    parse_trace_id(value)
+}
+
+/// 将追踪信息以多头部格式写入请求头
+///
+/// # 参数
+/// * `headers` - 要写入的请求头
+/// * `trace_id` - 追踪ID（以128位形式写入）
+/// * `span_id` - 当前跨度ID
+/// * `sampled` - 采样决策，None表示交由下游决定（不写入 `X-B3-Sampled`）
+pub fn inject_multi(
+    headers: &mut HeaderMap,
+    trace_id: &TraceId,
+    span_id: &SpanId,
+    sampled: Option<bool>,
+) {
+    headers.insert(B3_TRACE_ID_HEADER, trace_id.to_header_value());
+    if let Ok(value) = HeaderValue::from_str(span_id.as_str()) {
+        headers.insert(B3_SPAN_ID_HEADER, value);
+    }
+    if let Some(sampled) = sampled {
+        headers.insert(
+            B3_SAMPLED_HEADER,
+            HeaderValue::from_static(if sampled { "1" } else { "0" }),
+        );
+    }
+}
+
+/// 将追踪信息以单头部格式写入请求头
+///
+/// # 参数
+/// * `headers` - 要写入的请求头
+/// * `trace_id` - 追踪ID（以128位形式写入）
+/// * `span_id` - 当前跨度ID
+/// * `sampled` - 采样决策，None表示交由下游决定（省略采样段）
+pub fn inject_single(
+    headers: &mut HeaderMap,
+    trace_id: &TraceId,
+    span_id: &SpanId,
+    sampled: Option<bool>,
+) {
+    let value = match sampled {
+        Some(sampled) => format!("{trace_id}-{span_id}-{}", if sampled { 1 } else { 0 }),
+        None => format!("{trace_id}-{span_id}"),
+    };
+    if let Ok(value) = HeaderValue::from_str(&value) {
+        headers.insert(B3_SINGLE_HEADER, value);
+    }
+}
+
+/// 解析 B3 追踪ID
+///
+/// 接受16或32个小写十六进制字符，16字符时左侧补零扩展为128位
+fn parse_trace_id(value: &str) -> Option<TraceId> {
+    match value.len() {
+        32 => TraceId::from_string_validated(value),
+        16 => TraceId::from_string_validated(&format!("{:0>32}", value)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_multi_128bit() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            B3_TRACE_ID_HEADER,
+            HeaderValue::from_static("0af7651916cd43dd8448eb211c80319c"),
+        );
+        let trace_id = extract(&headers).expect("应提取到追踪ID");
+        assert_eq!(trace_id.as_str(), "0af7651916cd43dd8448eb211c80319c");
+    }
+
+    #[test]
+    fn test_extract_multi_64bit_padded() {
+        let mut headers = HeaderMap::new();
+        // 头部名称大小写不敏感
+        headers.insert("X-B3-TraceId", HeaderValue::from_static("8448eb211c80319c"));
+        let trace_id = extract(&headers).expect("应提取到追踪ID");
+        assert_eq!(trace_id.as_str(), "00000000000000008448eb211c80319c");
+    }
+
+    #[test]
+    fn test_extract_single() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            B3_SINGLE_HEADER,
+            HeaderValue::from_static(
+                "80f198ee56343ba864fe8b2a57d3eff7-e457b5a2e4d86bd1-1-05e3ac9a4f6e3b90",
+            ),
+        );
+        let trace_id = extract(&headers).expect("应提取到追踪ID");
+        assert_eq!(trace_id.as_str(), "80f198ee56343ba864fe8b2a57d3eff7");
+
+        // 单头部优先于多头部
+        headers.insert(
+            B3_TRACE_ID_HEADER,
+            HeaderValue::from_static("0af7651916cd43dd8448eb211c80319c"),
+        );
+        assert_eq!(
+            extract(&headers).unwrap().as_str(),
+            "80f198ee56343ba864fe8b2a57d3eff7"
+        );
+    }
+
+    #[test]
+    fn test_extract_invalid() {
+        let invalid = [
+            "",
+            "0",
+            "8448eb211c80319",
+            "0000000000000000",
+            "0AF7651916CD43DD8448EB211C80319C",
+            "0af7651916cd43dd8448eb211c80319g",
+        ];
+        for value in invalid {
+            let mut headers = HeaderMap::new();
+            headers.insert(B3_TRACE_ID_HEADER, HeaderValue::from_str(value).unwrap());
+            assert!(extract(&headers).is_none(), "应拒绝: {value}");
+        }
+
+        // 仅包含采样标志的单头部
+        let mut headers = HeaderMap::new();
+        headers.insert(B3_SINGLE_HEADER, HeaderValue::from_static("0"));
+        assert!(extract(&headers).is_none());
+    }
+
+    #[test]
+    fn test_inject_roundtrip() {
+        let trace_id = TraceId::new();
+        let span_id = SpanId::new();
+
+        let mut headers = HeaderMap::new();
+        inject_multi(&mut headers, &trace_id, &span_id, Some(true));
+        assert_eq!(extract_multi(&headers), Some(trace_id));
+        assert_eq!(headers.get(B3_SPAN_ID_HEADER).unwrap(), span_id.as_str());
+        assert_eq!(headers.get(B3_SAMPLED_HEADER).unwrap(), "1");
+
+        let mut headers = HeaderMap::new();
+        inject_single(&mut headers, &trace_id, &span_id, None);
+        assert_eq!(extract_single(&headers), Some(trace_id));
+        assert_eq!(
+            headers.get(B3_SINGLE_HEADER).unwrap(),
+            format!("{trace_id}-{span_id}").as_str()
+        );
+    }
+}
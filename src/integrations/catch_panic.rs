@@ -0,0 +1,200 @@
+//! 在追踪上下文中捕获处理器 panic
+//!
+//! `TraceIdLayer` 只能为内部服务返回的响应添加追踪ID头部。外部的 panic 捕获层
+//! （如 `tower_http::catch_panic::CatchPanicLayer`）位于 `TraceIdLayer` 之上时，
+//! panic 会越过 `TraceIdService`，生成的 500 响应中便没有追踪ID。
+//!
+//! `TraceIdLayer::with_catch_panic()` 在 `TraceIdService` 内部捕获 panic 并转换为
+//! `500 Internal Server Error`，该响应与普通响应一样在追踪上下文中添加头部，panic 日志也会带有追踪ID。
+
+use crate::integrations::tower::{TraceIdLayer, TraceIdService};
+use http::{Request, Response, StatusCode};
+use std::{
+    any::Any,
+    future::Future,
+    panic::{catch_unwind, AssertUnwindSafe},
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+/// 捕获内部服务 panic 的追踪ID层，由 `TraceIdLayer::with_catch_panic()` 创建
+#[derive(Clone)]
+pub struct CatchPanicLayer {
+    layer: TraceIdLayer,
+}
+
+impl TraceIdLayer {
+    /// 在追踪上下文中捕获内部服务的 panic，并转换为带有追踪ID头部的 500 响应
+    ///
+    /// 内部服务返回的错误（`Err`）不会被转换，原样向外传递。
+    /// 响应体使用 `Default::default()` 构造，因此要求响应体类型实现 `Default`
+    /// （axum、warp、tonic 的响应体均满足）
+    ///
+    /// # 示例
+    /// ```
+    /// use trace_id::TraceIdLayer;
+    ///
+    /// let layer = TraceIdLayer::new().with_catch_panic();
+    /// ```
+    pub fn with_catch_panic(self) -> CatchPanicLayer {
+        CatchPanicLayer { layer: self }
+    }
+}
+
+impl<S> Layer<S> for CatchPanicLayer {
+    type Service = TraceIdService<CatchPanic<S>>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        self.layer.layer(CatchPanic { inner })
+    }
+}
+
+/// 将 panic 转换为 500 响应的服务，位于 `TraceIdService` 内部
+#[derive(Clone)]
+pub struct CatchPanic<S> {
+    inner: S,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for CatchPanic<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+    ResBody: Default + Send,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = CatchPanicFuture<S::Future, ResBody>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        // 同步部分的 panic 同样需要捕获
+        match catch_unwind(AssertUnwindSafe(|| self.inner.call(req))) {
+            Ok(future) => CatchPanicFuture::Inner(Box::pin(future)),
+            Err(payload) => CatchPanicFuture::Panicked(Some(panic_response(payload))),
+        }
+    }
+}
+
+/// `CatchPanic` 的响应 future
+pub enum CatchPanicFuture<F, B> {
+    /// 内部服务的 future
+    Inner(Pin<Box<F>>),
+    /// 调用内部服务时已发生 panic
+    Panicked(Option<Response<B>>),
+}
+
+impl<F, B, E> Future for CatchPanicFuture<F, B>
+where
+    F: Future<Output = Result<Response<B>, E>>,
+    B: Default,
+{
+    type Output = Result<Response<B>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // 内部 future 已装箱，`CatchPanicFuture` 本身不依赖固定地址
+        match self.get_mut() {
+            CatchPanicFuture::Inner(future) => {
+                match catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(cx))) {
+                    Ok(poll) => poll,
+                    Err(payload) => Poll::Ready(Ok(panic_response(payload))),
+                }
+            }
+            CatchPanicFuture::Panicked(response) => Poll::Ready(Ok(response
+                .take()
+                .expect("CatchPanicFuture polled after completion"))),
+        }
+    }
+}
+
+impl<F, B> Unpin for CatchPanicFuture<F, B> {}
+
+/// 记录 panic 信息并构造 500 响应
+fn panic_response<B: Default>(payload: Box<dyn Any + Send>) -> Response<B> {
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic");
+    tracing::error!(panic = message, "request handler panicked");
+
+    let mut response = Response::new(B::default());
+    *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{context, TRACE_ID_HEADER};
+    use std::convert::Infallible;
+    use tower::{service_fn, ServiceExt};
+
+    const VALID_ID: &str = "0af7651916cd43dd8448eb211c80319c";
+
+    fn request() -> Request<()> {
+        Request::builder()
+            .header(TRACE_ID_HEADER, VALID_ID)
+            .body(())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_panic_in_future() {
+        let service = TraceIdLayer::new().with_catch_panic().layer(service_fn(
+            |_req: Request<()>| async move {
+                // panic 发生在追踪上下文中
+                assert_eq!(context::get_trace_id().as_str(), VALID_ID);
+                if true {
+                    panic!("handler failed");
+                }
+                Ok::<_, Infallible>(Response::new(String::new()))
+            },
+        ));
+
+        let response = service.oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(response.headers().get(TRACE_ID_HEADER).unwrap(), VALID_ID);
+    }
+
+    #[derive(Clone)]
+    struct PanicOnCall;
+
+    impl Service<Request<()>> for PanicOnCall {
+        type Response = Response<String>;
+        type Error = Infallible;
+        type Future = std::future::Ready<Result<Response<String>, Infallible>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _req: Request<()>) -> Self::Future {
+            panic!("call failed")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_panic_in_call() {
+        let service = TraceIdLayer::new().with_catch_panic().layer(PanicOnCall);
+
+        let response = service.oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(response.headers().get(TRACE_ID_HEADER).unwrap(), VALID_ID);
+    }
+
+    #[tokio::test]
+    async fn test_normal_response_unchanged() {
+        let service = TraceIdLayer::new().with_catch_panic().layer(service_fn(
+            |_req: Request<()>| async move { Ok::<_, Infallible>(Response::new("ok".to_string())) },
+        ));
+
+        let response = service.oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get(TRACE_ID_HEADER).unwrap(), VALID_ID);
+        assert_eq!(response.into_body(), "ok");
+    }
+}
@@ -0,0 +1,170 @@
+//! Datadog 头部传播支持
+//!
+//! Datadog 追踪器以十进制的64位整数传播追踪ID（`x-datadog-trace-id`）与父级跨度ID
+//! （`x-datadog-parent-id`）。128位追踪ID的高64位以十六进制放在 `x-datadog-tags`
+//! 的 `_dd.p.tid` 标签中，缺失时高64位补零。
+
+use crate::{trace_context::SpanId, trace_id::TraceId};
+use http::{HeaderMap, HeaderValue};
+
+/// Datadog 追踪ID头部名称（十进制，低64位）
+pub const DATADOG_TRACE_ID_HEADER: &str = "x-datadog-trace-id";
+
+/// Datadog 父级跨度ID头部名称（十进制）
+pub const DATADOG_PARENT_ID_HEADER: &str = "x-datadog-parent-id";
+
+/// Datadog 传播标签头部名称（逗号分隔的 `key=value`）
+pub const DATADOG_TAGS_HEADER: &str = "x-datadog-tags";
+
+/// 携带128位追踪ID高64位的传播标签
+const TRACE_ID_HIGH_TAG: &str = "_dd.p.tid";
+
+/// 从请求头中提取 Datadog 追踪ID
+///
+/// 读取十进制的 `x-datadog-trace-id` 作为低64位；
+/// 存在有效的 `_dd.p.tid` 标签时将其作为高64位，否则高64位补零
+///
+/// # 参数
+/// * `headers` - 请求头
+///
+/// # 返回
+/// 如果存在有效（非零）的 Datadog 追踪ID则返回Some(TraceId)，否则返回None
+pub fn extract(headers: &HeaderMap) -> Option<TraceId> {
+    let low = parse_decimal(headers.get(DATADOG_TRACE_ID_HEADER)?.to_str().ok()?)?;
+    let high = headers
+        .get(DATADOG_TAGS_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(extract_trace_id_high)
+        .unwrap_or(0);
+    TraceId::from_u128(((high as u128) << 64) | low as u128)
+}
+
+/// 将追踪信息以 Datadog 格式写入请求头
+///
+/// 追踪ID的低64位以十进制写入 `x-datadog-trace-id`；
+/// 高64位非零时以 `_dd.p.tid` 标签写入 `x-datadog-tags`，以便下游还原完整的128位ID
+///
+/// # 参数
+/// * `headers` - 要写入的请求头
+/// * `trace_id` - 追踪ID
+/// * `span_id` - 当前跨度ID，以十进制写入 `x-datadog-parent-id`
+pub fn inject(headers: &mut HeaderMap, trace_id: &TraceId, span_id: &SpanId) {
+    let value = trace_id.to_u128();
+    headers.insert(DATADOG_TRACE_ID_HEADER, HeaderValue::from(value as u64));
+
+    if let Ok(parent_id) = u64::from_str_radix(span_id.as_str(), 16) {
+        headers.insert(DATADOG_PARENT_ID_HEADER, HeaderValue::from(parent_id));
+    }
+
+    let high = (value >> 64) as u64;
+    if high != 0 {
+        if let Ok(tags) = HeaderValue::from_str(&format!("{TRACE_ID_HIGH_TAG}={high:016x}")) {
+            headers.insert(DATADOG_TAGS_HEADER, tags);
+        }
+    }
+}
+
+/// 解析十进制的64位ID，零值视为无效
+fn parse_decimal(value: &str) -> Option<u64> {
+    if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok().filter(|&id| id != 0)
+}
+
+/// 从传播标签中读取 `_dd.p.tid`（16个小写十六进制字符）
+fn extract_trace_id_high(tags: &str) -> Option<u64> {
+    let (_, value) = tags
+        .split(',')
+        .filter_map(|tag| tag.trim().split_once('='))
+        .find(|(key, _)| *key == TRACE_ID_HIGH_TAG)?;
+    if value.len() != 16
+        || !value
+            .bytes()
+            .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+    {
+        return None;
+    }
+    u64::from_str_radix(value, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_64bit() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            DATADOG_TRACE_ID_HEADER,
+            HeaderValue::from_static("9532127138774266268"),
+        );
+        let trace_id = extract(&headers).expect("应提取到追踪ID");
+        assert_eq!(trace_id.as_str(), "00000000000000008448eb211c80319c");
+    }
+
+    #[test]
+    fn test_extract_128bit() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            DATADOG_TRACE_ID_HEADER,
+            HeaderValue::from_static("9532127138774266268"),
+        );
+        headers.insert(
+            DATADOG_TAGS_HEADER,
+            HeaderValue::from_static("_dd.p.dm=-1,_dd.p.tid=0af7651916cd43dd"),
+        );
+        let trace_id = extract(&headers).expect("应提取到追踪ID");
+        assert_eq!(trace_id.as_str(), "0af7651916cd43dd8448eb211c80319c");
+
+        // 无效的 _dd.p.tid 被忽略，高64位补零
+        headers.insert(
+            DATADOG_TAGS_HEADER,
+            HeaderValue::from_static("_dd.p.tid=0AF7651916CD43DD"),
+        );
+        assert_eq!(
+            extract(&headers).unwrap().as_str(),
+            "00000000000000008448eb211c80319c"
+        );
+    }
+
+    #[test]
+    fn test_extract_invalid() {
+        let invalid = ["", "0", "-1", "+1", "abc", "18446744073709551616", "1.5"];
+        for value in invalid {
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                DATADOG_TRACE_ID_HEADER,
+                HeaderValue::from_str(value).unwrap(),
+            );
+            assert!(extract(&headers).is_none(), "应拒绝: {value}");
+        }
+        assert!(extract(&HeaderMap::new()).is_none());
+    }
+
+    #[test]
+    fn test_inject_roundtrip() {
+        let span_id = SpanId::from_string_validated("00f067aa0ba902b7").unwrap();
+
+        // 64位追踪ID：不写入 _dd.p.tid
+        let trace_id = TraceId::from_u128(0x8448eb211c80319c).unwrap();
+        let mut headers = HeaderMap::new();
+        inject(&mut headers, &trace_id, &span_id);
+        assert_eq!(
+            headers.get(DATADOG_TRACE_ID_HEADER).unwrap(),
+            "9532127138774266268"
+        );
+        assert_eq!(
+            headers.get(DATADOG_PARENT_ID_HEADER).unwrap(),
+            "67667974448284343"
+        );
+        assert!(headers.get(DATADOG_TAGS_HEADER).is_none());
+        assert_eq!(extract(&headers), Some(trace_id));
+
+        // 128位追踪ID：通过 _dd.p.tid 完整往返
+        let trace_id = TraceId::new();
+        let mut headers = HeaderMap::new();
+        inject(&mut headers, &trace_id, &span_id);
+        assert_eq!(extract(&headers), Some(trace_id));
+    }
+}
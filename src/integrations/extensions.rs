@@ -0,0 +1,54 @@
+//! 从 `http` 扩展中读取追踪ID
+//!
+//! `TraceIdLayer` 在进入追踪上下文之前，把同一个追踪ID写入请求扩展。两份副本的值始终相同，
+//! 但可用的时机不同：
+//! - task_local（`get_trace_id()`）只在请求 future 被轮询期间有效，
+//!   下层服务同步执行的 `Service::call` 中无法读取
+//! - 请求扩展随请求对象传递，下层的 tower 层在 `call` 中、拆分为 `Parts` 后都能读取，
+//!   且不依赖 tokio 的 task_local
+//!
+//! 修改扩展中的副本不会影响 task_local 上下文，反之亦然。
+//! 设置 `TraceIdConfig::enable_extensions = false` 时不写入扩展，`trace_id()` 返回None。
+
+use crate::trace_id::TraceId;
+use http::{request::Parts, Extensions, Request};
+
+/// 读取 `TraceIdLayer` 写入请求扩展的追踪ID
+///
+/// # 示例
+/// ```
+/// use http::Request;
+/// use trace_id::{TraceId, TraceIdExt};
+///
+/// let mut request = Request::new(());
+/// assert!(request.trace_id().is_none());
+///
+/// let trace_id = TraceId::new();
+/// request.extensions_mut().insert(trace_id);
+/// assert_eq!(request.trace_id(), Some(&trace_id));
+/// ```
+pub trait TraceIdExt {
+    /// 获取请求扩展中的追踪ID
+    ///
+    /// # 返回
+    /// 请求经过 `TraceIdLayer` 时返回Some(&TraceId)，否则返回None
+    fn trace_id(&self) -> Option<&TraceId>;
+}
+
+impl TraceIdExt for Extensions {
+    fn trace_id(&self) -> Option<&TraceId> {
+        self.get::<TraceId>()
+    }
+}
+
+impl TraceIdExt for Parts {
+    fn trace_id(&self) -> Option<&TraceId> {
+        self.extensions.trace_id()
+    }
+}
+
+impl<B> TraceIdExt for Request<B> {
+    fn trace_id(&self) -> Option<&TraceId> {
+        self.extensions().trace_id()
+    }
+}
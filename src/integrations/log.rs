@@ -0,0 +1,180 @@
+//! `log` crate 集成
+//!
+//! 仍在使用 `log`（而非 `tracing`）的代码同样可以让日志携带追踪ID，无需迁移：
+//! - `TraceId` 实现了 `log::kv::ToValue`，可直接作为键值对记录：
+//!   `log::info!(trace_id = trace_id::get_trace_id(); "...")`
+//! - `current()` 以 `log::kv::Source` 的形式返回当前追踪ID，便于在自定义 `Log` 实现中使用
+//! - `TraceIdLogger` 包装任意 `Log`，为追踪上下文中的每条日志自动附加 `trace_id`（类似 MDC）
+
+use crate::{context, trace_id::TraceId};
+use ::log::{
+    kv::{Key, Source, ToValue, Value},
+    Log, Metadata, Record,
+};
+
+/// 追踪ID在日志键值对中使用的键名
+pub const TRACE_ID_KEY: &str = "trace_id";
+
+impl ToValue for TraceId {
+    fn to_value(&self) -> Value<'_> {
+        Value::from(self.as_str())
+    }
+}
+
+/// 获取当前追踪ID对应的日志键值对
+///
+/// 返回值实现了 `log::kv::Source`，不在追踪上下文中时为None（不产生任何键值对），
+/// 与 `get_trace_id_opt()` 一样不会生成新ID或记录日志
+///
+/// # 示例
+/// ```
+/// use log::kv::Source;
+///
+/// let kvs = trace_id::log::current();
+/// assert_eq!(kvs.count(), 0);
+/// ```
+pub fn current() -> Option<(&'static str, TraceId)> {
+    context::get_trace_id_opt().map(|trace_id| (TRACE_ID_KEY, trace_id))
+}
+
+/// 为每条日志附加当前追踪ID的 `Log` 包装器
+///
+/// 在追踪上下文中记录的日志会额外带有 `trace_id` 键值对；
+/// 日志中已包含 `trace_id` 或不在追踪上下文中时原样转发给内部 `Log`
+///
+/// # 示例
+/// ```
+/// use trace_id::log::TraceIdLogger;
+///
+/// struct StdoutLogger;
+///
+/// impl log::Log for StdoutLogger {
+///     fn enabled(&self, _: &log::Metadata) -> bool {
+///         true
+///     }
+///
+///     fn log(&self, record: &log::Record) {
+///         println!("{} {:?}", record.args(), record.key_values().get("trace_id".into()));
+///     }
+///
+///     fn flush(&self) {}
+/// }
+///
+/// log::set_boxed_logger(Box::new(TraceIdLogger::new(StdoutLogger))).unwrap();
+/// log::set_max_level(log::LevelFilter::Info);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct TraceIdLogger<L> {
+    inner: L,
+}
+
+impl<L> TraceIdLogger<L> {
+    /// 包装指定的 `Log` 实现
+    pub fn new(inner: L) -> Self {
+        Self { inner }
+    }
+
+    /// 获取内部的 `Log` 实现
+    pub fn into_inner(self) -> L {
+        self.inner
+    }
+}
+
+impl<L: Log> Log for TraceIdLogger<L> {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        let pair = match current() {
+            Some(pair)
+                if record
+                    .key_values()
+                    .get(Key::from_str(TRACE_ID_KEY))
+                    .is_none() =>
+            {
+                pair
+            }
+            _ => return self.inner.log(record),
+        };
+
+        let key_values: [&dyn Source; 2] = [record.key_values(), &pair];
+        self.inner
+            .log(&record.to_builder().key_values(&key_values).build());
+    }
+
+    fn flush(&self) {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// 记录每条日志 `trace_id` 键值对的测试用 `Log`
+    #[derive(Default)]
+    struct Capture(Mutex<Vec<Option<String>>>);
+
+    impl Log for Capture {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn log(&self, record: &Record<'_>) {
+            let trace_id = record
+                .key_values()
+                .get(Key::from_str(TRACE_ID_KEY))
+                .map(|value| value.to_string());
+            self.0.lock().unwrap().push(trace_id);
+        }
+
+        fn flush(&self) {}
+    }
+
+    #[test]
+    fn test_to_value() {
+        let trace_id = TraceId::from_string_validated("0af7651916cd43dd8448eb211c80319c").unwrap();
+        assert_eq!(
+            trace_id.to_value().to_borrowed_str(),
+            Some("0af7651916cd43dd8448eb211c80319c")
+        );
+
+        // 可直接用于 `log` 宏的键值对语法
+        ::log::info!(trace_id = trace_id; "with trace id");
+    }
+
+    #[tokio::test]
+    async fn test_logger_attaches_trace_id() {
+        let logger = TraceIdLogger::new(Capture::default());
+        let trace_id = TraceId::new();
+
+        // 不在追踪上下文中时原样转发
+        assert!(current().is_none());
+        logger.log(&Record::builder().args(format_args!("outside")).build());
+
+        context::with_trace_id(trace_id, async {
+            assert_eq!(current(), Some((TRACE_ID_KEY, trace_id)));
+            logger.log(&Record::builder().args(format_args!("inside")).build());
+
+            // 日志中已有的 trace_id 不会被覆盖
+            let explicit = [(TRACE_ID_KEY, "explicit")];
+            let record = Record::builder()
+                .args(format_args!("explicit"))
+                .key_values(&explicit)
+                .build();
+            logger.log(&record);
+        })
+        .await;
+
+        assert_eq!(
+            *logger.into_inner().0.lock().unwrap(),
+            [
+                None,
+                Some(trace_id.to_string()),
+                Some("explicit".to_string())
+            ]
+        );
+    }
+}
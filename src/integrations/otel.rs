@@ -0,0 +1,124 @@
+//! OpenTelemetry 桥接
+//!
+//! 将 `TraceId` 转换为 `opentelemetry::trace::TraceId`，并构造以其为远程父级的 OTel `Context`。
+//! 配合 `tracing-opentelemetry` 使用时，在该上下文中创建的根 span 会沿用同一追踪ID，
+//! 使导出到采集端的 span 与 HTTP 层的 `x-trace-id` 保持一致。
+
+use crate::{trace_context::TraceContext, trace_id::TraceId};
+use opentelemetry::{
+    trace::{
+        SpanContext, SpanId as OtelSpanId, TraceContextExt, TraceFlags, TraceId as OtelTraceId,
+        TraceState as OtelTraceState,
+    },
+    Context, ContextGuard,
+};
+
+/// 将追踪ID转换为 OpenTelemetry 追踪ID
+pub fn to_otel_trace_id(trace_id: &TraceId) -> OtelTraceId {
+    OtelTraceId::from_bytes(*trace_id.as_bytes())
+}
+
+/// 将 OpenTelemetry 追踪ID转换为追踪ID
+///
+/// # 返回
+/// 无效（全零）的 OTel 追踪ID返回None
+pub fn from_otel_trace_id(trace_id: OtelTraceId) -> Option<TraceId> {
+    TraceId::from_u128(u128::from_be_bytes(trace_id.to_bytes()))
+}
+
+/// 构造以指定追踪ID为远程父级的 OTel 上下文
+///
+/// 父级 span ID 随机生成，并标记为已采样
+///
+/// # 参数
+/// * `trace_id` - 追踪ID
+///
+/// # 返回
+/// 基于 `Context::current()` 派生的新上下文
+pub fn context_with_trace_id(trace_id: &TraceId) -> Context {
+    let span_id = OtelSpanId::from_bytes(crate::random::nonzero_u64().to_be_bytes());
+    remote_context(trace_id, span_id, TraceFlags::SAMPLED)
+}
+
+/// 构造以上游 W3C TraceContext 为远程父级的 OTel 上下文
+///
+/// 沿用上游的 parent-id 与 trace-flags，使导出的 span 正确挂接到上游调用链
+///
+/// # 参数
+/// * `ctx` - 从 `traceparent` 解析出的上下文
+///
+/// # 返回
+/// 基于 `Context::current()` 派生的新上下文
+pub fn context_with_trace_context(ctx: &TraceContext) -> Context {
+    let span_id = OtelSpanId::from_hex(ctx.parent_id.as_str()).unwrap_or(OtelSpanId::INVALID);
+    remote_context(&ctx.trace_id, span_id, TraceFlags::new(ctx.flags))
+}
+
+/// 将指定追踪ID设置为当前线程的活动 OTel 上下文
+///
+/// 返回的守卫被丢弃时恢复之前的上下文。守卫不能跨 `.await` 持有，
+/// 异步代码请使用 `opentelemetry::context::FutureExt::with_context`
+///
+/// # 参数
+/// * `trace_id` - 追踪ID
+pub fn attach(trace_id: &TraceId) -> ContextGuard {
+    context_with_trace_id(trace_id).attach()
+}
+
+fn remote_context(trace_id: &TraceId, span_id: OtelSpanId, flags: TraceFlags) -> Context {
+    let span_context = SpanContext::new(
+        to_otel_trace_id(trace_id),
+        span_id,
+        flags,
+        true,
+        OtelTraceState::default(),
+    );
+    Context::current().with_remote_span_context(span_context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace_context::SpanId;
+
+    #[test]
+    fn test_trace_id_conversion() {
+        let trace_id = TraceId::new();
+        let otel_id = to_otel_trace_id(&trace_id);
+        assert_eq!(otel_id.to_string(), trace_id.as_str());
+        assert_eq!(from_otel_trace_id(otel_id), Some(trace_id));
+        assert!(from_otel_trace_id(OtelTraceId::INVALID).is_none());
+    }
+
+    #[test]
+    fn test_attach() {
+        let trace_id = TraceId::new();
+        {
+            let _guard = attach(&trace_id);
+            let current = Context::current();
+            let span_context = current.span().span_context().clone();
+            assert_eq!(span_context.trace_id(), to_otel_trace_id(&trace_id));
+            assert!(span_context.is_valid());
+            assert!(span_context.is_remote());
+            assert!(span_context.is_sampled());
+        }
+        assert!(
+            !Context::current().has_active_span(),
+            "守卫丢弃后应恢复之前的上下文"
+        );
+    }
+
+    #[test]
+    fn test_context_with_trace_context() {
+        let ctx = TraceContext {
+            trace_id: TraceId::new(),
+            parent_id: SpanId::new(),
+            flags: 0,
+        };
+        let otel_cx = context_with_trace_context(&ctx);
+        let span_context = otel_cx.span().span_context().clone();
+        assert_eq!(span_context.trace_id(), to_otel_trace_id(&ctx.trace_id));
+        assert_eq!(span_context.span_id().to_string(), ctx.parent_id.as_str());
+        assert!(!span_context.is_sampled(), "应沿用上游的采样标志");
+    }
+}
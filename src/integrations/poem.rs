@@ -0,0 +1,427 @@
+//! poem 框架的追踪ID集成
+//!
+//! - `TraceIdMiddleware`：提取或生成追踪ID，在追踪上下文中执行处理器并添加响应头，
+//!   接受与 `TraceIdLayer` 相同的 `TraceIdConfig`，行为与 Axum 中间件层一致
+//! - `TraceId` 实现了 poem 的 `FromRequest`，处理器可直接将其作为参数
+
+#[cfg(feature = "otel")]
+use crate::integrations::otel;
+use crate::{
+    context,
+    integrations::tower::{
+        extract_correlation, extract_or_generate_trace_id, extract_trace_context,
+        extract_trace_state, record_latency, resolve_trace_id, response_time_value,
+        response_trace_id, response_traceparent, scope, ResponseLatency, SkipMode, TraceIdConfig,
+    },
+    trace_context::{SpanId, TraceState},
+    trace_id::TraceId,
+    RESPONSE_TIME_HEADER, TRACEPARENT_HEADER, TRACESTATE_HEADER,
+};
+use poem::{
+    http::StatusCode, Endpoint, FromRequest, Middleware, Request, RequestBody, Response, Result,
+};
+use std::time::Instant;
+use tracing::Instrument;
+
+/// poem 追踪ID中间件
+///
+/// # 示例
+/// ```
+/// use poem::{get, handler, EndpointExt, Route};
+/// use trace_id::{poem::TraceIdMiddleware, TraceId};
+///
+/// #[handler]
+/// fn index(trace_id: TraceId) -> String {
+///     format!("Your trace ID is: {trace_id}")
+/// }
+///
+/// let app = Route::new().at("/", get(index)).with(TraceIdMiddleware::new());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct TraceIdMiddleware {
+    config: TraceIdConfig,
+}
+
+impl TraceIdMiddleware {
+    /// 使用默认配置创建中间件
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 使用自定义配置创建中间件
+    ///
+    /// # 参数
+    /// * `config` - 与 `TraceIdLayer::with_config` 相同的配置
+    pub fn with_config(config: TraceIdConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for TraceIdMiddleware {
+    type Output = TraceIdEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        TraceIdEndpoint {
+            inner: ep,
+            config: self.config.clone(),
+        }
+    }
+}
+
+/// 由 `TraceIdMiddleware` 包装后的端点
+pub struct TraceIdEndpoint<E> {
+    inner: E,
+    config: TraceIdConfig,
+}
+
+impl<E: Endpoint> Endpoint for TraceIdEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let config = &self.config;
+
+        // 根据配置从收到请求开始计时
+        let start = config.measures_latency().then(Instant::now);
+
+        // 健康检查等路径按 `skip_mode` 跳过追踪，完全跳过时直接执行后续处理
+        let skipped = config.is_skipped_path(req.uri().path());
+        if skipped && config.skip_mode == SkipMode::Bypass {
+            return Ok(self.inner.get_response(req).await);
+        }
+
+        // 根据配置解析上游的 traceparent 与 tracestate
+        let trace_context = if config.enable_traceparent {
+            extract_trace_context(req.headers())
+        } else {
+            None
+        };
+        let trace_state = if config.enable_tracestate {
+            extract_trace_state(req.headers())
+        } else {
+            None
+        };
+
+        let correlation = extract_correlation(req.headers(), config);
+        let Some(trace_id) =
+            resolve_trace_id(req.headers(), trace_context.as_ref(), config, None, None)
+        else {
+            // 按 `MissingIdPolicy::Reject` 拒绝请求，不调用内部端点
+            return Ok(StatusCode::BAD_REQUEST.into());
+        };
+        if config.enable_extensions {
+            req.extensions_mut().insert(trace_id);
+        }
+
+        #[cfg(feature = "otel")]
+        let otel_cx = config.enable_otel.then(|| match &trace_context {
+            Some(ctx) => otel::context_with_trace_context(ctx),
+            None => otel::context_with_trace_id(&trace_id),
+        });
+
+        let span_id = SpanId::new();
+
+        let span = if config.enable_span && !skipped {
+            #[cfg(feature = "otel")]
+            let _guard = otel_cx.clone().map(opentelemetry::Context::attach);
+            Some(tracing::info_span!(
+                "request",
+                trace_id = %trace_id.as_str(),
+                span_id = %span_id.as_str(),
+                method = %req.method(),
+                uri = %req.uri(),
+                latency_ms = tracing::field::Empty
+            ))
+        } else {
+            None
+        };
+        let latency_span = span.clone().filter(|_| config.record_latency);
+
+        let echo_state = if config.echo_tracestate {
+            trace_state.as_ref().map(TraceState::to_header)
+        } else {
+            None
+        };
+        let traceparent =
+            response_traceparent(config, trace_id, &span_id, trace_context.as_ref(), None);
+
+        let handle = async move {
+            // 错误同样转换为响应，保证错误响应也带有追踪ID头部
+            let mut response = self.inner.get_response(req).await;
+
+            // 内部端点返回响应时结束计时
+            if let Some(start) = start {
+                let latency = start.elapsed();
+                if config.record_latency {
+                    if let Some(span) = &latency_span {
+                        record_latency(span, latency);
+                    }
+                    response.extensions_mut().insert(ResponseLatency(latency));
+                }
+                if config.latency_header {
+                    response
+                        .headers_mut()
+                        .insert(RESPONSE_TIME_HEADER, response_time_value(latency));
+                }
+            }
+
+            if config.enable_response_header {
+                response.headers_mut().insert(
+                    config.response_header().clone(),
+                    response_trace_id(trace_id).to_header_value(),
+                );
+            }
+
+            if let Some(Ok(header_value)) = traceparent.map(|header| header.parse()) {
+                response
+                    .headers_mut()
+                    .insert(TRACEPARENT_HEADER, header_value);
+            }
+
+            if let Some(Ok(header_value)) = echo_state.map(|state| state.parse()) {
+                response
+                    .headers_mut()
+                    .insert(TRACESTATE_HEADER, header_value);
+            }
+
+            Ok(response)
+        };
+
+        #[cfg(feature = "otel")]
+        let handle = crate::integrations::tower::with_otel_context(handle, otel_cx);
+
+        // 嵌套的上下文作用域会多次复制内部 future，先装箱以避免调用栈上出现过大的 future
+        let handle = Box::pin(handle);
+
+        match span {
+            Some(span) => {
+                scope(
+                    trace_id,
+                    trace_context,
+                    trace_state,
+                    None,
+                    Some(span_id),
+                    correlation,
+                    handle,
+                )
+                .instrument(span)
+                .await
+            }
+            None => {
+                scope(
+                    trace_id,
+                    trace_context,
+                    trace_state,
+                    None,
+                    Some(span_id),
+                    correlation,
+                    handle,
+                )
+                .await
+            }
+        }
+    }
+}
+
+/// poem 提取器，用于在处理器参数中直接获取 TraceId
+///
+/// 经过 `TraceIdMiddleware` 时使用中间件确定的追踪ID；否则使用当前追踪上下文，
+/// 都不存在时按默认配置从请求头提取或生成。该提取器永远不会失败。
+impl<'a> FromRequest<'a> for TraceId {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(req
+            .extensions()
+            .get::<TraceId>()
+            .copied()
+            .or_else(context::get_trace_id_opt)
+            .or_else(|| {
+                extract_or_generate_trace_id(req.headers(), &TraceIdConfig::default(), None)
+            })
+            .unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TRACEPARENT_HEADER, TRACE_ID_HEADER};
+    use poem::{get, handler, http::StatusCode, test::TestClient, EndpointExt, Route};
+
+    const VALID_ID: &str = "0af7651916cd43dd8448eb211c80319c";
+
+    #[handler]
+    fn echo(trace_id: TraceId) -> String {
+        assert_eq!(trace_id, context::get_trace_id());
+        trace_id.to_string()
+    }
+
+    #[handler]
+    fn fail() -> Result<String> {
+        Err(poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR))
+    }
+
+    #[tokio::test]
+    async fn test_middleware_propagates_trace_id() {
+        let app = Route::new()
+            .at("/", get(echo))
+            .with(TraceIdMiddleware::new());
+        let client = TestClient::new(app);
+
+        let response = client
+            .get("/")
+            .header(TRACE_ID_HEADER, VALID_ID)
+            .send()
+            .await;
+        response.assert_status_is_ok();
+        response.assert_header(TRACE_ID_HEADER, VALID_ID);
+        response.assert_text(VALID_ID).await;
+
+        // 缺失时生成新的ID，响应头与处理器中的ID一致
+        let response = client.get("/").send().await;
+        let header = response.0.headers().get(TRACE_ID_HEADER).unwrap().clone();
+        let body = response.0.into_body().into_string().await.unwrap();
+        assert_eq!(header, body.as_str());
+        assert!(TraceId::from_string_validated(&body).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_middleware_config() {
+        let config = TraceIdConfig {
+            enable_traceparent: true,
+            enable_response_header: false,
+            ..TraceIdConfig::default()
+        };
+        let app = Route::new()
+            .at("/", get(echo))
+            .with(TraceIdMiddleware::with_config(config));
+        let response = TestClient::new(app)
+            .get("/")
+            .header(
+                TRACEPARENT_HEADER,
+                "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            )
+            .send()
+            .await;
+        response.assert_header_is_not_exist(TRACE_ID_HEADER);
+        response
+            .assert_text("4bf92f3577b34da6a3ce929d0e0e4736")
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_response_header_name() {
+        let config = TraceIdConfig::builder()
+            .header_name(poem::http::HeaderName::from_static("x-request-id"))
+            .response_header_name(poem::http::HeaderName::from_static(TRACE_ID_HEADER))
+            .build();
+        let app = Route::new()
+            .at("/", get(echo))
+            .with(TraceIdMiddleware::with_config(config));
+        let response = TestClient::new(app)
+            .get("/")
+            .header("x-request-id", VALID_ID)
+            .send()
+            .await;
+        response.assert_header(TRACE_ID_HEADER, VALID_ID);
+        response.assert_header_is_not_exist("x-request-id");
+    }
+
+    #[tokio::test]
+    async fn test_missing_id_policy_reject() {
+        let config = TraceIdConfig::builder()
+            .missing_id_policy(crate::MissingIdPolicy::Reject)
+            .build();
+        let app = Route::new()
+            .at("/", get(echo))
+            .with(TraceIdMiddleware::with_config(config));
+        let client = TestClient::new(app);
+
+        let response = client.get("/").send().await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        response.assert_header_is_not_exist(TRACE_ID_HEADER);
+
+        let response = client
+            .get("/")
+            .header(TRACE_ID_HEADER, VALID_ID)
+            .send()
+            .await;
+        response.assert_status_is_ok();
+        response.assert_text(VALID_ID).await;
+    }
+
+    #[tokio::test]
+    async fn test_skip_paths() {
+        #[handler]
+        fn has_context() -> String {
+            context::get_trace_id_opt().is_some().to_string()
+        }
+
+        let config = TraceIdConfig::builder().skip_paths(["/healthz"]).build();
+        let app = Route::new()
+            .at("/healthz", get(has_context))
+            .at("/", get(echo))
+            .with(TraceIdMiddleware::with_config(config));
+        let client = TestClient::new(app);
+
+        let response = client.get("/healthz").send().await;
+        response.assert_status_is_ok();
+        response.assert_header_is_not_exist(TRACE_ID_HEADER);
+        response.assert_text("false").await;
+
+        client
+            .get("/")
+            .send()
+            .await
+            .assert_header_exist(TRACE_ID_HEADER);
+    }
+
+    #[tokio::test]
+    async fn test_record_latency() {
+        let config = TraceIdConfig::builder()
+            .record_latency(true)
+            .latency_header(true)
+            .build();
+        let app = Route::new()
+            .at("/", get(echo))
+            .with(TraceIdMiddleware::with_config(config));
+        let response = TestClient::new(app).get("/").send().await;
+        response.assert_status_is_ok();
+        let header = response.0.headers().get(RESPONSE_TIME_HEADER).unwrap();
+        assert!(header.to_str().unwrap().ends_with("ms"));
+
+        let app = Route::new()
+            .at("/", get(echo))
+            .with(TraceIdMiddleware::new());
+        let response = TestClient::new(app).get("/").send().await;
+        response.assert_header_is_not_exist(RESPONSE_TIME_HEADER);
+    }
+
+    #[tokio::test]
+    async fn test_error_response_has_header() {
+        let app = Route::new()
+            .at("/fail", get(fail))
+            .with(TraceIdMiddleware::new());
+        let response = TestClient::new(app)
+            .get("/fail")
+            .header(TRACE_ID_HEADER, VALID_ID)
+            .send()
+            .await;
+        response.assert_status(StatusCode::INTERNAL_SERVER_ERROR);
+        response.assert_header(TRACE_ID_HEADER, VALID_ID);
+    }
+
+    #[tokio::test]
+    async fn test_extractor_without_middleware() {
+        let app = Route::new().at("/", get(echo_without_context));
+        let response = TestClient::new(app)
+            .get("/")
+            .header(TRACE_ID_HEADER, VALID_ID)
+            .send()
+            .await;
+        response.assert_text(VALID_ID).await;
+    }
+
+    #[handler]
+    fn echo_without_context(trace_id: TraceId) -> String {
+        trace_id.to_string()
+    }
+}
@@ -0,0 +1,248 @@
+//! reqwest 客户端的追踪ID传播中间件
+//!
+//! 基于 `reqwest-middleware`，自动将当前异步任务的追踪ID写入每个出站请求的请求头。
+//! 启用 `with_traceparent()` 后同时写入 W3C `traceparent`，以本服务的跨度ID作为下游的 parent-id。
+
+use crate::{context, TRACEPARENT_HEADER, TRACE_ID_HEADER};
+use http::{Extensions, HeaderName};
+use reqwest_middleware::{
+    reqwest::{Request, Response},
+    Middleware, Next, Result,
+};
+
+/// 出站请求追踪ID传播中间件
+///
+/// 在追踪上下文中发起的请求会自动携带当前追踪ID；
+/// 不在追踪上下文中时不做任何处理，不会生成新的追踪ID。
+/// 已显式设置同名请求头的请求保持不变。
+///
+/// # 示例
+/// ```no_run
+/// use reqwest_middleware::ClientBuilder;
+/// use trace_id::TraceIdMiddleware;
+///
+/// let client = ClientBuilder::new(reqwest_middleware::reqwest::Client::new())
+///     .with(TraceIdMiddleware::new())
+///     .build();
+/// ```
+#[derive(Clone, Debug)]
+pub struct TraceIdMiddleware {
+    header_name: HeaderName,
+    traceparent: bool,
+}
+
+impl TraceIdMiddleware {
+    /// 创建新的传播中间件，使用默认的 `x-trace-id` 请求头
+    pub fn new() -> Self {
+        Self {
+            header_name: HeaderName::from_static(TRACE_ID_HEADER),
+            traceparent: false,
+        }
+    }
+
+    /// 设置写入追踪ID所使用的请求头名称
+    ///
+    /// # 参数
+    /// * `name` - 头部名称（例如 `x-request-id`）
+    pub fn with_header_name(mut self, name: impl Into<HeaderName>) -> Self {
+        self.header_name = name.into();
+        self
+    }
+
+    /// 同时写入 W3C `traceparent` 请求头
+    ///
+    /// 每一跳沿用入站的 trace-id，parent-id 为服务端中间件为当前请求生成的跨度ID
+    /// （`get_span_id()`），trace-flags 沿用采样决策，下游据此还原正确的调用树。
+    /// 与 `inject_traceparent()` 写入的值相同；已显式设置 `traceparent` 的请求保持不变
+    ///
+    /// # 示例
+    /// ```no_run
+    /// use reqwest_middleware::ClientBuilder;
+    /// use trace_id::TraceIdMiddleware;
+    ///
+    /// let client = ClientBuilder::new(reqwest_middleware::reqwest::Client::new())
+    ///     .with(TraceIdMiddleware::new().with_traceparent())
+    ///     .build();
+    /// ```
+    pub fn with_traceparent(mut self) -> Self {
+        self.traceparent = true;
+        self
+    }
+}
+
+impl Default for TraceIdMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait::async_trait]
+impl Middleware for TraceIdMiddleware {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        if let Some(trace_id) = context::get_trace_id_opt() {
+            if !req.headers().contains_key(&self.header_name) {
+                req.headers_mut()
+                    .insert(self.header_name.clone(), trace_id.to_header_value());
+            }
+
+            if self.traceparent && !req.headers().contains_key(TRACEPARENT_HEADER) {
+                let ctx = context::outbound_trace_context(trace_id);
+                if let Ok(value) = ctx.to_header().parse() {
+                    req.headers_mut().insert(TRACEPARENT_HEADER, value);
+                }
+            }
+        }
+
+        next.run(req, extensions).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace_id::TraceId;
+    use reqwest_middleware::{reqwest, ClientBuilder, ClientWithMiddleware};
+
+    /// 不实际发送请求，而是将收到的请求头回显为响应体
+    struct EchoHeader(HeaderName);
+
+    #[async_trait::async_trait]
+    impl Middleware for EchoHeader {
+        async fn handle(
+            &self,
+            req: Request,
+            _extensions: &mut Extensions,
+            _next: Next<'_>,
+        ) -> Result<Response> {
+            let value = req
+                .headers()
+                .get(&self.0)
+                .map(|v| v.to_str().unwrap().to_string())
+                .unwrap_or_default();
+            Ok(Response::from(http::Response::new(value)))
+        }
+    }
+
+    fn client(middleware: TraceIdMiddleware, echo: &'static str) -> ClientWithMiddleware {
+        ClientBuilder::new(reqwest::Client::new())
+            .with(middleware)
+            .with(EchoHeader(HeaderName::from_static(echo)))
+            .build()
+    }
+
+    #[tokio::test]
+    async fn test_inject_current_trace_id() {
+        let client = client(TraceIdMiddleware::new(), TRACE_ID_HEADER);
+        let trace_id = TraceId::new();
+
+        let body = context::with_trace_id(trace_id, async {
+            client
+                .get("http://localhost/")
+                .send()
+                .await
+                .unwrap()
+                .text()
+                .await
+                .unwrap()
+        })
+        .await;
+        assert_eq!(body, trace_id.as_str());
+    }
+
+    #[tokio::test]
+    async fn test_no_context_is_noop() {
+        let client = client(TraceIdMiddleware::new(), TRACE_ID_HEADER);
+        let body = client
+            .get("http://localhost/")
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(body.is_empty(), "不在追踪上下文中时不应写入追踪ID");
+    }
+
+    #[tokio::test]
+    async fn test_explicit_header_and_custom_name() {
+        let middleware =
+            TraceIdMiddleware::new().with_header_name(HeaderName::from_static("x-request-id"));
+        let client = client(middleware, "x-request-id");
+
+        context::with_trace_id(TraceId::new(), async {
+            // 显式设置的请求头保持不变
+            let body = client
+                .get("http://localhost/")
+                .header("x-request-id", "explicit")
+                .send()
+                .await
+                .unwrap()
+                .text()
+                .await
+                .unwrap();
+            assert_eq!(body, "explicit");
+
+            // 使用自定义头部名称写入
+            let body = client
+                .get("http://localhost/")
+                .send()
+                .await
+                .unwrap()
+                .text()
+                .await
+                .unwrap();
+            assert_eq!(body, crate::context::get_trace_id().as_str());
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_traceparent_uses_current_span_as_parent() {
+        use crate::{SpanId, TraceContext};
+
+        let upstream =
+            TraceContext::parse("00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-00").unwrap();
+        let span_id = SpanId::new();
+        let send = |client: ClientWithMiddleware| async move {
+            client
+                .get("http://localhost/")
+                .send()
+                .await
+                .unwrap()
+                .text()
+                .await
+                .unwrap()
+        };
+
+        // 默认不写入 traceparent
+        let body = context::with_trace_context(
+            upstream.clone(),
+            send(client(TraceIdMiddleware::new(), TRACEPARENT_HEADER)),
+        )
+        .await;
+        assert!(body.is_empty());
+
+        // 沿用 trace-id 与 flags，parent-id 替换为本服务的跨度ID
+        let client = client(
+            TraceIdMiddleware::new().with_traceparent(),
+            TRACEPARENT_HEADER,
+        );
+        let body = context::with_trace_context(
+            upstream.clone(),
+            context::with_span_id(span_id.clone(), send(client.clone())),
+        )
+        .await;
+        let outbound = TraceContext::parse(&body).unwrap();
+        assert_eq!(outbound.trace_id, upstream.trace_id);
+        assert_eq!(outbound.parent_id, span_id);
+        assert_eq!(outbound.flags, upstream.flags);
+
+        // 不在追踪上下文中时不写入
+        assert!(send(client).await.is_empty());
+    }
+}
@@ -0,0 +1,510 @@
+//! rocket 框架的追踪ID集成
+//!
+//! - `TraceIdFairing`：在请求到达时提取或生成追踪ID并保存到请求本地缓存，在响应时添加响应头，
+//!   接受与 `TraceIdLayer` 相同的 `TraceIdConfig`
+//! - `TraceId` 实现了 rocket 的 `FromRequest`，处理器可直接将其作为参数
+//! - `TraceScope` 请求守卫携带本次请求完整的追踪上下文，`TraceScope::run` 在其中执行异步操作
+//!
+//! # 上下文传播
+//!
+//! rocket 的 fairing 只在处理器执行前后回调，无法像其他框架的中间件那样包装处理器的 future，
+//! 因此 fairing 不会建立追踪上下文：解析结果保存在请求本地缓存中，由请求守卫读取。
+//! 处理器中需要 `get_trace_id()`（如日志、出站请求）的代码应以 `TraceScope::run` 包装，
+//! 它与其他框架的中间件一样设置追踪ID、上游 `traceparent`、跨度ID与关联字段；
+//! 只需要追踪ID时也可以直接使用 `with_trace_id`。
+//!
+//! 同样由于无法包装处理器，`enable_span` 不会生效（不创建 `request` span），
+//! `SkipMode::SpanOnly` 因此与不跳过相同；`record_latency` 也不会生效，
+//! `latency_header` 则从 fairing 收到请求到生成响应计时。按 `MissingIdPolicy::Reject` 拒绝的请求中，
+//! 以 `TraceId` 或 `TraceScope` 为参数的路由在执行处理器前返回 `400 Bad Request`；
+//! 其他路由的处理器仍会执行，但响应同样被替换为 `400 Bad Request`。
+
+#[cfg(feature = "otel")]
+use crate::integrations::otel;
+use crate::{
+    context,
+    correlation::CorrelationFields,
+    integrations::tower::{
+        extract_correlation, extract_or_generate_trace_id, extract_trace_context,
+        extract_trace_state, resolve_trace_id, response_time_value, response_trace_id,
+        response_traceparent, scope, SkipMode, TraceIdConfig,
+    },
+    trace_context::{SpanId, TraceContext, TraceState},
+    trace_id::TraceId,
+    RESPONSE_TIME_HEADER, TRACEPARENT_HEADER, TRACESTATE_HEADER,
+};
+use http::{HeaderMap, HeaderName, HeaderValue};
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    http::Status,
+    request::{FromRequest, Outcome},
+    Data, Request, Response,
+};
+use std::{future::Future, time::Instant};
+
+/// rocket 追踪ID fairing
+///
+/// # 示例
+/// ```
+/// use trace_id::{rocket::TraceIdFairing, TraceId};
+///
+/// #[rocket::get("/")]
+/// fn index(trace_id: TraceId) -> String {
+///     format!("Your trace ID is: {trace_id}")
+/// }
+///
+/// let rocket = rocket::build()
+///     .mount("/", rocket::routes![index])
+///     .attach(TraceIdFairing::new());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct TraceIdFairing {
+    config: TraceIdConfig,
+}
+
+impl TraceIdFairing {
+    /// 使用默认配置创建 fairing
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 使用自定义配置创建 fairing
+    ///
+    /// # 参数
+    /// * `config` - 与 `TraceIdLayer::with_config` 相同的配置
+    pub fn with_config(config: TraceIdConfig) -> Self {
+        Self { config }
+    }
+}
+
+/// fairing 保存在请求本地缓存中的解析结果
+enum RequestTrace {
+    /// 未经过 fairing
+    Unresolved,
+    /// 按 `skip_paths` 完全跳过
+    Skipped,
+    /// 按 `MissingIdPolicy::Reject` 拒绝
+    Rejected,
+    /// 已确定追踪ID
+    Resolved(Box<ResolvedTrace>),
+}
+
+/// 已确定追踪ID的请求的追踪状态
+struct ResolvedTrace {
+    scope: TraceScope,
+    /// 写入响应的追踪ID头部，未启用 `enable_response_header` 时为None
+    response_header: Option<(String, String)>,
+    /// 写入响应的 `traceparent`
+    traceparent: Option<String>,
+    /// 原样回传的 `tracestate`
+    echo_state: Option<String>,
+    /// 启用 `latency_header` 时收到请求的时刻
+    start: Option<Instant>,
+}
+
+/// 读取请求本地缓存中的解析结果，未经过 fairing 时为 `Unresolved`
+fn request_trace<'r>(req: &'r Request<'_>) -> &'r RequestTrace {
+    req.local_cache(|| RequestTrace::Unresolved)
+}
+
+/// 将 rocket 的请求头转换为 `http::HeaderMap`，以复用与其他框架相同的提取逻辑
+///
+/// 名称或值不合法的头部会被忽略
+fn to_header_map(headers: &rocket::http::HeaderMap<'_>) -> HeaderMap {
+    headers
+        .iter()
+        .filter_map(|header| {
+            let name = HeaderName::from_bytes(header.name().as_str().as_bytes()).ok()?;
+            let value = HeaderValue::from_str(header.value()).ok()?;
+            Some((name, value))
+        })
+        .collect()
+}
+
+#[rocket::async_trait]
+impl Fairing for TraceIdFairing {
+    fn info(&self) -> Info {
+        Info {
+            name: "Trace ID",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, req: &mut Request<'_>, _data: &mut Data<'_>) {
+        let trace = self.resolve(req);
+        req.local_cache(|| trace);
+    }
+
+    async fn on_response<'r>(&self, req: &'r Request<'_>, res: &mut Response<'r>) {
+        let trace = match request_trace(req) {
+            RequestTrace::Resolved(trace) => trace,
+            RequestTrace::Rejected => {
+                res.set_status(Status::BadRequest);
+                res.set_sized_body(0, std::io::Cursor::new(""));
+                return;
+            }
+            RequestTrace::Unresolved | RequestTrace::Skipped => return,
+        };
+
+        if let Some((name, value)) = &trace.response_header {
+            res.set_raw_header(name.clone(), value.clone());
+        }
+
+        if let Some(traceparent) = &trace.traceparent {
+            res.set_raw_header(TRACEPARENT_HEADER, traceparent.clone());
+        }
+
+        if let Some(echo_state) = &trace.echo_state {
+            res.set_raw_header(TRACESTATE_HEADER, echo_state.clone());
+        }
+
+        if let Some(start) = trace.start {
+            let value = response_time_value(start.elapsed());
+            res.set_raw_header(
+                RESPONSE_TIME_HEADER,
+                value.to_str().unwrap_or_default().to_owned(),
+            );
+        }
+    }
+}
+
+impl TraceIdFairing {
+    /// 按配置解析请求的追踪状态
+    fn resolve(&self, req: &Request<'_>) -> RequestTrace {
+        let config = &self.config;
+        let start = config.latency_header.then(Instant::now);
+
+        // 健康检查等路径按 `skip_mode` 跳过追踪；不创建 span，`SpanOnly` 与不跳过相同
+        if config.is_skipped_path(req.uri().path().as_str()) && config.skip_mode == SkipMode::Bypass
+        {
+            return RequestTrace::Skipped;
+        }
+
+        let headers = to_header_map(req.headers());
+
+        // 根据配置解析上游的 traceparent 与 tracestate
+        let trace_context = if config.enable_traceparent {
+            extract_trace_context(&headers)
+        } else {
+            None
+        };
+        let trace_state = if config.enable_tracestate {
+            extract_trace_state(&headers)
+        } else {
+            None
+        };
+
+        let correlation = extract_correlation(&headers, config);
+        let Some(trace_id) = resolve_trace_id(&headers, trace_context.as_ref(), config, None, None)
+        else {
+            return RequestTrace::Rejected;
+        };
+
+        let span_id = SpanId::new();
+
+        let response_header = config.enable_response_header.then(|| {
+            let value = response_trace_id(trace_id).to_header_value();
+            (
+                config.response_header().as_str().to_owned(),
+                value.to_str().unwrap_or_default().to_owned(),
+            )
+        });
+        let traceparent =
+            response_traceparent(config, trace_id, &span_id, trace_context.as_ref(), None);
+        let echo_state = if config.echo_tracestate {
+            trace_state.as_ref().map(TraceState::to_header)
+        } else {
+            None
+        };
+
+        #[cfg(feature = "otel")]
+        let otel_cx = config.enable_otel.then(|| match &trace_context {
+            Some(ctx) => otel::context_with_trace_context(ctx),
+            None => otel::context_with_trace_id(&trace_id),
+        });
+
+        RequestTrace::Resolved(Box::new(ResolvedTrace {
+            scope: TraceScope {
+                trace_id,
+                trace_context,
+                trace_state,
+                span_id: Some(span_id),
+                correlation,
+                #[cfg(feature = "otel")]
+                otel_cx,
+            },
+            response_header,
+            traceparent,
+            echo_state,
+            start,
+        }))
+    }
+}
+
+/// 本次请求的追踪上下文，作为请求守卫在处理器中使用
+///
+/// 经过 `TraceIdFairing` 时携带 fairing 解析出的追踪ID、上游 `traceparent`/`tracestate`、
+/// 本次请求的跨度ID与关联字段；否则与 `TraceId` 守卫相同地确定追踪ID，其余部分为空。
+///
+/// # 示例
+/// ```
+/// use trace_id::rocket::TraceScope;
+///
+/// #[rocket::get("/")]
+/// async fn index(scope: TraceScope) -> String {
+///     scope
+///         .run(async { format!("Your trace ID is: {}", trace_id::get_trace_id()) })
+///         .await
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct TraceScope {
+    trace_id: TraceId,
+    trace_context: Option<TraceContext>,
+    trace_state: Option<TraceState>,
+    span_id: Option<SpanId>,
+    correlation: Option<CorrelationFields>,
+    #[cfg(feature = "otel")]
+    otel_cx: Option<opentelemetry::Context>,
+}
+
+impl TraceScope {
+    /// 获取本次请求的追踪ID
+    pub fn trace_id(&self) -> TraceId {
+        self.trace_id
+    }
+
+    /// 在本次请求的追踪上下文中执行异步操作
+    ///
+    /// # 参数
+    /// * `future` - 要执行的异步操作，通常是处理器的主体
+    ///
+    /// # 返回
+    /// 异步操作的结果
+    pub async fn run<F: Future>(self, future: F) -> F::Output {
+        #[cfg(feature = "otel")]
+        let future = crate::integrations::tower::with_otel_context(future, self.otel_cx);
+
+        // 嵌套的上下文作用域会多次复制内部 future，先装箱以避免调用栈上出现过大的 future
+        scope(
+            self.trace_id,
+            self.trace_context,
+            self.trace_state,
+            None,
+            self.span_id,
+            self.correlation,
+            Box::pin(future),
+        )
+        .await
+    }
+}
+
+/// 未经过 fairing（或被跳过）时确定追踪ID：使用当前追踪上下文，
+/// 都不存在时按默认配置从请求头提取或生成
+fn fallback_trace_id(req: &Request<'_>) -> TraceId {
+    context::get_trace_id_opt()
+        .or_else(|| {
+            extract_or_generate_trace_id(
+                &to_header_map(req.headers()),
+                &TraceIdConfig::default(),
+                None,
+            )
+        })
+        .unwrap_or_default()
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for TraceScope {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match request_trace(req) {
+            RequestTrace::Resolved(trace) => Outcome::Success(trace.scope.clone()),
+            RequestTrace::Rejected => Outcome::Error((Status::BadRequest, ())),
+            RequestTrace::Unresolved | RequestTrace::Skipped => Outcome::Success(TraceScope {
+                trace_id: fallback_trace_id(req),
+                trace_context: None,
+                trace_state: None,
+                span_id: None,
+                correlation: None,
+                #[cfg(feature = "otel")]
+                otel_cx: None,
+            }),
+        }
+    }
+}
+
+/// rocket 请求守卫，用于在处理器参数中直接获取 TraceId
+///
+/// 经过 `TraceIdFairing` 时使用 fairing 确定的追踪ID；否则使用当前追踪上下文，
+/// 都不存在时按默认配置从请求头提取或生成。仅在 fairing 按 `MissingIdPolicy::Reject`
+/// 拒绝请求时失败（`400 Bad Request`）
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for TraceId {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match request_trace(req) {
+            RequestTrace::Resolved(trace) => Outcome::Success(trace.scope.trace_id),
+            RequestTrace::Rejected => Outcome::Error((Status::BadRequest, ())),
+            RequestTrace::Unresolved | RequestTrace::Skipped => {
+                Outcome::Success(fallback_trace_id(req))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TRACE_ID_HEADER;
+    use rocket::{get, http::Header, local::asynchronous::Client, routes};
+
+    const VALID_ID: &str = "0af7651916cd43dd8448eb211c80319c";
+
+    #[get("/")]
+    fn echo(trace_id: TraceId) -> String {
+        trace_id.to_string()
+    }
+
+    #[get("/scope")]
+    async fn in_scope(scope: TraceScope) -> String {
+        let trace_id = scope.trace_id();
+        scope
+            .run(async move {
+                assert_eq!(context::get_trace_id(), trace_id);
+                assert!(context::get_span_id().is_some());
+                trace_id.to_string()
+            })
+            .await
+    }
+
+    #[get("/plain")]
+    fn plain() -> &'static str {
+        "ok"
+    }
+
+    async fn client(fairing: TraceIdFairing) -> Client {
+        let rocket = rocket::build()
+            .mount("/", routes![echo, in_scope, plain])
+            .attach(fairing);
+        Client::tracked(rocket).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_fairing_propagates_trace_id() {
+        let client = client(TraceIdFairing::new()).await;
+
+        let response = client
+            .get("/")
+            .header(Header::new(TRACE_ID_HEADER, VALID_ID))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.headers().get_one(TRACE_ID_HEADER), Some(VALID_ID));
+        assert_eq!(response.into_string().await.unwrap(), VALID_ID);
+
+        // 缺失时生成新的ID，响应头与处理器中的ID一致
+        let response = client.get("/").dispatch().await;
+        let header = response
+            .headers()
+            .get_one(TRACE_ID_HEADER)
+            .unwrap()
+            .to_owned();
+        assert_eq!(response.into_string().await.unwrap(), header);
+        assert!(TraceId::from_string_validated(&header).is_some());
+
+        // 不使用守卫的路由同样带有响应头
+        let response = client.get("/plain").dispatch().await;
+        assert!(response.headers().get_one(TRACE_ID_HEADER).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_scope_sets_context() {
+        let config = TraceIdConfig::builder().enable_traceparent(true).build();
+        let client = client(TraceIdFairing::with_config(config)).await;
+
+        let response = client
+            .get("/scope")
+            .header(Header::new(
+                TRACEPARENT_HEADER,
+                "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            ))
+            .dispatch()
+            .await;
+        let traceparent = response
+            .headers()
+            .get_one(TRACEPARENT_HEADER)
+            .map(TraceContext::parse)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            traceparent.trace_id.as_str(),
+            "4bf92f3577b34da6a3ce929d0e0e4736"
+        );
+        assert_eq!(
+            response.into_string().await.unwrap(),
+            "4bf92f3577b34da6a3ce929d0e0e4736"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_missing_id_policy_reject() {
+        let config = TraceIdConfig::builder()
+            .missing_id_policy(crate::MissingIdPolicy::Reject)
+            .build();
+        let client = client(TraceIdFairing::with_config(config)).await;
+
+        for path in ["/", "/plain"] {
+            let response = client.get(path).dispatch().await;
+            assert_eq!(response.status(), Status::BadRequest);
+            assert!(response.headers().get_one(TRACE_ID_HEADER).is_none());
+        }
+
+        let response = client
+            .get("/")
+            .header(Header::new(TRACE_ID_HEADER, VALID_ID))
+            .dispatch()
+            .await;
+        assert_eq!(response.into_string().await.unwrap(), VALID_ID);
+    }
+
+    #[tokio::test]
+    async fn test_skip_paths() {
+        let config = TraceIdConfig::builder().skip_paths(["/plain"]).build();
+        let client = client(TraceIdFairing::with_config(config)).await;
+
+        let response = client.get("/plain").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        assert!(response.headers().get_one(TRACE_ID_HEADER).is_none());
+
+        let response = client.get("/").dispatch().await;
+        assert!(response.headers().get_one(TRACE_ID_HEADER).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_latency_header() {
+        let config = TraceIdConfig::builder().latency_header(true).build();
+        let timed = client(TraceIdFairing::with_config(config)).await;
+        let response = timed.get("/plain").dispatch().await;
+        let header = response.headers().get_one(RESPONSE_TIME_HEADER).unwrap();
+        assert!(header.ends_with("ms"));
+
+        let untimed = client(TraceIdFairing::new()).await;
+        let response = untimed.get("/plain").dispatch().await;
+        assert!(response.headers().get_one(RESPONSE_TIME_HEADER).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_guard_without_fairing() {
+        let rocket = rocket::build().mount("/", routes![echo]);
+        let client = Client::tracked(rocket).await.unwrap();
+        let response = client
+            .get("/")
+            .header(Header::new(TRACE_ID_HEADER, VALID_ID))
+            .dispatch()
+            .await;
+        assert!(response.headers().get_one(TRACE_ID_HEADER).is_none());
+        assert_eq!(response.into_string().await.unwrap(), VALID_ID);
+    }
+}
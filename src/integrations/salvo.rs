@@ -0,0 +1,366 @@
+//! salvo 框架的追踪ID集成
+//!
+//! - `TraceIdMiddleware`：作为 salvo 的中间件（hoop）提取或生成追踪ID，在追踪上下文中执行后续处理器
+//!   并添加响应头，接受与 `TraceIdLayer` 相同的 `TraceIdConfig`，行为与 Axum 中间件层一致
+//! - 处理器中可通过 `get_trace_id()` 读取追踪ID，也可通过 `TraceIdExt` 从 `Depot` 中读取
+
+#[cfg(feature = "otel")]
+use crate::integrations::otel;
+use crate::{
+    integrations::{
+        extensions::TraceIdExt,
+        tower::{
+            extract_correlation, extract_trace_context, extract_trace_state, record_latency,
+            resolve_trace_id, response_time_value, response_trace_id, response_traceparent, scope,
+            ResponseLatency, SkipMode, TraceIdConfig,
+        },
+    },
+    trace_context::{SpanId, TraceState},
+    trace_id::TraceId,
+    RESPONSE_TIME_HEADER, TRACEPARENT_HEADER, TRACESTATE_HEADER,
+};
+use salvo::{async_trait, http::StatusCode, Depot, FlowCtrl, Handler, Request, Response};
+use std::time::Instant;
+use tracing::Instrument;
+
+/// salvo 追踪ID中间件
+///
+/// 启用 `enable_extensions`（默认）时，追踪ID同时写入请求扩展与 `Depot`
+///
+/// # 示例
+/// ```
+/// use salvo::prelude::*;
+/// use trace_id::{salvo::TraceIdMiddleware, TraceIdExt};
+///
+/// #[handler]
+/// async fn index(depot: &mut Depot) -> String {
+///     assert_eq!(depot.trace_id().copied(), trace_id::get_trace_id_opt());
+///     format!("Your trace ID is: {}", trace_id::get_trace_id())
+/// }
+///
+/// let router = Router::new().hoop(TraceIdMiddleware::new()).get(index);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct TraceIdMiddleware {
+    config: TraceIdConfig,
+}
+
+impl TraceIdMiddleware {
+    /// 使用默认配置创建中间件
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 使用自定义配置创建中间件
+    ///
+    /// # 参数
+    /// * `config` - 与 `TraceIdLayer::with_config` 相同的配置
+    pub fn with_config(config: TraceIdConfig) -> Self {
+        Self { config }
+    }
+}
+
+#[async_trait]
+impl Handler for TraceIdMiddleware {
+    async fn handle(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        ctrl: &mut FlowCtrl,
+    ) {
+        let config = &self.config;
+
+        // 根据配置从收到请求开始计时
+        let start = config.measures_latency().then(Instant::now);
+
+        // 健康检查等路径按 `skip_mode` 跳过追踪，完全跳过时直接执行后续处理
+        let skipped = config.is_skipped_path(req.uri().path());
+        if skipped && config.skip_mode == SkipMode::Bypass {
+            return;
+        }
+
+        // 根据配置解析上游的 traceparent 与 tracestate
+        let trace_context = if config.enable_traceparent {
+            extract_trace_context(req.headers())
+        } else {
+            None
+        };
+        let trace_state = if config.enable_tracestate {
+            extract_trace_state(req.headers())
+        } else {
+            None
+        };
+
+        let correlation = extract_correlation(req.headers(), config);
+        let Some(trace_id) =
+            resolve_trace_id(req.headers(), trace_context.as_ref(), config, None, None)
+        else {
+            // 按 `MissingIdPolicy::Reject` 拒绝请求，不再执行后续处理器
+            res.status_code(StatusCode::BAD_REQUEST);
+            ctrl.skip_rest();
+            return;
+        };
+        if config.enable_extensions {
+            req.extensions_mut().insert(trace_id);
+            depot.insert_typed(trace_id);
+        }
+
+        #[cfg(feature = "otel")]
+        let otel_cx = config.enable_otel.then(|| match &trace_context {
+            Some(ctx) => otel::context_with_trace_context(ctx),
+            None => otel::context_with_trace_id(&trace_id),
+        });
+
+        let span_id = SpanId::new();
+
+        let span = if config.enable_span && !skipped {
+            #[cfg(feature = "otel")]
+            let _guard = otel_cx.clone().map(opentelemetry::Context::attach);
+            Some(tracing::info_span!(
+                "request",
+                trace_id = %trace_id.as_str(),
+                span_id = %span_id.as_str(),
+                method = %req.method(),
+                uri = %req.uri(),
+                latency_ms = tracing::field::Empty
+            ))
+        } else {
+            None
+        };
+        let latency_span = span.clone().filter(|_| config.record_latency);
+
+        let echo_state = if config.echo_tracestate {
+            trace_state.as_ref().map(TraceState::to_header)
+        } else {
+            None
+        };
+        let traceparent =
+            response_traceparent(config, trace_id, &span_id, trace_context.as_ref(), None);
+
+        // 后续处理器的错误会写入 `res`，因此错误响应同样带有追踪ID头部
+        let handle = async {
+            ctrl.call_next(req, depot, res).await;
+
+            // 后续处理器执行完毕时结束计时（在 span 内记录）
+            if let Some(start) = start {
+                let latency = start.elapsed();
+                if config.record_latency {
+                    if let Some(span) = &latency_span {
+                        record_latency(span, latency);
+                    }
+                    res.extensions.insert(ResponseLatency(latency));
+                }
+                if config.latency_header {
+                    res.headers_mut()
+                        .insert(RESPONSE_TIME_HEADER, response_time_value(latency));
+                }
+            }
+        };
+
+        #[cfg(feature = "otel")]
+        let handle = crate::integrations::tower::with_otel_context(handle, otel_cx);
+
+        // 嵌套的上下文作用域会多次复制内部 future，先装箱以避免调用栈上出现过大的 future
+        let handle = Box::pin(handle);
+
+        match span {
+            Some(span) => {
+                scope(
+                    trace_id,
+                    trace_context,
+                    trace_state,
+                    None,
+                    Some(span_id),
+                    correlation,
+                    handle,
+                )
+                .instrument(span)
+                .await
+            }
+            None => {
+                scope(
+                    trace_id,
+                    trace_context,
+                    trace_state,
+                    None,
+                    Some(span_id),
+                    correlation,
+                    handle,
+                )
+                .await
+            }
+        }
+
+        if config.enable_response_header {
+            res.headers_mut().insert(
+                config.response_header().clone(),
+                response_trace_id(trace_id).to_header_value(),
+            );
+        }
+
+        if let Some(Ok(header_value)) = traceparent.map(|header| header.parse()) {
+            res.headers_mut().insert(TRACEPARENT_HEADER, header_value);
+        }
+
+        if let Some(Ok(header_value)) = echo_state.map(|state| state.parse()) {
+            res.headers_mut().insert(TRACESTATE_HEADER, header_value);
+        }
+    }
+}
+
+/// 读取 `TraceIdMiddleware` 写入 `Depot` 的追踪ID
+impl TraceIdExt for Depot {
+    fn trace_id(&self) -> Option<&TraceId> {
+        self.get_typed::<TraceId>().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{context, TRACEPARENT_HEADER, TRACE_ID_HEADER};
+    use salvo::{
+        handler,
+        http::StatusCode,
+        test::{ResponseExt, TestClient},
+        Router, Service,
+    };
+
+    const VALID_ID: &str = "0af7651916cd43dd8448eb211c80319c";
+    const URL: &str = "http://127.0.0.1:5800/";
+
+    #[handler]
+    async fn echo(depot: &mut Depot) -> String {
+        let trace_id = context::get_trace_id();
+        assert_eq!(depot.trace_id(), Some(&trace_id));
+        trace_id.to_string()
+    }
+
+    #[handler]
+    async fn fail(res: &mut Response) {
+        res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    fn service(middleware: TraceIdMiddleware) -> Service {
+        Service::new(
+            Router::new()
+                .hoop(middleware)
+                .get(echo)
+                .push(Router::with_path("fail").get(fail)),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_middleware_propagates_trace_id() {
+        let service = service(TraceIdMiddleware::new());
+
+        let mut response = TestClient::get(URL)
+            .add_header(TRACE_ID_HEADER, VALID_ID, true)
+            .send(&service)
+            .await;
+        assert_eq!(response.headers().get(TRACE_ID_HEADER).unwrap(), VALID_ID);
+        assert_eq!(response.take_string().await.unwrap(), VALID_ID);
+
+        // 缺失时生成新的ID，响应头与处理器中的ID一致
+        let mut response = TestClient::get(URL).send(&service).await;
+        let header = response.headers().get(TRACE_ID_HEADER).unwrap().clone();
+        let body = response.take_string().await.unwrap();
+        assert_eq!(header, body.as_str());
+        assert!(TraceId::from_string_validated(&body).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_middleware_config() {
+        let config = TraceIdConfig::builder()
+            .enable_traceparent(true)
+            .enable_response_header(false)
+            .build();
+        let service = service(TraceIdMiddleware::with_config(config));
+
+        let mut response = TestClient::get(URL)
+            .add_header(
+                TRACEPARENT_HEADER,
+                "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+                true,
+            )
+            .send(&service)
+            .await;
+        assert!(response.headers().get(TRACE_ID_HEADER).is_none());
+        assert_eq!(
+            response.take_string().await.unwrap(),
+            "4bf92f3577b34da6a3ce929d0e0e4736"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_missing_id_policy_reject() {
+        let config = TraceIdConfig::builder()
+            .missing_id_policy(crate::MissingIdPolicy::Reject)
+            .build();
+        let service = service(TraceIdMiddleware::with_config(config));
+
+        let response = TestClient::get(URL).send(&service).await;
+        assert_eq!(response.status_code, Some(StatusCode::BAD_REQUEST));
+        assert!(response.headers().get(TRACE_ID_HEADER).is_none());
+
+        let mut response = TestClient::get(URL)
+            .add_header(TRACE_ID_HEADER, VALID_ID, true)
+            .send(&service)
+            .await;
+        assert_eq!(response.take_string().await.unwrap(), VALID_ID);
+    }
+
+    #[tokio::test]
+    async fn test_skip_paths() {
+        let config = TraceIdConfig::builder().skip_paths(["/fail"]).build();
+        let service = service(TraceIdMiddleware::with_config(config));
+
+        // 跳过的路径照常执行处理器，但不写入响应头
+        let response = TestClient::get(format!("{URL}fail"))
+            .add_header(TRACE_ID_HEADER, VALID_ID, true)
+            .send(&service)
+            .await;
+        assert_eq!(
+            response.status_code,
+            Some(StatusCode::INTERNAL_SERVER_ERROR)
+        );
+        assert!(response.headers().get(TRACE_ID_HEADER).is_none());
+
+        let response = TestClient::get(URL).send(&service).await;
+        assert!(response.headers().get(TRACE_ID_HEADER).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_record_latency() {
+        let config = TraceIdConfig::builder()
+            .record_latency(true)
+            .latency_header(true)
+            .build();
+        let timed = service(TraceIdMiddleware::with_config(config));
+        let response = TestClient::get(URL).send(&timed).await;
+        assert!(response.extensions.get::<ResponseLatency>().is_some());
+        let header = response.headers().get(RESPONSE_TIME_HEADER).unwrap();
+        assert!(header.to_str().unwrap().ends_with("ms"));
+
+        let response = TestClient::get(URL)
+            .send(&service(TraceIdMiddleware::new()))
+            .await;
+        assert!(response.headers().get(RESPONSE_TIME_HEADER).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_error_response_has_header() {
+        let service = service(TraceIdMiddleware::new());
+
+        let response = TestClient::get(format!("{URL}fail"))
+            .add_header(TRACE_ID_HEADER, VALID_ID, true)
+            .send(&service)
+            .await;
+        assert_eq!(
+            response.status_code,
+            Some(StatusCode::INTERNAL_SERVER_ERROR)
+        );
+        assert_eq!(response.headers().get(TRACE_ID_HEADER).unwrap(), VALID_ID);
+    }
+}
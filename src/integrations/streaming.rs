@@ -0,0 +1,236 @@
+//! 在整个响应体的生命周期内保持追踪上下文
+//!
+//! `TraceIdService` 只在内部服务的 future 执行期间设置上下文并进入请求 span。
+//! Server-Sent Events 等流式响应中，处理器很快返回流式响应体，
+//! 之后才逐块产生数据，此时上下文已经退出，span 也已关闭，生成数据时记录的日志便没有追踪ID。
+//!
+//! `TraceIdLayer::with_streaming_body()` 在响应生成时捕获追踪上下文与当前 span，
+//! 将响应体包装为 `TracedBody`：每次轮询响应体时重新进入上下文与 span，
+//! span 一直保持打开，直到响应体被完全消费或丢弃。
+
+use crate::context::ContextSnapshot;
+use crate::integrations::tower::{TraceIdLayer, TraceIdService};
+use http::{Request, Response};
+use http_body::{Body, Frame, SizeHint};
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+use tracing::Span;
+
+/// 保持响应体追踪上下文的追踪ID层，由 `TraceIdLayer::with_streaming_body()` 创建
+#[derive(Clone)]
+pub struct StreamingBodyLayer {
+    layer: TraceIdLayer,
+}
+
+impl TraceIdLayer {
+    /// 在响应体被完全消费之前保持追踪上下文与请求 span
+    ///
+    /// 响应体被包装为 `TracedBody`，轮询响应体（如 SSE 流产生下一个事件）期间
+    /// `get_trace_id()` 等返回本次请求的上下文，记录的日志也位于请求 span 内。
+    /// 每次轮询都会重新进入上下文，只有流式响应需要启用
+    ///
+    /// # 示例
+    /// ```
+    /// use trace_id::TraceIdLayer;
+    ///
+    /// let layer = TraceIdLayer::new().with_streaming_body();
+    /// ```
+    pub fn with_streaming_body(self) -> StreamingBodyLayer {
+        StreamingBodyLayer { layer: self }
+    }
+}
+
+impl<S> Layer<S> for StreamingBodyLayer {
+    type Service = TraceIdService<StreamingBody<S>>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        self.layer.layer(StreamingBody { inner })
+    }
+}
+
+/// 将响应体包装为 `TracedBody` 的服务，位于 `TraceIdService` 内部
+#[derive(Clone)]
+pub struct StreamingBody<S> {
+    inner: S,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for StreamingBody<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = Response<TracedBody<ResBody>>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let future = self.inner.call(req);
+
+        // `call` 在上下文之外执行，需在 future 中（已处于上下文与 span 内）捕获
+        Box::pin(async move {
+            let response = future.await?;
+            let context = ContextSnapshot::capture();
+            let span = Span::current();
+            Ok(response.map(|body| TracedBody {
+                inner: body,
+                context,
+                span,
+            }))
+        })
+    }
+}
+
+pin_project_lite::pin_project! {
+    /// 在捕获的追踪上下文与 span 中轮询的响应体
+    ///
+    /// 持有请求 span 的句柄，span 在响应体丢弃后才会关闭
+    pub struct TracedBody<B> {
+        #[pin]
+        inner: B,
+        context: ContextSnapshot,
+        span: Span,
+    }
+}
+
+impl<B> TracedBody<B> {
+    /// 获取内部的响应体
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
+
+impl<B: Default> Default for TracedBody<B> {
+    /// 不带追踪上下文的空响应体（如 `MissingIdPolicy::Reject` 生成的 400 响应）
+    fn default() -> Self {
+        Self {
+            inner: B::default(),
+            context: ContextSnapshot::default(),
+            span: Span::none(),
+        }
+    }
+}
+
+impl<B: Body> Body for TracedBody<B> {
+    type Data = B::Data;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        let _entered = this.span.enter();
+        let inner = this.inner;
+        this.context.enter(|| inner.poll_frame(cx))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{context, TRACE_ID_HEADER};
+    use std::{
+        convert::Infallible,
+        sync::{Arc, Mutex},
+    };
+    use tower::{service_fn, ServiceExt};
+
+    const VALID_ID: &str = "0af7651916cd43dd8448eb211c80319c";
+
+    /// 逐块产生数据的流式响应体，记录每次产生数据时的追踪ID
+    #[derive(Default)]
+    struct EventStream {
+        remaining: usize,
+        seen: Arc<Mutex<Vec<Option<String>>>>,
+    }
+
+    impl Body for EventStream {
+        type Data = &'static [u8];
+        type Error = Infallible;
+
+        fn poll_frame(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+            if self.remaining == 0 {
+                return Poll::Ready(None);
+            }
+            self.remaining -= 1;
+
+            let trace_id = context::get_trace_id_opt().map(|trace_id| trace_id.to_string());
+            tracing::info!(?trace_id, "sending event");
+            self.seen.lock().unwrap().push(trace_id);
+            Poll::Ready(Some(Ok(Frame::data(&b"data: tick\n\n"[..]))))
+        }
+    }
+
+    /// 轮询响应体直到结束，返回产生的数据块数量
+    async fn drain<B: Body + Unpin>(mut body: B) -> usize {
+        let mut frames = 0;
+        while let Some(frame) = std::future::poll_fn(|cx| Pin::new(&mut body).poll_frame(cx)).await
+        {
+            assert!(frame.is_ok());
+            frames += 1;
+        }
+        frames
+    }
+
+    fn request() -> Request<()> {
+        Request::builder()
+            .header(TRACE_ID_HEADER, VALID_ID)
+            .body(())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_context_kept_while_streaming() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let handler_seen = Arc::clone(&seen);
+        let service = TraceIdLayer::new()
+            .with_streaming_body()
+            .layer(service_fn(move |_req: Request<()>| {
+                let seen = Arc::clone(&handler_seen);
+                async move { Ok::<_, Infallible>(Response::new(EventStream { remaining: 3, seen })) }
+            }));
+
+        let response = service.oneshot(request()).await.unwrap();
+        assert_eq!(response.headers().get(TRACE_ID_HEADER).unwrap(), VALID_ID);
+
+        // 响应已返回、请求 future 已结束，之后才开始产生数据
+        assert_eq!(drain(Box::pin(response.into_body())).await, 3);
+        assert_eq!(*seen.lock().unwrap(), vec![Some(VALID_ID.to_string()); 3]);
+
+        // 轮询结束后上下文已恢复
+        assert!(context::get_trace_id_opt().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_without_streaming_body_context_is_lost() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let handler_seen = Arc::clone(&seen);
+        let service = TraceIdLayer::new().layer(service_fn(move |_req: Request<()>| {
+            let seen = Arc::clone(&handler_seen);
+            async move { Ok::<_, Infallible>(Response::new(EventStream { remaining: 1, seen })) }
+        }));
+
+        let response = service.oneshot(request()).await.unwrap();
+        assert_eq!(drain(Box::pin(response.into_body())).await, 1);
+        assert_eq!(*seen.lock().unwrap(), vec![None]);
+    }
+}
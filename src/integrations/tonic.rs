@@ -0,0 +1,226 @@
+//! tonic（gRPC）的追踪ID拦截器
+//!
+//! gRPC 通过 metadata 而非 HTTP 头部传递追踪ID：ASCII 格式使用 `x-trace-id` 键，
+//! 二进制格式使用 `x-trace-id-bin` 键（16字节原始数据，传输时由 tonic 进行 base64 编码）。
+//!
+//! tonic 的 `Interceptor` 是同步调用，无法包装处理器的执行过程，因此服务端拦截器只将
+//! 追踪ID写入请求扩展；处理器可通过 `with_request_trace_id()` 在追踪上下文中执行。
+//! 需要自动设置上下文时，也可以在 tonic 服务上使用通用的 `TraceIdLayer`（`tower` 特性）。
+
+use crate::{context, trace_id::TraceId, TRACE_ID_HEADER};
+use tonic::{
+    metadata::{BinaryMetadataValue, MetadataValue},
+    service::Interceptor,
+    Request, Status,
+};
+
+/// 二进制格式追踪ID的 metadata 键（16字节原始数据）
+pub const TRACE_ID_BIN_METADATA: &str = "x-trace-id-bin";
+
+/// 服务端拦截器：从 metadata 提取或生成追踪ID
+///
+/// 优先读取 ASCII 格式的 `x-trace-id`，其次读取二进制格式的 `x-trace-id-bin`；
+/// 值缺失、包含非ASCII字符或格式无效时生成新的ID，不会拒绝请求。
+/// 结果写入请求扩展，可通过 `trace_id()` 获取。
+///
+/// # 示例
+/// ```ignore
+/// use trace_id::tonic::ServerInterceptor;
+///
+/// let service = GreeterServer::with_interceptor(MyGreeter::default(), ServerInterceptor::new());
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ServerInterceptor;
+
+impl ServerInterceptor {
+    /// 创建新的服务端拦截器
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Interceptor for ServerInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let trace_id = extract(&request).unwrap_or_default();
+        request.extensions_mut().insert(trace_id);
+        Ok(request)
+    }
+}
+
+/// 客户端拦截器：将当前追踪ID写入出站请求的 metadata
+///
+/// 与 reqwest 中间件相同，不在追踪上下文中时不做任何处理，不会生成新的追踪ID；
+/// 已显式设置 `x-trace-id` 的请求保持不变。
+///
+/// # 示例
+/// ```ignore
+/// use trace_id::tonic::ClientInterceptor;
+///
+/// let client = GreeterClient::with_interceptor(channel, ClientInterceptor::new());
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ClientInterceptor;
+
+impl ClientInterceptor {
+    /// 创建新的客户端拦截器
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Interceptor for ClientInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        if let Some(trace_id) = context::get_trace_id_opt() {
+            let metadata = request.metadata_mut();
+            if !metadata.contains_key(TRACE_ID_HEADER) {
+                if let Ok(value) = MetadataValue::try_from(trace_id.as_str()) {
+                    metadata.insert(TRACE_ID_HEADER, value);
+                }
+            }
+        }
+        Ok(request)
+    }
+}
+
+/// 获取服务端拦截器写入请求扩展的追踪ID
+///
+/// # 参数
+/// * `request` - gRPC 请求
+///
+/// # 返回
+/// 请求经过 `ServerInterceptor` 时返回Some(TraceId)，否则返回None
+pub fn trace_id<T>(request: &Request<T>) -> Option<TraceId> {
+    request.extensions().get::<TraceId>().copied()
+}
+
+/// 在请求的追踪上下文中执行异步操作
+///
+/// 使用 `ServerInterceptor` 写入的追踪ID，未经过拦截器时直接从 metadata 提取或生成。
+/// 处理器中调用后，内部可直接使用 `get_trace_id()` 与 reqwest/tonic 客户端传播。
+///
+/// # 参数
+/// * `request` - gRPC 请求
+/// * `future` - 要执行的异步操作
+///
+/// # 返回
+/// 异步操作的结果
+pub async fn with_request_trace_id<T, F>(request: &Request<T>, future: F) -> F::Output
+where
+    F: std::future::Future,
+{
+    let trace_id = trace_id(request)
+        .or_else(|| extract(request))
+        .unwrap_or_default();
+    context::with_trace_id(trace_id, future).await
+}
+
+/// 从 metadata 中提取追踪ID（先 ASCII 后二进制）
+fn extract<T>(request: &Request<T>) -> Option<TraceId> {
+    let metadata = request.metadata();
+    metadata
+        .get(TRACE_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(TraceId::from_string_validated)
+        .or_else(|| decode_binary(metadata.get_bin(TRACE_ID_BIN_METADATA)?))
+}
+
+/// 解析二进制格式的追踪ID：必须恰好为16字节且非零
+fn decode_binary(value: &BinaryMetadataValue) -> Option<TraceId> {
+    let bytes: [u8; 16] = value.to_bytes().ok()?.as_ref().try_into().ok()?;
+    TraceId::from_u128(u128::from_be_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALID_ID: &str = "0af7651916cd43dd8448eb211c80319c";
+
+    fn intercept(request: Request<()>) -> TraceId {
+        let request = ServerInterceptor::new().call(request).unwrap();
+        trace_id(&request).unwrap()
+    }
+
+    #[test]
+    fn test_server_ascii_metadata() {
+        let mut request = Request::new(());
+        request
+            .metadata_mut()
+            .insert(TRACE_ID_HEADER, MetadataValue::from_static(VALID_ID));
+        assert_eq!(intercept(request).as_str(), VALID_ID);
+
+        // 缺失时生成新的ID
+        let trace_id = intercept(Request::new(()));
+        assert!(TraceId::from_string_validated(trace_id.as_str()).is_some());
+    }
+
+    #[test]
+    fn test_server_binary_metadata() {
+        let expected = TraceId::from_string_validated(VALID_ID).unwrap();
+        let mut request = Request::new(());
+        request.metadata_mut().insert_bin(
+            TRACE_ID_BIN_METADATA,
+            BinaryMetadataValue::from_bytes(expected.as_bytes()),
+        );
+        assert_eq!(intercept(request), expected);
+
+        // 长度错误或全零的二进制值被忽略
+        for bytes in [&[0xab; 8][..], &[0; 16][..]] {
+            let mut request = Request::new(());
+            request.metadata_mut().insert_bin(
+                TRACE_ID_BIN_METADATA,
+                BinaryMetadataValue::from_bytes(bytes),
+            );
+            let trace_id = intercept(request);
+            assert_ne!(trace_id.as_bytes()[..], [0; 16][..]);
+            assert_ne!(trace_id.as_bytes()[..8], [0xab; 8][..]);
+        }
+    }
+
+    #[test]
+    fn test_server_rejects_non_ascii_gracefully() {
+        let mut request = Request::new(());
+        let value = MetadataValue::try_from(&b"0af7651916cd43dd8448eb211c80319\xff"[..]).unwrap();
+        request.metadata_mut().insert(TRACE_ID_HEADER, value);
+
+        // 非ASCII值不会导致请求被拒绝，而是生成新的ID
+        let trace_id = intercept(request);
+        assert_ne!(trace_id.as_str(), VALID_ID);
+        assert_eq!(trace_id.as_str().len(), 32);
+    }
+
+    #[tokio::test]
+    async fn test_client_injects_current_trace_id() {
+        let trace_id = TraceId::from_string_validated(VALID_ID).unwrap();
+
+        // 不在追踪上下文中时不写入
+        let request = ClientInterceptor::new().call(Request::new(())).unwrap();
+        assert!(request.metadata().get(TRACE_ID_HEADER).is_none());
+
+        context::with_trace_id(trace_id, async {
+            let request = ClientInterceptor::new().call(Request::new(())).unwrap();
+            assert_eq!(request.metadata().get(TRACE_ID_HEADER).unwrap(), VALID_ID);
+
+            // 显式设置的值保持不变
+            let mut request = Request::new(());
+            request
+                .metadata_mut()
+                .insert(TRACE_ID_HEADER, MetadataValue::from_static("explicit"));
+            let request = ClientInterceptor::new().call(request).unwrap();
+            assert_eq!(request.metadata().get(TRACE_ID_HEADER).unwrap(), "explicit");
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_with_request_trace_id() {
+        let mut request = Request::new(());
+        request
+            .metadata_mut()
+            .insert(TRACE_ID_HEADER, MetadataValue::from_static(VALID_ID));
+        let request = ServerInterceptor::new().call(request).unwrap();
+
+        let current = with_request_trace_id(&request, async { context::get_trace_id() }).await;
+        assert_eq!(current.as_str(), VALID_ID);
+    }
+}
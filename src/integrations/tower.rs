@@ -0,0 +1,3229 @@
+//! 通用 tower 追踪ID中间件
+//!
+//! 基于 `http::Request<B>`/`http::Response<B>`，与具体Web框架无关，
+//! 可用于 hyper、tonic 等任意 tower 中间件栈，Axum 集成也构建于此之上。
+
+#[cfg(feature = "metrics")]
+use crate::metrics::MetricsExporter;
+use crate::{
+    context,
+    correlation::CorrelationFields,
+    integrations::{b3, datadog, xray},
+    metrics::MetricsHook,
+    sampler::{Sampler, TRACE_FLAG_SAMPLED},
+    trace_context::{SpanId, TraceContext, TraceState},
+    trace_id::{TraceId, TraceIdParseError},
+    RESPONSE_TIME_HEADER, TRACEPARENT_HEADER, TRACESTATE_HEADER, TRACE_ID_HEADER,
+};
+use http::{request::Parts, HeaderMap, HeaderName, HeaderValue, Request, Response, StatusCode};
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+use tower::{Layer, Service};
+use tracing::{Instrument, Span};
+
+#[cfg(feature = "otel")]
+use crate::integrations::otel;
+
+/// 用于生成追踪ID的函数签名
+///
+/// 字符串生成器（`with_generator`）在构造时包装为校验后的 `TraceId` 生成器，
+/// 结果无效时返回None并回退到默认生成器
+type Generator = Arc<dyn Fn() -> Option<TraceId> + Send + Sync>;
+
+/// 按请求选择的生成器（`with_generator_when`）：谓词返回true时使用该生成器
+type ConditionalGenerator = (
+    Arc<dyn Fn(&Parts) -> bool + Send + Sync>,
+    Arc<dyn Fn() -> TraceId + Send + Sync>,
+);
+
+/// 用于创建请求 span 的函数签名
+type MakeSpan = Arc<dyn Fn(&Parts, &TraceId) -> Span + Send + Sync>;
+
+/// 用于判断请求是否跳过追踪的谓词
+type SkipPredicate = Arc<dyn Fn(&Parts) -> bool + Send + Sync>;
+
+/// 用于决定新追踪是否采样的采样器
+type SharedSampler = Arc<dyn Sampler + Send + Sync>;
+
+/// 用于观察追踪ID来源的回调
+type SharedMetrics = Arc<dyn MetricsHook + Send + Sync>;
+
+/// 层、服务与请求 future 共享的配置，克隆只增加引用计数
+type SharedConfig = Arc<TraceIdConfig>;
+
+/// 追踪ID中间件配置选项
+///
+/// 标记为 `#[non_exhaustive]`，新增选项不会破坏已有代码：crate 外无法使用结构体字面量构造，
+/// 请通过 `TraceIdConfig::builder()` 或修改 `TraceIdConfig::default()` 的字段创建
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct TraceIdConfig {
+    /// 是否启用 tracing span（默认启用）
+    pub enable_span: bool,
+    /// 是否启用响应头（默认启用）
+    ///
+    /// 内部服务返回的所有响应（包括 4xx/5xx 错误响应、内部 panic 捕获层生成的 500 响应）都会添加头部；
+    /// 位于 `TraceIdLayer` 之外的层生成的响应无法添加，需捕获 panic 时应将捕获层置于内部，
+    /// 或使用 `TraceIdLayer::with_catch_panic()`
+    pub enable_response_header: bool,
+    /// 是否将追踪ID写入请求扩展（默认启用）
+    ///
+    /// 只通过 `get_trace_id()` 或提取器读取追踪ID时可以禁用，省去每个请求一次扩展表插入；
+    /// 禁用后下层服务无法通过 `TraceIdExt` 读取
+    pub enable_extensions: bool,
+    /// 是否读取 W3C `traceparent` 头部（默认禁用）
+    ///
+    /// 启用后，有效的 `traceparent` 优先于 `x-trace-id`，
+    /// 解析出的上下文可通过 `get_trace_context()` 获取；
+    /// 同时启用 `enable_response_header` 时，响应中写入以本次请求的跨度ID为 parent-id 的 `traceparent`
+    pub enable_traceparent: bool,
+    /// 是否读取 W3C `tracestate` 头部（默认禁用）
+    ///
+    /// 启用后，解析出的厂商键值对可通过 `get_trace_state()` 获取
+    pub enable_tracestate: bool,
+    /// 是否在响应中原样回写 `tracestate` 头部（默认禁用，需同时启用 `enable_tracestate`）
+    pub echo_tracestate: bool,
+    /// 是否在响应中写入完整的 `traceparent` 头部（默认禁用，需启用 `enable_response_header`）
+    ///
+    /// 不要求启用 `enable_traceparent`：由追踪ID、本次请求的跨度ID与 trace-flags 重建，
+    /// 只收到 `x-trace-id` 时同样生成合法的 parent-id 与 trace-flags，供客户端拼接调用链。
+    /// 启用 `enable_traceparent` 时总会写入，无需设置该选项
+    pub echo_traceparent: bool,
+    /// 当 `x-trace-id` 缺失或无效时，是否回退读取 B3（Zipkin）头部（默认禁用）
+    ///
+    /// 支持单头部 `b3` 与多头部 `X-B3-TraceId`，64位ID会左侧补零
+    pub enable_b3: bool,
+    /// 当 `x-trace-id`（以及启用时的 B3 头部）缺失或无效时，是否回退读取 Datadog 头部（默认禁用）
+    ///
+    /// 十进制的 `x-datadog-trace-id` 作为低64位，`x-datadog-tags` 中的 `_dd.p.tid` 作为高64位
+    pub enable_datadog: bool,
+    /// 当以上头部均缺失或无效时，是否回退读取 AWS X-Ray 的 `X-Amzn-Trace-Id` 头部（默认禁用）
+    ///
+    /// 使用其中 `Root=1-{8位十六进制}-{24位十六进制}` 字段，去掉版本号与分隔符后即为追踪ID
+    pub enable_xray: bool,
+    /// 是否使用64位短追踪ID（默认禁用）
+    ///
+    /// 启用后，入站头部同时接受32字符与16字符的追踪ID，新ID由 `TraceId::new_short()` 生成，
+    /// 响应头中为16个字符。只适用于不经过 W3C 系统的内部服务；
+    /// `traceparent` 的解析仍严格要求32字符，写出时短ID会左侧补零
+    pub enable_short_ids: bool,
+    /// 是否生成完全随机的追踪ID（默认禁用）
+    ///
+    /// 启用后，新ID由 `TraceId::new_random()` 生成，不包含时间戳与机器ID，
+    /// 适用于不希望暴露服务器时钟或请求先后顺序的隐私敏感部署。
+    /// 只影响新生成的ID；同时启用 `enable_short_ids` 时短ID优先，自定义生成器优先于两者
+    pub enable_random_ids: bool,
+    /// 读取与回写追踪ID所使用的头部名称（默认 `x-trace-id`）
+    ///
+    /// `HeaderName` 在构造时完成校验，请求处理路径上无需再次解析；
+    /// 设置 `response_header_name` 时只用于读取
+    pub header_name: HeaderName,
+    /// 按优先级排列的入站追踪ID头部名称（默认为空，即只读取 `header_name`）
+    ///
+    /// 非空时依次尝试各头部，使用第一个有效的追踪ID，均无效时才回退到 B3/Datadog 或生成新ID；
+    /// 其中的 `traceparent` 只取其 trace-id 部分。响应头仍使用 `response_header()`
+    pub header_names: Vec<HeaderName>,
+    /// 回写追踪ID所使用的响应头名称（默认None，即与 `header_name` 相同）
+    ///
+    /// 用于入站与出站头部不同的部署，例如读取 `x-request-id` 而向下一跳写出 `traceparent`。
+    /// 设置为 `traceparent` 时写入完整的 W3C 值（与 `echo_traceparent` 相同），而非单独的追踪ID
+    pub response_header_name: Option<HeaderName>,
+    /// 作为关联字段传递的请求头名称（默认为空）
+    ///
+    /// 请求中存在的头部以头部名称（小写）为键写入 `CorrelationFields`，
+    /// 处理请求期间可通过 `get_correlation_field()` 读取，非ASCII的值被忽略
+    pub correlation_headers: Vec<HeaderName>,
+    /// 头部中没有有效追踪ID时回退读取的 Cookie 名称（默认None，即不读取 Cookie）
+    ///
+    /// 适用于由浏览器发起、以 Cookie 而非请求头携带追踪ID的流程。优先级为：
+    /// 追踪ID头部（`header_name`/`header_names`，以及启用时的 B3/Datadog/X-Ray 头部）、
+    /// 该 Cookie、最后才生成新ID。Cookie 值的校验规则与头部相同（两侧的双引号会被去除），
+    /// 名称区分大小写；同名 Cookie 出现多次时只使用第一个
+    pub trace_id_cookie: Option<String>,
+    /// 跳过追踪的请求路径（默认为空）
+    ///
+    /// 健康检查、指标采集等被高频探测的端点无需追踪。按路径段前缀匹配：`/healthz` 匹配
+    /// `/healthz` 与 `/healthz/live`，但不匹配 `/healthz2`；以 `/` 结尾的前缀匹配其下的所有路径。
+    /// 匹配的请求按 `skip_mode` 处理；需要按方法、头部等条件跳过时使用 `TraceIdLayer::with_skip`
+    pub skip_paths: Vec<String>,
+    /// 跳过追踪的请求的处理方式（默认 `SkipMode::Bypass`）
+    pub skip_mode: SkipMode,
+    /// 是否记录请求的处理耗时（默认禁用）
+    ///
+    /// 从中间件收到请求到内部服务返回响应（不含响应体的传输）计时，以毫秒（保留微秒精度）
+    /// 记录到请求 span 的 `latency_ms` 字段，并以 `ResponseLatency` 写入响应扩展，
+    /// 未创建 span（高性能模式、未采样）时外层服务仍可从扩展中读取。
+    /// 内置 span 已声明该字段；`with_make_span` 创建的 span 需自行声明
+    /// `latency_ms = tracing::field::Empty` 才能记录
+    pub record_latency: bool,
+    /// 是否在响应中写入 `x-response-time` 头部（默认禁用），值为处理耗时的毫秒数，如 `12.345ms`
+    ///
+    /// 计时方式与 `record_latency` 相同，两者可以单独启用
+    pub latency_header: bool,
+    /// 入站追踪ID头部存在但校验失败时记录事件的日志级别（默认 `DEBUG`，None表示不记录）
+    ///
+    /// 事件包含头部名称、失败原因以及截断并转义后的头部值，可用于发现探测行为；
+    /// 控制字符等会被转义，无法借此向日志中注入内容
+    pub invalid_header_log_level: Option<tracing::Level>,
+    /// 入站追踪ID均无效时，是否将其原始值经 `TraceId::from_opaque` 映射为有效的追踪ID（默认禁用）
+    ///
+    /// 适用于上游只传递 ULID、带连字符的UUID 等非 W3C 格式关联ID的部署，映射代替生成新ID，
+    /// 保留与上游的关联。按 `header_name`/`header_names`、追踪ID Cookie 的顺序取第一个存在的非空值
+    /// （`traceparent` 除外），并以 `INFO` 级别记录截断、转义后的原始值与映射得到的追踪ID；
+    /// 哈希不可逆，下游只能看到映射后的ID，需按原始ID检索时依赖这条事件
+    pub map_opaque_ids: bool,
+    /// 请求中没有有效追踪ID时的处理策略（默认 `MissingIdPolicy::Generate`）
+    ///
+    /// 面向外部客户端的边缘服务应生成新ID；内部服务可选择记录警告或直接拒绝请求
+    pub missing_id_policy: MissingIdPolicy,
+    /// 是否将追踪ID设置为活动的 OpenTelemetry 上下文（默认禁用，需启用 `otel` 特性）
+    ///
+    /// 启用后，请求 span 创建与请求处理期间的 `opentelemetry::Context::current()`
+    /// 以该追踪ID为远程父级，配合 `tracing-opentelemetry` 时导出的 span 与响应头中的追踪ID一致
+    #[cfg(feature = "otel")]
+    pub enable_otel: bool,
+}
+
+impl Default for TraceIdConfig {
+    fn default() -> Self {
+        Self {
+            enable_span: true,
+            enable_response_header: true,
+            enable_extensions: true,
+            enable_traceparent: false,
+            enable_tracestate: false,
+            echo_tracestate: false,
+            echo_traceparent: false,
+            enable_b3: false,
+            enable_datadog: false,
+            enable_xray: false,
+            enable_short_ids: false,
+            enable_random_ids: false,
+            header_name: HeaderName::from_static(TRACE_ID_HEADER),
+            header_names: Vec::new(),
+            response_header_name: None,
+            correlation_headers: Vec::new(),
+            trace_id_cookie: None,
+            skip_paths: Vec::new(),
+            skip_mode: SkipMode::Bypass,
+            record_latency: false,
+            latency_header: false,
+            invalid_header_log_level: Some(tracing::Level::DEBUG),
+            map_opaque_ids: false,
+            missing_id_policy: MissingIdPolicy::Generate,
+            #[cfg(feature = "otel")]
+            enable_otel: false,
+        }
+    }
+}
+
+impl TraceIdConfig {
+    /// 是否需要为请求计时（`record_latency` 或 `latency_header`）
+    pub(crate) fn measures_latency(&self) -> bool {
+        self.record_latency || self.latency_header
+    }
+
+    /// 创建配置构建器，未设置的选项使用默认值
+    ///
+    /// # 示例
+    /// ```
+    /// use trace_id::{TraceIdConfig, TraceIdLayer};
+    ///
+    /// let config = TraceIdConfig::builder()
+    ///     .enable_span(false)
+    ///     .enable_traceparent(true)
+    ///     .header_name(http::HeaderName::from_static("x-request-id"))
+    ///     .build();
+    /// let layer = TraceIdLayer::with_config(config);
+    /// ```
+    pub fn builder() -> TraceIdConfigBuilder {
+        TraceIdConfigBuilder::default()
+    }
+
+    /// 获取回写追踪ID所使用的响应头名称
+    ///
+    /// # 返回
+    /// 设置了 `response_header_name` 时返回它，否则返回 `header_name`
+    pub fn response_header(&self) -> &HeaderName {
+        self.response_header_name
+            .as_ref()
+            .unwrap_or(&self.header_name)
+    }
+
+    /// 判断请求路径是否匹配 `skip_paths`
+    pub(crate) fn is_skipped_path(&self, path: &str) -> bool {
+        self.skip_paths.iter().any(|prefix| {
+            path.strip_prefix(prefix.as_str()).is_some_and(|rest| {
+                rest.is_empty() || rest.starts_with('/') || prefix.ends_with('/')
+            })
+        })
+    }
+}
+
+/// 请求中没有有效追踪ID（头部缺失、无效，且没有可用的回退头部）时的处理策略
+///
+/// 由 `TraceIdConfig::missing_id_policy` 配置，决定 `extract_or_generate_trace_id` 与各中间件的行为
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MissingIdPolicy {
+    /// 生成新的追踪ID（默认），适用于直接面向外部客户端的边缘服务
+    #[default]
+    Generate,
+    /// 严格模式，视为违反调用约定：中间件直接返回 `400 Bad Request`，不调用内部处理器，
+    /// 响应中也不会写入追踪ID头部
+    ///
+    /// 通用的 `TraceIdLayer` 以 `ResBody::default()`（空响应体）构造该响应，
+    /// 并将 `TraceIdRejection` 写入响应扩展，外层可据此识别并替换响应体
+    Reject,
+    /// 生成新的追踪ID，同时记录一条 `WARN` 级别的事件，便于发现未传递追踪ID的上游
+    GenerateAndWarn,
+}
+
+/// 按 `MissingIdPolicy::Reject` 拒绝请求的原因
+///
+/// 通用的 `TraceIdLayer` 返回的 `400 Bad Request` 响应在扩展中携带该类型。
+/// 由于中间件对响应体类型只要求 `Default`，响应体为空；需要错误说明时，
+/// 外层服务（如 Axum 的 `map_response`）可以读取扩展并用 `to_response()` 替换响应，
+/// 启用 `axum` 特性时该类型也实现了 `IntoResponse`
+///
+/// # 示例
+/// ```
+/// use trace_id::TraceIdRejection;
+///
+/// let response: http::Response<String> = TraceIdRejection::Missing.to_response();
+/// assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+/// assert_eq!(response.extensions().get(), Some(&TraceIdRejection::Missing));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TraceIdRejection {
+    /// 请求没有携带追踪ID头部
+    Missing,
+    /// 请求携带了追踪ID头部（包括已启用的 `traceparent` 与追踪ID Cookie），但值无效
+    Invalid,
+}
+
+impl TraceIdRejection {
+    /// 按请求头判断拒绝原因
+    ///
+    /// 配置的追踪ID头部、已启用的 `traceparent` 或配置的追踪ID Cookie 任一存在时为 `Invalid`
+    pub(crate) fn from_headers(headers: &HeaderMap, config: &TraceIdConfig) -> Self {
+        let has_traceparent = config.enable_traceparent && headers.contains_key(TRACEPARENT_HEADER);
+        let has_cookie = config
+            .trace_id_cookie
+            .as_deref()
+            .is_some_and(|name| find_cookie(headers, name).is_some());
+
+        if has_inbound_header(headers, config) || has_traceparent || has_cookie {
+            Self::Invalid
+        } else {
+            Self::Missing
+        }
+    }
+
+    /// 拒绝响应的状态码（`400 Bad Request`）
+    pub fn status(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+
+    /// 构造带有错误说明的拒绝响应
+    ///
+    /// 响应体为 `Display` 的输出（`text/plain`），扩展中携带本类型。
+    /// `axum::body::Body`、`http_body_util::Full<Bytes>`、`String` 等响应体类型都实现了 `From<String>`
+    ///
+    /// # 返回
+    /// `400 Bad Request` 响应
+    pub fn to_response<B: From<String>>(&self) -> Response<B> {
+        let mut response = Response::new(B::from(self.to_string()));
+        *response.status_mut() = self.status();
+        response.headers_mut().insert(
+            http::header::CONTENT_TYPE,
+            HeaderValue::from_static("text/plain; charset=utf-8"),
+        );
+        response.extensions_mut().insert(*self);
+        response
+    }
+}
+
+impl std::fmt::Display for TraceIdRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Missing => f.write_str("missing trace id header"),
+            Self::Invalid => {
+                f.write_str("invalid trace id header: expected non-zero lowercase hex characters")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TraceIdRejection {}
+
+/// 跳过追踪的请求（`skip_paths` 或 `TraceIdLayer::with_skip` 匹配）的处理方式
+///
+/// 由 `TraceIdConfig::skip_mode` 配置
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SkipMode {
+    /// 完全跳过中间件（默认）：不解析或生成追踪ID，不设置上下文，不创建 span，
+    /// 也不写入响应头，开销与未安装中间件相同
+    #[default]
+    Bypass,
+    /// 只跳过 span 创建：追踪ID照常解析或生成、设置到上下文中并写入响应头
+    SpanOnly,
+}
+
+/// `TraceIdConfig` 的构建器，由 `TraceIdConfig::builder()` 创建
+///
+/// 各方法与 `TraceIdConfig` 的同名字段一一对应
+#[derive(Clone, Debug, Default)]
+pub struct TraceIdConfigBuilder {
+    config: TraceIdConfig,
+}
+
+impl TraceIdConfigBuilder {
+    /// 设置是否启用 tracing span
+    pub fn enable_span(mut self, enable: bool) -> Self {
+        self.config.enable_span = enable;
+        self
+    }
+
+    /// 设置是否启用响应头
+    pub fn enable_response_header(mut self, enable: bool) -> Self {
+        self.config.enable_response_header = enable;
+        self
+    }
+
+    /// 设置是否将追踪ID写入请求扩展
+    pub fn enable_extensions(mut self, enable: bool) -> Self {
+        self.config.enable_extensions = enable;
+        self
+    }
+
+    /// 设置是否读取 W3C `traceparent` 头部
+    pub fn enable_traceparent(mut self, enable: bool) -> Self {
+        self.config.enable_traceparent = enable;
+        self
+    }
+
+    /// 设置是否读取 W3C `tracestate` 头部
+    pub fn enable_tracestate(mut self, enable: bool) -> Self {
+        self.config.enable_tracestate = enable;
+        self
+    }
+
+    /// 设置是否在响应中原样回写 `tracestate` 头部
+    pub fn echo_tracestate(mut self, enable: bool) -> Self {
+        self.config.echo_tracestate = enable;
+        self
+    }
+
+    /// 设置是否在响应中写入完整的 `traceparent` 头部
+    pub fn echo_traceparent(mut self, enable: bool) -> Self {
+        self.config.echo_traceparent = enable;
+        self
+    }
+
+    /// 设置是否回退读取 B3（Zipkin）头部
+    pub fn enable_b3(mut self, enable: bool) -> Self {
+        self.config.enable_b3 = enable;
+        self
+    }
+
+    /// 设置是否回退读取 Datadog 头部
+    pub fn enable_datadog(mut self, enable: bool) -> Self {
+        self.config.enable_datadog = enable;
+        self
+    }
+
+    /// 设置是否回退读取 AWS X-Ray 头部
+    pub fn enable_xray(mut self, enable: bool) -> Self {
+        self.config.enable_xray = enable;
+        self
+    }
+
+    /// 设置是否使用64位短追踪ID
+    pub fn enable_short_ids(mut self, enable: bool) -> Self {
+        self.config.enable_short_ids = enable;
+        self
+    }
+
+    /// 设置是否生成完全随机的追踪ID
+    pub fn enable_random_ids(mut self, enable: bool) -> Self {
+        self.config.enable_random_ids = enable;
+        self
+    }
+
+    /// 设置读取与回写追踪ID所使用的头部名称
+    pub fn header_name(mut self, name: impl Into<HeaderName>) -> Self {
+        self.config.header_name = name.into();
+        self
+    }
+
+    /// 设置按优先级排列的入站追踪ID头部名称
+    pub fn header_names<I>(mut self, names: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<HeaderName>,
+    {
+        self.config.header_names = names.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// 设置回写追踪ID所使用的响应头名称（不设置时与 `header_name` 相同）
+    pub fn response_header_name(mut self, name: impl Into<HeaderName>) -> Self {
+        self.config.response_header_name = Some(name.into());
+        self
+    }
+
+    /// 设置入站追踪ID无效时记录事件的日志级别，None表示不记录
+    pub fn invalid_header_log_level(mut self, level: Option<tracing::Level>) -> Self {
+        self.config.invalid_header_log_level = level;
+        self
+    }
+
+    /// 设置是否将无效的入站追踪ID经 `TraceId::from_opaque` 映射为有效的追踪ID
+    pub fn map_opaque_ids(mut self, enable: bool) -> Self {
+        self.config.map_opaque_ids = enable;
+        self
+    }
+
+    /// 设置请求中没有有效追踪ID时的处理策略
+    pub fn missing_id_policy(mut self, policy: MissingIdPolicy) -> Self {
+        self.config.missing_id_policy = policy;
+        self
+    }
+
+    /// 设置作为关联字段传递的请求头名称
+    pub fn correlation_headers<I>(mut self, names: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<HeaderName>,
+    {
+        self.config.correlation_headers = names.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// 设置头部中没有有效追踪ID时回退读取的 Cookie 名称
+    pub fn trace_id_cookie(mut self, name: impl Into<String>) -> Self {
+        self.config.trace_id_cookie = Some(name.into());
+        self
+    }
+
+    /// 设置跳过追踪的请求路径（按路径段前缀匹配）
+    pub fn skip_paths<I>(mut self, paths: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.config.skip_paths = paths.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// 设置跳过追踪的请求的处理方式
+    pub fn skip_mode(mut self, mode: SkipMode) -> Self {
+        self.config.skip_mode = mode;
+        self
+    }
+
+    /// 设置是否记录请求的处理耗时
+    pub fn record_latency(mut self, enable: bool) -> Self {
+        self.config.record_latency = enable;
+        self
+    }
+
+    /// 设置是否在响应中写入 `x-response-time` 头部
+    pub fn latency_header(mut self, enable: bool) -> Self {
+        self.config.latency_header = enable;
+        self
+    }
+
+    /// 设置是否将追踪ID设置为活动的 OpenTelemetry 上下文
+    #[cfg(feature = "otel")]
+    pub fn enable_otel(mut self, enable: bool) -> Self {
+        self.config.enable_otel = enable;
+        self
+    }
+
+    /// 构建配置
+    pub fn build(self) -> TraceIdConfig {
+        self.config
+    }
+}
+
+/// 高性能追踪中间件层
+///
+/// 支持性能优化配置，只负责trace_id的提取、生成和传递
+#[derive(Clone)]
+pub struct TraceIdLayer {
+    generator: Option<Generator>,
+    generator_when: Option<ConditionalGenerator>,
+    make_span: Option<MakeSpan>,
+    skip: Option<SkipPredicate>,
+    sampler: Option<SharedSampler>,
+    metrics: Option<SharedMetrics>,
+    config: SharedConfig,
+}
+
+impl TraceIdLayer {
+    /// 创建新的追踪ID层，使用默认配置和高性能生成器
+    pub fn new() -> Self {
+        Self::with_config(TraceIdConfig::default())
+    }
+
+    /// 创建高性能模式的追踪ID层
+    ///
+    /// 禁用 tracing span 以获得最佳性能
+    pub fn new_high_performance() -> Self {
+        Self::with_config(TraceIdConfig {
+            enable_span: false,
+            ..TraceIdConfig::default()
+        })
+    }
+
+    /// 使用自定义配置创建追踪ID层
+    pub fn with_config(config: TraceIdConfig) -> Self {
+        Self {
+            generator: None,
+            generator_when: None,
+            make_span: None,
+            skip: None,
+            sampler: None,
+            metrics: None,
+            config: Arc::new(config),
+        }
+    }
+
+    /// 使用自定义的生成器创建追踪ID层
+    ///
+    /// 生成的字符串会按 W3C 规范校验，无效时回退到默认生成器
+    ///
+    /// # 参数
+    /// * `generator` - 一个返回String的函数，用于生成ID
+    ///
+    /// # 示例
+    /// ```
+    /// use trace_id::TraceIdLayer;
+    ///
+    /// // 使用nanoid作为生成器
+    /// // let layer = TraceIdLayer::new().with_generator(|| nanoid::nanoid!());
+    /// ```
+    pub fn with_generator<F>(mut self, generator: F) -> Self
+    where
+        F: Fn() -> String + Send + Sync + 'static,
+    {
+        self.generator = Some(Arc::new(move || {
+            TraceId::from_string_validated(&generator())
+        }));
+        self
+    }
+
+    /// 只对满足谓词的请求使用自定义生成器，其余请求使用常规生成器
+    ///
+    /// 适用于生成器开销较大、只在特定请求（如携带某个头部）上需要的场景。
+    /// 中间件为请求确定追踪ID时按以下顺序处理：
+    ///
+    /// 1. 请求携带了有效的追踪ID（或 `traceparent` 等已启用的协议头部）时沿用该ID，
+    ///    不调用谓词，也不调用任何生成器
+    /// 2. 按 `MissingIdPolicy::Reject` 拒绝请求时不调用谓词与生成器
+    /// 3. 需要生成新ID时才调用 `predicate`；返回true时调用本方法的 `generator`，
+    ///    结果与 `with_trace_id_generator` 一样直接使用，不经过字符串解析与校验
+    /// 4. 否则使用 `with_generator`/`with_trace_id_generator` 设置的生成器，未设置时使用默认生成器
+    ///    （`TraceId::new()`，或按 `enable_short_ids`/`enable_random_ids`）
+    ///
+    /// 谓词与 `generator` 每个请求至多各调用一次，`SkipMode::Bypass` 跳过的请求均不调用。
+    /// 调用者需自行保证 `generator` 不返回全零等无效ID。多次调用时以最后一次为准
+    ///
+    /// # 参数
+    /// * `predicate` - 接收请求头部信息，返回true表示使用 `generator`
+    /// * `generator` - 一个返回TraceId的函数，用于生成ID
+    ///
+    /// # 示例
+    /// ```
+    /// use trace_id::{TraceId, TraceIdLayer};
+    ///
+    /// // 常规请求使用 `TraceId::new()`，只有合作方的请求使用完全随机的ID
+    /// let layer = TraceIdLayer::new().with_generator_when(
+    ///     |parts| parts.headers.contains_key("x-partner-id"),
+    ///     TraceId::new_random,
+    /// );
+    /// ```
+    pub fn with_generator_when<P, F>(mut self, predicate: P, generator: F) -> Self
+    where
+        P: Fn(&Parts) -> bool + Send + Sync + 'static,
+        F: Fn() -> TraceId + Send + Sync + 'static,
+    {
+        self.generator_when = Some((Arc::new(predicate), Arc::new(generator)));
+        self
+    }
+
+    /// 使用直接返回 `TraceId` 的自定义生成器创建追踪ID层
+    ///
+    /// 与 `with_generator` 不同，生成结果不经过字符串解析与校验，直接作为追踪ID使用；
+    /// 调用者需自行保证不返回全零等无效ID
+    ///
+    /// # 参数
+    /// * `generator` - 一个返回TraceId的函数，用于生成ID
+    ///
+    /// # 示例
+    /// ```
+    /// use trace_id::{TraceId, TraceIdLayer};
+    ///
+    /// let layer = TraceIdLayer::new().with_trace_id_generator(|| TraceId::new_with_rng(&|| 42));
+    /// ```
+    pub fn with_trace_id_generator<F>(mut self, generator: F) -> Self
+    where
+        F: Fn() -> TraceId + Send + Sync + 'static,
+    {
+        self.generator = Some(Arc::new(move || Some(generator())));
+        self
+    }
+
+    /// 使用 v4 UUID 作为追踪ID生成器
+    ///
+    /// 等价于 `with_trace_id_generator(TraceId::new_uuid)`。
+    /// 带连字符的 `Uuid::to_string()` 不符合32字符的格式要求，不能直接交给 `with_generator`
+    ///
+    /// # 示例
+    /// ```
+    /// use trace_id::TraceIdLayer;
+    ///
+    /// let layer = TraceIdLayer::new().with_uuid_generator();
+    /// ```
+    #[cfg(feature = "uuid")]
+    pub fn with_uuid_generator(self) -> Self {
+        self.with_trace_id_generator(TraceId::new_uuid)
+    }
+
+    /// 使用自定义函数创建请求 span
+    ///
+    /// 默认创建 `info_span!("request", trace_id, method, uri)`。
+    /// 由于 tracing 的 span 名称与字段名需要在编译期确定，
+    /// 自定义名称、路由或静态字段（如 `service.name`）需通过该函数构造。
+    /// 禁用 `enable_span` 时不会调用。
+    ///
+    /// # 参数
+    /// * `make_span` - 接收请求头部信息与追踪ID，返回要进入的 span
+    ///
+    /// # 示例
+    /// ```
+    /// use trace_id::TraceIdLayer;
+    ///
+    /// let layer = TraceIdLayer::new().with_make_span(|parts, trace_id| {
+    ///     tracing::info_span!(
+    ///         "http",
+    ///         service.name = "orders",
+    ///         trace_id = %trace_id,
+    ///         path = %parts.uri.path(),
+    ///     )
+    /// });
+    /// ```
+    pub fn with_make_span<F>(mut self, make_span: F) -> Self
+    where
+        F: Fn(&Parts, &TraceId) -> Span + Send + Sync + 'static,
+    {
+        self.make_span = Some(Arc::new(make_span));
+        self
+    }
+
+    /// 使用自定义谓词决定请求是否跳过追踪
+    ///
+    /// 与 `TraceIdConfig::skip_paths` 任一匹配即跳过，按 `TraceIdConfig::skip_mode` 处理。
+    /// 谓词在每个请求上调用，应只做轻量的判断
+    ///
+    /// # 参数
+    /// * `predicate` - 接收请求头部信息，返回true表示跳过
+    ///
+    /// # 示例
+    /// ```
+    /// use trace_id::TraceIdLayer;
+    ///
+    /// // 跳过 Kubernetes 探针的请求
+    /// let layer = TraceIdLayer::new().with_skip(|parts| {
+    ///     parts
+    ///         .headers
+    ///         .get(http::header::USER_AGENT)
+    ///         .is_some_and(|agent| agent.as_bytes().starts_with(b"kube-probe/"))
+    /// });
+    /// ```
+    pub fn with_skip<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&Parts) -> bool + Send + Sync + 'static,
+    {
+        self.skip = Some(Arc::new(predicate));
+        self
+    }
+
+    /// 使用采样器决定是否创建 span 以及向下游传播的 trace-flags
+    ///
+    /// 上游 `traceparent` 存在时沿用其采样决策（parent-based），
+    /// 否则由采样器根据追踪ID决定；决策通过 `get_trace_flags()` 暴露给下游传播。
+    /// 未设置采样器时所有请求均创建 span，且不设置 trace-flags。
+    ///
+    /// # 参数
+    /// * `sampler` - 采样器（如 `RatioSampler::new(0.1)`）
+    ///
+    /// # 示例
+    /// ```
+    /// use trace_id::{RatioSampler, TraceIdLayer};
+    ///
+    /// let layer = TraceIdLayer::new().with_sampler(RatioSampler::new(0.1));
+    /// ```
+    pub fn with_sampler<T>(mut self, sampler: T) -> Self
+    where
+        T: Sampler + Send + Sync + 'static,
+    {
+        self.sampler = Some(Arc::new(sampler));
+        self
+    }
+
+    /// 注册追踪ID来源的回调，用于统计追踪覆盖率
+    ///
+    /// 每个请求会触发 `on_propagated` 或 `on_generated` 之一；配置的头部存在但无效时额外触发
+    /// `on_invalid_header`，自定义生成器结果无效时额外触发 `on_generator_fallback`。
+    /// 未注册时不产生任何额外开销。
+    ///
+    /// # 参数
+    /// * `metrics` - 回调实现，如 `Arc<TraceIdMetrics>`
+    ///
+    /// # 示例
+    /// ```
+    /// use std::sync::Arc;
+    /// use trace_id::{TraceIdLayer, TraceIdMetrics};
+    ///
+    /// let metrics = Arc::new(TraceIdMetrics::new());
+    /// let layer = TraceIdLayer::new().with_metrics(metrics.clone());
+    /// assert_eq!(metrics.snapshot().generated, 0);
+    /// ```
+    pub fn with_metrics<M>(mut self, metrics: M) -> Self
+    where
+        M: MetricsHook + Send + Sync + 'static,
+    {
+        self.metrics = Some(Arc::new(metrics));
+        self
+    }
+
+    /// 设置读取与回写追踪ID所使用的头部名称
+    ///
+    /// 同时控制从哪个请求头读取追踪ID，以及写入哪个响应头，默认为 `x-trace-id`；
+    /// 响应头需要使用不同名称时配合 `with_response_header_name`
+    ///
+    /// # 参数
+    /// * `name` - 头部名称（例如 `x-request-id`、`x-correlation-id`）
+    ///
+    /// # 示例
+    /// ```
+    /// use http::HeaderName;
+    /// use trace_id::TraceIdLayer;
+    ///
+    /// let layer = TraceIdLayer::new().with_header_name(HeaderName::from_static("x-request-id"));
+    /// ```
+    pub fn with_header_name(mut self, name: impl Into<HeaderName>) -> Self {
+        Arc::make_mut(&mut self.config).header_name = name.into();
+        self
+    }
+
+    /// 设置按优先级排列的入站追踪ID头部名称
+    ///
+    /// 依次尝试各头部并使用第一个有效的追踪ID，均无效时才生成新ID。
+    /// 列表中的 `traceparent` 会解析出其中的 trace-id，而不是将整个头部值当作追踪ID。
+    /// 响应头仍写入 `header_name`（默认 `x-trace-id`）或 `with_response_header_name` 设置的名称
+    ///
+    /// # 参数
+    /// * `names` - 按优先级排列的头部名称
+    ///
+    /// # 示例
+    /// ```
+    /// use http::HeaderName;
+    /// use trace_id::TraceIdLayer;
+    ///
+    /// let layer = TraceIdLayer::new().with_header_names(vec![
+    ///     HeaderName::from_static("traceparent"),
+    ///     HeaderName::from_static("x-trace-id"),
+    ///     HeaderName::from_static("x-request-id"),
+    /// ]);
+    /// ```
+    pub fn with_header_names(mut self, names: Vec<HeaderName>) -> Self {
+        Arc::make_mut(&mut self.config).header_names = names;
+        self
+    }
+
+    /// 设置回写追踪ID所使用的响应头名称，与读取的请求头名称分开配置
+    ///
+    /// 设置为 `traceparent` 时写入完整的 W3C `traceparent`（追踪ID、本次请求的跨度ID与 trace-flags）
+    ///
+    /// # 参数
+    /// * `name` - 响应头名称
+    ///
+    /// # 示例
+    /// ```
+    /// use http::HeaderName;
+    /// use trace_id::TraceIdLayer;
+    ///
+    /// // 从 `x-request-id` 读取，向下一跳写出 `traceparent`
+    /// let layer = TraceIdLayer::new()
+    ///     .with_header_name(HeaderName::from_static("x-request-id"))
+    ///     .with_response_header_name(HeaderName::from_static("traceparent"));
+    /// ```
+    pub fn with_response_header_name(mut self, name: impl Into<HeaderName>) -> Self {
+        Arc::make_mut(&mut self.config).response_header_name = Some(name.into());
+        self
+    }
+
+    /// 获取当前的中间件配置
+    pub fn config(&self) -> &TraceIdConfig {
+        &self.config
+    }
+
+    /// 获取配置的可变引用，用于在已有层的基础上修改配置
+    ///
+    /// 配置与克隆出的其他层共享时先复制一份，修改不会影响其他层；
+    /// 生成器、span 构造函数、采样器与指标保持不变
+    ///
+    /// # 示例
+    /// ```
+    /// use trace_id::{TraceId, TraceIdLayer};
+    ///
+    /// let base = TraceIdLayer::new().with_trace_id_generator(TraceId::new_short);
+    ///
+    /// // 复用 base 的生成器，只关闭 span
+    /// let mut quiet = base.clone();
+    /// quiet.config_mut().enable_span = false;
+    /// assert!(base.config().enable_span);
+    /// assert!(quiet.generator().is_some());
+    /// ```
+    pub fn config_mut(&mut self) -> &mut TraceIdConfig {
+        Arc::make_mut(&mut self.config)
+    }
+
+    /// 获取配置的自定义生成器，未配置时返回None
+    ///
+    /// 字符串生成器（`with_generator`）返回的是包装后的函数，结果无效时返回None；
+    /// 未配置生成器时中间件使用 `TraceId::new()`
+    pub fn generator(&self) -> Option<&(dyn Fn() -> Option<TraceId> + Send + Sync)> {
+        self.generator.as_deref()
+    }
+}
+
+impl Default for TraceIdLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for TraceIdLayer {
+    type Service = TraceIdService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TraceIdService {
+            inner,
+            generator: self.generator.clone(),
+            generator_when: self.generator_when.clone(),
+            make_span: self.make_span.clone(),
+            skip: self.skip.clone(),
+            sampler: self.sampler.clone(),
+            metrics: self.metrics.clone(),
+            config: Arc::clone(&self.config),
+        }
+    }
+}
+
+/// 高性能追踪ID服务
+///
+/// 响应体类型需要实现 `Default`：按 `MissingIdPolicy::Reject` 拒绝请求时不调用内部服务，
+/// 以 `ResBody::default()` 构造 `400 Bad Request` 响应（扩展中携带 `TraceIdRejection`）。
+/// `axum::body::Body`、`http_body_util::Full<Bytes>`、`String` 与 `()` 都满足该约束
+#[derive(Clone)]
+pub struct TraceIdService<S> {
+    inner: S,
+    generator: Option<Generator>,
+    generator_when: Option<ConditionalGenerator>,
+    make_span: Option<MakeSpan>,
+    skip: Option<SkipPredicate>,
+    sampler: Option<SharedSampler>,
+    metrics: Option<SharedMetrics>,
+    config: SharedConfig,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for TraceIdService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>> + Send + 'static,
+    S::Future: Send + 'static,
+    ResBody: Default + Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Self::Response, Self::Error>> + Send>,
+    >;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        // 根据配置从收到请求开始计时
+        let start = self.config.measures_latency().then(Instant::now);
+
+        // 判断是否跳过追踪（健康检查等），完全跳过时直接调用内部服务
+        let mut skipped = self.config.is_skipped_path(req.uri().path());
+        if let (false, Some(skip)) = (skipped, &self.skip) {
+            let (parts, body) = req.into_parts();
+            skipped = skip(&parts);
+            req = Request::from_parts(parts, body);
+        }
+        if skipped && self.config.skip_mode == SkipMode::Bypass {
+            return Box::pin(self.inner.call(req));
+        }
+
+        // 根据配置解析上游的 traceparent
+        let trace_context = if self.config.enable_traceparent {
+            extract_trace_context(req.headers())
+        } else {
+            None
+        };
+
+        // 根据配置解析上游的 tracestate
+        let trace_state = if self.config.enable_tracestate {
+            extract_trace_state(req.headers())
+        } else {
+            None
+        };
+
+        // 根据配置收集关联字段
+        let correlation = extract_correlation(req.headers(), &self.config);
+
+        // 从请求头中获取或生成追踪ID；`with_generator_when` 的谓词只在需要生成新ID时调用
+        let (parts, body) = req.into_parts();
+        let generator = self
+            .generator
+            .as_deref()
+            .map(|generator_fn| generator_fn as &dyn Fn() -> Option<TraceId>);
+        let (config, metrics) = (&*self.config, self.metrics.as_deref());
+        let conditional = self
+            .generator_when
+            .as_ref()
+            .map(|(predicate, conditional)| {
+                let parts = &parts;
+                move || {
+                    Some(if predicate(parts) {
+                        conditional()
+                    } else {
+                        generate_trace_id(generator, config, metrics)
+                    })
+                }
+            });
+        let resolved = resolve_trace_id(
+            &parts.headers,
+            trace_context.as_ref(),
+            config,
+            match &conditional {
+                Some(conditional) => Some(conditional),
+                None => generator,
+            },
+            metrics,
+        );
+        req = Request::from_parts(parts, body);
+
+        let Some(trace_id) = resolved else {
+            // 按 `MissingIdPolicy::Reject` 拒绝请求，不调用内部服务；
+            // 响应体类型只要求 `Default`，拒绝原因通过扩展传递
+            let rejection = TraceIdRejection::from_headers(req.headers(), &self.config);
+            let mut response = Response::new(ResBody::default());
+            *response.status_mut() = rejection.status();
+            response.extensions_mut().insert(rejection);
+            return Box::pin(async move { Ok(response) });
+        };
+
+        // 将追踪ID添加到请求扩展中，下层服务在同步的 `call` 中即可通过 `TraceIdExt` 读取
+        if self.config.enable_extensions {
+            req.extensions_mut().insert(trace_id);
+        }
+
+        // 写入响应的追踪ID只确定一次，响应头与 trailers 共用
+        let response_id = response_trace_id(trace_id);
+        req.extensions_mut().insert(ResponseTraceId(response_id));
+
+        // 根据采样器决定 trace-flags，上游 traceparent 存在时沿用其决策
+        let trace_flags = self.sampler.as_ref().map(|sampler| match &trace_context {
+            Some(ctx) => ctx.flags,
+            None if sampler.should_sample(&trace_id) => TRACE_FLAG_SAMPLED,
+            None => 0,
+        });
+        let sampled = trace_flags.is_none_or(|flags| flags & TRACE_FLAG_SAMPLED != 0);
+
+        // 根据配置构造 OpenTelemetry 上下文，存在上游 traceparent 时沿用其 parent-id
+        #[cfg(feature = "otel")]
+        let otel_cx = self.config.enable_otel.then(|| match &trace_context {
+            Some(ctx) => otel::context_with_trace_context(ctx),
+            None => otel::context_with_trace_id(&trace_id),
+        });
+
+        // 为本次请求生成跨度ID，与追踪ID一起记录到 span 中
+        let span_id = SpanId::new();
+
+        // 根据配置与采样决策决定是否创建 span（在req被移动之前）
+        let span = if self.config.enable_span && sampled && !skipped {
+            // 仅在创建 span 时激活 OTel 上下文
+            #[cfg(feature = "otel")]
+            let _guard = otel_cx.clone().map(opentelemetry::Context::attach);
+            Some(match &self.make_span {
+                Some(make_span) => {
+                    let (parts, body) = req.into_parts();
+                    let span = make_span(&parts, &trace_id);
+                    req = Request::from_parts(parts, body);
+                    span
+                }
+                None => tracing::info_span!(
+                    "request",
+                    trace_id = %trace_id.as_str(),
+                    span_id = %span_id.as_str(),
+                    method = %req.method(),
+                    uri = %req.uri(),
+                    latency_ms = tracing::field::Empty
+                ),
+            })
+        } else {
+            None
+        };
+
+        let future = self.inner.call(req);
+
+        let config = Arc::clone(&self.config);
+        let latency_span = span.clone().filter(|_| config.record_latency);
+
+        Box::pin(async move {
+            // 需要回写到响应中的 tracestate
+            let echo_state = if config.echo_tracestate {
+                trace_state.as_ref().map(TraceState::to_header)
+            } else {
+                None
+            };
+
+            // 需要写入响应中的 traceparent
+            let traceparent = response_traceparent(
+                &config,
+                trace_id,
+                &span_id,
+                trace_context.as_ref(),
+                trace_flags,
+            );
+
+            let handle = async move {
+                let mut response = future.await?;
+
+                // 内部服务返回响应时结束计时
+                if let Some(start) = start {
+                    let latency = start.elapsed();
+                    if config.record_latency {
+                        if let Some(span) = &latency_span {
+                            record_latency(span, latency);
+                        }
+                        response.extensions_mut().insert(ResponseLatency(latency));
+                    }
+                    if config.latency_header {
+                        response
+                            .headers_mut()
+                            .insert(RESPONSE_TIME_HEADER, response_time_value(latency));
+                    }
+                }
+
+                // 根据配置决定是否添加响应头
+                if config.enable_response_header {
+                    response.headers_mut().insert(
+                        config.response_header().clone(),
+                        response_id.to_header_value(),
+                    );
+                }
+
+                if let Some(Ok(header_value)) = traceparent.map(|header| header.parse()) {
+                    response
+                        .headers_mut()
+                        .insert(TRACEPARENT_HEADER, header_value);
+                }
+
+                // 原样回写上游的 tracestate
+                if let Some(Ok(header_value)) = echo_state.map(|state| state.parse()) {
+                    response
+                        .headers_mut()
+                        .insert(TRACESTATE_HEADER, header_value);
+                }
+
+                Ok(response)
+            };
+
+            #[cfg(feature = "otel")]
+            let handle = with_otel_context(handle, otel_cx);
+
+            // 嵌套的上下文作用域会多次复制内部 future，先装箱以避免调用栈上出现过大的 future
+            let handle = Box::pin(handle);
+
+            match span {
+                // 在span和task_local上下文中执行请求处理
+                Some(span) => {
+                    scope(
+                        trace_id,
+                        trace_context,
+                        trace_state,
+                        trace_flags,
+                        Some(span_id),
+                        correlation,
+                        handle,
+                    )
+                    .instrument(span)
+                    .await
+                }
+                // 高性能模式或未采样：跳过 span 创建
+                None => {
+                    scope(
+                        trace_id,
+                        trace_context,
+                        trace_state,
+                        trace_flags,
+                        Some(span_id),
+                        correlation,
+                        handle,
+                    )
+                    .await
+                }
+            }
+        })
+    }
+}
+
+/// 在追踪上下文中执行异步操作
+///
+/// 存在上游 `traceparent` 时设置完整上下文，否则仅设置追踪ID；
+/// 存在上游 `tracestate`、采样决策、跨度ID或关联字段时一并设置
+pub(crate) async fn scope<F, T>(
+    trace_id: TraceId,
+    trace_context: Option<TraceContext>,
+    trace_state: Option<TraceState>,
+    trace_flags: Option<u8>,
+    span_id: Option<SpanId>,
+    correlation: Option<CorrelationFields>,
+    future: F,
+) -> T
+where
+    F: std::future::Future<Output = T>,
+{
+    let future = async move {
+        match correlation {
+            Some(fields) => context::with_correlation(fields, future).await,
+            None => future.await,
+        }
+    };
+
+    let future = async move {
+        match span_id {
+            Some(span_id) => context::with_span_id(span_id, future).await,
+            None => future.await,
+        }
+    };
+
+    let future = async move {
+        match trace_context {
+            Some(ctx) => context::with_trace_context(ctx, future).await,
+            None => context::with_trace_id(trace_id, future).await,
+        }
+    };
+
+    let future = async move {
+        match trace_state {
+            Some(state) => context::with_trace_state(state, future).await,
+            None => future.await,
+        }
+    };
+
+    match trace_flags {
+        Some(flags) => context::with_trace_flags(flags, future).await,
+        None => future.await,
+    }
+}
+
+/// 确定写入响应（响应头与 trailers）的追踪ID
+///
+/// 追踪ID不符合 W3C 格式（如 `with_trace_id_generator` 返回了
+/// `from_string_unchecked` 解码非法输入得到的全零ID）时记录警告，
+/// 并为响应生成新的合法ID，保证客户端总能拿到可用的追踪ID。
+/// 请求上下文中的追踪ID不受影响。每个请求只应调用一次，
+/// 同一响应的头部与 trailers 使用同一个结果（见 `ResponseTraceId`）
+pub(crate) fn response_trace_id(trace_id: TraceId) -> TraceId {
+    if !trace_id.is_nil() {
+        return trace_id;
+    }
+
+    let replacement = TraceId::new();
+    tracing::warn!(
+        %trace_id,
+        %replacement,
+        reason = %TraceIdParseError::AllZeros,
+        "non-conformant trace id, using a new one for the response"
+    );
+    replacement
+}
+
+/// `TraceIdService` 为本次请求确定的响应追踪ID（见 `response_trace_id`），
+/// 写入请求扩展供内层的 `with_trailers()` 复用，使响应头与 trailers 一致
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ResponseTraceId(pub(crate) TraceId);
+
+/// 请求的处理耗时，启用 `TraceIdConfig::record_latency` 时写入响应扩展
+///
+/// 从中间件收到请求到内部服务返回响应（不含响应体的传输）的时间，
+/// 不依赖 span，外层服务可据此统计请求耗时
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ResponseLatency(pub Duration);
+
+/// 将处理耗时以毫秒（保留微秒精度）记录到 span 的 `latency_ms` 字段
+pub(crate) fn record_latency(span: &Span, latency: Duration) {
+    span.record("latency_ms", latency.as_secs_f64() * 1000.0);
+}
+
+/// 构造 `x-response-time` 头部值，如 `12.345ms`
+pub(crate) fn response_time_value(latency: Duration) -> HeaderValue {
+    let millis = format!("{:.3}ms", latency.as_secs_f64() * 1000.0);
+    // 数字与字母组成的字符串总是合法的头部值
+    HeaderValue::from_str(&millis).unwrap_or(HeaderValue::from_static("0ms"))
+}
+
+/// 按配置构造写入响应的 `traceparent`
+///
+/// 需启用 `enable_response_header`，以及 `enable_traceparent`、`echo_traceparent` 之一，
+/// 或将响应头名称设置为 `traceparent`（此时覆盖先写入的单独追踪ID）。
+/// parent-id 为本次请求的跨度ID，trace-flags 依次取采样决策、上游 `traceparent` 的值，
+/// 均不存在时视为已采样
+pub(crate) fn response_traceparent(
+    config: &TraceIdConfig,
+    trace_id: TraceId,
+    span_id: &SpanId,
+    trace_context: Option<&TraceContext>,
+    trace_flags: Option<u8>,
+) -> Option<String> {
+    let enabled = config.enable_traceparent
+        || config.echo_traceparent
+        || config.response_header() == TRACEPARENT_HEADER;
+    if !(enabled && config.enable_response_header) {
+        return None;
+    }
+
+    let flags = trace_flags
+        .or(trace_context.map(|ctx| ctx.flags))
+        .unwrap_or(TRACE_FLAG_SAMPLED);
+    let ctx = TraceContext {
+        trace_id,
+        parent_id: span_id.clone(),
+        flags,
+    };
+    Some(ctx.to_header())
+}
+
+/// 在指定的 OpenTelemetry 上下文中执行异步操作（每次poll时激活）
+#[cfg(feature = "otel")]
+pub(crate) async fn with_otel_context<F>(
+    future: F,
+    otel_cx: Option<opentelemetry::Context>,
+) -> F::Output
+where
+    F: std::future::Future,
+{
+    use opentelemetry::context::FutureExt;
+
+    match otel_cx {
+        Some(otel_cx) => future.with_context(otel_cx).await,
+        None => future.await,
+    }
+}
+
+/// 按配置从请求头中收集关联字段，没有任何字段时返回None
+pub(crate) fn extract_correlation(
+    headers: &HeaderMap,
+    config: &TraceIdConfig,
+) -> Option<CorrelationFields> {
+    let fields: CorrelationFields = config
+        .correlation_headers
+        .iter()
+        .filter_map(|name| Some((name.as_str(), headers.get(name)?.to_str().ok()?)))
+        .collect();
+    (!fields.is_empty()).then_some(fields)
+}
+
+/// 从请求头中解析 W3C `tracestate`
+pub(crate) fn extract_trace_state(headers: &HeaderMap) -> Option<TraceState> {
+    let header_value = headers.get(TRACESTATE_HEADER)?;
+    TraceState::parse(header_value.to_str().ok()?)
+}
+
+/// 从请求头中解析 W3C `traceparent`
+pub(crate) fn extract_trace_context(headers: &HeaderMap) -> Option<TraceContext> {
+    let header_value = headers.get(TRACEPARENT_HEADER)?;
+    TraceContext::parse(header_value.to_str().ok()?)
+}
+
+/// 从请求头中提取或生成新的追踪ID
+///
+/// 这是本crate标准的追踪ID提取流程，与 `TraceIdLayer` 的行为完全一致：
+/// 上游 `traceparent`（需启用 `enable_traceparent`） > 配置的追踪ID头部（依次） >
+/// 已启用的 B3/Datadog/X-Ray 头部 > 按 `missing_id_policy` 处理。头部名称不区分大小写，
+/// 值两侧的ASCII空白去除后再校验，无效时视为缺失。
+///
+/// 为尚未支持的框架（salvo、poem、rocket 等）编写中间件时应调用该函数，
+/// 而不是自行解析请求头；本crate的各框架集成同样基于它实现。
+///
+/// # 参数
+/// * `headers` - 请求头
+/// * `config` - 中间件配置，通常为 `TraceIdConfig::default()`
+/// * `generator` - 自定义ID生成器，None时使用 `TraceId::new()`
+///
+/// # 返回
+/// 提取到的或新生成的追踪ID；策略为 `MissingIdPolicy::Reject` 且没有有效ID时返回None，
+/// 中间件应据此返回 `400 Bad Request`
+///
+/// # 示例
+/// ```
+/// use http::HeaderMap;
+/// use trace_id::{extract_or_generate_trace_id, TraceIdConfig, TRACE_ID_HEADER};
+///
+/// let mut headers = HeaderMap::new();
+/// headers.insert(TRACE_ID_HEADER, "0af7651916cd43dd8448eb211c80319c".parse().unwrap());
+///
+/// let trace_id = extract_or_generate_trace_id(&headers, &TraceIdConfig::default(), None);
+/// assert_eq!(trace_id.unwrap().as_str(), "0af7651916cd43dd8448eb211c80319c");
+/// ```
+pub fn extract_or_generate_trace_id(
+    headers: &HeaderMap,
+    config: &TraceIdConfig,
+    generator: Option<&dyn Fn() -> TraceId>,
+) -> Option<TraceId> {
+    let trace_context = if config.enable_traceparent {
+        extract_trace_context(headers)
+    } else {
+        None
+    };
+    let generator = generator.map(|generator_fn| move || Some(generator_fn()));
+
+    resolve_trace_id(
+        headers,
+        trace_context.as_ref(),
+        config,
+        generator
+            .as_ref()
+            .map(|generator_fn| generator_fn as &dyn Fn() -> Option<TraceId>),
+        None,
+    )
+}
+
+/// 从请求头中提取或生成新的追踪ID（高性能版本）
+///
+/// 与 `extract_or_generate_trace_id` 相同，但由调用方传入已解析的 `traceparent`，
+/// 生成器可返回无效结果（回退到默认生成器）。注册了 `metrics` 时按追踪ID的来源触发对应回调，
+/// 被拒绝的请求不计入 `on_generated`
+pub(crate) fn resolve_trace_id(
+    headers: &HeaderMap,
+    trace_context: Option<&TraceContext>,
+    config: &TraceIdConfig,
+    generator: Option<&dyn Fn() -> Option<TraceId>>,
+    metrics: Option<&(dyn MetricsHook + Send + Sync)>,
+) -> Option<TraceId> {
+    let extracted = match trace_context {
+        Some(ctx) => Some(ctx.trace_id),
+        None => extract_inbound_trace_id(headers, config)
+            .or_else(|| extract_fallback_trace_id(headers, config))
+            .or_else(|| map_opaque_trace_id(headers, config)),
+    };
+
+    if let Some(trace_id) = extracted {
+        notify(metrics, |hook| hook.on_propagated());
+        return Some(trace_id);
+    }
+
+    if (cfg!(feature = "metrics") || metrics.is_some()) && has_inbound_header(headers, config) {
+        notify(metrics, |hook| hook.on_invalid_header());
+    }
+
+    match config.missing_id_policy {
+        MissingIdPolicy::Generate => {}
+        MissingIdPolicy::Reject => return None,
+        MissingIdPolicy::GenerateAndWarn => {
+            tracing::warn!("request has no valid inbound trace id, generating a new one");
+        }
+    }
+
+    notify(metrics, |hook| hook.on_generated());
+    Some(generate_trace_id(generator, config, metrics))
+}
+
+/// 触发追踪ID来源回调；启用 `metrics` 特性时同时写入 `metrics` 门面的计数器
+fn notify(metrics: Option<&(dyn MetricsHook + Send + Sync)>, event: impl Fn(&dyn MetricsHook)) {
+    #[cfg(feature = "metrics")]
+    event(&MetricsExporter);
+    if let Some(metrics) = metrics {
+        event(metrics);
+    }
+}
+
+/// 判断请求是否携带了配置的追踪ID头部（无论值是否有效）
+fn has_inbound_header(headers: &HeaderMap, config: &TraceIdConfig) -> bool {
+    if config.header_names.is_empty() {
+        headers.contains_key(&config.header_name)
+    } else {
+        config
+            .header_names
+            .iter()
+            .any(|name| headers.contains_key(name))
+    }
+}
+
+/// 按配置的入站头部名称依次提取追踪ID
+pub(crate) fn extract_inbound_trace_id(
+    headers: &HeaderMap,
+    config: &TraceIdConfig,
+) -> Option<TraceId> {
+    if config.header_names.is_empty() {
+        return extract_trace_id(headers, &config.header_name, config);
+    }
+
+    config.header_names.iter().find_map(|name| {
+        if name == TRACEPARENT_HEADER {
+            extract_trace_context(headers).map(|ctx| ctx.trace_id)
+        } else {
+            extract_trace_id(headers, name, config)
+        }
+    })
+}
+
+/// 从指定请求头中提取追踪ID（高性能版本）
+///
+/// 头部名称按 HTTP 规范不区分大小写（`HeaderMap` 保证）；值两侧的ASCII空白在校验前去除。
+/// 启用 `enable_short_ids` 时同时接受16字符的短ID；头部存在但无效时按配置记录事件
+fn extract_trace_id(
+    headers: &HeaderMap,
+    header_name: &HeaderName,
+    config: &TraceIdConfig,
+) -> Option<TraceId> {
+    let value = headers.get(header_name)?;
+
+    // 快速路径：直接在头部值的字节上校验，跳过UTF-8检查
+    if let Some(trace_id) = TraceId::from_header_value(value) {
+        return Some(trace_id);
+    }
+
+    let Ok(id_str) = value.to_str() else {
+        log_invalid_header(config, header_name, value.as_bytes(), &"not visible ASCII");
+        return None;
+    };
+    let id_str = id_str.trim_ascii();
+
+    // 去除两侧空白后再次解析：与快速路径、`from_string_validated` 使用同一套校验规则
+    // （包括拒绝全零ID），失败时同时得到原因
+    let reason = match id_str.parse::<TraceId>() {
+        Ok(trace_id) => return Some(trace_id),
+        Err(reason) => reason,
+    };
+    if config.enable_short_ids {
+        if let Some(trace_id) = TraceId::from_string_validated_short(id_str) {
+            return Some(trace_id);
+        }
+    }
+
+    log_invalid_header(config, header_name, id_str.as_bytes(), &reason);
+    None
+}
+
+/// 事件中保留的无效头部值的最大字节数
+const INVALID_VALUE_MAX_LEN: usize = 64;
+
+/// 按配置的级别记录被拒绝的入站追踪ID
+fn log_invalid_header(
+    config: &TraceIdConfig,
+    header_name: &HeaderName,
+    value: &[u8],
+    reason: &dyn std::fmt::Display,
+) {
+    use tracing::Level;
+
+    let Some(level) = config.invalid_header_log_level else {
+        return;
+    };
+    let value = sanitize_header_value(value);
+    const MESSAGE: &str = "rejected invalid inbound trace id";
+    match level {
+        Level::ERROR => tracing::error!(header = %header_name, %value, %reason, "{MESSAGE}"),
+        Level::WARN => tracing::warn!(header = %header_name, %value, %reason, "{MESSAGE}"),
+        Level::INFO => tracing::info!(header = %header_name, %value, %reason, "{MESSAGE}"),
+        Level::DEBUG => tracing::debug!(header = %header_name, %value, %reason, "{MESSAGE}"),
+        Level::TRACE => tracing::trace!(header = %header_name, %value, %reason, "{MESSAGE}"),
+    }
+}
+
+/// 截断并转义头部值，控制字符、引号与非ASCII字节均以转义形式输出
+fn sanitize_header_value(value: &[u8]) -> String {
+    let truncated = &value[..value.len().min(INVALID_VALUE_MAX_LEN)];
+    let mut sanitized: String = truncated.escape_ascii().to_string();
+    if value.len() > INVALID_VALUE_MAX_LEN {
+        sanitized.push_str("...");
+    }
+    sanitized
+}
+
+/// 按配置从其他追踪协议的头部中提取追踪ID
+fn extract_fallback_trace_id(headers: &HeaderMap, config: &TraceIdConfig) -> Option<TraceId> {
+    if config.enable_b3 {
+        if let Some(trace_id) = b3::extract(headers) {
+            return Some(trace_id);
+        }
+    }
+
+    if config.enable_datadog {
+        if let Some(trace_id) = datadog::extract(headers) {
+            return Some(trace_id);
+        }
+    }
+
+    if config.enable_xray {
+        if let Some(trace_id) = xray::extract(headers) {
+            return Some(trace_id);
+        }
+    }
+
+    if let Some(name) = &config.trace_id_cookie {
+        return extract_cookie_trace_id(headers, name, config);
+    }
+
+    None
+}
+
+/// 启用 `map_opaque_ids` 时，将第一个存在的非空入站追踪ID头部（或追踪ID Cookie）的值
+/// 经 `TraceId::from_opaque` 映射为追踪ID，并记录原始值与映射结果的对应关系
+fn map_opaque_trace_id(headers: &HeaderMap, config: &TraceIdConfig) -> Option<TraceId> {
+    if !config.map_opaque_ids {
+        return None;
+    }
+
+    let names = if config.header_names.is_empty() {
+        std::slice::from_ref(&config.header_name)
+    } else {
+        &config.header_names
+    };
+    let (source, value) = names
+        .iter()
+        .filter(|name| *name != TRACEPARENT_HEADER)
+        .find_map(|name| {
+            let value = headers.get(name)?.to_str().ok()?.trim_ascii();
+            (!value.is_empty()).then_some((name, value))
+        })
+        .or_else(|| {
+            let value = find_cookie(headers, config.trace_id_cookie.as_deref()?)?;
+            (!value.is_empty()).then_some((&http::header::COOKIE, value))
+        })?;
+
+    let trace_id = TraceId::from_opaque(value);
+    let value = sanitize_header_value(value.as_bytes());
+    tracing::info!(header = %source, %value, %trace_id, "mapped opaque inbound trace id");
+    Some(trace_id)
+}
+
+/// 从 `Cookie` 请求头中提取指定名称的追踪ID
+///
+/// HTTP/2 可能将 Cookie 拆分为多个头部，依次查找所有 `Cookie` 头部中的 `name=value` 对，
+/// 使用第一个同名 Cookie 的值，按与追踪ID头部相同的规则校验
+fn extract_cookie_trace_id(
+    headers: &HeaderMap,
+    name: &str,
+    config: &TraceIdConfig,
+) -> Option<TraceId> {
+    let value = find_cookie(headers, name)?;
+
+    let reason = match value.parse::<TraceId>() {
+        Ok(trace_id) => return Some(trace_id),
+        Err(reason) => reason,
+    };
+    if config.enable_short_ids {
+        if let Some(trace_id) = TraceId::from_string_validated_short(value) {
+            return Some(trace_id);
+        }
+    }
+
+    log_invalid_header(config, &http::header::COOKIE, value.as_bytes(), &reason);
+    None
+}
+
+/// 在所有 `Cookie` 请求头中查找第一个指定名称的 Cookie 值，去除包裹的双引号
+fn find_cookie<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    let value = headers
+        .get_all(http::header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|cookies| cookies.split(';'))
+        .filter_map(|pair| pair.trim_ascii().split_once('='))
+        .find_map(|(key, value)| (key == name).then_some(value))?;
+
+    // RFC 6265 允许以双引号包裹 Cookie 值
+    Some(
+        value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .unwrap_or(value),
+    )
+}
+
+/// 生成新的追踪ID
+///
+/// 未配置自定义生成器，或自定义生成器结果无效时使用默认生成器
+/// （启用 `enable_short_ids` 时为 `TraceId::new_short()`，
+/// 启用 `enable_random_ids` 时为 `TraceId::new_random()`）
+fn generate_trace_id(
+    generator: Option<&dyn Fn() -> Option<TraceId>>,
+    config: &TraceIdConfig,
+    metrics: Option<&(dyn MetricsHook + Send + Sync)>,
+) -> TraceId {
+    let default_generator = || {
+        if config.enable_short_ids {
+            TraceId::new_short()
+        } else if config.enable_random_ids {
+            TraceId::new_random()
+        } else {
+            TraceId::new()
+        }
+    };
+
+    match generator {
+        Some(generator_fn) => generator_fn().unwrap_or_else(|| {
+            notify(metrics, |hook| hook.on_generator_fallback());
+            default_generator()
+        }),
+        None => default_generator(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+    use std::convert::Infallible;
+    use tower::{service_fn, ServiceExt};
+
+    /// 收集格式化输出的写入器
+    #[derive(Clone, Default)]
+    struct Buffer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Buffer {
+        fn take(&self) -> String {
+            String::from_utf8(std::mem::take(&mut *self.0.lock().unwrap())).unwrap()
+        }
+    }
+
+    // --- 辅助函数测试 ---
+    mod id_extraction {
+        use super::*;
+
+        fn default_generator() -> Option<&'static dyn Fn() -> Option<TraceId>> {
+            None
+        }
+
+        fn extract_or_generate_trace_id(
+            headers: &HeaderMap,
+            generator: Option<&dyn Fn() -> Option<TraceId>>,
+        ) -> TraceId {
+            super::resolve_trace_id(headers, None, &TraceIdConfig::default(), generator, None)
+                .unwrap()
+        }
+
+        #[test]
+        fn test_extract_trace_id_from_headers() {
+            let mut headers = HeaderMap::new();
+            let valid_trace_id = "0af7651916cd43dd8448eb211c80319c";
+            headers.insert(TRACE_ID_HEADER, HeaderValue::from_static(valid_trace_id));
+
+            let trace_id = extract_or_generate_trace_id(&headers, default_generator());
+            assert_eq!(trace_id.as_str(), valid_trace_id);
+        }
+
+        #[test]
+        fn test_generate_trace_id_when_missing() {
+            let headers = HeaderMap::new();
+            let trace_id = extract_or_generate_trace_id(&headers, default_generator());
+            assert_eq!(trace_id.as_str().len(), 32);
+            assert!(TraceId::from_string_validated(trace_id.as_str()).is_some());
+        }
+
+        #[test]
+        fn test_extract_with_invalid_header() {
+            // 全零ID不符合 W3C 规范，应生成新ID
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                TRACE_ID_HEADER,
+                HeaderValue::from_static("00000000000000000000000000000000"),
+            );
+            let trace_id = extract_or_generate_trace_id(&headers, default_generator());
+            assert_ne!(trace_id.to_u128(), 0);
+
+            let mut headers = HeaderMap::new();
+            headers.insert(TRACE_ID_HEADER, HeaderValue::from_static(""));
+            let trace_id = extract_or_generate_trace_id(&headers, default_generator());
+            assert_ne!(trace_id.as_str(), "");
+            assert_eq!(trace_id.as_str().len(), 32);
+
+            let mut headers = HeaderMap::new();
+            let long_id = "a".repeat(129);
+            headers.insert(TRACE_ID_HEADER, HeaderValue::from_str(&long_id).unwrap());
+            let trace_id = extract_or_generate_trace_id(&headers, default_generator());
+            assert_ne!(trace_id.as_str(), long_id);
+        }
+
+        #[test]
+        fn test_with_custom_generator() {
+            let headers = HeaderMap::new();
+            let custom_id = "0af7651916cd43dd8448eb211c80319c";
+            let generator = || TraceId::from_string_validated(custom_id);
+            let trace_id = extract_or_generate_trace_id(&headers, Some(&generator));
+            assert_eq!(trace_id.as_str(), custom_id);
+        }
+
+        #[test]
+        fn test_public_extract_or_generate() {
+            let config = TraceIdConfig {
+                enable_traceparent: true,
+                ..TraceIdConfig::default()
+            };
+
+            // traceparent 优先于 x-trace-id
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                TRACE_ID_HEADER,
+                HeaderValue::from_static("0af7651916cd43dd8448eb211c80319c"),
+            );
+            headers.insert(
+                TRACEPARENT_HEADER,
+                HeaderValue::from_static("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"),
+            );
+            let trace_id = super::extract_or_generate_trace_id(&headers, &config, None).unwrap();
+            assert_eq!(trace_id.as_str(), "4bf92f3577b34da6a3ce929d0e0e4736");
+
+            // 缺失时使用自定义生成器
+            let custom_id = TraceId::from_u128(0x0af7651916cd43dd8448eb211c80319c).unwrap();
+            let trace_id = super::extract_or_generate_trace_id(
+                &HeaderMap::new(),
+                &config,
+                Some(&move || custom_id),
+            )
+            .unwrap();
+            assert_eq!(trace_id, custom_id);
+        }
+
+        #[test]
+        fn test_custom_generator_fallback() {
+            let headers = HeaderMap::new();
+            let invalid_id = "this-is-not-a-valid-id";
+            let generator = || TraceId::from_string_validated(invalid_id);
+            let trace_id = extract_or_generate_trace_id(&headers, Some(&generator));
+            assert_ne!(trace_id.as_str(), invalid_id);
+            assert_eq!(trace_id.as_str().len(), 32);
+        }
+
+        #[test]
+        fn test_config_builder() {
+            let config = TraceIdConfig::builder()
+                .enable_span(false)
+                .enable_b3(true)
+                .header_name(HeaderName::from_static("x-request-id"))
+                .header_names([TRACE_ID_HEADER, "x-request-id"].map(HeaderName::from_static))
+                .build();
+            assert!(!config.enable_span);
+            assert!(config.enable_b3);
+            assert!(config.enable_response_header, "未设置的选项保持默认值");
+            assert_eq!(config.header_name, "x-request-id");
+            assert_eq!(config.header_names.len(), 2);
+        }
+
+        #[test]
+        fn test_header_name_case_and_whitespace() {
+            let valid_id = "0af7651916cd43dd8448eb211c80319c";
+
+            // 头部名称不区分大小写
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                HeaderName::from_bytes(b"X-Trace-Id").unwrap(),
+                HeaderValue::from_static(valid_id),
+            );
+            let trace_id = extract_or_generate_trace_id(&headers, default_generator());
+            assert_eq!(trace_id, valid_id);
+
+            // 值两侧的空白在校验前去除，不会导致重新生成
+            for value in [
+                " 0af7651916cd43dd8448eb211c80319c ",
+                "\t0af7651916cd43dd8448eb211c80319c",
+            ] {
+                headers.insert(TRACE_ID_HEADER, HeaderValue::from_str(value).unwrap());
+                let trace_id = extract_or_generate_trace_id(&headers, default_generator());
+                assert_eq!(trace_id, valid_id);
+            }
+
+            // 中间的空白仍然无效
+            headers.insert(
+                TRACE_ID_HEADER,
+                HeaderValue::from_static("0af7651916cd43dd 8448eb211c80319c"),
+            );
+            let trace_id = extract_or_generate_trace_id(&headers, default_generator());
+            assert_ne!(trace_id, valid_id);
+        }
+
+        #[test]
+        fn test_invalid_header_event() {
+            let buffer = Buffer::default();
+            let writer = buffer.clone();
+            let subscriber = tracing_subscriber::fmt()
+                .with_ansi(false)
+                .with_max_level(tracing::Level::TRACE)
+                .with_writer(move || writer.clone())
+                .finish();
+            let _guard = tracing::subscriber::set_default(subscriber);
+
+            let mut headers = HeaderMap::new();
+            headers.insert(TRACE_ID_HEADER, HeaderValue::from_static("not-a-trace-id"));
+            extract_or_generate_trace_id(&headers, default_generator());
+            let output = buffer.take();
+            assert!(output.contains("DEBUG"));
+            assert!(output.contains("rejected invalid inbound trace id"));
+            assert!(output.contains("header=x-trace-id"));
+            assert!(output.contains("value=not-a-trace-id"));
+            assert!(output.contains("reason="));
+
+            // 控制字符与非ASCII字节被转义，超长的值被截断
+            let mut raw = b"0af7651916cd43dd\t\"".to_vec();
+            raw.extend_from_slice(&[0xff; 100]);
+            headers.insert(TRACE_ID_HEADER, HeaderValue::from_bytes(&raw).unwrap());
+            extract_or_generate_trace_id(&headers, default_generator());
+            let output = buffer.take();
+            assert_eq!(output.lines().count(), 1);
+            assert!(!output.contains('\t'));
+            assert!(output.contains("0af7651916cd43dd\\t\\\"\\xff"));
+            assert!(output.contains("not visible ASCII"));
+            assert!(output.contains("..."));
+
+            // 合法或缺失的头部不记录事件
+            headers.insert(
+                TRACE_ID_HEADER,
+                HeaderValue::from_static("0af7651916cd43dd8448eb211c80319c"),
+            );
+            extract_or_generate_trace_id(&headers, default_generator());
+            extract_or_generate_trace_id(&HeaderMap::new(), default_generator());
+            assert!(buffer.take().is_empty());
+
+            // 级别可配置，None 表示关闭
+            headers.insert(TRACE_ID_HEADER, HeaderValue::from_static("bad"));
+            let config = TraceIdConfig::builder()
+                .invalid_header_log_level(Some(tracing::Level::WARN))
+                .build();
+            super::resolve_trace_id(&headers, None, &config, None, None);
+            assert!(buffer.take().contains("WARN"));
+
+            let config = TraceIdConfig::builder()
+                .invalid_header_log_level(None)
+                .build();
+            super::resolve_trace_id(&headers, None, &config, None, None);
+            assert!(buffer.take().is_empty());
+        }
+
+        #[test]
+        fn test_map_opaque_ids() {
+            let buffer = Buffer::default();
+            let writer = buffer.clone();
+            let subscriber = tracing_subscriber::fmt()
+                .with_ansi(false)
+                .with_writer(move || writer.clone())
+                .finish();
+            let _guard = tracing::subscriber::set_default(subscriber);
+
+            let ulid = "01ARZ3NDEKTSV4RRFFQ69G5FAV";
+            let mut headers = HeaderMap::new();
+            headers.insert(TRACE_ID_HEADER, HeaderValue::from_static(ulid));
+
+            // 默认禁用：无效的ID被替换为新生成的ID
+            let resolve = |headers: &HeaderMap, config: &TraceIdConfig| {
+                super::resolve_trace_id(headers, None, config, None, None).unwrap()
+            };
+            assert_ne!(
+                resolve(&headers, &TraceIdConfig::default()),
+                TraceId::from_opaque(ulid)
+            );
+            buffer.take();
+
+            // 启用后映射为确定的ID，并记录原始值与映射结果
+            let config = TraceIdConfig::builder().map_opaque_ids(true).build();
+            let trace_id = resolve(&headers, &config);
+            assert_eq!(trace_id, TraceId::from_opaque(ulid));
+            let output = buffer.take();
+            assert!(output.contains("mapped opaque inbound trace id"));
+            assert!(output.contains(&format!("value={ulid}")));
+            assert!(output.contains(&format!("trace_id={trace_id}")));
+
+            // 有效的ID与其他来源优先于映射
+            let config = TraceIdConfig::builder()
+                .map_opaque_ids(true)
+                .enable_b3(true)
+                .trace_id_cookie("trace_id")
+                .build();
+            headers.insert(
+                "b3",
+                HeaderValue::from_static("0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331"),
+            );
+            assert_eq!(
+                resolve(&headers, &config),
+                "0af7651916cd43dd8448eb211c80319c"
+            );
+            headers.remove("b3");
+
+            // 头部缺失时映射 Cookie 的值
+            headers.remove(TRACE_ID_HEADER);
+            headers.insert(
+                http::header::COOKIE,
+                HeaderValue::from_static("trace_id=req-42"),
+            );
+            assert_eq!(resolve(&headers, &config), TraceId::from_opaque("req-42"));
+            assert!(buffer.take().contains("header=cookie"));
+
+            // 空值不映射
+            headers.insert(TRACE_ID_HEADER, HeaderValue::from_static(" "));
+            headers.remove(http::header::COOKIE);
+            assert_ne!(resolve(&headers, &config), TraceId::from_opaque(""));
+        }
+
+        #[test]
+        fn test_trace_id_cookie() {
+            let config = TraceIdConfig::builder().trace_id_cookie("trace_id").build();
+            let extract = |headers: &HeaderMap| {
+                super::extract_or_generate_trace_id(headers, &config, None)
+                    .unwrap()
+                    .to_string()
+            };
+            let cookie_id = "0af7651916cd43dd8448eb211c80319c";
+            let header_id = "1234567890abcdef1234567890abcdef";
+
+            // 头部缺失时读取 Cookie，允许双引号与多个 Cookie 头部
+            for cookies in [
+                vec!["session=abc; trace_id=0af7651916cd43dd8448eb211c80319c"],
+                vec!["trace_id=\"0af7651916cd43dd8448eb211c80319c\"; session=abc"],
+                vec![
+                    "session=abc",
+                    "theme=dark;trace_id=0af7651916cd43dd8448eb211c80319c",
+                ],
+            ] {
+                let mut headers = HeaderMap::new();
+                for cookie in cookies {
+                    headers.append(http::header::COOKIE, HeaderValue::from_static(cookie));
+                }
+                assert_eq!(extract(&headers), cookie_id);
+
+                // 头部优先于 Cookie
+                headers.insert(TRACE_ID_HEADER, HeaderValue::from_static(header_id));
+                assert_eq!(extract(&headers), header_id);
+            }
+
+            // 无效值、名称不匹配（区分大小写）或前缀相同的 Cookie 不被使用，生成新ID
+            for cookie in [
+                "trace_id=invalid",
+                "trace_id=00000000000000000000000000000000",
+                "Trace_Id=0af7651916cd43dd8448eb211c80319c",
+                "trace_id_v2=0af7651916cd43dd8448eb211c80319c",
+            ] {
+                let mut headers = HeaderMap::new();
+                headers.insert(http::header::COOKIE, HeaderValue::from_static(cookie));
+                let trace_id = extract(&headers);
+                assert_ne!(trace_id, cookie_id);
+                assert!(TraceId::is_valid(&trace_id));
+            }
+
+            // 默认配置不读取 Cookie
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                http::header::COOKIE,
+                HeaderValue::from_static("trace_id=0af7651916cd43dd8448eb211c80319c"),
+            );
+            let trace_id =
+                super::extract_or_generate_trace_id(&headers, &TraceIdConfig::default(), None)
+                    .unwrap();
+            assert_ne!(trace_id.as_str(), cookie_id);
+        }
+
+        #[test]
+        fn test_random_ids() {
+            let config = TraceIdConfig::builder().enable_random_ids(true).build();
+
+            // 生成的ID不包含本机的机器ID与时间戳前缀
+            let ids: Vec<_> = (0..100)
+                .map(|_| {
+                    super::extract_or_generate_trace_id(&HeaderMap::new(), &config, None).unwrap()
+                })
+                .collect();
+            assert!(ids.iter().all(|id| TraceId::is_valid(id.as_str())));
+            assert!(ids.iter().any(|id| id.machine_id() != ids[0].machine_id()));
+            assert!(ids
+                .iter()
+                .any(|id| id.to_u128() >> 80 != ids[0].to_u128() >> 80));
+
+            // 入站ID照常沿用
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                TRACE_ID_HEADER,
+                HeaderValue::from_static("0af7651916cd43dd8448eb211c80319c"),
+            );
+            let trace_id = super::extract_or_generate_trace_id(&headers, &config, None).unwrap();
+            assert_eq!(trace_id.as_str(), "0af7651916cd43dd8448eb211c80319c");
+        }
+
+        #[test]
+        fn test_short_ids() {
+            let config = TraceIdConfig {
+                enable_short_ids: true,
+                ..TraceIdConfig::default()
+            };
+
+            // 启用后同时接受16字符与32字符的ID
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                TRACE_ID_HEADER,
+                HeaderValue::from_static("8448eb211c80319c"),
+            );
+            let trace_id = super::extract_or_generate_trace_id(&headers, &config, None).unwrap();
+            assert_eq!(trace_id.as_str(), "8448eb211c80319c");
+            assert!(trace_id.is_short());
+
+            headers.insert(
+                TRACE_ID_HEADER,
+                HeaderValue::from_static("0af7651916cd43dd8448eb211c80319c"),
+            );
+            let trace_id = super::extract_or_generate_trace_id(&headers, &config, None).unwrap();
+            assert_eq!(trace_id.as_str(), "0af7651916cd43dd8448eb211c80319c");
+
+            // 缺失时生成短ID
+            let trace_id =
+                super::extract_or_generate_trace_id(&HeaderMap::new(), &config, None).unwrap();
+            assert_eq!(trace_id.as_str().len(), 16);
+
+            // 默认配置拒绝短ID
+            headers.insert(
+                TRACE_ID_HEADER,
+                HeaderValue::from_static("8448eb211c80319c"),
+            );
+            let trace_id =
+                super::extract_or_generate_trace_id(&headers, &TraceIdConfig::default(), None)
+                    .unwrap();
+            assert_ne!(trace_id.as_str(), "8448eb211c80319c");
+            assert_eq!(trace_id.as_str().len(), 32);
+        }
+    }
+
+    // --- TraceId 生成器测试 ---
+    #[tokio::test]
+    async fn test_trace_id_generator() {
+        let custom_id = TraceId::from_u128(0x0af7651916cd43dd8448eb211c80319c).unwrap();
+        let service = TraceIdLayer::new()
+            .with_trace_id_generator(move || custom_id)
+            .layer(service_fn(|_req: Request<()>| async move {
+                Ok::<_, Infallible>(Response::new(context::get_trace_id()))
+            }));
+
+        let response = service.clone().oneshot(Request::new(())).await.unwrap();
+        assert_eq!(response.into_body(), custom_id);
+
+        // 请求头中的追踪ID仍然优先
+        let request = Request::builder()
+            .header(TRACE_ID_HEADER, "4bf92f3577b34da6a3ce929d0e0e4736")
+            .body(())
+            .unwrap();
+        let response = service.oneshot(request).await.unwrap();
+        assert_eq!(
+            response.into_body().as_str(),
+            "4bf92f3577b34da6a3ce929d0e0e4736"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_generator_when() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let partner_id = TraceId::from_u128(0x0af7651916cd43dd8448eb211c80319c).unwrap();
+        let regular_id = TraceId::from_u128(0x4bf92f3577b34da6a3ce929d0e0e4736).unwrap();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let predicate_calls = Arc::new(AtomicUsize::new(0));
+        let counted = calls.clone();
+        let predicate_counted = predicate_calls.clone();
+        let service = TraceIdLayer::new()
+            .with_trace_id_generator(move || regular_id)
+            .with_generator_when(
+                move |parts| {
+                    predicate_counted.fetch_add(1, Ordering::Relaxed);
+                    parts.headers.contains_key("x-partner-id")
+                },
+                move || {
+                    counted.fetch_add(1, Ordering::Relaxed);
+                    partner_id
+                },
+            )
+            .layer(service_fn(|_req: Request<()>| async move {
+                Ok::<_, Infallible>(Response::new(context::get_trace_id()))
+            }));
+
+        let partner = || Request::builder().header("x-partner-id", "acme");
+
+        // 谓词为true时使用条件生成器，否则使用常规生成器
+        let response = service
+            .clone()
+            .oneshot(partner().body(()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.into_body(), partner_id);
+        let response = service.clone().oneshot(Request::new(())).await.unwrap();
+        assert_eq!(response.into_body(), regular_id);
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        assert_eq!(predicate_calls.load(Ordering::Relaxed), 2);
+
+        // 携带了有效追踪ID时不调用谓词与生成器
+        let request = partner()
+            .header(TRACE_ID_HEADER, "00f067aa0ba902b700f067aa0ba902b7")
+            .body(())
+            .unwrap();
+        let response = service.oneshot(request).await.unwrap();
+        assert_eq!(response.into_body(), "00f067aa0ba902b700f067aa0ba902b7");
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        assert_eq!(predicate_calls.load(Ordering::Relaxed), 2);
+
+        // 谓词为false且没有常规生成器时使用默认生成器
+        let service = TraceIdLayer::new()
+            .with_generator_when(|_| false, move || partner_id)
+            .layer(service_fn(|_req: Request<()>| async move {
+                Ok::<_, Infallible>(Response::new(context::get_trace_id()))
+            }));
+        let trace_id = service.oneshot(Request::new(())).await.unwrap().into_body();
+        assert_ne!(trace_id, partner_id);
+        assert!(TraceId::from_string_validated(trace_id.as_str()).is_some());
+    }
+
+    #[test]
+    fn test_layer_accessors() {
+        let layer = TraceIdLayer::new_high_performance();
+        assert!(!layer.config().enable_span);
+        assert!(layer.generator().is_none());
+
+        let custom_id = TraceId::from_u128(0x0af7651916cd43dd8448eb211c80319c).unwrap();
+        let layer = layer.with_trace_id_generator(move || custom_id);
+        assert_eq!(
+            layer.generator().and_then(|generator| generator()),
+            Some(custom_id)
+        );
+
+        // 字符串生成器的无效结果为None
+        let invalid = TraceIdLayer::new().with_generator(|| "invalid".to_string());
+        assert_eq!(invalid.generator().and_then(|generator| generator()), None);
+
+        // 修改克隆出的层不影响原来的层，生成器保持不变
+        let mut modified = layer.clone();
+        modified.config_mut().enable_response_header = false;
+        assert!(layer.config().enable_response_header);
+        assert!(!modified.config().enable_response_header);
+        assert_eq!(
+            modified.generator().and_then(|generator| generator()),
+            Some(custom_id)
+        );
+    }
+
+    #[cfg(feature = "uuid")]
+    #[tokio::test]
+    async fn test_uuid_generator() {
+        let service = TraceIdLayer::new().with_uuid_generator().layer(service_fn(
+            |_req: Request<()>| async move {
+                Ok::<_, Infallible>(Response::new(context::get_trace_id()))
+            },
+        ));
+
+        let trace_id = service.oneshot(Request::new(())).await.unwrap().into_body();
+        let uuid = uuid::Uuid::from_bytes(*trace_id.as_bytes());
+        assert_eq!(uuid.get_version_num(), 4);
+    }
+
+    // --- 关联字段测试 ---
+    #[tokio::test]
+    async fn test_correlation_headers() {
+        let config = TraceIdConfig {
+            correlation_headers: vec![
+                HeaderName::from_static("x-tenant-id"),
+                HeaderName::from_static("x-user-id"),
+            ],
+            ..TraceIdConfig::default()
+        };
+        let service =
+            TraceIdLayer::with_config(config).layer(service_fn(|_req: Request<()>| async move {
+                Ok::<_, Infallible>(Response::new(context::get_correlation()))
+            }));
+
+        // 只收集请求中存在的头部
+        let request = Request::builder()
+            .header("X-Tenant-Id", "acme")
+            .body(())
+            .unwrap();
+        let fields = service.clone().oneshot(request).await.unwrap().into_body();
+        assert_eq!(
+            fields,
+            Some([("x-tenant-id", "acme")].into_iter().collect())
+        );
+
+        // 没有任何关联头部时不设置关联字段
+        let fields = service.oneshot(Request::new(())).await.unwrap().into_body();
+        assert!(fields.is_none());
+    }
+
+    // --- 错误响应测试 ---
+    #[tokio::test]
+    async fn test_error_response_has_header() {
+        let service = TraceIdLayer::new().layer(service_fn(|_req: Request<()>| async move {
+            let mut response = Response::new(String::new());
+            *response.status_mut() = http::StatusCode::INTERNAL_SERVER_ERROR;
+            Ok::<_, Infallible>(response)
+        }));
+
+        let valid_id = "0af7651916cd43dd8448eb211c80319c";
+        let request = Request::builder()
+            .header(TRACE_ID_HEADER, valid_id)
+            .body(())
+            .unwrap();
+        let response = service.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(response.headers().get(TRACE_ID_HEADER).unwrap(), valid_id);
+    }
+
+    // --- 缺失追踪ID策略测试 ---
+    #[tokio::test]
+    async fn test_missing_id_policy() {
+        let valid_id = "0af7651916cd43dd8448eb211c80319c";
+        let config = TraceIdConfig::builder()
+            .missing_id_policy(MissingIdPolicy::Reject)
+            .build();
+        assert_eq!(
+            TraceIdConfig::default().missing_id_policy,
+            MissingIdPolicy::Generate
+        );
+
+        // 公开的提取函数在拒绝时返回None
+        let mut headers = HeaderMap::new();
+        assert!(super::extract_or_generate_trace_id(&headers, &config, None).is_none());
+        headers.insert(TRACE_ID_HEADER, HeaderValue::from_static(valid_id));
+        assert_eq!(
+            super::extract_or_generate_trace_id(&headers, &config, None).unwrap(),
+            valid_id
+        );
+
+        // 缺失或无效时直接返回400，不调用内部服务
+        let service =
+            TraceIdLayer::with_config(config).layer(service_fn(|_req: Request<()>| async move {
+                Ok::<_, Infallible>(Response::new(context::get_trace_id().to_string()))
+            }));
+        for request in [
+            Request::new(()),
+            Request::builder()
+                .header(TRACE_ID_HEADER, "invalid")
+                .body(())
+                .unwrap(),
+        ] {
+            let response = service.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+            assert!(response.headers().get(TRACE_ID_HEADER).is_none());
+            assert!(response.extensions().get::<TraceIdRejection>().is_some());
+            assert!(response.into_body().is_empty());
+        }
+
+        let request = Request::builder()
+            .header(TRACE_ID_HEADER, valid_id)
+            .body(())
+            .unwrap();
+        let response = service.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+        assert_eq!(response.into_body(), valid_id);
+
+        // 拒绝原因区分缺失与无效
+        let invalid = Request::builder()
+            .header(TRACE_ID_HEADER, "invalid")
+            .body(())
+            .unwrap();
+        let response = service.clone().oneshot(invalid).await.unwrap();
+        assert_eq!(
+            response.extensions().get(),
+            Some(&TraceIdRejection::Invalid)
+        );
+        let response = service.oneshot(Request::new(())).await.unwrap();
+        assert_eq!(
+            response.extensions().get(),
+            Some(&TraceIdRejection::Missing)
+        );
+        let response: Response<String> = TraceIdRejection::Invalid.to_response();
+        assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+        assert_eq!(response.into_body(), TraceIdRejection::Invalid.to_string());
+        assert!(!TraceIdRejection::Invalid.to_string().contains("32"));
+
+        // 无效的 traceparent 与追踪ID Cookie 同样视为无效而非缺失
+        let config = TraceIdConfig::builder()
+            .missing_id_policy(MissingIdPolicy::Reject)
+            .enable_traceparent(true)
+            .trace_id_cookie("trace_id")
+            .build();
+        let mut headers = HeaderMap::new();
+        headers.insert(TRACEPARENT_HEADER, HeaderValue::from_static("00-invalid"));
+        assert_eq!(
+            TraceIdRejection::from_headers(&headers, &config),
+            TraceIdRejection::Invalid
+        );
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            http::header::COOKIE,
+            HeaderValue::from_static("session=1; trace_id=invalid"),
+        );
+        assert_eq!(
+            TraceIdRejection::from_headers(&headers, &config),
+            TraceIdRejection::Invalid
+        );
+        headers.insert(http::header::COOKIE, HeaderValue::from_static("session=1"));
+        assert_eq!(
+            TraceIdRejection::from_headers(&headers, &config),
+            TraceIdRejection::Missing
+        );
+
+        // 生成并警告：与默认策略一样生成新ID
+        let config = TraceIdConfig::builder()
+            .missing_id_policy(MissingIdPolicy::GenerateAndWarn)
+            .build();
+        let trace_id = super::extract_or_generate_trace_id(&HeaderMap::new(), &config, None);
+        assert!(trace_id.is_some());
+    }
+
+    // --- 全零ID测试 ---
+    #[tokio::test]
+    async fn test_all_zero_header_is_regenerated() {
+        let service = TraceIdLayer::new().layer(service_fn(|_req: Request<()>| async {
+            Ok::<_, Infallible>(Response::new(context::get_trace_id()))
+        }));
+
+        // 快速路径与去除空白后的解析路径都拒绝全零ID
+        let zeros = "0".repeat(32);
+        for value in [zeros.clone(), format!(" {zeros} "), format!("\t{zeros}")] {
+            let request = Request::builder()
+                .header(TRACE_ID_HEADER, value)
+                .body(())
+                .unwrap();
+            let response = service.clone().oneshot(request).await.unwrap();
+            let header = response.headers()[TRACE_ID_HEADER].to_str().unwrap();
+            assert_ne!(header, zeros);
+            assert!(TraceId::is_valid(header));
+            assert_eq!(response.body(), header);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_non_conformant_generated_id_is_replaced_in_response() {
+        // 非ASCII输入不经校验解码，得到的是全零ID
+        let unchecked = TraceId::from_string_unchecked(&"é".repeat(16));
+        assert_eq!(unchecked.to_u128(), 0);
+
+        let service = TraceIdLayer::new()
+            .with_trace_id_generator(move || unchecked)
+            .layer(service_fn(|_req: Request<()>| async {
+                Ok::<_, Infallible>(Response::new(context::get_trace_id()))
+            }));
+        let response = service
+            .oneshot(Request::builder().body(()).unwrap())
+            .await
+            .unwrap();
+
+        // 响应头使用新生成的合法ID，上下文中仍是生成器返回的ID
+        let header = response.headers()[TRACE_ID_HEADER].to_str().unwrap();
+        assert!(TraceId::is_valid(header));
+        assert_eq!(*response.body(), unchecked);
+
+        // 合法ID原样写入
+        let trace_id = TraceId::new();
+        assert_eq!(response_trace_id(trace_id), trace_id);
+    }
+
+    // --- 跳过追踪测试 ---
+    #[tokio::test]
+    async fn test_skip_paths() {
+        /// 返回请求是否处于追踪上下文、是否处于请求 span 中
+        async fn probe(_req: Request<()>) -> Result<Response<String>, Infallible> {
+            let in_span = !Span::current().is_none();
+            let in_context = context::get_trace_id_opt().is_some();
+            Ok(Response::new(format!("{in_context},{in_span}")))
+        }
+
+        async fn get(config: &TraceIdConfig, uri: &'static str) -> (bool, String) {
+            let service = TraceIdLayer::with_config(config.clone()).layer(service_fn(probe));
+            let request = Request::builder()
+                .uri(uri)
+                .header(TRACE_ID_HEADER, "0af7651916cd43dd8448eb211c80319c")
+                .body(())
+                .unwrap();
+            let response = service.oneshot(request).await.unwrap();
+            let header = response.headers().contains_key(TRACE_ID_HEADER);
+            (header, response.into_body())
+        }
+
+        // 在订阅器下运行，使 span 真正被创建
+        let subscriber = tracing_subscriber::registry();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let config = TraceIdConfig::builder()
+            .skip_paths(["/healthz", "/internal/"])
+            .build();
+
+        // 完全跳过：没有响应头、上下文与 span
+        for uri in ["/healthz", "/healthz/live", "/internal/metrics"] {
+            assert_eq!(
+                get(&config, uri).await,
+                (false, "false,false".to_string()),
+                "{uri}"
+            );
+        }
+        // 只匹配完整的路径段
+        for uri in ["/healthz2", "/internal", "/"] {
+            assert_eq!(
+                get(&config, uri).await,
+                (true, "true,true".to_string()),
+                "{uri}"
+            );
+        }
+
+        // 只跳过 span：追踪ID照常传播并写入响应头
+        let config = TraceIdConfig {
+            skip_mode: SkipMode::SpanOnly,
+            ..config
+        };
+        assert_eq!(
+            get(&config, "/healthz").await,
+            (true, "true,false".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_skip_predicate() {
+        let service = TraceIdLayer::new()
+            .with_skip(|parts| parts.method == http::Method::HEAD)
+            .layer(service_fn(|_req: Request<()>| async {
+                Ok::<_, Infallible>(Response::new(context::get_trace_id_opt()))
+            }));
+
+        let request = Request::builder()
+            .method(http::Method::HEAD)
+            .body(())
+            .unwrap();
+        let response = service.clone().oneshot(request).await.unwrap();
+        assert!(!response.headers().contains_key(TRACE_ID_HEADER));
+        assert_eq!(*response.body(), None);
+
+        let response = service
+            .oneshot(Request::builder().body(()).unwrap())
+            .await
+            .unwrap();
+        assert!(response.headers().contains_key(TRACE_ID_HEADER));
+        assert!(response.body().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_record_latency() {
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_ansi(false)
+            .with_span_events(tracing_subscriber::fmt::format::FmtSpan::CLOSE)
+            .with_writer(move || writer.clone())
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let slow = |_req: Request<()>| async {
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            Ok::<_, Infallible>(Response::new(()))
+        };
+
+        // 默认不计时
+        let response = TraceIdLayer::new()
+            .layer(service_fn(slow))
+            .oneshot(Request::new(()))
+            .await
+            .unwrap();
+        assert!(response.extensions().get::<ResponseLatency>().is_none());
+        assert!(!response.headers().contains_key(RESPONSE_TIME_HEADER));
+
+        let config = TraceIdConfig::builder()
+            .record_latency(true)
+            .latency_header(true)
+            .build();
+        let response = TraceIdLayer::with_config(config.clone())
+            .layer(service_fn(slow))
+            .oneshot(Request::new(()))
+            .await
+            .unwrap();
+        let ResponseLatency(latency) = *response.extensions().get().unwrap();
+        assert!(latency >= std::time::Duration::from_millis(20));
+        let header = response.headers()[RESPONSE_TIME_HEADER].to_str().unwrap();
+        let millis: f64 = header.strip_suffix("ms").unwrap().parse().unwrap();
+        assert!(millis >= 20.0);
+        assert!(buffer.take().contains("latency_ms="));
+
+        // 不创建 span 时仍写入响应扩展
+        let config = TraceIdConfig {
+            enable_span: false,
+            latency_header: false,
+            ..config
+        };
+        let response = TraceIdLayer::with_config(config)
+            .layer(service_fn(slow))
+            .oneshot(Request::new(()))
+            .await
+            .unwrap();
+        let ResponseLatency(latency) = *response.extensions().get().unwrap();
+        assert!(latency >= std::time::Duration::from_millis(20));
+        assert!(!response.headers().contains_key(RESPONSE_TIME_HEADER));
+        assert!(!buffer.take().contains("latency_ms="));
+    }
+
+    // --- 自定义头部名称测试 ---
+    #[tokio::test]
+    async fn test_custom_header_name() {
+        let service = TraceIdLayer::new()
+            .with_header_name(HeaderName::from_static("x-request-id"))
+            .layer(service_fn(|_req: Request<()>| async move {
+                Ok::<_, Infallible>(Response::new(context::get_trace_id()))
+            }));
+
+        let valid_id = "0af7651916cd43dd8448eb211c80319c";
+
+        // 从自定义头部读取（大小写不敏感），并写入同名响应头
+        let request = Request::builder()
+            .header("X-Request-Id", valid_id)
+            .body(())
+            .unwrap();
+        let response = service.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.headers().get("x-request-id").unwrap(), valid_id);
+        assert!(response.headers().get(TRACE_ID_HEADER).is_none());
+        assert_eq!(response.into_body().as_str(), valid_id);
+
+        // 默认头部不再被读取
+        let request = Request::builder()
+            .header(TRACE_ID_HEADER, valid_id)
+            .body(())
+            .unwrap();
+        let response = service.oneshot(request).await.unwrap();
+        assert_ne!(response.into_body().as_str(), valid_id);
+    }
+
+    #[tokio::test]
+    async fn test_response_header_name() {
+        let valid_id = "0af7651916cd43dd8448eb211c80319c";
+        let request = || {
+            Request::builder()
+                .header("x-request-id", valid_id)
+                .body(())
+                .unwrap()
+        };
+        let inner = service_fn(|_req: Request<()>| async move {
+            Ok::<_, Infallible>(Response::new(context::get_trace_id()))
+        });
+
+        // 从 x-request-id 读取，写入 x-trace-id
+        let config = TraceIdConfig::builder()
+            .header_name(HeaderName::from_static("x-request-id"))
+            .response_header_name(HeaderName::from_static(TRACE_ID_HEADER))
+            .build();
+        assert_eq!(config.response_header(), TRACE_ID_HEADER);
+        let response = TraceIdLayer::with_config(config)
+            .layer(inner)
+            .oneshot(request())
+            .await
+            .unwrap();
+        assert_eq!(response.headers().get(TRACE_ID_HEADER).unwrap(), valid_id);
+        assert!(response.headers().get("x-request-id").is_none());
+        assert_eq!(response.into_body(), valid_id);
+
+        // 响应头为 traceparent 时写入完整的 W3C 值
+        let response = TraceIdLayer::new()
+            .with_header_name(HeaderName::from_static("x-request-id"))
+            .with_response_header_name(HeaderName::from_static(TRACEPARENT_HEADER))
+            .layer(inner)
+            .oneshot(request())
+            .await
+            .unwrap();
+        assert!(response.headers().get("x-request-id").is_none());
+        let traceparent = response.headers().get(TRACEPARENT_HEADER).unwrap();
+        let ctx = TraceContext::parse(traceparent.to_str().unwrap()).unwrap();
+        assert_eq!(ctx.trace_id, valid_id);
+
+        // 未设置时与读取的头部名称相同
+        assert_eq!(TraceIdConfig::default().response_header(), TRACE_ID_HEADER);
+    }
+
+    #[tokio::test]
+    async fn test_header_names_priority() {
+        let service = TraceIdLayer::new()
+            .with_header_names(vec![
+                HeaderName::from_static(TRACEPARENT_HEADER),
+                HeaderName::from_static(TRACE_ID_HEADER),
+                HeaderName::from_static("x-request-id"),
+            ])
+            .layer(service_fn(|_req: Request<()>| async move {
+                Ok::<_, Infallible>(Response::new(context::get_trace_id()))
+            }));
+
+        let traceparent_id = "4bf92f3577b34da6a3ce929d0e0e4736";
+        let trace_id = "0af7651916cd43dd8448eb211c80319c";
+        let request_id = "fedcba0987654321fedcba0987654321";
+
+        // 使用第一个有效的头部，traceparent 只取其 trace-id
+        let request = Request::builder()
+            .header(
+                TRACEPARENT_HEADER,
+                format!("00-{traceparent_id}-00f067aa0ba902b7-01"),
+            )
+            .header(TRACE_ID_HEADER, trace_id)
+            .header("x-request-id", request_id)
+            .body(())
+            .unwrap();
+        let response = service.clone().oneshot(request).await.unwrap();
+        assert_eq!(
+            response.headers().get(TRACE_ID_HEADER).unwrap(),
+            traceparent_id
+        );
+        assert_eq!(response.into_body().as_str(), traceparent_id);
+
+        // 靠前的头部无效时继续尝试后续头部
+        let request = Request::builder()
+            .header(TRACEPARENT_HEADER, "invalid")
+            .header(TRACE_ID_HEADER, "invalid")
+            .header("x-request-id", request_id)
+            .body(())
+            .unwrap();
+        let response = service.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.into_body().as_str(), request_id);
+
+        // 均不存在时生成新ID
+        let response = service.oneshot(Request::new(())).await.unwrap();
+        let generated = response.into_body();
+        assert!(![traceparent_id, trace_id, request_id].contains(&generated.as_str()));
+    }
+
+    #[tokio::test]
+    async fn test_metrics() {
+        use crate::metrics::{MetricsSnapshot, TraceIdMetrics};
+
+        let metrics = Arc::new(TraceIdMetrics::new());
+        let service = TraceIdLayer::new()
+            .with_generator(|| "not-a-valid-id".to_string())
+            .with_metrics(metrics.clone())
+            .layer(service_fn(|_req: Request<()>| async move {
+                Ok::<_, Infallible>(Response::new(()))
+            }));
+
+        // 沿用有效的追踪ID
+        let request = Request::builder()
+            .header(TRACE_ID_HEADER, "0af7651916cd43dd8448eb211c80319c")
+            .body(())
+            .unwrap();
+        service.clone().oneshot(request).await.unwrap();
+
+        // 头部无效：生成新ID，自定义生成器结果无效而回退
+        let request = Request::builder()
+            .header(TRACE_ID_HEADER, "invalid")
+            .body(())
+            .unwrap();
+        service.clone().oneshot(request).await.unwrap();
+
+        // 头部缺失：生成新ID
+        service.oneshot(Request::new(())).await.unwrap();
+
+        assert_eq!(
+            metrics.snapshot(),
+            MetricsSnapshot {
+                propagated: 1,
+                generated: 2,
+                invalid_header: 1,
+                generator_fallback: 2,
+            }
+        );
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn test_metrics_exporter() {
+        use ::metrics::{
+            Counter, Gauge, Histogram, Key, KeyName, Metadata, Recorder, SharedString, Unit,
+        };
+        use std::{collections::HashMap, sync::atomic::AtomicU64, sync::Mutex};
+
+        /// 按名称记录计数器的 `metrics` 记录器
+        #[derive(Default)]
+        struct CountingRecorder(Mutex<HashMap<String, Arc<AtomicU64>>>);
+
+        impl CountingRecorder {
+            fn get(&self, name: &str) -> u64 {
+                self.0.lock().unwrap().get(name).map_or(0, |counter| {
+                    counter.load(std::sync::atomic::Ordering::Relaxed)
+                })
+            }
+        }
+
+        impl Recorder for CountingRecorder {
+            fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+            fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+            fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+            fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+                let mut counters = self.0.lock().unwrap();
+                Counter::from_arc(counters.entry(key.name().to_string()).or_default().clone())
+            }
+
+            fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+                Gauge::noop()
+            }
+
+            fn register_histogram(&self, _: &Key, _: &Metadata<'_>) -> Histogram {
+                Histogram::noop()
+            }
+        }
+
+        let recorder = CountingRecorder::default();
+        ::metrics::with_local_recorder(&recorder, || {
+            let config = TraceIdConfig::default();
+            let mut headers = HeaderMap::new();
+            super::extract_or_generate_trace_id(&headers, &config, None);
+
+            headers.insert(TRACE_ID_HEADER, HeaderValue::from_static("invalid"));
+            super::extract_or_generate_trace_id(&headers, &config, None);
+
+            headers.insert(
+                TRACE_ID_HEADER,
+                HeaderValue::from_static("0af7651916cd43dd8448eb211c80319c"),
+            );
+            super::extract_or_generate_trace_id(&headers, &config, None);
+
+            // 未通过 `with_metrics` 注册回调时同样计数
+            let invalid = || None;
+            super::resolve_trace_id(&HeaderMap::new(), None, &config, Some(&invalid), None);
+        });
+
+        assert_eq!(recorder.get("trace_id_propagated_total"), 1);
+        assert_eq!(recorder.get("trace_id_generated_total"), 3);
+        assert_eq!(recorder.get("trace_id_invalid_total"), 1);
+        assert_eq!(recorder.get("trace_id_generator_fallback_total"), 1);
+    }
+
+    // --- 回退协议测试 ---
+    mod fallback_extraction {
+        use super::*;
+
+        #[tokio::test]
+        async fn test_b3_fallback() {
+            let config = TraceIdConfig {
+                enable_b3: true,
+                ..TraceIdConfig::default()
+            };
+            let service = TraceIdLayer::with_config(config).layer(service_fn(
+                |_req: Request<()>| async move {
+                    Ok::<_, Infallible>(Response::new(context::get_trace_id()))
+                },
+            ));
+
+            // 仅存在 B3 头部时使用 B3 追踪ID（64位左侧补零）
+            let request = Request::builder()
+                .header(b3::B3_TRACE_ID_HEADER, "8448eb211c80319c")
+                .body(())
+                .unwrap();
+            let response = service.clone().oneshot(request).await.unwrap();
+            assert_eq!(
+                response.into_body().as_str(),
+                "00000000000000008448eb211c80319c"
+            );
+
+            // x-trace-id 优先于 B3
+            let request = Request::builder()
+                .header(TRACE_ID_HEADER, "0af7651916cd43dd8448eb211c80319c")
+                .header(b3::B3_TRACE_ID_HEADER, "8448eb211c80319c")
+                .body(())
+                .unwrap();
+            let response = service.oneshot(request).await.unwrap();
+            assert_eq!(
+                response.into_body().as_str(),
+                "0af7651916cd43dd8448eb211c80319c"
+            );
+        }
+
+        #[tokio::test]
+        async fn test_datadog_fallback() {
+            let config = TraceIdConfig {
+                enable_b3: true,
+                enable_datadog: true,
+                ..TraceIdConfig::default()
+            };
+            let service = TraceIdLayer::with_config(config).layer(service_fn(
+                |_req: Request<()>| async move {
+                    Ok::<_, Infallible>(Response::new(context::get_trace_id()))
+                },
+            ));
+
+            // 仅存在 Datadog 头部时使用其追踪ID（高64位补零）
+            let request = Request::builder()
+                .header(datadog::DATADOG_TRACE_ID_HEADER, "9532127138774266268")
+                .body(())
+                .unwrap();
+            let response = service.clone().oneshot(request).await.unwrap();
+            assert_eq!(
+                response.into_body().as_str(),
+                "00000000000000008448eb211c80319c"
+            );
+
+            // B3 优先于 Datadog
+            let request = Request::builder()
+                .header(b3::B3_TRACE_ID_HEADER, "0af7651916cd43dd8448eb211c80319c")
+                .header(datadog::DATADOG_TRACE_ID_HEADER, "9532127138774266268")
+                .body(())
+                .unwrap();
+            let response = service.oneshot(request).await.unwrap();
+            assert_eq!(
+                response.into_body().as_str(),
+                "0af7651916cd43dd8448eb211c80319c"
+            );
+        }
+
+        #[tokio::test]
+        async fn test_xray_fallback() {
+            let config = TraceIdConfig {
+                enable_datadog: true,
+                enable_xray: true,
+                ..TraceIdConfig::default()
+            };
+            let service = TraceIdLayer::with_config(config).layer(service_fn(
+                |_req: Request<()>| async move {
+                    Ok::<_, Infallible>(Response::new(context::get_trace_id()))
+                },
+            ));
+
+            // 仅存在 X-Ray 头部时使用其 Root
+            let request = Request::builder()
+                .header(
+                    xray::XRAY_TRACE_ID_HEADER,
+                    "Root=1-5759e988-bd862e3fe1be46a994272793;Sampled=1",
+                )
+                .body(())
+                .unwrap();
+            let response = service.clone().oneshot(request).await.unwrap();
+            assert_eq!(
+                response.into_body().as_str(),
+                "5759e988bd862e3fe1be46a994272793"
+            );
+
+            // Datadog 优先于 X-Ray
+            let request = Request::builder()
+                .header(datadog::DATADOG_TRACE_ID_HEADER, "9532127138774266268")
+                .header(
+                    xray::XRAY_TRACE_ID_HEADER,
+                    "Root=1-5759e988-bd862e3fe1be46a994272793",
+                )
+                .body(())
+                .unwrap();
+            let response = service.oneshot(request).await.unwrap();
+            assert_eq!(
+                response.into_body().as_str(),
+                "00000000000000008448eb211c80319c"
+            );
+        }
+
+        #[test]
+        fn test_fallbacks_disabled_by_default() {
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                b3::B3_TRACE_ID_HEADER,
+                HeaderValue::from_static("8448eb211c80319c"),
+            );
+            headers.insert(
+                datadog::DATADOG_TRACE_ID_HEADER,
+                HeaderValue::from_static("9532127138774266268"),
+            );
+            headers.insert(
+                xray::XRAY_TRACE_ID_HEADER,
+                HeaderValue::from_static("Root=1-5759e988-bd862e3fe1be46a994272793"),
+            );
+            assert!(extract_fallback_trace_id(&headers, &TraceIdConfig::default()).is_none());
+        }
+    }
+
+    // --- 通用服务测试 ---
+    mod generic_service {
+        use super::*;
+
+        /// 返回当前追踪ID作为响应体的通用服务
+        async fn echo_trace_id(_req: Request<String>) -> Result<Response<String>, Infallible> {
+            Ok(Response::new(context::get_trace_id().to_string()))
+        }
+
+        #[tokio::test]
+        async fn test_generic_body_types() {
+            let service = TraceIdLayer::new().layer(service_fn(echo_trace_id));
+
+            let valid_id = "0af7651916cd43dd8448eb211c80319c";
+            let request = Request::builder()
+                .header(TRACE_ID_HEADER, valid_id)
+                .body(String::new())
+                .unwrap();
+            let response = service.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.headers().get(TRACE_ID_HEADER).unwrap(), valid_id);
+            assert_eq!(response.body(), valid_id);
+
+            // 未提供ID时生成新的ID，响应头与上下文一致
+            let request = Request::new(String::new());
+            let response = service.oneshot(request).await.unwrap();
+            let header_id = response.headers().get(TRACE_ID_HEADER).unwrap();
+            assert_eq!(header_id.len(), 32);
+            assert_eq!(header_id.to_str().unwrap(), response.body());
+        }
+
+        #[test]
+        fn test_config_shared() {
+            let layer =
+                TraceIdLayer::new().with_header_name(HeaderName::from_static("x-request-id"));
+            let service = layer.layer(service_fn(echo_trace_id));
+            let cloned = service.clone();
+
+            // 层、服务及其克隆共享同一份配置
+            assert!(Arc::ptr_eq(&layer.config, &service.config));
+            assert!(Arc::ptr_eq(&service.config, &cloned.config));
+            assert_eq!(service.config.header_name, "x-request-id");
+        }
+
+        #[tokio::test]
+        async fn test_request_extension() {
+            let service = TraceIdLayer::new().layer(service_fn(|req: Request<()>| async move {
+                let trace_id = req.extensions().get::<TraceId>().cloned();
+                Ok::<_, Infallible>(Response::new(trace_id))
+            }));
+
+            let valid_id = "0af7651916cd43dd8448eb211c80319c";
+            let request = Request::builder()
+                .header(TRACE_ID_HEADER, valid_id)
+                .body(())
+                .unwrap();
+            let response = service.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.into_body().unwrap().as_str(), valid_id);
+
+            // 禁用后不写入扩展，task_local 上下文不受影响
+            let config = TraceIdConfig::builder().enable_extensions(false).build();
+            let service = TraceIdLayer::with_config(config).layer(service_fn(
+                |req: Request<()>| async move {
+                    assert!(context::get_trace_id_opt().is_some());
+                    Ok::<_, Infallible>(Response::new(req.extensions().get::<TraceId>().cloned()))
+                },
+            ));
+            let response = service.oneshot(Request::new(())).await.unwrap();
+            assert!(response.into_body().is_none());
+        }
+
+        /// 在同步的 `call` 中记录扩展与 task_local 中追踪ID的服务
+        #[derive(Clone)]
+        struct RecordOnCall;
+
+        impl Service<Request<()>> for RecordOnCall {
+            type Response = Response<(Option<TraceId>, Option<TraceId>)>;
+            type Error = Infallible;
+            type Future = std::future::Ready<Result<Self::Response, Infallible>>;
+
+            fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+                Poll::Ready(Ok(()))
+            }
+
+            fn call(&mut self, req: Request<()>) -> Self::Future {
+                use crate::integrations::extensions::TraceIdExt;
+
+                let (parts, _body) = req.into_parts();
+                let ids = (parts.trace_id().copied(), context::get_trace_id_opt());
+                std::future::ready(Ok(Response::new(ids)))
+            }
+        }
+
+        #[tokio::test]
+        async fn test_extension_visible_in_inner_call() {
+            let valid_id = "0af7651916cd43dd8448eb211c80319c";
+            let request = Request::builder()
+                .header(TRACE_ID_HEADER, valid_id)
+                .body(())
+                .unwrap();
+            let response = TraceIdLayer::new()
+                .layer(RecordOnCall)
+                .oneshot(request)
+                .await
+                .unwrap();
+
+            // 同步 `call` 执行时尚未进入 task_local 作用域，扩展中的副本已可用
+            let (extension, task_local) = response.into_body();
+            assert_eq!(extension.unwrap().as_str(), valid_id);
+            assert!(task_local.is_none());
+        }
+
+        #[tokio::test]
+        async fn test_custom_make_span() {
+            let _subscriber = tracing::subscriber::set_default(tracing_subscriber::registry());
+
+            /// 返回当前 span 的名称，并确认请求扩展在拆分重组后仍然保留
+            async fn span_name(req: Request<()>) -> Result<Response<&'static str>, Infallible> {
+                assert!(req.extensions().get::<TraceId>().is_some());
+                let name = Span::current().metadata().map(|m| m.name());
+                Ok(Response::new(name.unwrap_or_default()))
+            }
+
+            let service = TraceIdLayer::new().layer(service_fn(span_name));
+            let response = service.oneshot(Request::new(())).await.unwrap();
+            assert_eq!(*response.body(), "request", "默认行为保持不变");
+
+            let service = TraceIdLayer::new()
+                .with_make_span(|parts, trace_id| {
+                    tracing::info_span!("custom", path = %parts.uri.path(), trace_id = %trace_id)
+                })
+                .layer(service_fn(span_name));
+            let response = service.oneshot(Request::new(())).await.unwrap();
+            assert_eq!(*response.body(), "custom");
+
+            // 禁用 span 时不调用自定义函数
+            let service = TraceIdLayer::new_high_performance()
+                .with_make_span(|_, _| unreachable!())
+                .layer(service_fn(span_name));
+            let response = service.oneshot(Request::new(())).await.unwrap();
+            assert_eq!(*response.body(), "");
+        }
+
+        #[tokio::test]
+        async fn test_sampler() {
+            use crate::sampler::{AlwaysOff, AlwaysOn};
+
+            let _subscriber = tracing::subscriber::set_default(tracing_subscriber::registry());
+
+            /// 返回是否存在 span 以及当前的 trace-flags
+            async fn sampling(
+                _req: Request<()>,
+            ) -> Result<Response<(bool, Option<u8>)>, Infallible> {
+                let has_span = !Span::current().is_none();
+                Ok(Response::new((has_span, context::get_trace_flags())))
+            }
+
+            // 未设置采样器时行为保持不变
+            let service = TraceIdLayer::new().layer(service_fn(sampling));
+            let response = service.oneshot(Request::new(())).await.unwrap();
+            assert_eq!(*response.body(), (true, None));
+
+            let service = TraceIdLayer::new()
+                .with_sampler(AlwaysOn)
+                .layer(service_fn(sampling));
+            let response = service.oneshot(Request::new(())).await.unwrap();
+            assert_eq!(*response.body(), (true, Some(TRACE_FLAG_SAMPLED)));
+
+            let config = TraceIdConfig {
+                enable_traceparent: true,
+                ..TraceIdConfig::default()
+            };
+            let service = TraceIdLayer::with_config(config)
+                .with_sampler(AlwaysOff)
+                .layer(service_fn(sampling));
+            let response = service.clone().oneshot(Request::new(())).await.unwrap();
+            assert_eq!(*response.body(), (false, Some(0)));
+
+            // 上游 traceparent 存在时沿用其采样决策
+            let request = Request::builder()
+                .header(
+                    TRACEPARENT_HEADER,
+                    "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+                )
+                .body(())
+                .unwrap();
+            let response = service.oneshot(request).await.unwrap();
+            assert_eq!(*response.body(), (true, Some(TRACE_FLAG_SAMPLED)));
+        }
+
+        #[tokio::test]
+        async fn test_span_id() {
+            let _subscriber = tracing::subscriber::set_default(tracing_subscriber::registry());
+
+            /// 返回当前跨度ID，并确认 span 上记录了 span_id 字段
+            async fn span_id(_req: Request<()>) -> Result<Response<String>, Infallible> {
+                let has_field = Span::current()
+                    .metadata()
+                    .is_some_and(|m| m.fields().field("span_id").is_some());
+                assert!(has_field);
+                Ok(Response::new(context::get_span_id().unwrap().to_string()))
+            }
+
+            // 默认不写入 traceparent
+            let service = TraceIdLayer::new().layer(service_fn(span_id));
+            let response = service.oneshot(Request::new(())).await.unwrap();
+            assert!(SpanId::from_string_validated(response.body()).is_some());
+            assert!(response.headers().get(TRACEPARENT_HEADER).is_none());
+
+            // W3C 模式下以跨度ID为 parent-id 写入 traceparent
+            let config = TraceIdConfig {
+                enable_traceparent: true,
+                ..TraceIdConfig::default()
+            };
+            let service = TraceIdLayer::with_config(config).layer(service_fn(span_id));
+            let request = Request::builder()
+                .header(
+                    TRACEPARENT_HEADER,
+                    "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-00",
+                )
+                .body(())
+                .unwrap();
+            let response = service.oneshot(request).await.unwrap();
+            let traceparent = response.headers().get(TRACEPARENT_HEADER).unwrap();
+            assert_eq!(
+                traceparent.to_str().unwrap(),
+                format!("00-0af7651916cd43dd8448eb211c80319c-{}-00", response.body())
+            );
+
+            // 只收到 x-trace-id 时补全 parent-id 与 trace-flags
+            let config = TraceIdConfig::builder().echo_traceparent(true).build();
+            let service = TraceIdLayer::with_config(config).layer(service_fn(span_id));
+            let request = Request::builder()
+                .header(TRACE_ID_HEADER, "4bf92f3577b34da6a3ce929d0e0e4736")
+                .body(())
+                .unwrap();
+            let response = service.oneshot(request).await.unwrap();
+            let traceparent = response.headers().get(TRACEPARENT_HEADER).unwrap();
+            let ctx = TraceContext::parse(traceparent.to_str().unwrap()).unwrap();
+            assert_eq!(ctx.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+            assert_eq!(ctx.parent_id.as_str(), response.body());
+            assert!(ctx.is_sampled());
+        }
+
+        #[cfg(feature = "otel")]
+        #[tokio::test]
+        async fn test_otel_context() {
+            use opentelemetry::trace::TraceContextExt;
+
+            let config = TraceIdConfig {
+                enable_otel: true,
+                ..TraceIdConfig::default()
+            };
+            let service =
+                TraceIdLayer::with_config(config).layer(service_fn(|_req: Request<()>| async {
+                    let otel_cx = opentelemetry::Context::current();
+                    let otel_id = otel_cx.span().span_context().trace_id().to_string();
+                    Ok::<_, Infallible>(Response::new(otel_id))
+                }));
+
+            let response = service.oneshot(Request::new(())).await.unwrap();
+            let header_id = response.headers().get(TRACE_ID_HEADER).unwrap();
+            assert_eq!(header_id.to_str().unwrap(), response.body());
+        }
+    }
+}
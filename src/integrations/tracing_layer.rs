@@ -0,0 +1,183 @@
+//! `tracing-subscriber` 集成
+//!
+//! 不经过 Axum/tower 中间件的代码（后台任务、消息消费者等）同样可以让日志自动携带追踪ID：
+//! - `TraceIdSubscriberLayer`：创建 span 时读取 `get_trace_id_opt()`，
+//!   将追踪ID记录到 span 扩展（`TraceIdExtension`）中，span 离开 `with_trace_id` 作用域后依然可用
+//! - `TraceIdFormat`：包装任意 `FormatEvent`，在每条事件前输出 `trace_id=<id>`
+//!
+//! `tracing` 的事件字段在编译期确定，Layer 无法向事件追加字段，
+//! 因此事件级别的追踪ID通过格式化器输出，或在其他 Layer 中通过 `event_trace_id()` 读取。
+
+use crate::{context, trace_id::TraceId};
+use std::fmt;
+use tracing::{span, Event, Subscriber};
+use tracing_subscriber::{
+    fmt::{format::Writer, FmtContext, FormatEvent, FormatFields},
+    layer::{Context, Layer},
+    registry::{LookupSpan, Scope},
+};
+
+/// 记录在 span 扩展中的追踪ID
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TraceIdExtension(pub TraceId);
+
+/// 将当前追踪ID记录到新建 span 扩展中的 Layer
+///
+/// # 示例
+/// ```
+/// use tracing_subscriber::{fmt, prelude::*};
+/// use trace_id::tracing_layer::{TraceIdFormat, TraceIdSubscriberLayer};
+///
+/// tracing_subscriber::registry()
+///     .with(TraceIdSubscriberLayer::new())
+///     .with(fmt::layer().event_format(TraceIdFormat::new(fmt::format())))
+///     .init();
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TraceIdSubscriberLayer;
+
+impl TraceIdSubscriberLayer {
+    /// 创建新的 Layer
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl<S> Layer<S> for TraceIdSubscriberLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, _attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let Some(trace_id) = context::get_trace_id_opt() else {
+            return;
+        };
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(TraceIdExtension(trace_id));
+        }
+    }
+}
+
+/// 获取事件对应的追踪ID
+///
+/// 优先使用事件发生时的追踪上下文（`get_trace_id_opt()`），
+/// 其次使用事件所在 span（由近及远）中由 `TraceIdSubscriberLayer` 记录的追踪ID。
+/// 供自定义 Layer 或格式化器使用。
+///
+/// # 参数
+/// * `event` - 事件
+/// * `ctx` - Layer 上下文
+///
+/// # 返回
+/// 找到时返回Some(TraceId)，否则返回None
+pub fn event_trace_id<S>(event: &Event<'_>, ctx: &Context<'_, S>) -> Option<TraceId>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    context::get_trace_id_opt().or_else(|| find_in_scope(ctx.event_scope(event)?))
+}
+
+/// 在事件所在的 span 中（由近及远）查找记录的追踪ID
+fn find_in_scope<S>(mut scope: Scope<'_, S>) -> Option<TraceId>
+where
+    S: for<'a> LookupSpan<'a>,
+{
+    scope.find_map(|span| span.extensions().get::<TraceIdExtension>().map(|ext| ext.0))
+}
+
+/// 在每条事件前输出 `trace_id=<id>` 的事件格式化器
+///
+/// 追踪ID的查找规则与 `event_trace_id()` 相同，找不到时原样输出事件
+#[derive(Clone, Debug)]
+pub struct TraceIdFormat<F> {
+    inner: F,
+}
+
+impl<F> TraceIdFormat<F> {
+    /// 包装已有的事件格式化器
+    ///
+    /// # 参数
+    /// * `inner` - 实际负责输出事件的格式化器（如 `tracing_subscriber::fmt::format()`）
+    pub fn new(inner: F) -> Self {
+        Self { inner }
+    }
+}
+
+impl<S, N, F> FormatEvent<S, N> for TraceIdFormat<F>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+    F: FormatEvent<S, N>,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let trace_id = context::get_trace_id_opt().or_else(|| find_in_scope(ctx.event_scope()?));
+        if let Some(trace_id) = trace_id {
+            write!(writer, "trace_id={trace_id} ")?;
+        }
+        self.inner.format_event(ctx, writer, event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tracing_subscriber::{fmt as tracing_fmt, prelude::*};
+
+    /// 收集格式化输出的写入器
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Buffer {
+        fn take(&self) -> String {
+            String::from_utf8(std::mem::take(&mut *self.0.lock().unwrap())).unwrap()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_events_carry_trace_id() {
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::registry()
+            .with(TraceIdSubscriberLayer::new())
+            .with(
+                tracing_fmt::layer()
+                    .event_format(TraceIdFormat::new(tracing_fmt::format().with_ansi(false)))
+                    .with_writer(move || writer.clone()),
+            );
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let trace_id = TraceId::new();
+        let span = context::with_trace_id(trace_id, async {
+            tracing::info!("inside scope");
+            tracing::info_span!("background")
+        })
+        .await;
+        assert!(buffer.take().contains(&format!("trace_id={trace_id} ")));
+
+        // 离开作用域后，在作用域内创建的 span 中依然能找到追踪ID
+        span.in_scope(|| tracing::info!("outside scope"));
+        assert!(buffer.take().contains(&format!("trace_id={trace_id} ")));
+
+        // 没有追踪ID时原样输出
+        tracing::info!("no trace id");
+        let output = buffer.take();
+        assert!(output.contains("no trace id"));
+        assert!(!output.contains("trace_id="));
+    }
+}
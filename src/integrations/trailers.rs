@@ -0,0 +1,345 @@
+//! 通过 HTTP trailers 传递追踪ID
+//!
+//! gRPC 与部分流式协议在响应体结束后以 trailers 携带状态（如 `grpc-status`），
+//! 只处理 trailers 的客户端与网格代理看不到响应头中的追踪ID。
+//!
+//! - `TraceIdLayer::with_trailers()` 将响应体包装为 `TrailerBody`，在响应体结束时
+//!   把追踪ID写入 trailers：内部响应体已有 trailers 时合并，否则追加一个 trailers 帧
+//! - `extract_trace_id_from_trailers()` 按与请求头相同的规则校验请求 trailers 中的追踪ID
+//!
+//! 请求 trailers 在请求体读完之后才到达，而追踪ID必须在调用处理器之前确定，
+//! 因此中间件不会从请求 trailers 中读取追踪ID；读取了请求体的处理器可以用
+//! `extract_trace_id_from_trailers()` 校验 trailers 中的追踪ID并与当前上下文比对。
+
+use crate::integrations::tower::{
+    extract_inbound_trace_id, ResponseTraceId, TraceIdConfig, TraceIdLayer, TraceIdService,
+};
+use crate::trace_id::TraceId;
+use http::{HeaderMap, HeaderName, HeaderValue, Request, Response};
+use http_body::{Body, Frame, SizeHint};
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+use tower::{Layer, Service};
+
+/// 在响应 trailers 中写入追踪ID的追踪ID层，由 `TraceIdLayer::with_trailers()` 创建
+#[derive(Clone)]
+pub struct TrailersLayer {
+    layer: TraceIdLayer,
+}
+
+impl TraceIdLayer {
+    /// 在响应体结束时将追踪ID写入 trailers
+    ///
+    /// trailer 名称与响应头相同（`TraceIdConfig::response_header()`），值也与响应头相同。
+    /// 内部响应体结束时已有 trailers（如 gRPC 的 `grpc-status`）则合并到其中，
+    /// 否则追加一个只含追踪ID的 trailers 帧。跳过追踪或被拒绝的请求不写入。
+    ///
+    /// 该选项改变响应体类型，因此通过本方法而非 `TraceIdConfig` 的字段启用（默认不启用）。
+    /// trailers 需要 HTTP/2，或 HTTP/1.1 的分块传输且客户端声明了 `TE: trailers`，
+    /// 否则会被传输层丢弃；gRPC-Web 的 trailers 由 gRPC-Web 转换层编码进响应体，
+    /// 本层需位于其内部
+    ///
+    /// # 示例
+    /// ```
+    /// use trace_id::TraceIdLayer;
+    ///
+    /// let layer = TraceIdLayer::new().with_trailers();
+    /// ```
+    pub fn with_trailers(self) -> TrailersLayer {
+        TrailersLayer { layer: self }
+    }
+}
+
+impl<S> Layer<S> for TrailersLayer {
+    type Service = TraceIdService<Trailers<S>>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        let name = self.layer.config().response_header().clone();
+        self.layer.layer(Trailers { inner, name })
+    }
+}
+
+/// 将响应体包装为 `TrailerBody` 的服务，位于 `TraceIdService` 内部
+#[derive(Clone)]
+pub struct Trailers<S> {
+    inner: S,
+    name: HeaderName,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for Trailers<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = Response<TrailerBody<ResBody>>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        // 复用外层 `TraceIdService` 为响应头确定的追踪ID，两者始终一致；
+        // 完全跳过追踪的请求没有该扩展，不写入 trailer
+        let trailer = req
+            .extensions()
+            .get::<ResponseTraceId>()
+            .map(|response_id| (self.name.clone(), response_id.0.to_header_value()));
+        let future = self.inner.call(req);
+
+        Box::pin(async move {
+            let response = future.await?;
+            Ok(response.map(|body| TrailerBody {
+                inner: body,
+                trailer,
+            }))
+        })
+    }
+}
+
+pin_project_lite::pin_project! {
+    /// 结束时在 trailers 中写入追踪ID的响应体
+    pub struct TrailerBody<B> {
+        #[pin]
+        inner: B,
+        // 尚未写入的 trailer，写入后为None
+        trailer: Option<(HeaderName, HeaderValue)>,
+    }
+}
+
+impl<B> TrailerBody<B> {
+    /// 获取内部的响应体
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
+
+impl<B: Default> Default for TrailerBody<B> {
+    /// 不写入 trailer 的空响应体（如 `MissingIdPolicy::Reject` 生成的 400 响应）
+    fn default() -> Self {
+        Self {
+            inner: B::default(),
+            trailer: None,
+        }
+    }
+}
+
+impl<B: Body> Body for TrailerBody<B> {
+    type Data = B::Data;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        let frame = match ready!(this.inner.poll_frame(cx)) {
+            Some(Ok(frame)) => frame,
+            Some(Err(error)) => return Poll::Ready(Some(Err(error))),
+            // 内部响应体没有 trailers，追加一个只含追踪ID的 trailers 帧
+            None => {
+                return Poll::Ready(this.trailer.take().map(|(name, value)| {
+                    let mut trailers = HeaderMap::new();
+                    trailers.insert(name, value);
+                    Ok(Frame::trailers(trailers))
+                }))
+            }
+        };
+
+        // 合并到内部响应体的 trailers 中
+        Poll::Ready(Some(Ok(match frame.into_trailers() {
+            Ok(mut trailers) => {
+                if let Some((name, value)) = this.trailer.take() {
+                    trailers.insert(name, value);
+                }
+                Frame::trailers(trailers)
+            }
+            Err(frame) => frame,
+        })))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.trailer.is_none() && self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+/// 从请求 trailers 中提取追踪ID
+///
+/// 按 `config` 中的入站头部名称（`header_name`/`header_names`）与校验规则（包括 `enable_short_ids`）
+/// 读取，与中间件处理请求头的方式相同，但不会回退到 B3 等其他协议的头部，也不会生成新ID。
+///
+/// # 参数
+/// * `trailers` - 读取请求体后得到的 trailers
+/// * `config` - 与中间件相同的配置
+///
+/// # 返回
+/// 存在有效的追踪ID时返回Some(TraceId)，否则返回None
+///
+/// # 示例
+/// ```
+/// use http::{HeaderMap, HeaderValue};
+/// use trace_id::{extract_trace_id_from_trailers, TraceIdConfig};
+///
+/// let mut trailers = HeaderMap::new();
+/// trailers.insert("x-trace-id", HeaderValue::from_static("0af7651916cd43dd8448eb211c80319c"));
+/// let trace_id = extract_trace_id_from_trailers(&trailers, &TraceIdConfig::default());
+/// assert_eq!(trace_id.unwrap(), "0af7651916cd43dd8448eb211c80319c");
+/// ```
+pub fn extract_trace_id_from_trailers(
+    trailers: &HeaderMap,
+    config: &TraceIdConfig,
+) -> Option<TraceId> {
+    extract_inbound_trace_id(trailers, config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TRACE_ID_HEADER;
+    use std::convert::Infallible;
+    use tower::{service_fn, ServiceExt};
+
+    const VALID_ID: &str = "0af7651916cd43dd8448eb211c80319c";
+
+    /// 产生一个数据帧，并可选地以 `grpc-status` trailers 结束的响应体
+    #[derive(Default)]
+    struct GrpcBody {
+        data_sent: bool,
+        trailers: Option<HeaderMap>,
+    }
+
+    impl GrpcBody {
+        fn new(with_status: bool) -> Self {
+            let trailers = with_status.then(|| {
+                let mut trailers = HeaderMap::new();
+                trailers.insert("grpc-status", HeaderValue::from_static("0"));
+                trailers
+            });
+            Self {
+                data_sent: false,
+                trailers,
+            }
+        }
+    }
+
+    impl Body for GrpcBody {
+        type Data = &'static [u8];
+        type Error = Infallible;
+
+        fn poll_frame(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+            if !self.data_sent {
+                self.data_sent = true;
+                return Poll::Ready(Some(Ok(Frame::data(&b"message"[..]))));
+            }
+            Poll::Ready(
+                self.trailers
+                    .take()
+                    .map(|trailers| Ok(Frame::trailers(trailers))),
+            )
+        }
+    }
+
+    /// 轮询响应体直到结束，返回数据帧数量与 trailers
+    async fn drain<B: Body + Unpin>(mut body: B) -> (usize, Option<HeaderMap>) {
+        let (mut frames, mut trailers) = (0, None);
+        while let Some(frame) = std::future::poll_fn(|cx| Pin::new(&mut body).poll_frame(cx)).await
+        {
+            let Ok(frame) = frame else {
+                panic!("body error")
+            };
+            match frame.into_trailers() {
+                Ok(frame_trailers) => {
+                    assert!(trailers.replace(frame_trailers).is_none());
+                }
+                Err(_) => frames += 1,
+            }
+        }
+        (frames, trailers)
+    }
+
+    fn request() -> Request<()> {
+        Request::builder()
+            .header(TRACE_ID_HEADER, VALID_ID)
+            .body(())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_trailer_merged_and_appended() {
+        for with_status in [true, false] {
+            let service = TraceIdLayer::new().with_trailers().layer(service_fn(
+                move |_req: Request<()>| async move {
+                    Ok::<_, Infallible>(Response::new(GrpcBody::new(with_status)))
+                },
+            ));
+
+            let response = service.oneshot(request()).await.unwrap();
+            assert_eq!(response.headers()[TRACE_ID_HEADER], VALID_ID);
+
+            let (frames, trailers) = drain(Box::pin(response.into_body())).await;
+            let trailers = trailers.unwrap();
+            assert_eq!(frames, 1);
+            assert_eq!(trailers[TRACE_ID_HEADER], VALID_ID);
+            assert_eq!(trailers.contains_key("grpc-status"), with_status);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replacement_id_shared_by_header_and_trailer() {
+        // 生成器返回全零ID时，响应头与 trailer 使用同一个替换ID
+        let service = TraceIdLayer::new()
+            .with_trace_id_generator(|| TraceId::NIL)
+            .with_trailers()
+            .layer(service_fn(|_req: Request<()>| async {
+                Ok::<_, Infallible>(Response::new(GrpcBody::new(true)))
+            }));
+
+        let response = service.oneshot(Request::new(())).await.unwrap();
+        let header = response.headers()[TRACE_ID_HEADER].clone();
+        assert!(TraceId::from_header_value(&header).is_some());
+
+        let (_, trailers) = drain(Box::pin(response.into_body())).await;
+        assert_eq!(trailers.unwrap()[TRACE_ID_HEADER], header);
+    }
+
+    #[tokio::test]
+    async fn test_no_trailer_for_skipped_requests() {
+        let config = TraceIdConfig::builder().skip_paths(["/healthz"]).build();
+        let service = TraceIdLayer::with_config(config)
+            .with_trailers()
+            .layer(service_fn(|_req: Request<()>| async {
+                Ok::<_, Infallible>(Response::new(GrpcBody::new(false)))
+            }));
+
+        let request = Request::builder().uri("/healthz").body(()).unwrap();
+        let response = service.oneshot(request).await.unwrap();
+        assert_eq!(drain(Box::pin(response.into_body())).await, (1, None));
+    }
+
+    #[test]
+    fn test_extract_from_trailers() {
+        let config = TraceIdConfig::default();
+        let mut trailers = HeaderMap::new();
+        assert_eq!(extract_trace_id_from_trailers(&trailers, &config), None);
+
+        trailers.insert(TRACE_ID_HEADER, HeaderValue::from_static("invalid"));
+        assert_eq!(extract_trace_id_from_trailers(&trailers, &config), None);
+
+        trailers.insert(TRACE_ID_HEADER, HeaderValue::from_static(VALID_ID));
+        assert_eq!(
+            extract_trace_id_from_trailers(&trailers, &config).unwrap(),
+            VALID_ID
+        );
+    }
+}
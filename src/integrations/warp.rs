@@ -0,0 +1,136 @@
+//! warp 框架的追踪ID集成
+//!
+//! warp 的 `Filter` 无法在外部包装处理器的执行过程，因此无法仅靠过滤器设置 task_local 上下文。
+//! 本模块提供两部分：
+//! - `trace_id_filter()`：在处理器参数中提取或生成追踪ID
+//! - `traced_service()`：用通用的 `TraceIdLayer` 包装 `warp::service(filter)`，
+//!   负责上下文、span 与响应头，行为与 Axum 中间件层完全一致
+
+use crate::{
+    integrations::tower::{
+        extract_or_generate_trace_id, TraceIdConfig, TraceIdLayer, TraceIdService,
+    },
+    trace_id::TraceId,
+};
+use http::Request;
+use std::convert::Infallible;
+use tower::{Layer, Service};
+use warp::{reject::Rejection, reply::Reply, Filter};
+
+/// 提取或生成追踪ID的过滤器
+///
+/// 经 `traced_service()`（或 `TraceIdLayer`）包装时直接复用中间件确定的追踪ID；
+/// 否则按默认配置从 `x-trace-id` 请求头提取，缺失或无效时生成新的ID。
+///
+/// # 示例
+/// ```
+/// use trace_id::{warp::trace_id_filter, TraceId};
+/// use warp::Filter;
+///
+/// let route = warp::path("hello")
+///     .and(trace_id_filter())
+///     .map(|trace_id: TraceId| format!("Your trace ID is: {trace_id}"));
+/// ```
+pub fn trace_id_filter() -> impl Filter<Extract = (TraceId,), Error = Infallible> + Clone {
+    warp::ext::optional::<TraceId>()
+        .and(warp::header::headers_cloned())
+        .map(|trace_id: Option<TraceId>, headers| {
+            trace_id
+                .or_else(|| extract_or_generate_trace_id(&headers, &TraceIdConfig::default(), None))
+                .unwrap_or_default()
+        })
+}
+
+/// 使用默认配置的 `TraceIdLayer` 包装 warp 过滤器
+///
+/// 返回的 tower 服务在追踪上下文中执行过滤器（处理器中可直接调用 `get_trace_id()`），
+/// 并添加响应头。`warp::serve` 只接受过滤器，需通过 hyper 等服务器运行；
+/// 需要自定义配置时可直接使用 `TraceIdLayer::with_config(...).layer(warp::service(filter))`。
+///
+/// # 参数
+/// * `filter` - 要包装的 warp 过滤器
+///
+/// # 返回
+/// 包装后的 tower 服务
+///
+/// # 示例
+/// ```
+/// use tower::ServiceExt;
+/// use trace_id::warp::traced_service;
+/// use warp::{http::Request, Filter};
+///
+/// # async fn run() {
+/// let route = warp::any().map(|| trace_id::get_trace_id().to_string());
+/// let response = traced_service(route)
+///     .oneshot(Request::new(String::new()))
+///     .await
+///     .unwrap();
+/// assert!(response.headers().contains_key(trace_id::TRACE_ID_HEADER));
+/// # }
+/// ```
+pub fn traced_service<F, B>(
+    filter: F,
+) -> TraceIdService<
+    impl Service<
+            Request<B>,
+            Response = warp::reply::Response,
+            Error = Infallible,
+            Future = impl Send,
+        > + Clone,
+>
+where
+    F: Filter + Send + Sync + 'static,
+    F::Extract: Reply + Send,
+    F::Error: Into<Rejection>,
+    B: http_body::Body + Send + Sync + 'static,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    TraceIdLayer::new().layer(warp::service(filter.boxed()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{context, TRACE_ID_HEADER};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_trace_id_filter() {
+        let valid_id = "0af7651916cd43dd8448eb211c80319c";
+        let filter = trace_id_filter();
+
+        let trace_id = warp::test::request()
+            .header(TRACE_ID_HEADER, valid_id)
+            .filter(&filter)
+            .await
+            .unwrap();
+        assert_eq!(trace_id.as_str(), valid_id);
+
+        // 缺失或无效时生成新的ID
+        let trace_id = warp::test::request()
+            .header(TRACE_ID_HEADER, "invalid")
+            .filter(&filter)
+            .await
+            .unwrap();
+        assert_ne!(trace_id.as_str(), "invalid");
+        assert_eq!(trace_id.as_str().len(), 32);
+    }
+
+    #[tokio::test]
+    async fn test_traced_service() {
+        let valid_id = "0af7651916cd43dd8448eb211c80319c";
+        let route = trace_id_filter().map(move |trace_id: TraceId| {
+            // 过滤器与上下文中的追踪ID一致
+            assert_eq!(trace_id.as_str(), valid_id);
+            assert_eq!(context::get_trace_id_opt(), Some(trace_id));
+            "ok"
+        });
+
+        let request = Request::builder()
+            .header(TRACE_ID_HEADER, valid_id)
+            .body(String::new())
+            .unwrap();
+        let response = traced_service(route).oneshot(request).await.unwrap();
+        assert_eq!(response.headers().get(TRACE_ID_HEADER).unwrap(), valid_id);
+    }
+}
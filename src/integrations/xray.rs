@@ -0,0 +1,142 @@
+//! AWS X-Ray 头部传播支持
+//!
+//! ALB 与 X-Ray 使用 `X-Amzn-Trace-Id` 头部（`Root=1-{8位十六进制}-{24位十六进制};Parent=...;Sampled=1`）。
+//! Root 中去掉版本号 `1-` 与分隔符后的32个十六进制字符与128位 `TraceId` 一一对应。
+//!
+//! X-Ray 要求 Root 的前8个十六进制字符为追踪开始时的 Unix 秒数；本crate生成的ID高位是毫秒时间戳，
+//! ALB 会原样转发，但直接上报给 X-Ray 服务时可能因时间不符而被拒绝。
+
+use crate::{trace_context::SpanId, trace_id::TraceId};
+use http::{HeaderMap, HeaderValue};
+
+/// AWS X-Ray 追踪头部名称
+pub const XRAY_TRACE_ID_HEADER: &str = "x-amzn-trace-id";
+
+/// X-Ray 追踪ID的版本号
+const XRAY_VERSION: &str = "1";
+
+/// 从请求头中提取 X-Ray 追踪ID
+///
+/// 读取 `X-Amzn-Trace-Id` 中的 `Root` 字段，其余字段（`Parent`、`Sampled`、`Self` 等）被忽略
+///
+/// # 参数
+/// * `headers` - 请求头
+///
+/// # 返回
+/// 如果存在有效的 Root 则返回Some(TraceId)，否则返回None
+pub fn extract(headers: &HeaderMap) -> Option<TraceId> {
+    let value = headers.get(XRAY_TRACE_ID_HEADER)?.to_str().ok()?;
+    value
+        .split(';')
+        .find_map(|field| field.trim().strip_prefix("Root="))
+        .and_then(from_root)
+}
+
+/// 将 X-Ray Root（`1-{8位十六进制}-{24位十六进制}`）转换为追踪ID
+///
+/// # 参数
+/// * `root` - X-Ray Root 字符串
+///
+/// # 返回
+/// 格式有效时返回Some(TraceId)，否则返回None
+pub fn from_root(root: &str) -> Option<TraceId> {
+    let mut parts = root.split('-');
+    let (version, time, id) = (parts.next()?, parts.next()?, parts.next()?);
+    if version != XRAY_VERSION || parts.next().is_some() || time.len() != 8 || id.len() != 24 {
+        return None;
+    }
+
+    let mut hex = [0u8; 32];
+    hex[..8].copy_from_slice(time.as_bytes());
+    hex[8..].copy_from_slice(id.as_bytes());
+    TraceId::from_string_validated(core::str::from_utf8(&hex).ok()?)
+}
+
+/// 将追踪ID转换为 X-Ray Root 格式
+///
+/// # 参数
+/// * `trace_id` - 追踪ID
+///
+/// # 返回
+/// `1-{前8个十六进制字符}-{后24个十六进制字符}`
+pub fn to_root(trace_id: &TraceId) -> String {
+    let hex = trace_id.padded().to_string();
+    format!("{XRAY_VERSION}-{}-{}", &hex[..8], &hex[8..])
+}
+
+/// 将追踪信息写入 `X-Amzn-Trace-Id` 请求头
+///
+/// # 参数
+/// * `headers` - 要写入的请求头
+/// * `trace_id` - 追踪ID（写入 `Root`）
+/// * `span_id` - 当前跨度ID（写入 `Parent`）
+/// * `sampled` - 采样决策，None表示交由下游决定（不写入 `Sampled`）
+pub fn inject(
+    headers: &mut HeaderMap,
+    trace_id: &TraceId,
+    span_id: &SpanId,
+    sampled: Option<bool>,
+) {
+    let mut value = format!("Root={};Parent={span_id}", to_root(trace_id));
+    if let Some(sampled) = sampled {
+        value.push_str(if sampled { ";Sampled=1" } else { ";Sampled=0" });
+    }
+    if let Ok(value) = HeaderValue::from_str(&value) {
+        headers.insert(XRAY_TRACE_ID_HEADER, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract() {
+        let mut headers = HeaderMap::new();
+        // 头部名称大小写不敏感，字段顺序不固定
+        headers.insert(
+            "X-Amzn-Trace-Id",
+            HeaderValue::from_static(
+                "Self=1-67891234-12456789abcdef012345678;Root=1-5759e988-bd862e3fe1be46a994272793;Sampled=1",
+            ),
+        );
+        let trace_id = extract(&headers).expect("应提取到追踪ID");
+        assert_eq!(trace_id.as_str(), "5759e988bd862e3fe1be46a994272793");
+    }
+
+    #[test]
+    fn test_extract_invalid() {
+        let invalid = [
+            "",
+            "Parent=53995c3f42cd8ad8;Sampled=1",
+            "Root=5759e988-bd862e3fe1be46a994272793",
+            "Root=2-5759e988-bd862e3fe1be46a994272793",
+            "Root=1-5759e98-bd862e3fe1be46a9942727930",
+            "Root=1-5759e988-bd862e3fe1be46a99427279",
+            "Root=1-5759e988-bd862e3fe1be46a994272793-00",
+            "Root=1-5759E988-BD862E3FE1BE46A994272793",
+            "Root=1-00000000-000000000000000000000000",
+        ];
+        for value in invalid {
+            let mut headers = HeaderMap::new();
+            headers.insert(XRAY_TRACE_ID_HEADER, HeaderValue::from_str(value).unwrap());
+            assert!(extract(&headers).is_none(), "应拒绝: {value}");
+        }
+    }
+
+    #[test]
+    fn test_inject_roundtrip() {
+        let trace_id = TraceId::from_string_validated("5759e988bd862e3fe1be46a994272793").unwrap();
+        assert_eq!(to_root(&trace_id), "1-5759e988-bd862e3fe1be46a994272793");
+        assert_eq!(from_root(&to_root(&trace_id)), Some(trace_id));
+
+        let span_id = SpanId::new();
+        let mut headers = HeaderMap::new();
+        inject(&mut headers, &trace_id, &span_id, Some(true));
+        assert_eq!(extract(&headers), Some(trace_id));
+        assert_eq!(
+            headers.get(XRAY_TRACE_ID_HEADER).unwrap(),
+            format!("Root=1-5759e988-bd862e3fe1be46a994272793;Parent={span_id};Sampled=1").as_str()
+        );
+    }
+}
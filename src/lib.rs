@@ -6,10 +6,12 @@
 //!
 //! ## Core Features
 //!
-//! - **High Performance**: Uses timestamp + atomic counter + machine ID combination, avoiding UUID generation overhead
+//! - **High Performance**: Uses timestamp + machine ID + sequence + random combination, avoiding UUID generation overhead
+//! - **Guaranteed Uniqueness**: A monotonic (timestamp, sequence) state makes IDs from one process collision-free
 //! - **W3C Compliant**: Generates 128-bit trace-id compliant with W3C TraceContext specification
 //! - **Async Friendly**: Context management based on tokio::task_local, supporting ID propagation between async tasks
 //! - **Framework Agnostic**: Core functionality doesn't depend on specific web frameworks
+//! - **Tower Integration**: Generic middleware for any `http`-based tower stack (hyper, tonic, ...)
 //! - **Axum Integration**: Provides out-of-the-box middleware and extractors
 //!
 //! ## Basic Usage
@@ -74,18 +76,55 @@
 //! }
 //! ```
 //!
+//! ## Tower Integration
+//!
+//! The `tower` feature provides the same layer for any `http::Request<B>`/`http::Response<B>`
+//! based tower stack, independent of Axum:
+//!
+//! ```ignore
+//! use tower::ServiceBuilder;
+//! use trace_id::TraceIdLayer;
+//!
+//! let service = ServiceBuilder::new()
+//!     .layer(TraceIdLayer::new())
+//!     .service(my_hyper_service);
+//! ```
+//!
+//! The response header is added to every response produced *inside* the layer, including error
+//! responses. Panic-catching layers must therefore sit inside `TraceIdLayer`; alternatively
+//! `TraceIdLayer::new().with_catch_panic()` converts panics into `500` responses within the trace context.
+//!
 //! ### Advanced Configuration
 //! ```ignore
 //! use trace_id::{TraceIdLayer, TraceIdConfig};
 //!
-//! let config = TraceIdConfig {
-//!     enable_span: true,           // Enable tracing span
-//!     enable_response_header: true, // Include trace ID in response
-//! };
+//! let config = TraceIdConfig::builder()
+//!     .enable_span(true)            // Enable tracing span
+//!     .enable_response_header(true) // Include trace ID in response
+//!     .enable_traceparent(true)     // Honor incoming W3C traceparent header
+//!     .build();
 //!
 //! let layer = TraceIdLayer::with_config(config)
-//!     .with_generator(|| uuid::Uuid::new_v4().to_string()); // Custom generator
+//!     .with_uuid_generator(); // v4 UUID ids ("uuid" feature)
 //! ```
+//!
+//! ## `no_std` Support
+//!
+//! The `std` feature is enabled by default. With `default-features = false` the crate is
+//! `no_std` and only the `TraceId` type remains: `from_bytes`, `as_bytes`, `to_u128`/`from_u128`,
+//! hex parsing/validation (`FromStr`, `from_string_validated`) and formatting (`as_str`, `Display`)
+//! work with `core` alone and never allocate. ID generation, context management and all
+//! framework integrations require `std`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(all(
+    feature = "std",
+    not(any(feature = "rng-fastrand", feature = "rng-rand"))
+))]
+compile_error!(
+    "the `std` feature needs a random source: enable `rng-fastrand` (default) or `rng-rand`"
+);
 
 // ================================================================================================
 // Module Declarations
@@ -94,6 +133,7 @@
 /// Trace ID context management module
 ///
 /// Provides async context management functionality based on tokio::task_local
+#[cfg(feature = "std")]
 mod context;
 
 /// Trace ID core struct module
@@ -101,6 +141,66 @@ mod context;
 /// Contains TraceId struct definition and related implementations
 mod trace_id;
 
+/// Clock abstraction module
+///
+/// Contains the `Clock` trait used for the timestamp portion of generated IDs
+#[cfg(feature = "std")]
+mod clock;
+
+/// Random source abstraction module
+///
+/// Contains the `RandomSource` trait used for the random portion of generated IDs
+#[cfg(feature = "std")]
+mod random;
+
+/// Sampling module
+///
+/// Contains the `Sampler` trait and the built-in always-on, always-off and ratio samplers
+#[cfg(feature = "std")]
+mod sampler;
+
+/// Metrics module
+///
+/// Contains the `MetricsHook` trait and the atomic-counter `TraceIdMetrics` implementation
+#[cfg(feature = "std")]
+mod metrics;
+
+/// Active trace registry module
+///
+/// Tracks the trace IDs currently inside `with_trace_id` scopes ("registry" feature)
+#[cfg(feature = "registry")]
+mod registry;
+
+/// Correlation fields module
+///
+/// Contains `CorrelationFields`, the small string map propagated alongside the trace ID
+#[cfg(feature = "std")]
+mod correlation;
+
+/// Panic hook module
+///
+/// Contains `install_panic_hook()`, which prints the current trace ID before panic messages
+#[cfg(feature = "std")]
+mod panic_hook;
+
+/// Outbound propagation module
+///
+/// Contains `HeaderInjector` and the helpers that write the trace ID into outgoing request headers
+#[cfg(feature = "std")]
+mod propagation;
+
+/// Item tagging module
+///
+/// Contains the iterator and stream adaptors that pair each item with the current trace ID
+#[cfg(feature = "std")]
+mod tagging;
+
+/// W3C TraceContext module
+///
+/// Contains SpanId and TraceContext definitions, with `traceparent` parsing and serialization
+#[cfg(feature = "std")]
+mod trace_context;
+
 // ================================================================================================
 // Public API Exports
 // ================================================================================================
@@ -108,33 +208,324 @@ mod trace_id;
 /// Re-export context management functions
 ///
 /// - `get_trace_id()`: Get the trace ID of the current async task
+/// - `set_missing_context_log_level()`: Configure (or silence) the log emitted when `get_trace_id()`
+///   is called outside a trace context
+/// - `get_trace_id_opt()`: Get the trace ID of the current async task, without generating a fallback
 /// - `with_trace_id()`: Execute async operations within specified trace context
-pub use context::{get_trace_id, with_trace_id};
+/// - `with_trace_id_sync()`: Execute a synchronous closure within specified trace context (no runtime needed)
+/// - `enter_trace_id()`: Enter a trace context until the returned `TraceIdGuard` is dropped, for
+///   imperative synchronous code
+/// - `spawn_traced()` / `spawn_traced_blocking()`: Drop-in replacements for `tokio::spawn` /
+///   `tokio::task::spawn_blocking` that carry the current trace ID and correlation fields into
+///   the new task; `spawn_blocking_traced()` is an alias named after `spawn_blocking`
+/// - `bind_trace_id_for_connection()`: Wrap a WebSocket (or other long-lived connection) handler
+///   so every message handled on the connection sees the upgrade request's trace ID
+/// - `get_trace_context()`: Get the upstream W3C TraceContext of the current async task, if any
+/// - `with_trace_context()`: Execute async operations within specified W3C TraceContext
+/// - `get_trace_flags()`: Get the trace-flags (sampling decision) to propagate downstream, if any
+/// - `with_trace_flags()`: Execute async operations within specified trace-flags
+/// - `is_sampled()`: Whether the current request is sampled (sampled when no trace-flags are set)
+/// - `get_span_id()`: Get the span ID the middleware generated for the current request, if any
+/// - `with_span_id()`: Execute async operations within specified span ID
+/// - `get_trace_state()`: Get the W3C `tracestate` of the current async task, if any
+/// - `with_trace_state()`: Execute async operations within specified `tracestate`
+/// - `get_correlation()` / `get_correlation_field()`: Get the correlation fields (tenant ID,
+///   user ID, ...) propagated alongside the trace ID, if any
+/// - `with_correlation()`: Execute async operations within specified correlation fields
+#[cfg(feature = "std")]
+pub use context::{
+    bind_trace_id_for_connection, enter_trace_id, get_correlation, get_correlation_field,
+    get_span_id, get_trace_context, get_trace_flags, get_trace_id, get_trace_id_opt,
+    get_trace_state, is_sampled, set_missing_context_log_level, spawn_blocking_traced,
+    spawn_traced, spawn_traced_blocking, with_correlation, with_span_id, with_trace_context,
+    with_trace_flags, with_trace_id, with_trace_id_sync, with_trace_state, TraceIdGuard,
+};
+
+/// Re-export the active trace listing ("registry" feature)
+///
+/// `active_traces()` returns every trace ID currently inside a `with_trace_id` scope together
+/// with how long it has been running, for diagnosing stuck requests
+#[cfg(feature = "registry")]
+pub use registry::active_traces;
+
+/// Re-export the panic hook that prefixes panic messages with the current trace ID
+///
+/// `install_panic_hook()` chains the previously installed hook, so custom panic reporting keeps
+/// working; panics outside a trace context are reported unchanged
+#[cfg(feature = "std")]
+pub use panic_hook::install_panic_hook;
+
+/// Re-export outbound trace ID injection, the counterpart of `extract_or_generate_trace_id()`
+///
+/// - `HeaderInjector`: Small trait for outbound request carriers (ureq, custom transports, ...)
+/// - `inject_into()`: Write a given trace ID into any `HeaderInjector` under the given header name
+/// - `inject_trace_id()`: Write the current trace ID into an `http::HeaderMap` ("http" feature),
+///   for hyper and other `http`-based clients
+/// - `inject_traceparent()`: Write the current W3C `traceparent` (trace ID, span ID and trace
+///   flags) into an `http::HeaderMap` ("http" feature)
+#[cfg(feature = "std")]
+pub use propagation::{inject_into, HeaderInjector};
+
+#[cfg(feature = "http")]
+pub use propagation::{inject_trace_id, inject_traceparent};
+
+/// Re-export correlation fields, used by `with_correlation()`
+#[cfg(feature = "std")]
+pub use correlation::CorrelationFields;
+
+/// Re-export the item tagging adaptors
+///
+/// - `TraceIdIterExt::with_current_trace_id()`: Pair every iterator item with the trace ID that
+///   was current when the adaptor was created
+/// - `TraceIdStreamExt::with_current_trace_id()`: The same for `futures_core::Stream` ("stream"
+///   feature)
+#[cfg(feature = "std")]
+pub use tagging::{TaggedIter, TraceIdIterExt};
+
+#[cfg(feature = "stream")]
+pub use tagging::{TaggedStream, TraceIdStreamExt};
+
+/// Re-export core trace ID struct, its parse error and the truncated display form
+pub use trace_id::{PaddedTraceId, TraceId, TraceIdParseError, TruncatedTraceId};
+
+/// Re-export the generator statistics snapshot returned by `TraceId::generation_stats()`
+#[cfg(feature = "std")]
+pub use trace_id::GenerationStats;
+
+/// Re-export machine ID and global generator configuration
+///
+/// - `set_machine_id()`: Assign a stable, unique 16-bit machine ID at startup
+/// - `MACHINE_ID_ENV`: Environment variable (`TRACE_ID_MACHINE_ID`) read when no ID was set explicitly
+/// - `set_global_generator()`: Replace the scheme used by `TraceId::new()` (and the middleware) at startup
+#[cfg(feature = "std")]
+pub use trace_id::{set_global_generator, set_machine_id, MACHINE_ID_ENV};
+
+/// Re-export the test-only reset of the machine ID and global generator
+#[cfg(feature = "test-util")]
+pub use trace_id::reset_globals;
+
+/// Re-export clock abstraction, used by `TraceId::new_with_clock()`
+#[cfg(feature = "std")]
+pub use clock::{Clock, SystemClock};
+
+/// Re-export random source abstraction, used by `TraceId::new_with_rng()`
+#[cfg(feature = "std")]
+pub use random::{FastRand, RandomSource};
+
+/// Re-export sampling types, used by `TraceIdLayer::with_sampler()`
+#[cfg(feature = "std")]
+pub use sampler::{AlwaysOff, AlwaysOn, RatioSampler, Sampler, TRACE_FLAG_SAMPLED};
 
-/// Re-export core trace ID struct
-pub use trace_id::TraceId;
+/// Re-export trace ID source metrics, used by `TraceIdLayer::with_metrics()`
+#[cfg(feature = "std")]
+pub use metrics::{MetricsHook, MetricsSnapshot, TraceIdMetrics};
+
+/// Re-export the `metrics`-crate exporter of trace ID source counters
+///
+/// With the "metrics" feature the middleware always increments `trace_id_propagated_total`,
+/// `trace_id_generated_total`, `trace_id_invalid_total` and `trace_id_generator_fallback_total`
+/// on the `metrics` facade; install any `metrics` exporter (e.g. Prometheus) to collect them.
+#[cfg(feature = "metrics")]
+pub use metrics::MetricsExporter;
+
+/// Re-export W3C TraceContext types
+#[cfg(feature = "std")]
+pub use trace_context::{SpanId, TraceContext, TraceState};
 
 /// Trace ID field name in HTTP headers
 ///
 /// Follows common tracing system conventions, used for passing trace ID in HTTP requests/responses
 pub const TRACE_ID_HEADER: &str = "x-trace-id";
 
+/// W3C TraceContext `traceparent` header name
+///
+/// Carries version, trace-id, parent-id and trace-flags, e.g. `00-<trace-id>-<parent-id>-01`
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+
+/// W3C TraceContext `tracestate` header name
+///
+/// Carries vendor-specific `key=value` pairs that must be propagated untouched
+pub const TRACESTATE_HEADER: &str = "tracestate";
+
+/// Response header carrying the request handling time, written when
+/// `TraceIdConfig::latency_header` is enabled (e.g. `12.345ms`)
+pub const RESPONSE_TIME_HEADER: &str = "x-response-time";
+
 // ================================================================================================
-// Axum Framework Integration (Optional Feature)
+// Framework Integrations (Optional Features)
 // ================================================================================================
 
-/// Axum framework integration module
+/// Framework integration modules
 ///
-/// Only available when "axum" feature is enabled
-#[cfg(feature = "axum")]
+/// Only available when an integration feature ("tower", "axum", "warp", "tonic", "reqwest", "otel",
+/// "tracing-layer", "log") is enabled
+#[cfg(any(
+    feature = "http",
+    feature = "otel",
+    feature = "tonic",
+    feature = "tracing-layer",
+    feature = "log"
+))]
 mod integrations;
 
-/// Re-export Axum middleware layer
+/// B3 (Zipkin) header propagation
+///
+/// Extract a `TraceId` from B3 single (`b3`) or multi (`X-B3-TraceId`) headers,
+/// and inject trace information into outgoing headers
+#[cfg(feature = "http")]
+pub use integrations::b3;
+
+/// Datadog header propagation
+///
+/// Extract a `TraceId` from the decimal `x-datadog-trace-id` header (plus the `_dd.p.tid`
+/// tag for the high 64 bits), and inject trace information into outgoing headers
+#[cfg(feature = "http")]
+pub use integrations::datadog;
+
+/// AWS X-Ray header propagation
 ///
-/// Provides out-of-the-box trace ID middleware, supporting:
+/// Convert between the X-Ray `Root=1-<8 hex>-<24 hex>` id carried in `X-Amzn-Trace-Id`
+/// and `TraceId`, and inject trace information into outgoing headers
+#[cfg(feature = "http")]
+pub use integrations::xray;
+
+/// Read the trace ID that `TraceIdLayer` stores in request extensions
+///
+/// Implemented for `http::Request`, `http::request::Parts` and `http::Extensions`, so layers
+/// stacked below `TraceIdLayer` can retrieve the ID without relying on the task-local context
+#[cfg(feature = "http")]
+pub use integrations::extensions::TraceIdExt;
+
+/// Re-export generic tower middleware layer
+///
+/// Works with any `http::Request<B>`/`http::Response<B>` based tower stack
+/// (hyper, tonic, axum, ...), supporting:
 /// - Automatically extract trace ID from request headers
-/// - Generate new trace ID (if not present in request)
+/// - Generate new trace ID (if not present in request), or reject the request with
+///   `400 Bad Request` according to `MissingIdPolicy` (the response carries a
+///   `TraceIdRejection` extension describing why)
 /// - Add trace ID to response headers
 /// - Create tracing span for log correlation
+/// - Optionally record the handling time (`latency_ms` span field, `ResponseLatency`
+///   response extension, `x-response-time` header)
+///
+/// The Axum integration ("axum" feature) uses this same layer and adds the `TraceId` extractor.
+#[cfg(feature = "tower")]
+pub use integrations::tower::{
+    MissingIdPolicy, ResponseLatency, SkipMode, TraceIdConfig, TraceIdConfigBuilder, TraceIdLayer,
+    TraceIdRejection, TraceIdService,
+};
+
+/// Canonical, framework-agnostic trace ID extraction
+///
+/// Resolves the trace ID for an `http::HeaderMap` exactly like `TraceIdLayer` does
+/// (validation, configured header priority, protocol fallbacks, generation).
+/// Use it when writing middleware for frameworks without a built-in integration.
+#[cfg(feature = "tower")]
+pub use integrations::tower::extract_or_generate_trace_id;
+
+/// Panic handling inside the trace context
+///
+/// Created by `TraceIdLayer::with_catch_panic()`: panics in the inner service become
+/// `500` responses that still carry the trace ID header
+#[cfg(feature = "tower")]
+pub use integrations::catch_panic::{CatchPanic, CatchPanicLayer};
+
+/// Trace context for streaming responses
+///
+/// Created by `TraceIdLayer::with_streaming_body()`: the response body is wrapped in
+/// `TracedBody`, which re-enters the trace context and request span while it is polled,
+/// so events logged by SSE or other streaming bodies keep the trace ID
+#[cfg(feature = "tower")]
+pub use integrations::streaming::{StreamingBody, StreamingBodyLayer, TracedBody};
+
+/// Trace ID in HTTP trailers
+///
+/// Created by `TraceIdLayer::with_trailers()`: the response body is wrapped in `TrailerBody`,
+/// which writes the trace ID into the trailers (merged with e.g. gRPC status trailers);
+/// `extract_trace_id_from_trailers()` validates a trace ID carried in request trailers
+#[cfg(feature = "tower")]
+pub use integrations::trailers::{
+    extract_trace_id_from_trailers, TrailerBody, Trailers, TrailersLayer,
+};
+
+/// warp integration
+///
+/// `trace_id_filter()` extracts or generates the trace ID inside warp filters, and
+/// `traced_service()` wraps a filter with `TraceIdLayer` to manage context and response headers
+#[cfg(feature = "warp")]
+pub use integrations::warp;
+
+/// poem integration
+///
+/// `TraceIdMiddleware` extracts or generates the trace ID (honoring `TraceIdConfig`), runs the
+/// endpoint inside the trace context and adds the response header; `TraceId` is a poem extractor
+#[cfg(feature = "poem")]
+pub use integrations::poem;
+
+/// salvo integration
+///
+/// `TraceIdMiddleware` is a salvo hoop that extracts or generates the trace ID (honoring
+/// `TraceIdConfig`), runs the remaining handlers inside the trace context and adds the response
+/// header; the ID is also available from the `Depot` through `TraceIdExt`
+#[cfg(feature = "salvo")]
+pub use integrations::salvo;
+
+/// rocket integration
+///
+/// `TraceIdFairing` extracts or generates the trace ID (honoring `TraceIdConfig`) and adds the
+/// response header; `TraceId` and `TraceScope` are request guards, and `TraceScope::run` runs the
+/// handler body inside the trace context (fairings cannot wrap the handler future)
+#[cfg(feature = "rocket")]
+pub use integrations::rocket;
+
+/// tonic (gRPC) interceptors
+///
+/// `ServerInterceptor` reads (or generates) the trace ID from ASCII `x-trace-id` or binary
+/// `x-trace-id-bin` metadata, and `ClientInterceptor` injects the current trace ID into outgoing metadata
+#[cfg(feature = "tonic")]
+pub use integrations::tonic;
+
+/// Re-export strict Axum extractor
+///
+/// `RequiredTraceId` rejects requests without a valid client-supplied `x-trace-id`
+/// with `400 Bad Request` (`MissingTraceId`), instead of generating a new ID
+#[cfg(feature = "axum")]
+pub use integrations::axum::{MissingTraceId, RequiredTraceId};
+
+/// Re-export the Axum path/query parameter type
+///
+/// `TraceIdParam` deserializes a trace ID with the same validation as `FromStr`, so
+/// `Path<TraceIdParam>` and `Query<...>` reject malformed IDs with `400 Bad Request`
+/// and the parse error message
 #[cfg(feature = "axum")]
-pub use integrations::axum::{TraceIdConfig, TraceIdLayer};
+pub use integrations::axum::TraceIdParam;
+
+/// Re-export reqwest client middleware
+///
+/// Automatically injects the current trace ID into every outgoing `reqwest` request,
+/// and does nothing when called outside a trace context
+#[cfg(feature = "reqwest")]
+pub use integrations::reqwest::TraceIdMiddleware;
+
+/// `tracing-subscriber` integration
+///
+/// `TraceIdSubscriberLayer` records the current trace ID on every new span, and `TraceIdFormat`
+/// prefixes every formatted event with `trace_id=<id>`, without requiring the HTTP middleware
+#[cfg(feature = "tracing-layer")]
+pub use integrations::tracing_layer;
+
+/// OpenTelemetry bridge
+///
+/// Convert a `TraceId` into an `opentelemetry::trace::TraceId` and install it as the
+/// remote parent of the active OTel `Context`, so exported spans share the HTTP-level trace ID
+#[cfg(feature = "otel")]
+pub use integrations::otel;
+
+/// `log` crate integration
+///
+/// `TraceId` implements `log::kv::ToValue`, `current()` exposes the current trace ID as a
+/// `log::kv::Source`, and `TraceIdLogger` wraps any `log::Log` to attach `trace_id` to every
+/// record logged inside a trace context
+#[cfg(feature = "log")]
+pub use integrations::log;
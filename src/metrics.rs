@@ -0,0 +1,213 @@
+//! 追踪ID来源统计
+//!
+//! 通过 `MetricsHook` 观察中间件中追踪ID的来源：沿用上游传入的ID、生成新ID、
+//! 请求头存在但无效、自定义生成器结果无效而回退。`TraceIdMetrics` 是基于原子计数器的内置实现。
+//!
+//! 启用 `metrics` 特性时，中间件还会把这些事件写入 `metrics` 门面的计数器（见 `MetricsExporter`），
+//! 安装任意 `metrics` 导出器（如 Prometheus）即可采集，无需注册回调。
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// 追踪ID来源的回调
+///
+/// 所有方法默认不做任何处理，只需实现关心的事件。回调在请求处理路径上同步调用，应保持轻量。
+pub trait MetricsHook {
+    /// 请求携带了有效的追踪ID并被沿用
+    fn on_propagated(&self) {}
+
+    /// 请求没有可用的追踪ID，生成了新ID
+    fn on_generated(&self) {}
+
+    /// 请求携带了配置的追踪ID头部，但值无效（随后会生成新ID）
+    fn on_invalid_header(&self) {}
+
+    /// 自定义生成器（`with_generator`）返回了无效ID，回退到默认生成器
+    fn on_generator_fallback(&self) {}
+}
+
+impl<T: MetricsHook + ?Sized> MetricsHook for Arc<T> {
+    fn on_propagated(&self) {
+        (**self).on_propagated()
+    }
+
+    fn on_generated(&self) {
+        (**self).on_generated()
+    }
+
+    fn on_invalid_header(&self) {
+        (**self).on_invalid_header()
+    }
+
+    fn on_generator_fallback(&self) {
+        (**self).on_generator_fallback()
+    }
+}
+
+/// 基于原子计数器的 `MetricsHook` 实现
+///
+/// 通常包装在 `Arc` 中，一份交给中间件，一份用于定期读取 `snapshot()`
+///
+/// # 示例
+/// ```ignore
+/// use std::sync::Arc;
+/// use trace_id::{TraceIdLayer, TraceIdMetrics};
+///
+/// let metrics = Arc::new(TraceIdMetrics::new());
+/// let layer = TraceIdLayer::new().with_metrics(metrics.clone());
+///
+/// let snapshot = metrics.snapshot();
+/// println!("propagated: {}, generated: {}", snapshot.propagated, snapshot.generated);
+/// ```
+#[derive(Debug, Default)]
+pub struct TraceIdMetrics {
+    propagated: AtomicU64,
+    generated: AtomicU64,
+    invalid_header: AtomicU64,
+    generator_fallback: AtomicU64,
+}
+
+/// `TraceIdMetrics` 在某一时刻的计数
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    /// 沿用上游追踪ID的请求数
+    pub propagated: u64,
+    /// 生成新追踪ID的请求数
+    pub generated: u64,
+    /// 追踪ID头部存在但无效的请求数
+    pub invalid_header: u64,
+    /// 自定义生成器结果无效而回退的次数
+    pub generator_fallback: u64,
+}
+
+impl TraceIdMetrics {
+    /// 创建所有计数为0的统计
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 读取当前计数
+    ///
+    /// 各计数分别读取，并发更新时彼此之间不保证严格一致
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            propagated: self.propagated.load(Ordering::Relaxed),
+            generated: self.generated.load(Ordering::Relaxed),
+            invalid_header: self.invalid_header.load(Ordering::Relaxed),
+            generator_fallback: self.generator_fallback.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl MetricsHook for TraceIdMetrics {
+    fn on_propagated(&self) {
+        self.propagated.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn on_generated(&self) {
+        self.generated.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn on_invalid_header(&self) {
+        self.invalid_header.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn on_generator_fallback(&self) {
+        self.generator_fallback.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// 将追踪ID来源写入 `metrics` 门面计数器的 `MetricsHook` 实现
+///
+/// 启用 `metrics` 特性后，中间件（包括 `extract_or_generate_trace_id`）总是调用它，
+/// 与 `with_metrics` 注册的回调互不影响；未安装 `metrics` 导出器时计数被丢弃。计数器为：
+///
+/// | 计数器 | 事件 |
+/// |--------|------|
+/// | `trace_id_propagated_total` | 沿用上游追踪ID |
+/// | `trace_id_generated_total` | 生成新追踪ID |
+/// | `trace_id_invalid_total` | 追踪ID头部存在但无效 |
+/// | `trace_id_generator_fallback_total` | 自定义生成器结果无效而回退 |
+#[cfg(feature = "metrics")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MetricsExporter;
+
+#[cfg(feature = "metrics")]
+impl MetricsExporter {
+    /// 向已安装的导出器登记计数器说明（如 Prometheus 的 `# HELP`）
+    ///
+    /// 计数器在首次递增时自动注册，本方法只补充说明，应在安装导出器之后调用
+    pub fn describe() {
+        ::metrics::describe_counter!(
+            PROPAGATED_TOTAL,
+            "Requests that reused a valid inbound trace id"
+        );
+        ::metrics::describe_counter!(
+            GENERATED_TOTAL,
+            "Requests that were assigned a newly generated trace id"
+        );
+        ::metrics::describe_counter!(
+            INVALID_TOTAL,
+            "Requests whose trace id header was present but invalid"
+        );
+        ::metrics::describe_counter!(
+            GENERATOR_FALLBACK_TOTAL,
+            "Custom generator results that were invalid and replaced by the default generator"
+        );
+    }
+}
+
+#[cfg(feature = "metrics")]
+const PROPAGATED_TOTAL: &str = "trace_id_propagated_total";
+#[cfg(feature = "metrics")]
+const GENERATED_TOTAL: &str = "trace_id_generated_total";
+#[cfg(feature = "metrics")]
+const INVALID_TOTAL: &str = "trace_id_invalid_total";
+#[cfg(feature = "metrics")]
+const GENERATOR_FALLBACK_TOTAL: &str = "trace_id_generator_fallback_total";
+
+#[cfg(feature = "metrics")]
+impl MetricsHook for MetricsExporter {
+    fn on_propagated(&self) {
+        ::metrics::counter!(PROPAGATED_TOTAL).increment(1);
+    }
+
+    fn on_generated(&self) {
+        ::metrics::counter!(GENERATED_TOTAL).increment(1);
+    }
+
+    fn on_invalid_header(&self) {
+        ::metrics::counter!(INVALID_TOTAL).increment(1);
+    }
+
+    fn on_generator_fallback(&self) {
+        ::metrics::counter!(GENERATOR_FALLBACK_TOTAL).increment(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counters() {
+        let metrics = Arc::new(TraceIdMetrics::new());
+        let hook: Arc<dyn MetricsHook + Send + Sync> = metrics.clone();
+
+        hook.on_propagated();
+        hook.on_propagated();
+        hook.on_generated();
+        hook.on_invalid_header();
+        hook.on_generator_fallback();
+
+        assert_eq!(
+            metrics.snapshot(),
+            MetricsSnapshot {
+                propagated: 2,
+                generated: 1,
+                invalid_header: 1,
+                generator_fallback: 1,
+            }
+        );
+    }
+}
@@ -0,0 +1,65 @@
+//! 在 panic 信息中附加追踪ID
+//!
+//! 默认的 panic 信息只包含线程名、位置与消息，无法与出错的请求对应。
+//! `install_panic_hook()` 在原有 panic hook 之前向标准错误输出当前追踪ID，
+//! 随后调用原有 hook，默认 hook 输出的 `thread '...' panicked at ...` 紧随其后。
+
+use crate::context::get_trace_id_opt;
+use crate::trace_id::TraceId;
+use std::io::Write;
+use std::sync::Once;
+
+/// 保证 hook 只安装一次，重复调用不会多次串联
+static INSTALL: Once = Once::new();
+
+/// 安装附加追踪ID的 panic hook
+///
+/// 新的 hook 串联在调用时已设置的 hook（默认为标准库的 hook）之前：
+/// 处于追踪上下文中时先向标准错误输出 `trace_id=<ID> `，再调用原有 hook；
+/// 不在追踪上下文中时直接调用原有 hook，输出与未安装时相同。原有 hook 总会被调用，
+/// 因此自定义的 panic 处理（如上报到错误收集服务）不受影响。
+/// 应在启动阶段、设置其他 panic hook 之后调用，多次调用只有第一次生效。
+///
+/// # 线程与异步任务
+/// panic hook 在发生 panic 的线程上、栈展开之前执行，读取的是 `get_trace_id_opt()`：
+/// - 请求处理器（中间件设置的上下文）、`spawn_traced` 创建的任务与
+///   `with_trace_id_sync` 闭包中的 panic 均能取得追踪ID
+/// - 直接通过 `tokio::spawn`、`std::thread::spawn` 创建的任务或线程没有继承上下文，
+///   其中的 panic 不会附加追踪ID
+/// - 通过 `JoinHandle` 等在其他任务中观察到的 panic 不会再次触发 hook，
+///   追踪ID只出现在最初发生 panic 时的输出中
+///
+/// # 示例
+/// ```
+/// trace_id::install_panic_hook();
+/// ```
+pub fn install_panic_hook() {
+    INSTALL.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            if let Some(trace_id) = get_trace_id_opt() {
+                // 写入失败（如标准错误已关闭）时仍需调用原有 hook
+                let _ = write_prefix(&mut std::io::stderr().lock(), &trace_id);
+            }
+            previous(info);
+        }));
+    });
+}
+
+/// 写入 panic 信息前的追踪ID前缀
+fn write_prefix(out: &mut impl Write, trace_id: &TraceId) -> std::io::Result<()> {
+    write!(out, "trace_id={trace_id} ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_prefix() {
+        let trace_id = TraceId::from_string_validated("0af7651916cd43dd8448eb211c80319c").unwrap();
+        let mut out = Vec::new();
+        write_prefix(&mut out, &trace_id).unwrap();
+        assert_eq!(out, b"trace_id=0af7651916cd43dd8448eb211c80319c ");
+    }
+}
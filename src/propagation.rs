@@ -0,0 +1,242 @@
+//! 出站请求的追踪ID注入
+//!
+//! 与入站方向的 `extract_or_generate_trace_id` 相对应：将追踪ID写入任意 HTTP 客户端的请求头。
+//! reqwest 可直接使用 `TraceIdMiddleware`；hyper 等基于 `http` 的客户端可对 `HeaderMap`
+//! 调用 `inject_trace_id`，ureq 等其他客户端实现 `HeaderInjector` 后使用 `inject_into`。
+
+use crate::trace_id::TraceId;
+
+/// 可写入请求头的出站请求载体
+///
+/// 为不基于 `http::HeaderMap` 的客户端（ureq、自定义传输等）实现该 trait 即可使用 `inject_into`。
+///
+/// # 示例
+/// ```
+/// use trace_id::{inject_into, HeaderInjector, TraceId};
+///
+/// /// 以键值对列表保存请求头的客户端
+/// struct Headers(Vec<(String, String)>);
+///
+/// impl HeaderInjector for Headers {
+///     fn set_header(&mut self, name: &str, value: &str) {
+///         self.0.retain(|(key, _)| !key.eq_ignore_ascii_case(name));
+///         self.0.push((name.to_string(), value.to_string()));
+///     }
+/// }
+///
+/// let trace_id = TraceId::new();
+/// let mut headers = Headers(Vec::new());
+/// inject_into(&mut headers, trace_id::TRACE_ID_HEADER, &trace_id);
+/// assert_eq!(headers.0[0].1, trace_id.as_str());
+/// ```
+pub trait HeaderInjector {
+    /// 设置请求头，已存在同名头部时替换其值
+    ///
+    /// # 参数
+    /// * `name` - 头部名称
+    /// * `value` - 头部值（追踪ID的十六进制表示，始终为合法的头部值）
+    fn set_header(&mut self, name: &str, value: &str);
+}
+
+/// 将指定的追踪ID写入请求载体
+///
+/// 已存在同名头部时替换。不在追踪上下文中、但需要关联出站请求时（如后台任务），
+/// 可以传入新生成的ID
+///
+/// # 参数
+/// * `carrier` - 出站请求的请求头载体
+/// * `header_name` - 头部名称（通常为 `TRACE_ID_HEADER`）
+/// * `trace_id` - 要写入的追踪ID
+pub fn inject_into<T>(carrier: &mut T, header_name: &str, trace_id: &TraceId)
+where
+    T: HeaderInjector + ?Sized,
+{
+    carrier.set_header(header_name, trace_id.as_str());
+}
+
+/// `HeaderMap` 的实现，名称不是合法头部名称时忽略
+#[cfg(feature = "http")]
+impl HeaderInjector for http::HeaderMap {
+    fn set_header(&mut self, name: &str, value: &str) {
+        if let (Ok(name), Ok(value)) = (
+            http::HeaderName::from_bytes(name.as_bytes()),
+            http::HeaderValue::from_str(value),
+        ) {
+            self.insert(name, value);
+        }
+    }
+}
+
+/// 将当前追踪ID写入出站请求的 `x-trace-id` 头部
+///
+/// 与 `TraceIdMiddleware`（reqwest）相同，不在追踪上下文中时不做任何处理，也不会生成新的追踪ID；
+/// 需要其他头部名称或指定ID时使用 `inject_into`
+///
+/// # 参数
+/// * `headers` - 出站请求的请求头
+///
+/// # 返回
+/// 写入的追踪ID，不在追踪上下文中时返回None
+///
+/// # 示例
+/// ```
+/// use http::HeaderMap;
+/// use trace_id::{inject_trace_id, with_trace_id, TraceId, TRACE_ID_HEADER};
+///
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// let trace_id = TraceId::new();
+/// let headers = with_trace_id(trace_id, async {
+///     let mut headers = HeaderMap::new();
+///     inject_trace_id(&mut headers);
+///     headers
+/// })
+/// .await;
+/// assert_eq!(headers[TRACE_ID_HEADER], trace_id.as_str());
+/// # });
+/// ```
+#[cfg(feature = "http")]
+pub fn inject_trace_id(headers: &mut http::HeaderMap) -> Option<TraceId> {
+    let trace_id = crate::context::get_trace_id_opt()?;
+    headers.insert(crate::TRACE_ID_HEADER, trace_id.to_header_value());
+    Some(trace_id)
+}
+
+/// 将当前追踪上下文以 W3C `traceparent` 写入出站请求
+///
+/// trace-id 为当前追踪ID，parent-id 为本服务为当前请求生成的跨度ID，
+/// trace-flags 沿用采样决策或上游 `traceparent`，均不存在时视为已采样，
+/// 下游据此决定是否记录。与 `inject_trace_id` 相同，不在追踪上下文中时不做任何处理
+///
+/// # 参数
+/// * `headers` - 出站请求的请求头
+///
+/// # 返回
+/// 写入的追踪上下文，不在追踪上下文中时返回None
+///
+/// # 示例
+/// ```
+/// use http::HeaderMap;
+/// use trace_id::{inject_traceparent, with_trace_context, TraceContext, TRACEPARENT_HEADER};
+///
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// let upstream =
+///     TraceContext::parse("00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-00").unwrap();
+/// let headers = with_trace_context(upstream, async {
+///     let mut headers = HeaderMap::new();
+///     inject_traceparent(&mut headers);
+///     headers
+/// })
+/// .await;
+/// // 沿用上游的 trace-id 与未采样标志
+/// let outbound = TraceContext::parse(headers[TRACEPARENT_HEADER].to_str().unwrap()).unwrap();
+/// assert!(!outbound.is_sampled());
+/// # });
+/// ```
+#[cfg(feature = "http")]
+pub fn inject_traceparent(headers: &mut http::HeaderMap) -> Option<crate::TraceContext> {
+    let trace_id = crate::context::get_trace_id_opt()?;
+    let ctx = crate::context::outbound_trace_context(trace_id);
+    if let Ok(value) = http::HeaderValue::from_str(&ctx.to_header()) {
+        headers.insert(crate::TRACEPARENT_HEADER, value);
+    }
+    Some(ctx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 记录写入顺序的测试载体
+    #[derive(Default)]
+    struct Recorded(Vec<(String, String)>);
+
+    impl HeaderInjector for Recorded {
+        fn set_header(&mut self, name: &str, value: &str) {
+            self.0.push((name.to_string(), value.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_inject_into_custom_carrier() {
+        let trace_id = TraceId::new();
+        let mut carrier = Recorded::default();
+        inject_into(&mut carrier, "x-request-id", &trace_id);
+        assert_eq!(
+            carrier.0,
+            [("x-request-id".to_string(), trace_id.to_string())]
+        );
+    }
+
+    #[cfg(feature = "http")]
+    #[tokio::test]
+    async fn test_inject_trace_id() {
+        use crate::TRACE_ID_HEADER;
+        use http::HeaderMap;
+
+        // 不在追踪上下文中时不写入
+        let mut headers = HeaderMap::new();
+        assert_eq!(inject_trace_id(&mut headers), None);
+        assert!(headers.is_empty());
+
+        let trace_id = TraceId::new();
+        let injected =
+            crate::context::with_trace_id(trace_id, async { inject_trace_id(&mut headers) }).await;
+        assert_eq!(injected, Some(trace_id));
+        assert_eq!(headers[TRACE_ID_HEADER], trace_id.as_str());
+
+        // 指定ID与头部名称，已有的值被替换
+        let other = TraceId::new();
+        inject_into(&mut headers, TRACE_ID_HEADER, &other);
+        inject_into(&mut headers, "x-request-id", &other);
+        assert_eq!(headers.get_all(TRACE_ID_HEADER).iter().count(), 1);
+        assert_eq!(headers[TRACE_ID_HEADER], other.as_str());
+        assert_eq!(headers["x-request-id"], other.as_str());
+
+        // 非法的头部名称被忽略
+        inject_into(&mut headers, "invalid header", &other);
+        assert_eq!(headers.len(), 2);
+    }
+
+    #[cfg(feature = "http")]
+    #[tokio::test]
+    async fn test_inject_traceparent() {
+        use crate::{context, TraceContext, TRACEPARENT_HEADER, TRACE_FLAG_SAMPLED};
+        use http::HeaderMap;
+
+        let mut headers = HeaderMap::new();
+        assert_eq!(inject_traceparent(&mut headers), None);
+        assert!(headers.is_empty());
+
+        let injected = |headers: &HeaderMap| {
+            TraceContext::parse(headers[TRACEPARENT_HEADER].to_str().unwrap()).unwrap()
+        };
+
+        // 上游 flags 原样传递
+        for flags in ["00", "01"] {
+            let upstream = TraceContext::parse(&format!(
+                "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-{flags}"
+            ))
+            .unwrap();
+            let ctx = context::with_trace_context(upstream.clone(), async {
+                inject_traceparent(&mut headers)
+            })
+            .await
+            .unwrap();
+            assert_eq!(injected(&headers), ctx);
+            assert_eq!(ctx.trace_id, upstream.trace_id);
+            assert_eq!(ctx.flags, upstream.flags);
+        }
+
+        // 只有追踪ID时视为已采样；采样决策覆盖默认值
+        let trace_id = TraceId::new();
+        context::with_trace_id(trace_id, async {
+            inject_traceparent(&mut headers);
+            assert_eq!(injected(&headers).trace_id, trace_id);
+            assert_eq!(injected(&headers).flags, TRACE_FLAG_SAMPLED);
+
+            context::with_trace_flags(0, async { inject_traceparent(&mut headers) }).await;
+            assert!(!injected(&headers).is_sampled());
+        })
+        .await;
+    }
+}
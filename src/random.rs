@@ -0,0 +1,118 @@
+//! 随机数源抽象
+//!
+//! `TraceId::new()` 的低44位来自默认随机数源（其上4位为生成器分片编号），由编译特性选择：
+//! `rng-fastrand`（默认）使用 `fastrand`，`rng-rand` 使用 `rand` 的线程局部生成器，
+//! 两者同时启用时使用 `rand`。crate 内所有随机数都经由本模块的内部函数获取。
+//! 通过 `RandomSource` trait 可以替换为确定性的随机数源（用于可复现的模糊测试），
+//! 或密码学安全的随机数生成器。
+
+/// 64位随机数源
+pub trait RandomSource {
+    /// 返回下一个64位随机数
+    ///
+    /// 仅低44位会写入追踪ID，其上4位为生成器分片编号
+    fn next_u64(&self) -> u64;
+}
+
+/// 默认随机数源
+///
+/// `rng-fastrand`（默认）下使用 `fastrand` 线程局部的 wyrand 生成器，速度快但不具备密码学安全性；
+/// 启用 `rng-rand` 时改用 `rand::rng()`。名称沿用最初的 `fastrand` 实现
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FastRand;
+
+impl RandomSource for FastRand {
+    #[inline]
+    fn next_u64(&self) -> u64 {
+        next_u64()
+    }
+}
+
+/// 返回下一个64位随机数
+#[cfg(feature = "rng-rand")]
+#[inline]
+pub(crate) fn next_u64() -> u64 {
+    rand::random()
+}
+
+/// 返回下一个128位随机数
+#[cfg(feature = "rng-rand")]
+#[inline]
+fn next_u128() -> u128 {
+    rand::random()
+}
+
+/// 返回下一个64位随机数
+#[cfg(all(feature = "rng-fastrand", not(feature = "rng-rand")))]
+#[inline]
+pub(crate) fn next_u64() -> u64 {
+    fastrand::u64(..)
+}
+
+/// 返回下一个128位随机数
+#[cfg(all(feature = "rng-fastrand", not(feature = "rng-rand")))]
+#[inline]
+fn next_u128() -> u128 {
+    fastrand::u128(..)
+}
+
+/// 未启用任何随机数源时的占位实现
+///
+/// 只为让 `lib.rs` 中的 `compile_error!` 成为唯一的编译错误，不会进入最终产物
+#[cfg(not(any(feature = "rng-fastrand", feature = "rng-rand")))]
+pub(crate) fn next_u64() -> u64 {
+    unreachable!("no random source feature is enabled")
+}
+
+/// 未启用任何随机数源时的占位实现，见 `next_u64`
+#[cfg(not(any(feature = "rng-fastrand", feature = "rng-rand")))]
+fn next_u128() -> u128 {
+    unreachable!("no random source feature is enabled")
+}
+
+/// 返回非零的64位随机数，用于跨度ID等不允许全零的标识
+#[inline]
+pub(crate) fn nonzero_u64() -> u64 {
+    loop {
+        // 全零的概率为 2^-64，重试几乎不会发生
+        let value = next_u64();
+        if value != 0 {
+            return value;
+        }
+    }
+}
+
+/// 返回非零的128位随机数，用于完全随机的追踪ID
+#[inline]
+pub(crate) fn nonzero_u128() -> u128 {
+    loop {
+        let value = next_u128();
+        if value != 0 {
+            return value;
+        }
+    }
+}
+
+impl<F> RandomSource for F
+where
+    F: Fn() -> u64,
+{
+    #[inline]
+    fn next_u64(&self) -> u64 {
+        self()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_source() {
+        // 两次64位随机数相同的概率可以忽略
+        assert_ne!(FastRand.next_u64(), FastRand.next_u64());
+        assert_ne!(nonzero_u64(), 0);
+        assert_ne!(nonzero_u128(), 0);
+        assert_ne!(nonzero_u128(), nonzero_u128());
+    }
+}
@@ -0,0 +1,134 @@
+//! 活动追踪ID登记表
+//!
+//! 启用 `registry` 特性后，`with_trace_id` 与 `with_trace_id_sync` 在进入作用域时将追踪ID
+//! 与开始时间登记到全局表中，作用域结束时移除，用于排查卡住的请求。
+//! 移除由守卫对象的 `Drop` 完成，future 被提前丢弃或发生 panic 时同样会移除。
+//! 每次进出作用域都需要获取一次全局锁，因此默认不启用。
+
+use crate::trace_id::TraceId;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock, PoisonError};
+use std::time::{Duration, Instant};
+
+/// 全局登记表：登记序号 -> (追踪ID, 开始时间)
+static ACTIVE: OnceLock<Mutex<HashMap<u64, (TraceId, Instant)>>> = OnceLock::new();
+
+/// 登记序号，同一追踪ID可能同时存在多个作用域
+static NEXT_TOKEN: AtomicU64 = AtomicU64::new(0);
+
+fn active() -> &'static Mutex<HashMap<u64, (TraceId, Instant)>> {
+    ACTIVE.get_or_init(Mutex::default)
+}
+
+/// 登记守卫，丢弃时移除对应的登记
+pub(crate) struct Registration(u64);
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        active()
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.0);
+    }
+}
+
+/// 登记一个进入作用域的追踪ID
+pub(crate) fn register(trace_id: TraceId) -> Registration {
+    let token = NEXT_TOKEN.fetch_add(1, Ordering::Relaxed);
+    active()
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(token, (trace_id, Instant::now()));
+    Registration(token)
+}
+
+/// 获取当前所有活动的追踪ID及其已运行时间
+///
+/// 同一追踪ID存在嵌套或并发的多个作用域时只返回一项，运行时间取最早进入的作用域。
+/// 结果按运行时间从长到短排序
+///
+/// # 返回
+/// (追踪ID, 已运行时间) 列表
+///
+/// # 示例
+/// ```
+/// for (trace_id, elapsed) in trace_id::active_traces() {
+///     if elapsed.as_secs() > 30 {
+///         eprintln!("slow request {trace_id}: {elapsed:?}");
+///     }
+/// }
+/// ```
+pub fn active_traces() -> Vec<(TraceId, Duration)> {
+    let now = Instant::now();
+    let mut earliest: HashMap<TraceId, Instant> = HashMap::new();
+    for &(trace_id, started) in active()
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .values()
+    {
+        earliest
+            .entry(trace_id)
+            .and_modify(|current| *current = (*current).min(started))
+            .or_insert(started);
+    }
+
+    let mut traces: Vec<_> = earliest
+        .into_iter()
+        .map(|(trace_id, started)| (trace_id, now.saturating_duration_since(started)))
+        .collect();
+    traces.sort_by_key(|&(_, elapsed)| std::cmp::Reverse(elapsed));
+    traces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::{with_trace_id, with_trace_id_sync};
+
+    fn is_active(trace_id: TraceId) -> bool {
+        active_traces().iter().any(|(id, _)| *id == trace_id)
+    }
+
+    #[tokio::test]
+    async fn test_registration_lifecycle() {
+        let trace_id = TraceId::new();
+
+        let count = with_trace_id(trace_id, async {
+            // 嵌套作用域只登记为一项
+            with_trace_id(trace_id, async {
+                active_traces()
+                    .iter()
+                    .filter(|(id, _)| *id == trace_id)
+                    .count()
+            })
+            .await
+        })
+        .await;
+        assert_eq!(count, 1);
+        assert!(!is_active(trace_id));
+
+        with_trace_id_sync(trace_id, || assert!(is_active(trace_id)));
+        assert!(!is_active(trace_id));
+    }
+
+    #[tokio::test]
+    async fn test_removed_when_dropped_or_panicked() {
+        // future 未完成即被丢弃
+        let trace_id = TraceId::new();
+        let task = tokio::spawn(with_trace_id(trace_id, std::future::pending::<()>()));
+        while !is_active(trace_id) {
+            tokio::task::yield_now().await;
+        }
+        task.abort();
+        let _ = task.await;
+        assert!(!is_active(trace_id));
+
+        // 作用域内发生 panic
+        let trace_id = TraceId::new();
+        let result =
+            tokio::spawn(with_trace_id(trace_id, async { panic!("handler failed") })).await;
+        assert!(result.is_err());
+        assert!(!is_active(trace_id));
+    }
+}
@@ -0,0 +1,136 @@
+//! 采样决策
+//!
+//! 通过 `Sampler` trait 决定某个追踪ID是否被采样。采样结果决定中间件是否创建
+//! tracing span，并以 trace-flags 的 sampled 位（`TRACE_FLAG_SAMPLED`）向下游传播。
+
+use crate::trace_id::TraceId;
+
+/// trace-flags 中的 sampled 位
+pub const TRACE_FLAG_SAMPLED: u8 = 0x01;
+
+/// 采样器
+pub trait Sampler {
+    /// 判断指定追踪ID是否应被采样
+    ///
+    /// 对于同一追踪ID应返回相同的结果，使不同服务的决策保持一致
+    fn should_sample(&self, trace_id: &TraceId) -> bool;
+}
+
+/// 全部采样
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AlwaysOn;
+
+impl Sampler for AlwaysOn {
+    #[inline]
+    fn should_sample(&self, _trace_id: &TraceId) -> bool {
+        true
+    }
+}
+
+/// 全部不采样
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AlwaysOff;
+
+impl Sampler for AlwaysOff {
+    #[inline]
+    fn should_sample(&self, _trace_id: &TraceId) -> bool {
+        false
+    }
+}
+
+/// 按比例采样
+///
+/// 对追踪ID做确定性哈希后与阈值比较：同一追踪ID在任何服务、任何时刻的决策都相同，
+/// 且不依赖ID内部各部分（时间戳、序列号）的分布
+#[derive(Clone, Copy, Debug)]
+pub struct RatioSampler {
+    /// 采样阈值，哈希值小于该值时采样
+    threshold: u64,
+    /// 是否全部采样（比例为1时阈值无法用 `u64` 表示）
+    always: bool,
+}
+
+impl RatioSampler {
+    /// 创建按比例采样的采样器
+    ///
+    /// # 参数
+    /// * `ratio` - 采样比例，超出 `[0, 1]` 的值会被截断，NaN 视为0
+    pub fn new(ratio: f64) -> Self {
+        let ratio = if ratio.is_nan() {
+            0.0
+        } else {
+            ratio.clamp(0.0, 1.0)
+        };
+        Self {
+            threshold: (ratio * u64::MAX as f64) as u64,
+            always: ratio >= 1.0,
+        }
+    }
+}
+
+impl Sampler for RatioSampler {
+    #[inline]
+    fn should_sample(&self, trace_id: &TraceId) -> bool {
+        self.always || hash_trace_id(trace_id) < self.threshold
+    }
+}
+
+impl<F> Sampler for F
+where
+    F: Fn(&TraceId) -> bool,
+{
+    #[inline]
+    fn should_sample(&self, trace_id: &TraceId) -> bool {
+        self(trace_id)
+    }
+}
+
+/// 追踪ID的确定性64位哈希（splitmix64 混合高低64位）
+fn hash_trace_id(trace_id: &TraceId) -> u64 {
+    let value = trace_id.to_u128();
+    let mut z = ((value >> 64) as u64) ^ (value as u64).rotate_left(32);
+    z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_always_samplers() {
+        let trace_id = TraceId::new();
+        assert!(AlwaysOn.should_sample(&trace_id));
+        assert!(!AlwaysOff.should_sample(&trace_id));
+    }
+
+    #[test]
+    fn test_ratio_sampler() {
+        let ids = TraceId::new_batch(10_000);
+
+        let count =
+            |sampler: &RatioSampler| ids.iter().filter(|id| sampler.should_sample(id)).count();
+        assert_eq!(count(&RatioSampler::new(0.0)), 0);
+        assert_eq!(count(&RatioSampler::new(1.0)), ids.len());
+        assert_eq!(count(&RatioSampler::new(f64::NAN)), 0);
+        assert_eq!(count(&RatioSampler::new(2.0)), ids.len());
+
+        // 连续生成的ID同样按比例分布
+        let sampled = count(&RatioSampler::new(0.25));
+        assert!(
+            (2_000..3_000).contains(&sampled),
+            "采样数量偏离预期: {sampled}"
+        );
+
+        // 同一追踪ID的决策保持一致
+        let sampler = RatioSampler::new(0.5);
+        for id in &ids[..100] {
+            assert_eq!(
+                sampler.should_sample(id),
+                RatioSampler::new(0.5).should_sample(id)
+            );
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//! 为迭代器与异步流中的元素附加追踪ID
+//!
+//! 在 `with_trace_id` 作用域内处理通道消息等批量数据时，下游日志需要每个元素所属的追踪ID。
+//! `TraceIdIterExt::with_current_trace_id` 与 `TraceIdStreamExt::with_current_trace_id`
+//! 在创建适配器时读取一次当前追踪ID，之后产生的每个元素都与该ID组成 `(TraceId, T)`。
+//!
+//! 追踪ID只在创建时捕获，与作用域的语义一致：适配器在作用域外被消费、
+//! 或被转移到其他任务中消费时，元素仍附加创建时的追踪ID。
+
+use crate::context::get_trace_id;
+use crate::trace_id::TraceId;
+
+/// 为迭代器元素附加当前追踪ID的扩展 trait，对所有迭代器自动实现
+///
+/// # 示例
+/// ```
+/// use trace_id::{with_trace_id_sync, TraceId, TraceIdIterExt};
+///
+/// let trace_id = TraceId::new();
+/// let tagged: Vec<_> = with_trace_id_sync(trace_id, || {
+///     vec!["a", "b"].into_iter().with_current_trace_id().collect()
+/// });
+/// assert_eq!(tagged, [(trace_id, "a"), (trace_id, "b")]);
+/// ```
+pub trait TraceIdIterExt: Iterator + Sized {
+    /// 将每个元素与当前追踪ID组成 `(TraceId, Item)`
+    ///
+    /// 追踪ID通过 `get_trace_id()` 在调用时读取一次；不在追踪上下文中时
+    /// 与 `get_trace_id()` 相同，生成一个新ID（并记录缺失上下文的日志），所有元素共享该ID
+    fn with_current_trace_id(self) -> TaggedIter<Self> {
+        TaggedIter {
+            inner: self,
+            trace_id: get_trace_id(),
+        }
+    }
+}
+
+impl<I: Iterator> TraceIdIterExt for I {}
+
+/// 为每个元素附加追踪ID的迭代器，由 `TraceIdIterExt::with_current_trace_id` 创建
+#[derive(Clone, Debug)]
+pub struct TaggedIter<I> {
+    inner: I,
+    trace_id: TraceId,
+}
+
+impl<I> TaggedIter<I> {
+    /// 获取附加到元素上的追踪ID
+    pub fn trace_id(&self) -> TraceId {
+        self.trace_id
+    }
+
+    /// 获取内部的迭代器
+    pub fn into_inner(self) -> I {
+        self.inner
+    }
+}
+
+impl<I: Iterator> Iterator for TaggedIter<I> {
+    type Item = (TraceId, I::Item);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|item| (self.trace_id, item))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<I: DoubleEndedIterator> DoubleEndedIterator for TaggedIter<I> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|item| (self.trace_id, item))
+    }
+}
+
+impl<I: ExactSizeIterator> ExactSizeIterator for TaggedIter<I> {}
+
+impl<I: std::iter::FusedIterator> std::iter::FusedIterator for TaggedIter<I> {}
+
+#[cfg(feature = "stream")]
+pub use self::stream::{TaggedStream, TraceIdStreamExt};
+
+/// `futures_core::Stream` 适配器（需要 `stream` 特性）
+#[cfg(feature = "stream")]
+mod stream {
+    use super::*;
+    use futures_core::Stream;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    /// 为异步流元素附加当前追踪ID的扩展 trait，对所有 `Stream` 自动实现
+    ///
+    /// # 示例
+    /// ```
+    /// use futures_core::Stream;
+    /// use trace_id::{TraceId, TraceIdStreamExt};
+    ///
+    /// /// 在请求处理器（追踪上下文）中调用，之后产生的消息都附加该请求的追踪ID
+    /// fn tag_messages(messages: impl Stream<Item = String>) -> impl Stream<Item = (TraceId, String)> {
+    ///     messages.with_current_trace_id()
+    /// }
+    /// ```
+    pub trait TraceIdStreamExt: Stream + Sized {
+        /// 将每个元素与当前追踪ID组成 `(TraceId, Item)`
+        ///
+        /// 追踪ID在调用时读取一次，而不是在每次轮询时读取：适配器被转移到
+        /// 没有上下文的任务中轮询时，元素仍附加创建时的追踪ID。
+        /// 不在追踪上下文中时与 `get_trace_id()` 相同，生成一个所有元素共享的新ID
+        fn with_current_trace_id(self) -> TaggedStream<Self> {
+            TaggedStream {
+                inner: self,
+                trace_id: get_trace_id(),
+            }
+        }
+    }
+
+    impl<S: Stream> TraceIdStreamExt for S {}
+
+    pin_project_lite::pin_project! {
+        /// 为每个元素附加追踪ID的异步流，由 `TraceIdStreamExt::with_current_trace_id` 创建
+        #[derive(Clone, Debug)]
+        pub struct TaggedStream<S> {
+            #[pin]
+            inner: S,
+            trace_id: TraceId,
+        }
+    }
+
+    impl<S> TaggedStream<S> {
+        /// 获取附加到元素上的追踪ID
+        pub fn trace_id(&self) -> TraceId {
+            self.trace_id
+        }
+
+        /// 获取内部的异步流
+        pub fn into_inner(self) -> S {
+            self.inner
+        }
+    }
+
+    impl<S: Stream> Stream for TaggedStream<S> {
+        type Item = (TraceId, S::Item);
+
+        fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            let this = self.project();
+            let trace_id = *this.trace_id;
+            this.inner
+                .poll_next(cx)
+                .map(|item| item.map(|item| (trace_id, item)))
+        }
+
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            self.inner.size_hint()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::{with_trace_id, with_trace_id_sync};
+
+    #[test]
+    fn test_iter_captures_trace_id_once() {
+        let trace_id = TraceId::new();
+        let mut tagged = with_trace_id_sync(trace_id, || {
+            vec![1, 2, 3].into_iter().with_current_trace_id()
+        });
+        assert_eq!(tagged.trace_id(), trace_id);
+        assert_eq!(tagged.len(), 3);
+
+        // 在作用域外、其他上下文中消费时仍附加创建时的ID
+        assert_eq!(tagged.next(), Some((trace_id, 1)));
+        let other = TraceId::new();
+        let rest: Vec<_> = with_trace_id_sync(other, || tagged.rev().collect());
+        assert_eq!(rest, [(trace_id, 3), (trace_id, 2)]);
+    }
+
+    #[tokio::test]
+    async fn test_iter_in_async_scope() {
+        let trace_id = TraceId::new();
+        let tagged: Vec<_> = with_trace_id(trace_id, async {
+            ["a", "b"].into_iter().with_current_trace_id().collect()
+        })
+        .await;
+        assert_eq!(tagged, [(trace_id, "a"), (trace_id, "b")]);
+    }
+
+    #[cfg(feature = "stream")]
+    #[tokio::test]
+    async fn test_stream_captures_trace_id_once() {
+        use futures_core::Stream;
+        use std::pin::Pin;
+
+        /// 从通道接收消息的异步流
+        struct Receiver(tokio::sync::mpsc::UnboundedReceiver<u32>);
+
+        impl Stream for Receiver {
+            type Item = u32;
+
+            fn poll_next(
+                mut self: Pin<&mut Self>,
+                cx: &mut std::task::Context<'_>,
+            ) -> std::task::Poll<Option<u32>> {
+                self.0.poll_recv(cx)
+            }
+        }
+
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        let trace_id = TraceId::new();
+        let mut tagged = Box::pin(
+            with_trace_id(trace_id, async {
+                Receiver(receiver).with_current_trace_id()
+            })
+            .await,
+        );
+
+        // 在另一个任务中发送，在作用域外轮询
+        tokio::spawn(async move {
+            for value in 0..3 {
+                sender.send(value).unwrap();
+            }
+        });
+        let mut items = Vec::new();
+        while let Some(item) = std::future::poll_fn(|cx| tagged.as_mut().poll_next(cx)).await {
+            items.push(item);
+        }
+        assert_eq!(items, [(trace_id, 0), (trace_id, 1), (trace_id, 2)]);
+    }
+}
@@ -0,0 +1,527 @@
+//! W3C TraceContext 相关类型定义
+//!
+//! 提供 `SpanId` 以及完整的 `traceparent`、`tracestate` 头部解析与序列化支持。
+
+use crate::sampler::TRACE_FLAG_SAMPLED;
+use crate::trace_id::TraceId;
+use std::fmt;
+
+/// 当前支持的 `traceparent` 版本号
+const TRACEPARENT_VERSION: &str = "00";
+
+/// 跨度ID结构体
+///
+/// 对应 W3C TraceContext 中的 parent-id 字段：16个小写十六进制字符（64位），且不能全为零
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpanId(String);
+
+impl SpanId {
+    /// 生成新的随机跨度ID
+    ///
+    /// 使用64位非零随机数，生成16字符的小写十六进制ID
+    ///
+    /// # 返回
+    /// 新生成的跨度ID
+    #[inline]
+    pub fn new() -> Self {
+        // 非零随机数保证不会生成全零ID
+        let value = crate::random::nonzero_u64();
+        Self(format!("{value:016x}"))
+    }
+
+    /// 从字符串创建跨度ID，并进行 W3C TraceContext 规范校验
+    ///
+    /// # 参数
+    /// * `id` - 跨度ID字符串
+    ///
+    /// # 返回
+    /// 如果格式有效则返回Some(SpanId)，否则返回None
+    ///
+    /// # 校验规则（符合 W3C TraceContext 规范）
+    /// - 长度必须是 16 个字符
+    /// - 只能包含小写十六进制字符（0-9, a-f）
+    /// - 不能全为零（0000000000000000）
+    #[inline]
+    pub fn from_string_validated(id: &str) -> Option<Self> {
+        if id.len() != 16 {
+            return None;
+        }
+
+        if !is_lower_hex(id.as_bytes()) {
+            return None;
+        }
+
+        if id == "0000000000000000" {
+            return None;
+        }
+
+        Some(Self(id.to_string()))
+    }
+
+    /// 获取跨度ID字符串
+    ///
+    /// # 返回
+    /// 跨度ID的字符串表示
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for SpanId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Default for SpanId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// W3C TraceContext 上下文
+///
+/// 对应完整的 `traceparent` 头部，例如：
+/// `00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceContext {
+    /// 追踪ID（trace-id）
+    pub trace_id: TraceId,
+    /// 上游调用方的跨度ID（parent-id）
+    pub parent_id: SpanId,
+    /// 追踪标志位（trace-flags）
+    pub flags: u8,
+}
+
+impl TraceContext {
+    /// 上游是否已采样（trace-flags 的 sampled 位）
+    pub fn is_sampled(&self) -> bool {
+        self.flags & TRACE_FLAG_SAMPLED != 0
+    }
+
+    /// 解析 `traceparent` 头部
+    ///
+    /// # 参数
+    /// * `header` - `traceparent` 头部值
+    ///
+    /// # 返回
+    /// 如果格式有效则返回Some(TraceContext)，否则返回None
+    ///
+    /// # 校验规则
+    /// - 必须恰好由 `-` 分隔为4段
+    /// - 版本号必须是 `00`
+    /// - trace-id 与 parent-id 需满足各自的校验规则
+    /// - trace-flags 必须是2个小写十六进制字符
+    pub fn parse(header: &str) -> Option<Self> {
+        let mut parts = header.split('-');
+        let version = parts.next()?;
+        let trace_id = parts.next()?;
+        let parent_id = parts.next()?;
+        let flags = parts.next()?;
+
+        // 段数必须恰好为4
+        if parts.next().is_some() {
+            return None;
+        }
+
+        if version != TRACEPARENT_VERSION {
+            return None;
+        }
+
+        if flags.len() != 2 || !is_lower_hex(flags.as_bytes()) {
+            return None;
+        }
+
+        Some(Self {
+            trace_id: TraceId::from_string_validated(trace_id)?,
+            parent_id: SpanId::from_string_validated(parent_id)?,
+            flags: u8::from_str_radix(flags, 16).ok()?,
+        })
+    }
+
+    /// 重建 `traceparent` 头部字符串
+    ///
+    /// # 返回
+    /// 形如 `00-<trace-id>-<parent-id>-<flags>` 的字符串
+    pub fn to_header(&self) -> String {
+        format!(
+            "{TRACEPARENT_VERSION}-{}-{}-{:02x}",
+            self.trace_id.padded(),
+            self.parent_id.as_str(),
+            self.flags
+        )
+    }
+
+    /// 从 `traceparent` 字符串解析追踪上下文
+    ///
+    /// 与 `parse` 相同，与 `to_traceparent` 互为逆操作：版本号必须是 `00`，
+    /// trace-id（32位）、parent-id（16位）与 trace-flags（2位）均需为小写十六进制，
+    /// 且 trace-id 与 parent-id 不能全为零
+    ///
+    /// # 参数
+    /// * `traceparent` - 形如 `00-<trace-id>-<parent-id>-<flags>` 的字符串
+    ///
+    /// # 返回
+    /// 如果格式有效则返回Some(TraceContext)，否则返回None
+    ///
+    /// # 示例
+    /// ```
+    /// use trace_id::TraceContext;
+    ///
+    /// let header = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+    /// let ctx = TraceContext::from_traceparent(header).unwrap();
+    /// assert!(ctx.is_sampled());
+    /// assert_eq!(ctx.to_traceparent(), header);
+    /// ```
+    #[inline]
+    pub fn from_traceparent(traceparent: &str) -> Option<Self> {
+        Self::parse(traceparent)
+    }
+
+    /// 序列化为 `traceparent` 字符串
+    ///
+    /// 与 `to_header` 相同，生成 `00-<trace-id>-<parent-id>-<flags>`，
+    /// 64位短追踪ID会左侧补零为32位
+    #[inline]
+    pub fn to_traceparent(&self) -> String {
+        self.to_header()
+    }
+}
+
+/// `tracestate` 最多允许的成员数量（W3C 规范）
+const TRACESTATE_MAX_MEMBERS: usize = 32;
+
+/// W3C `tracestate` 厂商键值对列表
+///
+/// 按顺序保存 `key=value` 成员，序列化时保持原始顺序不变
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TraceState(Vec<(String, String)>);
+
+impl TraceState {
+    /// 解析 `tracestate` 头部
+    ///
+    /// # 参数
+    /// * `header` - `tracestate` 头部值
+    ///
+    /// # 返回
+    /// 如果格式有效则返回Some(TraceState)，否则返回None
+    ///
+    /// # 校验规则（符合 W3C TraceContext 规范）
+    /// - 成员以 `,` 分隔，允许成员周围存在空格/制表符，空成员会被忽略
+    /// - 成员数量不能超过 32 个，且键不能重复
+    /// - 键与值需满足规范定义的字符规则
+    pub fn parse(header: &str) -> Option<Self> {
+        let mut entries: Vec<(String, String)> = Vec::new();
+
+        for member in header.split(',') {
+            let member = member.trim_matches(|c| c == ' ' || c == '\t');
+            if member.is_empty() {
+                continue;
+            }
+
+            let (key, value) = member.split_once('=')?;
+            if !is_valid_key(key) || !is_valid_value(value) {
+                return None;
+            }
+            if entries.iter().any(|(k, _)| k == key) {
+                return None;
+            }
+
+            entries.push((key.to_string(), value.to_string()));
+            if entries.len() > TRACESTATE_MAX_MEMBERS {
+                return None;
+            }
+        }
+
+        Some(Self(entries))
+    }
+
+    /// 获取指定键对应的值
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// 插入或更新一个成员
+    ///
+    /// 按照 W3C 规范，新增或更新的成员会移动到列表最前端；
+    /// 超出 32 个成员时丢弃最末尾的成员。
+    ///
+    /// # 返回
+    /// 如果键或值不符合规范则返回false，列表保持不变
+    pub fn insert(&mut self, key: &str, value: &str) -> bool {
+        if !is_valid_key(key) || !is_valid_value(value) {
+            return false;
+        }
+
+        self.0.retain(|(k, _)| k != key);
+        self.0.insert(0, (key.to_string(), value.to_string()));
+        self.0.truncate(TRACESTATE_MAX_MEMBERS);
+        true
+    }
+
+    /// 移除指定键对应的成员
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        let index = self.0.iter().position(|(k, _)| k == key)?;
+        Some(self.0.remove(index).1)
+    }
+
+    /// 按顺序迭代所有成员
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// 成员数量
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// 是否没有任何成员
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// 重建 `tracestate` 头部字符串
+    ///
+    /// # 返回
+    /// 以 `,` 连接、保持原始顺序的 `key=value` 列表
+    pub fn to_header(&self) -> String {
+        let mut header = String::new();
+        for (index, (key, value)) in self.0.iter().enumerate() {
+            if index > 0 {
+                header.push(',');
+            }
+            header.push_str(key);
+            header.push('=');
+            header.push_str(value);
+        }
+        header
+    }
+}
+
+/// 校验 `tracestate` 成员的键
+///
+/// - 简单键：小写字母开头，最多256个字符，由 `a-z0-9_-*/` 组成
+/// - 多租户键：`<tenant-id>@<system-id>`，tenant-id 最多241个字符且以小写字母或数字开头，
+///   system-id 最多14个字符且以小写字母开头
+fn is_valid_key(key: &str) -> bool {
+    fn is_key_char(b: u8) -> bool {
+        matches!(b, b'a'..=b'z' | b'0'..=b'9' | b'_' | b'-' | b'*' | b'/')
+    }
+
+    match key.split_once('@') {
+        None => {
+            let bytes = key.as_bytes();
+            matches!(bytes.first(), Some(b'a'..=b'z'))
+                && bytes.len() <= 256
+                && bytes.iter().all(|&b| is_key_char(b))
+        }
+        Some((tenant, system)) => {
+            let tenant = tenant.as_bytes();
+            let system = system.as_bytes();
+            matches!(tenant.first(), Some(b'a'..=b'z' | b'0'..=b'9'))
+                && tenant.len() <= 241
+                && tenant.iter().all(|&b| is_key_char(b))
+                && matches!(system.first(), Some(b'a'..=b'z'))
+                && system.len() <= 14
+                && system.iter().all(|&b| is_key_char(b))
+        }
+    }
+}
+
+/// 校验 `tracestate` 成员的值
+///
+/// 1到256个可打印ASCII字符，不能包含 `,` 和 `=`，且不能以空格结尾
+fn is_valid_value(value: &str) -> bool {
+    let bytes = value.as_bytes();
+    !bytes.is_empty()
+        && bytes.len() <= 256
+        && bytes.last() != Some(&b' ')
+        && bytes
+            .iter()
+            .all(|&b| matches!(b, 0x20..=0x2B | 0x2D..=0x3C | 0x3E..=0x7E))
+}
+
+/// 字节级小写十六进制字符验证
+#[inline]
+fn is_lower_hex(bytes: &[u8]) -> bool {
+    bytes
+        .iter()
+        .all(|&b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALID_TRACEPARENT: &str = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+
+    #[test]
+    fn test_span_id_creation() {
+        let span_id = SpanId::new();
+        assert_eq!(span_id.as_str().len(), 16);
+        assert!(SpanId::from_string_validated(span_id.as_str()).is_some());
+    }
+
+    #[test]
+    fn test_span_id_validation() {
+        assert!(SpanId::from_string_validated("b7ad6b7169203331").is_some());
+
+        // 长度不正确
+        assert!(SpanId::from_string_validated("b7ad6b716920333").is_none());
+        // 包含大写字符
+        assert!(SpanId::from_string_validated("B7AD6B7169203331").is_none());
+        // 包含非十六进制字符
+        assert!(SpanId::from_string_validated("b7ad6b716920333g").is_none());
+        // 全为零
+        assert!(SpanId::from_string_validated("0000000000000000").is_none());
+    }
+
+    #[test]
+    fn test_parse_traceparent() {
+        let ctx = TraceContext::parse(VALID_TRACEPARENT).expect("应成功解析");
+        assert_eq!(ctx.trace_id.as_str(), "0af7651916cd43dd8448eb211c80319c");
+        assert_eq!(ctx.parent_id.as_str(), "b7ad6b7169203331");
+        assert_eq!(ctx.flags, 0x01);
+    }
+
+    #[test]
+    fn test_parse_invalid_traceparent() {
+        let invalid = [
+            "",
+            // 版本号不支持
+            "01-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+            // 段数不正确
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331",
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01-extra",
+            // trace-id 全为零
+            "00-00000000000000000000000000000000-b7ad6b7169203331-01",
+            // parent-id 全为零
+            "00-0af7651916cd43dd8448eb211c80319c-0000000000000000-01",
+            // flags 格式错误
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-1",
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-0G",
+            // 大写字符
+            "00-0AF7651916CD43DD8448EB211C80319C-b7ad6b7169203331-01",
+        ];
+
+        for header in invalid {
+            assert!(TraceContext::parse(header).is_none(), "应拒绝: {header}");
+        }
+    }
+
+    #[test]
+    fn test_to_header_roundtrip() {
+        let ctx = TraceContext::parse(VALID_TRACEPARENT).unwrap();
+        assert_eq!(ctx.to_header(), VALID_TRACEPARENT);
+
+        let ctx = TraceContext {
+            trace_id: TraceId::new(),
+            parent_id: SpanId::new(),
+            flags: 0,
+        };
+        assert_eq!(TraceContext::parse(&ctx.to_header()), Some(ctx));
+    }
+
+    #[test]
+    fn test_traceparent_conversions() {
+        let ctx = TraceContext::from_traceparent(VALID_TRACEPARENT).unwrap();
+        assert_eq!(Some(&ctx), TraceContext::parse(VALID_TRACEPARENT).as_ref());
+        assert_eq!(ctx.to_traceparent(), VALID_TRACEPARENT);
+
+        // 各段长度、十六进制与版本号的校验与 `parse` 相同
+        for header in [
+            "01-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+            "00-0af7651916cd43dd8448eb211c80319-b7ad6b7169203331-01",
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b716920333-01",
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-001",
+            "00-0af7651916cd43dd8448eb211c80319x-b7ad6b7169203331-01",
+        ] {
+            assert!(
+                TraceContext::from_traceparent(header).is_none(),
+                "应拒绝: {header}"
+            );
+        }
+
+        // 短追踪ID补零后写出
+        let ctx = TraceContext {
+            trace_id: TraceId::from_u64(0x8448eb211c80319c).unwrap(),
+            parent_id: SpanId::from_string_validated("b7ad6b7169203331").unwrap(),
+            flags: 0,
+        };
+        assert_eq!(
+            ctx.to_traceparent(),
+            "00-00000000000000008448eb211c80319c-b7ad6b7169203331-00"
+        );
+    }
+
+    #[test]
+    fn test_parse_tracestate() {
+        let state = TraceState::parse("congo=t61rcWkgMzE, rojo=00f067aa0ba902b7,,tenant@vendor=x")
+            .expect("应成功解析");
+        assert_eq!(state.len(), 3);
+        assert_eq!(state.get("congo"), Some("t61rcWkgMzE"));
+        assert_eq!(state.get("tenant@vendor"), Some("x"));
+        assert_eq!(
+            state.to_header(),
+            "congo=t61rcWkgMzE,rojo=00f067aa0ba902b7,tenant@vendor=x"
+        );
+
+        // 空头部合法
+        assert!(TraceState::parse("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_invalid_tracestate() {
+        let invalid = [
+            // 缺少 '='
+            "congo",
+            // 键以大写或数字开头
+            "Congo=1",
+            "1congo=1",
+            // 值为空或包含不可打印字符
+            "congo=",
+            "congo=a\u{7f}",
+            // 值包含 '='
+            "congo=a=b",
+            // 重复的键
+            "congo=1,congo=2",
+            // system-id 过长
+            "tenant@abcdefghijklmno=1",
+        ];
+
+        for header in invalid {
+            assert!(TraceState::parse(header).is_none(), "应拒绝: {header}");
+        }
+
+        // 超过 32 个成员
+        let too_many = (0..33)
+            .map(|i| format!("k{i}=v"))
+            .collect::<Vec<_>>()
+            .join(",");
+        assert!(TraceState::parse(&too_many).is_none());
+    }
+
+    #[test]
+    fn test_tracestate_insert() {
+        let mut state = TraceState::parse("congo=1,rojo=2").unwrap();
+
+        // 新增成员移动到最前端
+        assert!(state.insert("acme", "3"));
+        assert_eq!(state.to_header(), "acme=3,congo=1,rojo=2");
+
+        // 更新已有成员同样移动到最前端
+        assert!(state.insert("rojo", "4"));
+        assert_eq!(state.to_header(), "rojo=4,acme=3,congo=1");
+
+        // 非法成员被拒绝
+        assert!(!state.insert("Bad", "1"));
+        assert_eq!(state.len(), 3);
+
+        assert_eq!(state.remove("acme"), Some("3".to_string()));
+        assert_eq!(state.to_header(), "rojo=4,congo=1");
+    }
+}
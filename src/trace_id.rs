@@ -1,74 +1,1095 @@
 //! TraceId 核心结构体定义
+//!
+//! 字节/十六进制转换、解析与校验只依赖 `core`，可在 `no_std` 环境中使用；
+//! 需要时钟、序列号与随机数的ID生成部分依赖 `std` 特性。
 
-use std::fmt;
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::LazyLock;
+use core::borrow::Borrow;
+use core::cmp::Ordering as CmpOrdering;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::str::FromStr;
+
+#[cfg(feature = "std")]
+use crate::clock::{Clock, SystemClock};
+#[cfg(feature = "std")]
+use crate::random::{FastRand, RandomSource};
+#[cfg(feature = "std")]
+use std::sync::atomic::{AtomicU16, AtomicU32, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+#[cfg(feature = "std")]
+use std::sync::{Arc, PoisonError, RwLock};
+#[cfg(feature = "std")]
 use std::time::{SystemTime, UNIX_EPOCH};
 
-/// 高性能追踪ID生成器
+/// 生成器分片数量的位数，分片编号写入随机部分的最高位
+#[cfg(feature = "std")]
+const SHARD_BITS: u32 = 4;
+
+/// 生成器分片数量
+#[cfg(feature = "std")]
+const SHARD_COUNT: usize = 1 << SHARD_BITS;
+
+/// 独占缓存行的分片状态，避免相邻分片之间的伪共享
+///
+/// 除生成器状态外还记录该分片观察到的最大时钟读数，用于检测时钟回拨（见 `generation_stats()`）
+#[cfg(feature = "std")]
+#[repr(align(128))]
+struct ShardState {
+    /// 生成器状态：`时间戳(48位) << 16 | 序列号(16位)`
+    state: AtomicU64,
+    /// 该分片观察到的最大时钟读数（毫秒）
+    observed_millis: AtomicU64,
+}
+
+#[cfg(feature = "std")]
+impl ShardState {
+    /// 尚未生成过ID的初始状态
+    const fn new() -> Self {
+        Self {
+            state: AtomicU64::new(0),
+            observed_millis: AtomicU64::new(0),
+        }
+    }
+}
+
+/// 各分片的生成器状态：`时间戳(48位) << 16 | 序列号(16位)`
+///
+/// 每次生成都将所在分片的状态推进到 `max(当前时间 << 16, 上次状态 + 1)`，因此分片内状态严格递增：
+/// - 同一毫秒内序列号递增，序列号溢出（单毫秒超过65536个ID）时自然进位到下一毫秒
+/// - 时钟回拨时沿用上次的时间戳继续递增，不会重复使用已分配的值
+///
+/// 分片编号同样写入ID，同一进程内生成的 `(时间戳, 序列号, 分片)` 组合因此保证唯一（不依赖随机数）
+#[cfg(feature = "std")]
+static STATE: [ShardState; SHARD_COUNT] = [const { ShardState::new() }; SHARD_COUNT];
+
+/// 检测到的时钟回拨次数（时钟读数早于所在分片此前观察到的最大读数）
+#[cfg(feature = "std")]
+static CLOCK_REGRESSIONS: AtomicU64 = AtomicU64::new(0);
+
+/// 下一个线程分配到的分片编号
+#[cfg(feature = "std")]
+static NEXT_SHARD: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(feature = "std")]
+std::thread_local! {
+    /// 当前线程使用的分片，首次生成时按轮询分配
+    ///
+    /// 线程数不超过分片数时各线程独占一个分片，生成路径上不存在跨核的缓存行争用；
+    /// 线程更多时多个线程共享分片，唯一性仍由分片内的原子状态保证
+    static SHARD: usize = NEXT_SHARD.fetch_add(1, Ordering::Relaxed) % SHARD_COUNT;
+}
+
+/// 获取当前线程的分片编号
+///
+/// 线程局部存储已销毁（如在其他线程局部变量的析构中生成ID）时退回分片0
+#[cfg(feature = "std")]
+#[inline]
+fn current_shard() -> usize {
+    SHARD.try_with(|shard| *shard).unwrap_or(0)
+}
+
+/// `new_sortable()` 固定使用的分片，所有线程共用其状态以保证进程内严格递增
+#[cfg(feature = "std")]
+const SORTABLE_SHARD: usize = 0;
+
+/// 时间戳占用的位数掩码（48位）
+#[cfg(feature = "std")]
+const TIMESTAMP_MASK: u64 = 0xFFFF_FFFF_FFFF;
+
+/// 随机部分占用的位数掩码（最低44位，其上4位为分片编号）
+#[cfg(feature = "std")]
+const RANDOM_MASK: u64 = (1 << (48 - SHARD_BITS)) - 1;
+
+/// `timestamp_millis()` 允许的时间戳超前于当前时间的容差（1天），用于兼容节点间的时钟偏差
+#[cfg(feature = "std")]
+const TIMESTAMP_TOLERANCE_MILLIS: u64 = 24 * 60 * 60 * 1000;
+
+/// 回填ID（`new_from_timestamp`）使用的序列号，独立于 `STATE` 以免历史时间戳影响实时生成
+#[cfg(feature = "std")]
+static BACKFILL_SEQUENCE: AtomicU16 = AtomicU16::new(0);
+
+/// 计算预留 `n` 个连续状态值后的新状态
 ///
-/// 使用时间戳 + 原子计数器的组合，生成符合W3C TraceContext规范的128位ID
-static COUNTER: AtomicU64 = AtomicU64::new(0);
+/// # 返回
+/// `(起始状态, 新状态)`，预留的区间为 `起始状态..起始状态 + n`
+#[cfg(feature = "std")]
+#[inline]
+fn advance_state(last: u64, now_millis: u64, n: u64) -> (u64, u64) {
+    let start = ((now_millis & TIMESTAMP_MASK) << 16).max(last.wrapping_add(1));
+    (start, start.wrapping_add(n - 1))
+}
+
+/// 从指定分片的状态中预留 `n` 个连续状态值（`n` 至少为1）
+#[cfg(feature = "std")]
+#[inline]
+fn reserve_state(shard: &ShardState, now_millis: u64, n: u64) -> u64 {
+    observe_clock(shard, now_millis);
+    let state = &shard.state;
+    let mut last = state.load(Ordering::Relaxed);
+    loop {
+        let (start, next) = advance_state(last, now_millis, n);
+        match state.compare_exchange_weak(last, next, Ordering::Relaxed, Ordering::Relaxed) {
+            Ok(_) => return start,
+            Err(current) => last = current,
+        }
+    }
+}
+
+/// 记录分片观察到的时钟读数，读数早于此前的最大读数时计为一次时钟回拨
+///
+/// 常见路径（时钟前进或停留在同一毫秒）只有一次对本分片缓存行的读取，
+/// 时钟前进时才执行一次 `fetch_max`
+#[cfg(feature = "std")]
+#[inline]
+fn observe_clock(shard: &ShardState, now_millis: u64) {
+    let observed = &shard.observed_millis;
+    let last = observed.load(Ordering::Relaxed);
+    if now_millis > last {
+        observed.fetch_max(now_millis, Ordering::Relaxed);
+    } else if now_millis < last {
+        CLOCK_REGRESSIONS.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// 生成器状态的快照，由 `TraceId::generation_stats()` 返回
+///
+/// 用于监控序列号与时间戳的健康状况：
+/// - `clock_regressions` 持续增长说明系统时钟在回拨（如 NTP 步进调整）
+/// - `state_timestamp_millis` 明显超前于 `last_timestamp_millis` 说明生成器正在预支未来的时间戳，
+///   原因是单毫秒内序列号溢出（生成速率超过每毫秒65536个）或时钟回拨后沿用旧时间戳
+///
+/// 各字段分别读取，并发生成时彼此之间不是原子一致的快照
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct GenerationStats {
+    /// 当前线程所在分片的序列号（同一毫秒内已分配的数量减一）
+    pub counter: u16,
+    /// 当前线程所在分片状态中的时间戳（毫秒），即下一个ID时间戳的下限
+    pub state_timestamp_millis: u64,
+    /// 所有分片观察到的最大时钟读数（毫秒），尚未生成过ID时为0
+    pub last_timestamp_millis: u64,
+    /// 进程启动以来检测到的时钟回拨次数
+    ///
+    /// 每次读到早于所在分片此前最大读数的时钟值时加一，因此一次回拨可能被计数多次
+    /// （回拨期间的每次生成各计一次）。共享分片的线程之间，读取时钟后被抢占的线程
+    /// 也可能读到较旧的值而计入一次，少量计数不一定代表真正的回拨
+    pub clock_regressions: u64,
+}
+
+#[cfg(feature = "std")]
+impl GenerationStats {
+    /// 读取 `current` 分片的状态与 `shards` 中观察到的最大时钟读数
+    fn collect(current: &ShardState, shards: &[ShardState]) -> Self {
+        let state = current.state.load(Ordering::Relaxed);
+        let last_timestamp_millis = shards
+            .iter()
+            .map(|shard| shard.observed_millis.load(Ordering::Relaxed))
+            .max()
+            .unwrap_or_default();
+
+        Self {
+            counter: (state & 0xFFFF) as u16,
+            state_timestamp_millis: (state >> 16) & TIMESTAMP_MASK,
+            last_timestamp_millis,
+            clock_regressions: CLOCK_REGRESSIONS.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// 指定机器ID的环境变量名称（十进制，0-65535）
+#[cfg(feature = "std")]
+pub const MACHINE_ID_ENV: &str = "TRACE_ID_MACHINE_ID";
+
+/// 机器ID，首次生成追踪ID时确定，之后不再变化
+///
+/// 优先级：`set_machine_id()` > 环境变量 `TRACE_ID_MACHINE_ID` > 基于进程ID和启动时间戳的派生值
+///
+/// 以 `MACHINE_ID_UNSET` 表示尚未确定（超出16位范围，不会与合法值冲突）
+#[cfg(feature = "std")]
+static MACHINE_ID: AtomicU32 = AtomicU32::new(MACHINE_ID_UNSET);
+
+/// 机器ID尚未确定时的取值
+#[cfg(feature = "std")]
+const MACHINE_ID_UNSET: u32 = u32::MAX;
+
+/// 设置机器ID
+///
+/// 必须在生成第一个追踪ID之前调用（通常在启动阶段），之后的调用不会生效。
+///
+/// 默认的机器ID由 `pid ^ 启动秒数` 截断为16位得到。容器环境中进程ID往往相同或相近，
+/// 同一秒启动的多个实例可能得到相同的机器ID，此时仅靠44位随机数区分，
+/// 同一毫秒内的碰撞概率随之上升。为每个实例（如每个 Pod）分配唯一的机器ID可以消除这部分风险；
+/// 机器ID只有16位，实例数量超过65536时仍无法完全避免重复。
+///
+/// # 参数
+/// * `machine_id` - 16位机器ID
+///
+/// # 返回
+/// 设置成功返回true；机器ID已确定（已调用过本函数或已生成过追踪ID）时返回false
+#[cfg(feature = "std")]
+pub fn set_machine_id(machine_id: u16) -> bool {
+    MACHINE_ID
+        .compare_exchange(
+            MACHINE_ID_UNSET,
+            u32::from(machine_id),
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        )
+        .is_ok()
+}
+
+/// 全局追踪ID生成器的函数签名
+#[cfg(feature = "std")]
+type GlobalGenerator = Arc<dyn Fn() -> TraceId + Send + Sync>;
+
+/// 全局生成器尚未确定
+#[cfg(feature = "std")]
+const GENERATOR_UNDECIDED: u8 = 0;
+
+/// 已确定使用内置的生成方案
+#[cfg(feature = "std")]
+const GENERATOR_BUILTIN: u8 = 1;
+
+/// 已确定使用 `set_global_generator()` 设置的生成器
+#[cfg(feature = "std")]
+const GENERATOR_CUSTOM: u8 = 2;
+
+/// 全局生成器的状态，首次调用 `TraceId::new()` 时确定，之后不再变化
+///
+/// 内置方案只需读取该原子变量，不会触及下面的锁
+#[cfg(feature = "std")]
+static GENERATOR_STATE: AtomicU8 = AtomicU8::new(GENERATOR_UNDECIDED);
+
+/// 全局追踪ID生成器，未通过 `set_global_generator()` 设置时为None
+#[cfg(feature = "std")]
+static GLOBAL_GENERATOR: RwLock<Option<GlobalGenerator>> = RwLock::new(None);
+
+/// 设置全局追踪ID生成器
+///
+/// 设置后 `TraceId::new()`（以及未配置生成器的中间件）都使用该生成器，
+/// 无需在每个 `TraceIdLayer` 和调用点分别传入。与 `set_machine_id()` 相同，
+/// 必须在第一次调用 `TraceId::new()` 之前（通常在启动阶段）设置，之后不可修改。
+///
+/// 生成器内部不能调用 `TraceId::new()`（会无限递归），
+/// 需要内置方案时请使用 `TraceId::new_with_clock(&SystemClock)` 等函数。
+/// `new_with_clock`、`new_with_rng`、`new_batch` 等显式指定来源的函数不受影响。
+///
+/// # 参数
+/// * `generator` - 返回TraceId的函数
+///
+/// # 返回
+/// 设置成功返回true；已设置过或已调用过 `TraceId::new()` 时返回false
+///
+/// # 示例
+/// ```
+/// use trace_id::{set_global_generator, SystemClock, TraceId};
+///
+/// // 在启动阶段设置：固定高8位为区域代码
+/// set_global_generator(|| {
+///     let value = TraceId::new_with_clock(&SystemClock).to_u128();
+///     TraceId::from_u128((0x42 << 120) | (value >> 8)).unwrap()
+/// });
+/// assert!(TraceId::new().as_str().starts_with("42"));
+/// ```
+#[cfg(feature = "std")]
+pub fn set_global_generator<F>(generator: F) -> bool
+where
+    F: Fn() -> TraceId + Send + Sync + 'static,
+{
+    let mut slot = GLOBAL_GENERATOR
+        .write()
+        .unwrap_or_else(PoisonError::into_inner);
+    let decided = GENERATOR_STATE.compare_exchange(
+        GENERATOR_UNDECIDED,
+        GENERATOR_CUSTOM,
+        Ordering::AcqRel,
+        Ordering::Acquire,
+    );
+    if decided.is_ok() {
+        *slot = Some(Arc::new(generator));
+    }
+    decided.is_ok()
+}
+
+/// 获取全局生成器，首次调用时确定使用内置方案还是自定义生成器
+///
+/// 返回生成器的副本而非持有锁，生成器内部的调用不会与锁相互影响
+#[cfg(feature = "std")]
+#[inline]
+fn global_generator() -> Option<GlobalGenerator> {
+    let mut state = GENERATOR_STATE.load(Ordering::Acquire);
+    if state == GENERATOR_UNDECIDED {
+        state = match GENERATOR_STATE.compare_exchange(
+            GENERATOR_UNDECIDED,
+            GENERATOR_BUILTIN,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => GENERATOR_BUILTIN,
+            Err(current) => current,
+        };
+    }
+    if state == GENERATOR_BUILTIN {
+        return None;
+    }
+    GLOBAL_GENERATOR
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// 重置机器ID与全局生成器，使其回到尚未确定的状态
+///
+/// **仅供测试使用，不要在生产代码中调用。** `set_machine_id()` 与 `set_global_generator()`
+/// 在进程内只能生效一次，同一测试二进制中先执行的测试会决定之后所有测试的配置；
+/// 在测试开始前调用本函数即可重新设置。
+///
+/// 重置后已生成的追踪ID不受影响，但新旧配置下生成的ID不再保证互不重复；
+/// 与正在进行的生成并发调用时，这些调用可能使用重置前或重置后的配置。
+/// 需要启用 `test-util` 特性。
+///
+/// # 示例
+/// ```
+/// use trace_id::{reset_globals, set_machine_id, TraceId};
+///
+/// assert!(set_machine_id(1));
+/// assert_eq!(TraceId::new().machine_id(), 1);
+///
+/// reset_globals();
+/// assert!(set_machine_id(2));
+/// assert_eq!(TraceId::new().machine_id(), 2);
+/// ```
+#[cfg(feature = "test-util")]
+pub fn reset_globals() {
+    let mut slot = GLOBAL_GENERATOR
+        .write()
+        .unwrap_or_else(PoisonError::into_inner);
+    *slot = None;
+    GENERATOR_STATE.store(GENERATOR_UNDECIDED, Ordering::Release);
+    MACHINE_ID.store(MACHINE_ID_UNSET, Ordering::Release);
+}
+
+/// 计算默认机器ID：优先读取环境变量，否则基于进程ID和启动时间戳派生
+#[cfg(feature = "std")]
+fn default_machine_id() -> u16 {
+    if let Some(machine_id) = parse_machine_id(std::env::var(MACHINE_ID_ENV).ok().as_deref()) {
+        return machine_id;
+    }
 
-/// 机器ID，基于进程ID和启动时间戳生成，确保不同进程/实例的ID不冲突
-static MACHINE_ID: LazyLock<u16> = LazyLock::new(|| {
     let pid = std::process::id();
     let timestamp = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap_or_default()
         .as_secs() as u32;
     ((pid ^ timestamp) & 0xFFFF) as u16
-});
+}
+
+/// 解析环境变量中的机器ID，无效值会被忽略
+#[cfg(feature = "std")]
+fn parse_machine_id(value: Option<&str>) -> Option<u16> {
+    let machine_id = value?.trim().parse().ok();
+    if machine_id.is_none() {
+        tracing::warn!("Ignoring invalid {MACHINE_ID_ENV} value, expected an integer in 0..=65535");
+    }
+    machine_id
+}
+
+/// 十六进制编码表（小写）
+const HEX_CHARS: &[u8; 16] = b"0123456789abcdef";
 
 /// 追踪ID结构体
 ///
-/// 支持多种ID格式：高性能模式使用时间戳+计数器，兼容模式使用UUID v4
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct TraceId(String);
+/// 内部以128位原始字节保存，同时缓存32字符的小写十六进制形式，
+/// 生成与复制均无需堆分配，`as_str`/`Display` 直接复用缓存的十六进制表示。
+/// 固定大小的表示使其实现 `Copy`，可以按值传递而无需 `.clone()`
+///
+/// 不使用 `Arc<str>` 等共享表示：复制49字节比原子引用计数的增减更快（见基准测试 `clone_1000`，
+/// 约为 `Arc<str>` 克隆开销的九分之一），且多线程持有同一ID时不会争用引用计数所在的缓存行；
+/// 共享表示还会使其失去 `Copy`、`const fn` 构造与 `no_std` 支持
+///
+/// 除标准的128位ID外，还支持仅用于内部服务的64位短ID（见 `new_short`），
+/// 其字符串表示为16个字符；需要 W3C 格式时通过 `padded` 获取补零后的32字符形式。
+/// 位宽是ID的一部分：数值相同的短ID与标准ID（如 `from_u64(1)` 与 `from_u128(1)`）
+/// 字符串表示不同，`Eq`、`Hash` 与 `Ord` 均视其为不同的ID
+#[derive(Clone, Copy)]
+pub struct TraceId {
+    /// 原始128位数据（大端序），短ID的高64位恒为零
+    bytes: [u8; 16],
+    /// 缓存的32字符小写十六进制表示（始终为合法ASCII）
+    hex: [u8; 32],
+    /// 是否为64位短ID，为true时 `as_str()` 只取低16个字符
+    short: bool,
+}
+
+/// ID生成（依赖时钟、序列号与随机数，需要 `std` 特性）
+#[cfg(feature = "std")]
+impl TraceId {
+    /// 获取机器ID
+    ///
+    /// 返回通过 `set_machine_id()`、环境变量或进程ID和启动时间戳确定的机器标识符
+    #[inline]
+    fn get_machine_id() -> u16 {
+        let machine_id = MACHINE_ID.load(Ordering::Acquire);
+        if machine_id != MACHINE_ID_UNSET {
+            return machine_id as u16;
+        }
+
+        // 并发初始化时以先写入者为准
+        let default = default_machine_id();
+        match MACHINE_ID.compare_exchange(
+            MACHINE_ID_UNSET,
+            u32::from(default),
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => default,
+            Err(current) => current as u16,
+        }
+    }
+
+    /// 生成新的追踪ID（符合 W3C TraceContext 规范）
+    ///
+    /// 使用时间戳+机器ID+序列号+随机数的组合，生成32字符的小写十六进制ID
+    /// 格式：符合 W3C TraceContext 规范的 trace-id
+    /// 长度：固定32字符（128位）
+    ///
+    /// # 唯一性
+    /// 时间戳与序列号来自当前线程所在分片内严格递增的状态，分片编号同样写入ID，
+    /// 同一进程内生成的ID保证互不重复（而非依赖随机数）：
+    /// 单毫秒内同一分片超过65536个ID时时间戳提前进位，时钟回拨时时间戳保持不变。
+    /// 不同进程之间依靠机器ID（见 `set_machine_id()`）与44位随机数区分。
+    ///
+    /// # 位布局
+    /// ```text
+    /// | 时间戳(48位) | 机器ID(16位) | 序列号(16位) | 分片(4位) | 随机数(44位) |
+    /// ```
+    /// 各线程按轮询固定使用16个分片之一，每个分片的状态独占一个缓存行，
+    /// 多线程并发生成时不会争用同一个原子变量。因此只有同一线程（同一分片）内的ID按生成顺序递增，
+    /// 不同线程之间的ID顺序仅按时间戳大致有序。
+    ///
+    /// # 性能优化
+    /// - 使用内联函数减少调用开销
+    /// - 直接位操作避免额外计算
+    /// - 机器ID与全局生成器的状态均为单次原子读取
+    /// - 按线程分片的状态避免多核之间的缓存行争用
+    /// - 定长内部表示，生成过程无堆分配
+    ///
+    /// # 全局生成器
+    /// 通过 `set_global_generator()` 设置了全局生成器时，使用其结果代替上述方案
+    ///
+    /// # 返回
+    /// 新生成的追踪ID
+    #[inline]
+    pub fn new() -> Self {
+        match global_generator() {
+            Some(generator) => generator(),
+            None => Self::new_with_clock(&SystemClock),
+        }
+    }
+
+    /// 使用指定的时钟源生成新的追踪ID
+    ///
+    /// 与 `new()` 相同，但时间戳取自 `clock`，便于测试时间戳部分，
+    /// 或模拟时钟偏移与毫秒边界。时钟仅影响高48位的时间戳；为保证唯一性，
+    /// 时间戳不会早于当前线程此前（任意时钟源）生成的ID，时钟落后时沿用上次的时间戳。
+    ///
+    /// # 参数
+    /// * `clock` - 时钟源，也可以直接传入返回毫秒数的闭包
+    ///
+    /// # 返回
+    /// 新生成的追踪ID
+    #[inline]
+    pub fn new_with_clock(clock: &impl Clock) -> Self {
+        Self::generate(clock, &FastRand)
+    }
+
+    /// 使用指定的随机数源生成新的追踪ID
+    ///
+    /// 与 `new()` 相同，但低44位随机部分取自 `rng`，
+    /// 可用于可复现的模糊测试，或接入密码学安全的随机数生成器。
+    ///
+    /// # 参数
+    /// * `rng` - 随机数源，也可以直接传入返回 `u64` 的闭包（仅使用低44位）
+    ///
+    /// # 返回
+    /// 新生成的追踪ID
+    #[inline]
+    pub fn new_with_rng(rng: &impl RandomSource) -> Self {
+        Self::generate(&SystemClock, rng)
+    }
+
+    /// 批量生成追踪ID
+    ///
+    /// 整批只读取一次时钟、只对当前线程的分片进行一次原子操作预留 `n` 个连续的序列号，
+    /// 适合压测等需要预先生成大量ID的场景。格式与唯一性保证与 `new()` 完全相同。
+    ///
+    /// # 参数
+    /// * `n` - 要生成的ID数量
+    ///
+    /// # 返回
+    /// 包含 `n` 个追踪ID的列表
+    pub fn new_batch(n: usize) -> Vec<Self> {
+        if n == 0 {
+            return Vec::new();
+        }
+
+        let machine_id = Self::get_machine_id();
+        let shard = current_shard();
+        let start = reserve_state(&STATE[shard], SystemClock.now_millis(), n as u64);
+
+        (0..n as u64)
+            .map(|i| {
+                Self::compose(
+                    start.wrapping_add(i),
+                    machine_id,
+                    shard,
+                    FastRand.next_u64(),
+                )
+            })
+            .collect()
+    }
+
+    /// 生成按字符串排序即按生成顺序排列的追踪ID
+    ///
+    /// 与 UUIDv7 相同，高48位为 Unix 毫秒时间戳（大端序），因此十六进制字符串的字典序
+    /// 首先按生成时间排列；格式仍为32个十六进制字符，不含连字符与版本号。
+    /// 其余部分的布局与 `new()` 相同，`machine_id()`、`counter()` 等同样适用。
+    ///
+    /// `new()` 只保证同一线程内递增；本函数所有线程共用同一个分片的状态，
+    /// 同一进程内生成的ID严格递增（时钟回拨或单毫秒超过65536个ID时时间戳提前，顺序依然成立），
+    /// 代价是多线程并发生成时会争用该状态。不同进程之间只按毫秒时间戳（其次按机器ID）有序。
+    /// 不受 `set_global_generator()` 影响。
+    ///
+    /// # 返回
+    /// 新生成的追踪ID
+    ///
+    /// # 示例
+    /// ```
+    /// use trace_id::TraceId;
+    ///
+    /// let first = TraceId::new_sortable();
+    /// let second = TraceId::new_sortable();
+    /// assert!(first.as_str() < second.as_str());
+    /// ```
+    pub fn new_sortable() -> Self {
+        Self::generate_in_shard(SORTABLE_SHARD, &SystemClock, &FastRand)
+    }
+
+    /// 生成完全随机的追踪ID
+    ///
+    /// 128位全部来自随机数（排除全零），不包含时间戳、机器ID与序列号，
+    /// 不会暴露服务器时钟，也无法据此关联不同请求的先后顺序，适用于隐私敏感的场景。
+    /// 代价是失去 `new()` 的进程内唯一性保证（依赖128位随机数，碰撞概率可忽略）、
+    /// 时间有序性与 `timestamp_millis` 等可解析的结构；随机数来自默认随机数源（见 `FastRand`）
+    ///
+    /// # 返回
+    /// 新生成的追踪ID
+    ///
+    /// # 示例
+    /// ```
+    /// use trace_id::TraceId;
+    ///
+    /// let trace_id = TraceId::new_random();
+    /// assert!(TraceId::is_valid(trace_id.as_str()));
+    /// ```
+    pub fn new_random() -> Self {
+        // 非零随机数保证不会生成全零ID
+        Self::from_bytes(crate::random::nonzero_u128().to_be_bytes())
+    }
+
+    /// 使用指定的历史时间戳生成追踪ID
+    ///
+    /// 用于为只有时间戳、没有追踪ID的历史记录（如导入的旧日志）补发ID，
+    /// 使基于时间戳的分片与排序仍然有效。高48位直接使用 `millis`，
+    /// 机器ID、序列号与随机数的组合方式与 `new()` 相同。
+    ///
+    /// 嵌入的时间仅供参考，不代表权威的事件时间：实时生成的ID在时钟回拨或
+    /// 单毫秒ID过多时时间戳会提前。回填ID不参与进程内的单调状态，
+    /// 其唯一性依赖独立的序列号与44位随机数，而非 `new()` 的严格保证。
+    ///
+    /// # 参数
+    /// * `millis` - Unix纪元以来的毫秒数，必须能用48位表示
+    ///
+    /// # 返回
+    /// 新生成的追踪ID
+    ///
+    /// # Panics
+    /// `millis` 超出48位（大于 `0xFFFF_FFFF_FFFF`）时panic
+    pub fn new_from_timestamp(millis: u64) -> Self {
+        assert!(
+            millis <= TIMESTAMP_MASK,
+            "timestamp {millis} does not fit in 48 bits"
+        );
+
+        let sequence = BACKFILL_SEQUENCE.fetch_add(1, Ordering::Relaxed) as u64;
+        Self::compose(
+            (millis << 16) | sequence,
+            Self::get_machine_id(),
+            current_shard(),
+            FastRand.next_u64(),
+        )
+    }
+
+    /// 生成64位短追踪ID
+    ///
+    /// 字符串表示只有16个字符，适合从不经过 W3C 系统的内部东西向流量，以节省日志与头部的空间。
+    /// 64位随机数的碰撞概率远高于128位ID，且不包含时间戳等可解析的结构。
+    /// `from_string_validated` 与 `FromStr` 会拒绝短ID，接收方需使用 `from_string_validated_short`
+    /// 或在中间件中启用 `TraceIdConfig::enable_short_ids`
+    ///
+    /// # 返回
+    /// 新生成的短追踪ID
+    ///
+    /// # 示例
+    /// ```
+    /// use trace_id::TraceId;
+    ///
+    /// let trace_id = TraceId::new_short();
+    /// assert_eq!(trace_id.as_str().len(), 16);
+    /// assert!(TraceId::from_string_validated(trace_id.as_str()).is_none());
+    /// assert_eq!(TraceId::from_string_validated_short(trace_id.as_str()), Some(trace_id));
+    /// ```
+    pub fn new_short() -> Self {
+        Self::from_u64(FastRand.next_u64().max(1)).unwrap_or_default()
+    }
+
+    /// 获取生成器状态的快照，用于监控时钟回拨与序列号溢出
+    ///
+    /// 统计覆盖 `new()`、`new_with_clock()`、`new_batch()`、`new_sortable()` 等使用内置方案的生成，
+    /// 不包括 `set_global_generator()` 设置的自定义生成器、`new_random()` 与 `new_from_timestamp()`
+    ///
+    /// # 返回
+    /// 当前的生成器统计
+    ///
+    /// # 示例
+    /// ```
+    /// use trace_id::TraceId;
+    ///
+    /// let trace_id = TraceId::new();
+    /// let stats = TraceId::generation_stats();
+    /// assert!(stats.last_timestamp_millis >= trace_id.timestamp_millis().unwrap());
+    /// ```
+    pub fn generation_stats() -> GenerationStats {
+        GenerationStats::collect(&STATE[current_shard()], &STATE)
+    }
+
+    /// 使用指定的时钟源和随机数源生成追踪ID
+    #[inline]
+    fn generate(clock: &impl Clock, rng: &impl RandomSource) -> Self {
+        Self::generate_in_shard(current_shard(), clock, rng)
+    }
+
+    /// 在指定分片中生成追踪ID
+    #[inline]
+    fn generate_in_shard(shard: usize, clock: &impl Clock, rng: &impl RandomSource) -> Self {
+        let machine_id = Self::get_machine_id();
+        let state = reserve_state(&STATE[shard], clock.now_millis(), 1);
+
+        Self::compose(state, machine_id, shard, rng.next_u64())
+    }
+
+    /// 将各部分组合成128位追踪ID
+    ///
+    /// 构造128位ID：timestamp(48位) + machine_id(16位) + sequence(16位) + shard(4位) + random(44位)
+    #[inline]
+    fn compose(state: u64, machine_id: u16, shard: usize, random_part: u64) -> Self {
+        let timestamp = (state >> 16) & TIMESTAMP_MASK;
+        let sequence = state & 0xFFFF;
+
+        let high_64 = (timestamp << 16) | (machine_id as u64);
+        let low_64 =
+            (sequence << 48) | ((shard as u64) << (48 - SHARD_BITS)) | (random_part & RANDOM_MASK);
+
+        let value = ((high_64 as u128) << 64) | (low_64 as u128);
+        Self::from_bytes(value.to_be_bytes())
+    }
+
+    /// 生成新的追踪ID，并将其32字符的十六进制表示写入调用方提供的缓冲区
+    ///
+    /// 与 `TraceId::new()` 使用相同的生成方案（包括全局生成器），
+    /// 适用于只需要字符串形式、且向预先分配的缓冲区格式化的日志框架或嵌入式场景。
+    /// 已有的追踪ID请使用 `write_hex`
+    ///
+    /// # 参数
+    /// * `buf` - 接收32个小写十六进制ASCII字符的缓冲区
+    ///
+    /// # 返回
+    /// 借用 `buf` 的追踪ID字符串
+    ///
+    /// # 示例
+    /// ```
+    /// use trace_id::TraceId;
+    ///
+    /// let mut buf = [0u8; 32];
+    /// let id = TraceId::generate_into(&mut buf);
+    /// assert!(TraceId::is_valid(id));
+    /// ```
+    #[inline]
+    pub fn generate_into(buf: &mut [u8; 32]) -> &str {
+        Self::new().write_hex(buf)
+    }
+
+    /// 获取大写十六进制表示
+    ///
+    /// 用于要求大写ID的外部系统；内部表示与 `as_str()` 始终为小写。
+    /// 无需分配时可使用 `format!("{:X}", trace_id)` 或直接写入格式化器
+    ///
+    /// # 返回
+    /// 32个字符的大写十六进制字符串
+    pub fn as_uppercase_hex(&self) -> String {
+        format!("{self:X}")
+    }
+
+    /// 获取嵌入在高48位的毫秒时间戳
+    ///
+    /// 仅对本crate生成的ID有意义，可用于粗略的时间排序与调试。
+    /// 外部传入的ID（如上游的 W3C trace-id）高位通常是随机数，无法可靠区分：
+    /// 这里只做合理性检查，时间戳为0或明显晚于当前时间（超过1天的时钟偏差容差）时返回None，
+    /// 通过检查并不代表该ID一定由本crate生成。
+    ///
+    /// 嵌入的时间仅供参考：时钟回拨或单毫秒ID过多时，生成的时间戳可能略晚于实际时间。
+    ///
+    /// # 返回
+    /// 看起来合理时返回Some(自 Unix 纪元以来的毫秒数)，否则返回None
+    pub fn timestamp_millis(&self) -> Option<u64> {
+        let timestamp = (self.to_u128() >> 80) as u64;
+        let latest = STATE
+            .iter()
+            .map(|shard| shard.state.load(Ordering::Relaxed) >> 16)
+            .fold(SystemClock.now_millis(), u64::max);
+        (timestamp != 0 && timestamp <= latest.saturating_add(TIMESTAMP_TOLERANCE_MILLIS))
+            .then_some(timestamp)
+    }
+}
+
+impl TraceId {
+    /// W3C TraceContext 规范中的全零追踪ID，用作显式的"无追踪"标记
+    ///
+    /// 部分协议以全零ID表示请求有意不属于任何追踪，与桥接这类系统的代码可以用它
+    /// 区分"明确没有追踪"与"有效的追踪"。全零ID不是有效的追踪ID：`from_string_validated`、
+    /// `FromStr` 等解析函数仍会拒绝它（`TraceIdParseError::AllZeros`），不要将其写入出站请求头；
+    /// 需要判断时使用 `is_nil()`
+    ///
+    /// # 示例
+    /// ```
+    /// use trace_id::TraceId;
+    ///
+    /// assert!(TraceId::NIL.is_nil());
+    /// assert_eq!(TraceId::NIL.as_str(), "00000000000000000000000000000000");
+    /// assert!(TraceId::from_string_validated(TraceId::NIL.as_str()).is_none());
+    /// ```
+    pub const NIL: Self = Self::from_bytes([0; 16]);
+
+    /// 获取全零的"无追踪"标记ID，与 `TraceId::NIL` 相同
+    ///
+    /// # 返回
+    /// 全零追踪ID
+    #[inline]
+    pub const fn empty() -> Self {
+        Self::NIL
+    }
+
+    /// 是否为全零的"无追踪"标记ID（见 `TraceId::NIL`）
+    ///
+    /// 只判断数值，不区分位宽；`from_u64` 拒绝零，因此短ID不会是全零
+    ///
+    /// # 返回
+    /// 数值为零时返回true
+    #[inline]
+    pub const fn is_nil(&self) -> bool {
+        u128::from_be_bytes(self.bytes) == 0
+    }
+
+    /// 从原始128位字节创建追踪ID
+    ///
+    /// 不进行校验：全零字节会得到 W3C 规范中的无效ID，调用者需自行保证
+    ///
+    /// # 参数
+    /// * `bytes` - 大端序的16字节原始数据
+    ///
+    /// # 返回
+    /// TraceId实例
+    ///
+    /// 这是 `const fn`，可用于 `const`/`static` 定义
+    #[inline]
+    pub const fn from_bytes(bytes: [u8; 16]) -> Self {
+        let mut hex = [0u8; 32];
+        let mut i = 0;
+        while i < 16 {
+            hex[i * 2] = HEX_CHARS[(bytes[i] >> 4) as usize];
+            hex[i * 2 + 1] = HEX_CHARS[(bytes[i] & 0x0F) as usize];
+            i += 1;
+        }
+        Self {
+            bytes,
+            hex,
+            short: false,
+        }
+    }
+
+    /// 从字符串字面量创建追踪ID，可在 `const`/`static` 中使用
+    ///
+    /// 用于测试夹具与示例，省去 `from_string_validated("...").unwrap()`。
+    /// 校验规则与 `from_string_validated` 相同；在常量上下文中使用时，无效输入在编译期报错
+    ///
+    /// # 参数
+    /// * `id` - 32个小写十六进制字符且不全为零的字面量
+    ///
+    /// # 返回
+    /// TraceId实例
+    ///
+    /// # Panics
+    /// `id` 不是有效的追踪ID时panic（常量上下文中为编译错误）
+    ///
+    /// # 示例
+    /// ```
+    /// use trace_id::TraceId;
+    ///
+    /// const FIXTURE: TraceId = TraceId::from_static_hex("0af7651916cd43dd8448eb211c80319c");
+    /// assert_eq!(FIXTURE, "0af7651916cd43dd8448eb211c80319c");
+    /// ```
+    ///
+    /// ```compile_fail
+    /// use trace_id::TraceId;
+    ///
+    /// const INVALID: TraceId = TraceId::from_static_hex("0AF7651916CD43DD8448EB211C80319C");
+    /// ```
+    pub const fn from_static_hex(id: &'static str) -> Self {
+        let hex = id.as_bytes();
+        assert!(hex.len() == 32, "trace id must be 32 characters long");
+
+        let mut bytes = [0u8; 16];
+        let mut all_zeros = true;
+        let mut i = 0;
+        while i < 32 {
+            assert!(
+                Self::is_valid_hex_byte(hex[i]),
+                "trace id must only contain lowercase hex characters"
+            );
+            all_zeros &= hex[i] == b'0';
+            bytes[i / 2] = (bytes[i / 2] << 4) | Self::decode_nibble(hex[i]);
+            i += 1;
+        }
+        assert!(!all_zeros, "trace id must not be all zeros");
+
+        Self::from_bytes(bytes)
+    }
+
+    /// 从64位整数创建短追踪ID
+    ///
+    /// 短ID的字符串表示为16个十六进制字符，只适用于不与 W3C 系统交互的内部服务。
+    /// 与 `to_u64` 互逆；结果与数值相同的 `from_u128(value as u128)` 不相等（位宽不同）
+    ///
+    /// # 参数
+    /// * `value` - 64位ID值
+    ///
+    /// # 返回
+    /// 非零时返回Some(TraceId)，零为无效ID返回None
+    ///
+    /// # 示例
+    /// ```
+    /// use trace_id::TraceId;
+    ///
+    /// let trace_id = TraceId::from_u64(0x8448eb211c80319c).unwrap();
+    /// assert_eq!(trace_id.as_str(), "8448eb211c80319c");
+    /// assert_eq!(trace_id.padded().to_string(), "00000000000000008448eb211c80319c");
+    /// assert!(TraceId::from_u64(0).is_none());
+    /// assert_ne!(trace_id, TraceId::from_u128(0x8448eb211c80319c).unwrap());
+    /// ```
+    #[inline]
+    pub const fn from_u64(value: u64) -> Option<Self> {
+        if value == 0 {
+            return None;
+        }
+        let trace_id = Self::from_bytes((value as u128).to_be_bytes());
+        Some(Self {
+            short: true,
+            ..trace_id
+        })
+    }
+
+    /// 获取64位整数形式的短追踪ID
+    ///
+    /// 与 `from_u64` 互逆，短ID应使用这一对函数往返，而不是 `to_u128`/`from_u128`
+    ///
+    /// # 返回
+    /// 短ID返回Some(值)，标准ID返回None
+    ///
+    /// # 示例
+    /// ```
+    /// use trace_id::TraceId;
+    ///
+    /// let trace_id = TraceId::new_short();
+    /// assert_eq!(TraceId::from_u64(trace_id.to_u64().unwrap()), Some(trace_id));
+    /// assert_eq!(TraceId::new().to_u64(), None);
+    /// ```
+    #[inline]
+    pub const fn to_u64(&self) -> Option<u64> {
+        if self.short {
+            Some(u128::from_be_bytes(self.bytes) as u64)
+        } else {
+            None
+        }
+    }
+
+    /// 是否为64位短ID
+    #[inline]
+    pub const fn is_short(&self) -> bool {
+        self.short
+    }
+
+    /// 从种子确定性地派生追踪ID
+    ///
+    /// 使用 128 位 FNV-1a 哈希，相同的种子在任何进程与平台上都得到相同的ID，
+    /// 适合让幂等批处理任务的重试与首次执行共享追踪ID。
+    /// FNV-1a 不是密码学哈希，不能用于隐藏种子内容。
+    /// 哈希结果为零（W3C 规范中的无效ID）时使用1代替，结果总能通过 `from_string_validated`
+    ///
+    /// # 参数
+    /// * `seed` - 任意字节序列（如任务的幂等键）
+    ///
+    /// # 返回
+    /// 由种子唯一确定的有效追踪ID
+    ///
+    /// # 示例
+    /// ```
+    /// use trace_id::TraceId;
+    ///
+    /// let first = TraceId::from_seed(b"invoice-2024-06-01");
+    /// let retry = TraceId::from_seed(b"invoice-2024-06-01");
+    /// assert_eq!(first, retry);
+    /// ```
+    pub fn from_seed(seed: &[u8]) -> Self {
+        let hash = seed.iter().fold(FNV_OFFSET_BASIS, |hash, &byte| {
+            (hash ^ byte as u128).wrapping_mul(FNV_PRIME)
+        });
+        Self::from_bytes(hash.max(1).to_be_bytes())
+    }
+
+    /// 从任意不透明ID（ULID、带连字符的UUID、自定义请求ID等）创建追踪ID
+    ///
+    /// 用于上游只提供非 W3C 格式关联ID的场景，避免这类ID被拒绝后丢失关联：
+    /// - 有效的 W3C 追踪ID原样使用
+    /// - 带连字符的UUID（大小写均可）去掉连字符后使用其128位值，与 `new_uuid` 的表示一致
+    /// - 其他输入通过 `from_seed` 确定性地哈希为有效的追踪ID，同一输入在任何进程中结果相同
+    ///
+    /// 哈希不可逆，且 `TraceId` 是定长的 `Copy` 值，不保存原始字符串：
+    /// 下游服务与日志中只能看到哈希后的ID。需要按原始ID检索时，
+    /// 应在转换处记录一次对应关系（例如 `tracing::info!(request_id = raw, %trace_id)`）。
+    /// 中间件启用 `TraceIdConfig::map_opaque_ids` 时会对无效的入站ID自动完成映射与记录
+    ///
+    /// # 参数
+    /// * `id` - 任意字符串
+    ///
+    /// # 返回
+    /// 有效的追踪ID
+    ///
+    /// # 示例
+    /// ```
+    /// use trace_id::TraceId;
+    ///
+    /// let from_uuid = TraceId::from_opaque("0AF76519-16CD-43DD-8448-EB211C80319C");
+    /// assert_eq!(from_uuid.as_str(), "0af7651916cd43dd8448eb211c80319c");
+    ///
+    /// let from_ulid = TraceId::from_opaque("01ARZ3NDEKTSV4RRFFQ69G5FAV");
+    /// assert_eq!(from_ulid, TraceId::from_opaque("01ARZ3NDEKTSV4RRFFQ69G5FAV"));
+    /// ```
+    pub fn from_opaque(id: &str) -> Self {
+        if let Some(trace_id) = Self::from_string_validated(id) {
+            return trace_id;
+        }
+
+        Self::from_hyphenated_uuid(id).unwrap_or_else(|| Self::from_seed(id.as_bytes()))
+    }
+
+    /// 解析 `8-4-4-4-12` 格式的UUID字符串（不区分大小写）
+    fn from_hyphenated_uuid(id: &str) -> Option<Self> {
+        let bytes = id.as_bytes();
+        if bytes.len() != 36 || [8, 13, 18, 23].iter().any(|&i| bytes[i] != b'-') {
+            return None;
+        }
+
+        let mut hex = [0u8; 32];
+        let digits = bytes.iter().filter(|&&b| b != b'-');
+        for (slot, &b) in hex.iter_mut().zip(digits) {
+            *slot = b;
+        }
+        Self::from_string_validated_ci(core::str::from_utf8(&hex).ok()?)
+    }
 
-impl TraceId {
-    /// 获取机器ID
+    /// 获取原始128位字节
     ///
-    /// 返回基于进程ID和启动时间戳生成的机器标识符
+    /// # 返回
+    /// 大端序的16字节原始数据，可直接写入二进制协议
     #[inline]
-    fn get_machine_id() -> u16 {
-        *MACHINE_ID
+    pub fn as_bytes(&self) -> &[u8; 16] {
+        &self.bytes
     }
 
-    /// 生成新的追踪ID（符合 W3C TraceContext 规范）
+    /// 从128位整数创建追踪ID
     ///
-    /// 使用时间戳+计数器+机器ID+随机数的组合，生成32字符的小写十六进制ID
-    /// 格式：符合 W3C TraceContext 规范的 trace-id
-    /// 长度：固定32字符（128位）
+    /// 高64位对应十六进制表示的前16个字符，与 `to_u128` 互逆。
+    /// 结果总是32字符的标准ID，短ID请使用 `from_u64`
     ///
-    /// # 性能优化
-    /// - 使用内联函数减少调用开销
-    /// - 直接位操作避免额外计算
-    /// - LazyLock确保机器ID初始化的线程安全
+    /// # 参数
+    /// * `value` - 128位整数形式的追踪ID
     ///
     /// # 返回
-    /// 新生成的追踪ID
+    /// 非零时返回Some(TraceId)，零值（W3C 规范中的无效ID）返回None
     #[inline]
-    pub fn new() -> Self {
-        // 获取当前时间戳（毫秒级）
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_millis() as u64;
+    pub fn from_u128(value: u128) -> Option<Self> {
+        if value == 0 {
+            return None;
+        }
+        Some(Self::from_bytes(value.to_be_bytes()))
+    }
 
-        let counter = COUNTER.fetch_add(1, Ordering::Relaxed);
-        let machine_id = Self::get_machine_id();
+    /// 获取128位整数形式的追踪ID
+    ///
+    /// 整数不包含位宽：短ID经 `from_u128` 往返后得到的是补零后的标准ID（即 `padded()` 的形式），
+    /// 与原短ID不相等；短ID请使用 `to_u64`/`from_u64` 往返
+    ///
+    /// # 返回
+    /// 按大端序解释原始字节得到的整数，格式化为 `{:032x}` 即为十六进制表示
+    #[inline]
+    pub fn to_u128(&self) -> u128 {
+        u128::from_be_bytes(self.bytes)
+    }
 
-        // 构造128位ID：timestamp(48位) + machine_id(16位) + counter(32位) + random(32位)
-        let random_part = fastrand::u32(..);
+    /// 获取机器ID部分（第49-64位）
+    ///
+    /// 仅对本crate生成的ID有意义，外部传入的ID返回的是对应位置的任意值
+    ///
+    /// # 返回
+    /// 生成该ID的进程的16位机器标识符
+    #[inline]
+    pub fn machine_id(&self) -> u16 {
+        u16::from_be_bytes([self.bytes[6], self.bytes[7]])
+    }
 
-        // 将各部分组合成128位数据
-        let high_64 = ((timestamp & 0xFFFFFFFFFFFF) << 16) | (machine_id as u64);
-        let low_64 = (counter & 0xFFFFFFFF) << 32 | (random_part as u64);
+    /// 获取序列号部分（第65-80位）
+    ///
+    /// 同一毫秒内由同一进程的同一分片（见 `shard()`）生成的ID按序列号递增。
+    /// 序列号当前占16位，返回 `u32` 为将来扩展预留空间；
+    /// 仅对本crate生成的ID有意义，外部传入的ID返回的是对应位置的任意值
+    ///
+    /// # 返回
+    /// 该ID的序列号
+    #[inline]
+    pub fn counter(&self) -> u32 {
+        u16::from_be_bytes([self.bytes[8], self.bytes[9]]) as u32
+    }
 
-        // 转换为32字符的小写十六进制字符串
-        let id = format!("{high_64:016x}{low_64:016x}");
-        Self(id)
+    /// 获取生成器分片编号（第81-84位）
+    ///
+    /// 同一进程内每个线程固定使用一个分片，序列号只在分片内递增；
+    /// 仅对本crate生成的ID有意义，外部传入的ID返回的是对应位置的任意值
+    ///
+    /// # 返回
+    /// 0-15之间的分片编号
+    #[inline]
+    pub fn shard(&self) -> u8 {
+        self.bytes[10] >> 4
     }
 
     /// 从字符串创建追踪ID，并进行 W3C TraceContext 规范校验
@@ -81,6 +1102,8 @@ impl TraceId {
     /// # 返回
     /// 如果格式有效则返回Some(TraceId)，否则返回None
     ///
+    /// 需要失败原因时请使用 `str::parse::<TraceId>()`，返回 `TraceIdParseError`
+    ///
     /// # 校验规则（符合 W3C TraceContext 规范）
     /// - 长度必须是 32 个字符
     /// - 只能包含小写十六进制字符（0-9, a-f）
@@ -92,22 +1115,115 @@ impl TraceId {
     /// - 内联函数减少调用开销
     #[inline]
     pub fn from_string_validated(id: &str) -> Option<Self> {
+        id.parse().ok()
+    }
+
+    /// 判断字符串是否为有效的追踪ID，不构造 `TraceId`
+    ///
+    /// 校验规则与 `from_string_validated` 相同（W3C TraceContext：32个小写十六进制字符且不全为零），
+    /// 适合在路由、过滤等只需要判断结果的代码中使用
+    ///
+    /// # 参数
+    /// * `id` - 追踪ID字符串
+    ///
+    /// # 返回
+    /// 有效时返回true
+    #[inline]
+    pub fn is_valid(id: &str) -> bool {
+        Self::is_valid_bytes(id.as_bytes())
+    }
+
+    /// 判断字节序列是否为有效的追踪ID，不构造 `TraceId`
+    ///
+    /// 与 `is_valid` 相同，但直接接受字节（如原始请求头值），无需先转换为 `&str`
+    ///
+    /// # 参数
+    /// * `id` - 追踪ID的ASCII字节
+    ///
+    /// # 返回
+    /// 有效时返回true
+    #[inline]
+    pub fn is_valid_bytes(id: &[u8]) -> bool {
+        id.len() == 32
+            && id.iter().all(|&b| Self::is_valid_hex_byte(b))
+            && id.iter().any(|&b| b != b'0')
+    }
+
+    /// 按 W3C TraceContext 规范校验并解码字节形式的追踪ID
+    ///
+    /// 字符串与头部值共用的校验路径：一次遍历同时完成ASCII与十六进制检查，
+    /// 失败时返回具体原因
+    fn parse_bytes(id: &[u8]) -> Result<Self, TraceIdParseError> {
         // 长度检查：必须是 32 个字符
         if id.len() != 32 {
-            return None;
+            return Err(TraceIdParseError::WrongLength(id.len()));
         }
 
         // 字符有效性检查：使用字节级验证，性能更优
-        if !Self::is_valid_hex_bytes(id.as_bytes()) {
-            return None;
+        if let Some(index) = id.iter().position(|&b| !Self::is_valid_hex_byte(b)) {
+            return Err(TraceIdParseError::InvalidChar(index));
         }
 
         // 不能全为零
-        if id == "00000000000000000000000000000000" {
+        if id == b"00000000000000000000000000000000" {
+            return Err(TraceIdParseError::AllZeros);
+        }
+
+        Ok(Self::decode_hex(id))
+    }
+
+    /// 从16字符的字符串创建64位短追踪ID
+    ///
+    /// 只接受16个小写十六进制字符且不全为零；32字符的ID请使用 `from_string_validated`。
+    /// `from_string_validated` 与 `FromStr` 严格遵循 W3C 规范，始终拒绝16字符的短ID
+    ///
+    /// # 参数
+    /// * `id` - 短追踪ID字符串
+    ///
+    /// # 返回
+    /// 如果格式有效则返回Some(TraceId)，否则返回None
+    #[inline]
+    pub fn from_string_validated_short(id: &str) -> Option<Self> {
+        if !Self::is_valid_short(id) {
+            return None;
+        }
+        Self::from_u64(u64::from_str_radix(id, 16).ok()?)
+    }
+
+    /// 判断字符串是否为有效的64位短追踪ID（16个小写十六进制字符且不全为零）
+    ///
+    /// # 参数
+    /// * `id` - 短追踪ID字符串
+    ///
+    /// # 返回
+    /// 有效时返回true
+    #[inline]
+    pub fn is_valid_short(id: &str) -> bool {
+        let id = id.as_bytes();
+        id.len() == 16
+            && id.iter().all(|&b| Self::is_valid_hex_byte(b))
+            && id.iter().any(|&b| b != b'0')
+    }
+
+    /// 从字符串创建追踪ID，不区分大小写
+    ///
+    /// 用于对接发送大写或大小写混合十六进制ID的外部系统：除大小写外，
+    /// 校验规则与 `from_string_validated` 相同，结果统一规范化为小写表示。
+    /// 严格遵循 W3C 规范的 `from_string_validated` 仍只接受小写。
+    ///
+    /// # 参数
+    /// * `id` - 追踪ID字符串（大写、小写或混合）
+    ///
+    /// # 返回
+    /// 如果格式有效则返回Some(TraceId)，否则返回None
+    #[inline]
+    pub fn from_string_validated_ci(id: &str) -> Option<Self> {
+        if id.len() != 32 || !id.bytes().all(|b| b.is_ascii_hexdigit()) {
             return None;
         }
 
-        Some(Self(id.to_string()))
+        let trace_id = Self::decode_hex(id.as_bytes());
+        (trace_id.to_u128() != 0).then_some(trace_id)
     }
 
     /// 高性能字节级十六进制字符验证
@@ -115,15 +1231,40 @@ impl TraceId {
     /// 使用字节比较避免Unicode处理开销
     ///
     /// # 参数
-    /// * `bytes` - 要验证的字节数组
+    /// * `b` - 要验证的字节
     ///
     /// # 返回
-    /// 如果所有字节都是小写十六进制字符则返回true
+    /// 如果是小写十六进制字符则返回true
     #[inline]
-    fn is_valid_hex_bytes(bytes: &[u8]) -> bool {
-        bytes
-            .iter()
-            .all(|&b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+    const fn is_valid_hex_byte(b: u8) -> bool {
+        matches!(b, b'0'..=b'9' | b'a'..=b'f')
+    }
+
+    /// 将十六进制字符解码为半字节
+    ///
+    /// 非十六进制字符按0处理
+    #[inline]
+    const fn decode_nibble(c: u8) -> u8 {
+        match c {
+            b'0'..=b'9' => c - b'0',
+            b'a'..=b'f' => c - b'a' + 10,
+            b'A'..=b'F' => c - b'A' + 10,
+            _ => 0,
+        }
+    }
+
+    /// 将最多32个十六进制字符解码为追踪ID
+    ///
+    /// 不足32个字符时低位补零，非十六进制字符按0处理
+    #[inline]
+    fn decode_hex(hex: &[u8]) -> Self {
+        let mut bytes = [0u8; 16];
+        for (i, pair) in hex.chunks(2).take(16).enumerate() {
+            let high = Self::decode_nibble(pair[0]);
+            let low = pair.get(1).map_or(0, |&c| Self::decode_nibble(c));
+            bytes[i] = (high << 4) | low;
+        }
+        Self::from_bytes(bytes)
     }
 
     /// 从字符串创建追踪ID（不进行校验，用于内部使用）
@@ -137,51 +1278,411 @@ impl TraceId {
     /// TraceId实例
     ///
     /// # Safety
-    /// 调用者需要确保输入字符串是有效的追踪ID格式
+    /// 调用者需要确保输入字符串是有效的追踪ID格式。
+    /// 对于无效输入，该函数不会panic，但得到的ID内容未定义
+    /// （非十六进制字符按0解码，超出32个字符的部分被忽略）
     ///
     /// # 性能优化
     /// - 内联函数减少调用开销
     /// - 跳过所有验证步骤
     #[inline]
     pub fn from_string_unchecked(id: &str) -> Self {
-        Self(id.to_string())
+        Self::decode_hex(id.as_bytes())
     }
 
     /// 从字符串创建追踪ID
     ///
     /// 仅用于测试，不进行格式验证。
-    #[cfg(test)]
+    #[cfg(all(test, feature = "std"))]
     pub(crate) fn from_string(id: &str) -> Self {
-        Self(id.to_string())
+        Self::from_string_unchecked(id)
+    }
+
+    /// 获取追踪ID字符串
+    ///
+    /// # 返回
+    /// 追踪ID的字符串表示
+    ///
+    /// # 性能优化
+    /// - 内联函数减少调用开销
+    /// - 直接返回缓存的十六进制表示，无需格式化或分配
+    ///
+    /// 十六进制格式化只在构造时（`from_bytes` 等）进行一次，开销为16字节的查表编码，
+    /// 之后的 `as_str`/`Display` 均复用结果。之所以在构造时而不是首次调用时格式化，
+    /// 是因为惰性缓存需要内部可变性，会使 `TraceId` 失去 `Copy` 与 `const fn` 构造，
+    /// 也无法在 `no_std` 下使用
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        // 十六进制缓存只由 `from_bytes` 写入，始终是合法的ASCII
+        core::str::from_utf8(self.hex_bytes()).unwrap_or_default()
+    }
+
+    /// 获取32字符的完整十六进制显示格式
+    ///
+    /// 对标准ID与 `as_str()` 相同；对64位短ID在左侧补零，
+    /// 用于 `traceparent` 等要求 W3C 格式的场景
+    ///
+    /// # 返回
+    /// 实现了 `Display` 的补零视图，不分配内存
+    #[inline]
+    pub fn padded(&self) -> PaddedTraceId<'_> {
+        PaddedTraceId(self)
+    }
+
+    /// 将32字符的十六进制表示写入调用方提供的缓冲区
+    ///
+    /// 内容与 `padded()` 相同：标准ID即 `as_str()`，64位短ID在左侧补零
+    ///
+    /// # 参数
+    /// * `buf` - 接收32个小写十六进制ASCII字符的缓冲区
+    ///
+    /// # 返回
+    /// 借用 `buf` 的追踪ID字符串
+    ///
+    /// # 示例
+    /// ```
+    /// use trace_id::TraceId;
+    ///
+    /// let trace_id = TraceId::from_string_validated("0af7651916cd43dd8448eb211c80319c").unwrap();
+    /// let mut buf = [0u8; 32];
+    /// assert_eq!(trace_id.write_hex(&mut buf), trace_id.as_str());
+    /// ```
+    #[inline]
+    pub fn write_hex<'a>(&self, buf: &'a mut [u8; 32]) -> &'a str {
+        // 短ID的高64位为零，缓存本身就是补零后的形式
+        *buf = self.hex;
+        core::str::from_utf8(buf).unwrap_or_default()
+    }
+
+    /// `as_str()` 对应的十六进制缓存片段（短ID只取低16个字符）
+    #[inline]
+    fn hex_bytes(&self) -> &[u8] {
+        if self.short {
+            &self.hex[16..]
+        } else {
+            &self.hex
+        }
+    }
+
+    /// 获取用于日志显示的缩写格式（末尾8个十六进制字符）
+    ///
+    /// 等价于 `truncated(8)`，只用于日志等展示场景，传播时必须使用完整的 `as_str()`。
+    /// 与64位短ID（`new_short`/`is_short`）无关，后者是完整的追踪ID
+    ///
+    /// # 返回
+    /// 追踪ID末尾的8个字符
+    #[inline]
+    pub fn abbreviated(&self) -> &str {
+        self.truncated(ABBREVIATED_LEN).as_str()
+    }
+
+    /// 获取截断为指定长度的显示格式
+    ///
+    /// 保留的是末尾而非开头的字符：本crate生成的ID高位为毫秒时间戳，
+    /// 相近时间生成的ID前缀几乎相同，末尾的随机部分才具有区分度。
+    /// 只用于日志等展示场景，传播时必须使用完整的 `as_str()`
+    ///
+    /// # 参数
+    /// * `len` - 保留的十六进制字符数，超过 `as_str()` 的长度时按完整长度处理
+    ///
+    /// # 返回
+    /// 实现了 `Display` 的截断视图，不分配内存
+    ///
+    /// # 示例
+    /// ```
+    /// use trace_id::TraceId;
+    ///
+    /// let trace_id = TraceId::from_string_validated("0af7651916cd43dd8448eb211c80319c").unwrap();
+    /// assert_eq!(trace_id.truncated(12).to_string(), "eb211c80319c");
+    /// assert_eq!(trace_id.abbreviated(), "1c80319c");
+    /// ```
+    #[inline]
+    pub fn truncated(&self, len: usize) -> TruncatedTraceId<'_> {
+        let hex = self.as_str();
+        TruncatedTraceId(&hex[hex.len() - len.min(hex.len())..])
+    }
+}
+
+/// 128 位 FNV-1a 哈希的初始值
+const FNV_OFFSET_BASIS: u128 = 0x6c62272e07bb014262b821756295c58d;
+
+/// 128 位 FNV-1a 哈希的乘数
+const FNV_PRIME: u128 = 0x0000000001000000000000000000013b;
+
+/// `abbreviated()` 保留的十六进制字符数
+const ABBREVIATED_LEN: usize = 8;
+
+/// 左侧补零到32字符的追踪ID显示格式，由 `TraceId::padded` 创建
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct PaddedTraceId<'a>(&'a TraceId);
+
+impl fmt::Display for PaddedTraceId<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // 短ID的高64位为零，缓存本身就是补零后的形式
+        f.write_str(core::str::from_utf8(&self.0.hex).unwrap_or_default())
+    }
+}
+
+impl fmt::Debug for PaddedTraceId<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("PaddedTraceId")
+            .field(&format_args!("{self}"))
+            .finish()
+    }
+}
+
+/// 截断的追踪ID显示格式，由 `TraceId::truncated` 创建
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct TruncatedTraceId<'a>(&'a str);
+
+impl<'a> TruncatedTraceId<'a> {
+    /// 获取截断后的字符串
+    #[inline]
+    pub fn as_str(&self) -> &'a str {
+        self.0
+    }
+}
+
+impl fmt::Display for TruncatedTraceId<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl fmt::Debug for TruncatedTraceId<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("TruncatedTraceId").field(&self.0).finish()
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl TraceId {
+    /// 使用随机的 v4 UUID 生成追踪ID
+    ///
+    /// 直接使用 UUID 的16字节（即去掉连字符的32个十六进制字符），结果总能通过校验。
+    /// 适用于要求追踪ID为 UUID 的系统；与 `new()` 不同，不包含时间戳与机器ID，也不保证进程内单调
+    ///
+    /// # 返回
+    /// 由 v4 UUID 构成的追踪ID
+    ///
+    /// # 示例
+    /// ```
+    /// use trace_id::TraceId;
+    ///
+    /// let trace_id = TraceId::new_uuid();
+    /// assert!(TraceId::is_valid(trace_id.as_str()));
+    /// ```
+    pub fn new_uuid() -> Self {
+        // v4 UUID 的版本位固定为4，不可能全为零
+        Self::from_bytes(uuid::Uuid::new_v4().into_bytes())
+    }
+}
+
+#[cfg(feature = "http")]
+impl TraceId {
+    /// 转换为HTTP头部值
+    ///
+    /// 直接使用缓存的ASCII十六进制表示构造 `HeaderValue`，
+    /// 跳过 `as_str().parse()` 的字符串解析路径，适合在自定义中间件中写入请求头或响应头
+    ///
+    /// # 返回
+    /// 内容与 `as_str()` 相同的头部值
+    #[inline]
+    pub fn to_header_value(&self) -> http::HeaderValue {
+        // 十六进制缓存始终是合法的可见ASCII，转换不会失败
+        http::HeaderValue::from_bytes(self.hex_bytes())
+            .unwrap_or_else(|_| http::HeaderValue::from_static(""))
+    }
+
+    /// 从HTTP头部值创建追踪ID，并进行 W3C TraceContext 规范校验
+    ///
+    /// 直接校验 `v.as_bytes()`，省去 `to_str()` 的UTF-8检查与中间的 `&str`：
+    /// 非ASCII字节不是小写十六进制字符，会在同一遍校验中被拒绝。
+    /// 校验规则与 `from_string_validated` 相同，值两侧的空白不会被去除
+    ///
+    /// # 参数
+    /// * `v` - HTTP头部值
+    ///
+    /// # 返回
+    /// 如果格式有效则返回Some(TraceId)，否则返回None
+    #[inline]
+    pub fn from_header_value(v: &http::HeaderValue) -> Option<Self> {
+        Self::parse_bytes(v.as_bytes()).ok()
+    }
+}
+
+impl fmt::Display for TraceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 小写十六进制格式化（`{:x}`），与 `Display` 相同
+impl fmt::LowerHex for TraceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 大写十六进制格式化（`{:X}`），用于要求大写的外部系统，不分配内存
+impl fmt::UpperHex for TraceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let upper = self.hex.map(|b| b.to_ascii_uppercase());
+        let upper = if self.short { &upper[16..] } else { &upper };
+        f.write_str(core::str::from_utf8(upper).unwrap_or_default())
+    }
+}
+
+impl fmt::Debug for TraceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("TraceId").field(&self.as_str()).finish()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for TraceId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 位宽与数值均相同时相等，与 `as_str()` 的字符串比较一致
+impl PartialEq for TraceId {
+    fn eq(&self, other: &Self) -> bool {
+        self.short == other.short && self.bytes == other.bytes
+    }
+}
+
+impl Eq for TraceId {}
+
+/// 与 `as_str()` 的哈希值相同，满足 `Borrow<str>` 的约定，
+/// `HashMap<TraceId, _>` 可以直接用 `&str` 查找
+impl Hash for TraceId {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
+/// 与 `as_str()` 的字典序一致：长度相同时直接按大端字节序比较，
+/// 短ID与标准ID之间按字符串比较
+impl Ord for TraceId {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        if self.short == other.short {
+            self.bytes.cmp(&other.bytes)
+        } else {
+            self.as_str().cmp(other.as_str())
+        }
+    }
+}
+
+impl PartialOrd for TraceId {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// 与字符串按 `as_str()` 比较，大写或带空白的字符串不相等
+impl PartialEq<str> for TraceId {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for TraceId {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<TraceId> for str {
+    fn eq(&self, other: &TraceId) -> bool {
+        self == other.as_str()
+    }
+}
+
+impl PartialEq<TraceId> for &str {
+    fn eq(&self, other: &TraceId) -> bool {
+        *self == other.as_str()
+    }
+}
+
+#[cfg(feature = "std")]
+impl PartialEq<String> for TraceId {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
+}
+
+#[cfg(feature = "std")]
+impl PartialEq<TraceId> for String {
+    fn eq(&self, other: &TraceId) -> bool {
+        self == other.as_str()
+    }
+}
+
+impl AsRef<str> for TraceId {
+    fn as_ref(&self) -> &str {
+        self.as_str()
     }
+}
 
-    /// 获取追踪ID字符串
-    ///
-    /// # 返回
-    /// 追踪ID的字符串表示
-    ///
-    /// # 性能优化
-    /// - 内联函数减少调用开销
-    /// - 直接返回内部字符串引用
-    #[inline]
-    pub fn as_str(&self) -> &str {
-        &self.0
+/// `Eq`、`Ord` 与 `Hash` 均与 `as_str()` 一致，可以用 `&str` 在以 `TraceId` 为键的集合中查找
+impl Borrow<str> for TraceId {
+    fn borrow(&self) -> &str {
+        self.as_str()
     }
 }
 
-impl fmt::Display for TraceId {
+/// 追踪ID解析错误
+///
+/// 描述字符串不符合 W3C TraceContext trace-id 规范的具体原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceIdParseError {
+    /// 长度不是32个字符，携带实际长度（字节数）
+    WrongLength(usize),
+    /// 包含非小写十六进制字符，携带首个非法字符的字节位置
+    InvalidChar(usize),
+    /// 全为零（W3C 规范中的无效ID，需要"无追踪"标记时使用 `TraceId::NIL`）
+    AllZeros,
+}
+
+impl fmt::Display for TraceIdParseError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.0)
+        match self {
+            Self::WrongLength(len) => {
+                write!(f, "trace id must be 32 characters long, got {len}")
+            }
+            Self::InvalidChar(index) => write!(
+                f,
+                "trace id contains a non lowercase hex character at position {index}"
+            ),
+            Self::AllZeros => f.write_str("trace id must not be all zeros"),
+        }
     }
 }
 
-impl Default for TraceId {
-    fn default() -> Self {
-        Self::new()
+impl core::error::Error for TraceIdParseError {}
+
+impl FromStr for TraceId {
+    type Err = TraceIdParseError;
+
+    /// 从字符串解析追踪ID，并进行 W3C TraceContext 规范校验
+    ///
+    /// 校验规则与 `from_string_validated` 一致，失败时返回具体原因
+    fn from_str(id: &str) -> Result<Self, Self::Err> {
+        Self::parse_bytes(id.as_bytes())
+    }
+}
+
+impl TryFrom<&str> for TraceId {
+    type Error = TraceIdParseError;
+
+    fn try_from(id: &str) -> Result<Self, Self::Error> {
+        id.parse()
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use super::*;
 
@@ -222,13 +1723,34 @@ mod tests {
         assert!(debug_str.contains("0af7651916cd43dd8448eb211c80319c"));
     }
 
+    #[test]
+    fn test_nil() {
+        const NIL: TraceId = TraceId::empty();
+        assert_eq!(NIL, TraceId::NIL);
+        assert!(NIL.is_nil());
+        assert_eq!(NIL.to_u128(), 0);
+        assert_eq!(NIL.to_string(), "0".repeat(32));
+
+        // 解析不受影响：不可信输入中的全零ID仍被拒绝
+        assert_eq!(
+            NIL.as_str().parse::<TraceId>(),
+            Err(TraceIdParseError::AllZeros)
+        );
+        assert_eq!(TraceId::from_bytes([0; 16]), NIL);
+
+        // 有效ID与短ID都不是标记ID
+        assert!(!TraceId::new().is_nil());
+        assert!(!TraceId::new_short().is_nil());
+        assert!(!TraceId::from_u128(1).unwrap().is_nil());
+    }
+
     #[test]
     fn test_from_string_validated() {
         // Valid case: 符合 W3C TraceContext 规范的 trace-id
         let valid_id = "0af7651916cd43dd8448eb211c80319c";
         assert_eq!(
             TraceId::from_string_validated(valid_id),
-            Some(TraceId(valid_id.to_string()))
+            Some(TraceId::from_string(valid_id))
         );
 
         // Invalid case: 长度不正确
@@ -272,20 +1794,20 @@ mod tests {
 
     #[test]
     fn test_w3c_traceparent_compatibility() {
-        // 测试生成的ID是否可以用于构造 W3C traceparent header
+        // 生成的ID可以直接用于构造 W3C traceparent header
         let trace_id = TraceId::new();
-        let parent_id = "b7ad6b7169203331";
-        let trace_flags = "01";
+        let ctx = crate::TraceContext {
+            trace_id,
+            parent_id: crate::SpanId::from_string_validated("b7ad6b7169203331").unwrap(),
+            flags: 0x01,
+        };
 
-        let traceparent = format!("00-{}-{}-{}", trace_id.as_str(), parent_id, trace_flags);
-
-        // 验证 traceparent 格式
-        let parts: Vec<&str> = traceparent.split('-').collect();
-        assert_eq!(parts.len(), 4);
-        assert_eq!(parts[0], "00"); // version
-        assert_eq!(parts[1].len(), 32); // trace-id
-        assert_eq!(parts[2].len(), 16); // parent-id
-        assert_eq!(parts[3].len(), 2); // trace-flags
+        let traceparent = ctx.to_traceparent();
+        assert_eq!(traceparent, format!("00-{trace_id}-b7ad6b7169203331-01"));
+        assert_eq!(
+            crate::TraceContext::from_traceparent(&traceparent),
+            Some(ctx)
+        );
     }
 
     #[test]
@@ -297,17 +1819,681 @@ mod tests {
 
         // 测试 Clone 和 PartialEq traits
         let id1 = TraceId::new();
-        let id2 = id1.clone();
+        let id2 = id1;
         let id3 = TraceId::new();
         assert_eq!(id1, id2, "Cloned ID should be equal to the original");
         assert_ne!(id1, id3, "Different IDs should not be equal");
 
         // 测试 from_string_unchecked
-        // 这个函数应该不进行任何验证，直接创建实例
+        // 这个函数应该不进行任何验证，直接创建实例（不会panic）
         let invalid_str = "this-is-not-a-valid-id";
         let unchecked_id = TraceId::from_string_unchecked(invalid_str);
-        assert_eq!(unchecked_id.as_str(), invalid_str);
+        assert_eq!(unchecked_id.as_str().len(), 32);
+        assert_ne!(unchecked_id.as_str(), invalid_str);
         // 确认 from_string_validated 会拒绝这个ID
         assert!(TraceId::from_string_validated(invalid_str).is_none());
+
+        // 有效输入经 from_string_unchecked 后保持不变
+        let valid_str = "0af7651916cd43dd8448eb211c80319c";
+        assert_eq!(
+            TraceId::from_string_unchecked(valid_str).as_str(),
+            valid_str
+        );
+    }
+
+    #[test]
+    fn test_parse_error_reasons() {
+        let valid_id = "0af7651916cd43dd8448eb211c80319c";
+        assert_eq!(
+            valid_id.parse::<TraceId>(),
+            Ok(TraceId::from_string(valid_id))
+        );
+        assert_eq!(
+            TraceId::try_from(valid_id),
+            Ok(TraceId::from_string(valid_id))
+        );
+
+        assert_eq!(
+            "short".parse::<TraceId>(),
+            Err(TraceIdParseError::WrongLength(5))
+        );
+        assert_eq!(
+            "0af7651916cd43dd8448eb211c80319g".parse::<TraceId>(),
+            Err(TraceIdParseError::InvalidChar(31))
+        );
+        assert_eq!(
+            TraceId::try_from("0AF7651916cd43dd8448eb211c80319c"),
+            Err(TraceIdParseError::InvalidChar(1))
+        );
+        assert_eq!(
+            "00000000000000000000000000000000".parse::<TraceId>(),
+            Err(TraceIdParseError::AllZeros)
+        );
+
+        // 错误信息可读，且实现了 std::error::Error
+        let err: Box<dyn std::error::Error> = Box::new(TraceIdParseError::WrongLength(5));
+        assert_eq!(
+            err.to_string(),
+            "trace id must be 32 characters long, got 5"
+        );
+    }
+
+    #[test]
+    fn test_bytes_roundtrip() {
+        let trace_id = TraceId::from_string("0af7651916cd43dd8448eb211c80319c");
+        assert_eq!(
+            trace_id.as_bytes(),
+            &[
+                0x0a, 0xf7, 0x65, 0x19, 0x16, 0xcd, 0x43, 0xdd, 0x84, 0x48, 0xeb, 0x21, 0x1c, 0x80,
+                0x31, 0x9c
+            ]
+        );
+
+        let restored = TraceId::from_bytes(*trace_id.as_bytes());
+        assert_eq!(restored, trace_id);
+        assert_eq!(restored.as_str(), "0af7651916cd43dd8448eb211c80319c");
+
+        // 新生成的ID同样可以通过字节往返
+        let generated = TraceId::new();
+        assert_eq!(TraceId::from_bytes(*generated.as_bytes()), generated);
+    }
+
+    #[test]
+    fn test_hash_and_ord() {
+        use std::collections::{BTreeMap, HashMap};
+
+        let ids = [
+            "ffffffffffffffffffffffffffffffff",
+            "0af7651916cd43dd8448eb211c80319c",
+            "0af7651916cd43dd8448eb211c80319d",
+            "a0000000000000000000000000000000",
+            "00000000000000000000000000000001",
+        ];
+
+        // 排序结果与十六进制字符串的字典序一致
+        let mut sorted_ids: Vec<TraceId> = ids.iter().map(|id| TraceId::from_string(id)).collect();
+        sorted_ids.sort();
+        let mut sorted_strs = ids.to_vec();
+        sorted_strs.sort();
+        let as_strs: Vec<&str> = sorted_ids.iter().map(|id| id.as_str()).collect();
+        assert_eq!(as_strs, sorted_strs);
+
+        let mut ordered = BTreeMap::new();
+        let mut in_flight = HashMap::new();
+        for (i, id) in ids.iter().enumerate() {
+            ordered.insert(TraceId::from_string(id), i);
+            in_flight.insert(TraceId::from_string(id), i);
+        }
+        assert_eq!(
+            ordered.keys().next().unwrap().as_str(),
+            "00000000000000000000000000000001"
+        );
+        assert_eq!(
+            in_flight.get(&TraceId::from_string("0af7651916cd43dd8448eb211c80319c")),
+            Some(&1)
+        );
+
+        // 通过 `Borrow<str>` 直接用字符串查找
+        assert_eq!(in_flight.get("0af7651916cd43dd8448eb211c80319c"), Some(&1));
+        assert_eq!(ordered.get("a0000000000000000000000000000000"), Some(&3));
+    }
+
+    #[test]
+    fn test_from_opaque() {
+        // 有效ID原样使用
+        let id = "0af7651916cd43dd8448eb211c80319c";
+        assert_eq!(TraceId::from_opaque(id), id);
+
+        // UUID去掉连字符
+        assert_eq!(
+            TraceId::from_opaque("0af76519-16cd-43dd-8448-eb211c80319c"),
+            id
+        );
+
+        // 其他输入确定性地哈希
+        let ulid = "01ARZ3NDEKTSV4RRFFQ69G5FAV";
+        let trace_id = TraceId::from_opaque(ulid);
+        assert!(TraceId::is_valid(trace_id.as_str()));
+        assert_eq!(trace_id, TraceId::from_seed(ulid.as_bytes()));
+        assert_ne!(trace_id, TraceId::from_opaque("01ARZ3NDEKTSV4RRFFQ69G5FAW"));
+
+        // 格式不完整的UUID与空字符串同样按哈希处理
+        let malformed = "0af76519-16cd-43dd-8448-eb211c80319z";
+        assert_eq!(
+            TraceId::from_opaque(malformed),
+            TraceId::from_seed(malformed.as_bytes())
+        );
+        assert!(TraceId::is_valid(TraceId::from_opaque("").as_str()));
+    }
+
+    #[test]
+    fn test_str_comparisons() {
+        let id = "0af7651916cd43dd8448eb211c80319c";
+        let trace_id = TraceId::from_string_validated(id).unwrap();
+
+        assert_eq!(trace_id, id);
+        assert_eq!(trace_id, *id);
+        assert_eq!(trace_id, id.to_string());
+        assert_eq!(id, trace_id);
+        assert_eq!(id.to_string(), trace_id);
+        assert_ne!(trace_id, id.to_uppercase());
+
+        fn len(s: impl AsRef<str>) -> usize {
+            s.as_ref().len()
+        }
+        assert_eq!(len(trace_id), 32);
+
+        let mut by_header: std::collections::HashMap<String, u32> = Default::default();
+        by_header.insert(id.to_string(), 7);
+        assert_eq!(by_header.get(Borrow::<str>::borrow(&trace_id)), Some(&7));
+    }
+
+    #[test]
+    fn test_from_static_hex() {
+        const FIXTURE: TraceId = TraceId::from_static_hex("0af7651916cd43dd8448eb211c80319c");
+        static BYTES: TraceId = TraceId::from_bytes([0xab; 16]);
+
+        assert_eq!(
+            FIXTURE,
+            TraceId::from_string_validated("0af7651916cd43dd8448eb211c80319c").unwrap()
+        );
+        assert_eq!(BYTES.as_str(), "ab".repeat(16));
+
+        // 运行时调用同样校验
+        for invalid in [
+            "0af7651916cd43dd",
+            "0AF7651916CD43DD8448EB211C80319C",
+            "00000000000000000000000000000000",
+        ] {
+            assert!(std::panic::catch_unwind(|| TraceId::from_static_hex(invalid)).is_err());
+        }
+    }
+
+    #[test]
+    fn test_u128_roundtrip() {
+        let trace_id = TraceId::from_string("0af7651916cd43dd8448eb211c80319c");
+        assert_eq!(trace_id.to_u128(), 0x0af7651916cd43dd8448eb211c80319c);
+        assert_eq!(TraceId::from_u128(trace_id.to_u128()), Some(trace_id));
+
+        // 与十六进制格式化保持一致
+        let generated = TraceId::new();
+        assert_eq!(format!("{:032x}", generated.to_u128()), generated.as_str());
+
+        assert_eq!(
+            TraceId::from_u128(1).unwrap().as_str(),
+            "00000000000000000000000000000001"
+        );
+        assert!(TraceId::from_u128(0).is_none(), "零值应被拒绝");
+    }
+
+    #[test]
+    fn test_new_with_clock() {
+        let timestamp = |id: TraceId| (id.to_u128() >> 80) as u64;
+
+        // 时间戳取自传入的时钟；读数不超前于当前时间，不会把共享的分片状态推到未来
+        let now = SystemClock.now_millis();
+        let trace_id = TraceId::new_with_clock(&|| now);
+        assert!(timestamp(trace_id) >= now);
+
+        // 超前或回拨的时钟读数只驱动独立的分片状态，不影响同一测试二进制中的其他生成
+        let shard = ShardState::new();
+        let millis = now + 1_000_000;
+        let generate = |millis| TraceId::compose(reserve_state(&shard, millis, 1), 1, 0, 42);
+
+        // 时间戳位于高48位（时钟超前于此前生成的所有ID时原样使用）
+        let trace_id = generate(millis);
+        assert_eq!(timestamp(trace_id), millis);
+        assert!(trace_id
+            .as_str()
+            .starts_with(&format!("{millis:012x}")[..8]));
+
+        // 跨越毫秒边界时时间戳递增，排序随之递增
+        let later = generate(millis + 1_000);
+        assert_eq!(timestamp(later), millis + 1_000);
+        assert!(later > trace_id);
+
+        // 时钟回拨时时间戳不会倒退
+        let skewed = generate(now);
+        assert_eq!(timestamp(skewed), timestamp(later));
+        assert!(skewed > later);
+    }
+
+    #[test]
+    fn test_generation_stats() {
+        // 在独立的分片状态上推进时钟，再以更早的读数生成，触发时钟回拨计数
+        let shard = ShardState::new();
+        let now = SystemClock.now_millis();
+        let stats = GenerationStats::collect(&shard, core::slice::from_ref(&shard));
+        assert_eq!(stats.last_timestamp_millis, 0);
+        assert_eq!(stats.state_timestamp_millis, 0);
+
+        reserve_state(&shard, now, 1);
+        reserve_state(&shard, now, 1);
+        let stats = GenerationStats::collect(&shard, core::slice::from_ref(&shard));
+        assert_eq!(stats.last_timestamp_millis, now);
+        assert_eq!(stats.state_timestamp_millis, now);
+        assert_eq!(stats.counter, 1);
+
+        reserve_state(&shard, now - 1_000, 1);
+        let after = GenerationStats::collect(&shard, core::slice::from_ref(&shard));
+        assert!(after.clock_regressions > stats.clock_regressions);
+        // 回拨期间时间戳沿用此前的状态，生成器超前于时钟读数
+        assert_eq!(after.last_timestamp_millis, now);
+        assert_eq!(after.state_timestamp_millis, now);
+        assert_eq!(after.counter, 2);
+
+        // 全局统计覆盖内置方案的生成
+        let trace_id = TraceId::new_with_clock(&SystemClock);
+        let global = TraceId::generation_stats();
+        assert!(global.last_timestamp_millis >= (trace_id.to_u128() >> 80) as u64);
+    }
+
+    #[test]
+    fn test_state_advance() {
+        let millis = 0x0123_4567_89ab_u64;
+
+        // 新的毫秒从序列号0开始
+        assert_eq!(advance_state(0, millis, 1), (millis << 16, millis << 16));
+
+        // 同一毫秒内序列号递增
+        let last = (millis << 16) | 7;
+        assert_eq!(advance_state(last, millis, 1).0, last + 1);
+
+        // 序列号溢出时进位到下一毫秒
+        let last = (millis << 16) | 0xFFFF;
+        assert_eq!(advance_state(last, millis, 1).0, (millis + 1) << 16);
+
+        // 时钟回拨时沿用上次的状态继续递增
+        assert_eq!(advance_state(last, millis - 5, 1).0, last + 1);
+
+        // 批量预留连续区间
+        assert_eq!(
+            advance_state(0, millis, 10),
+            (millis << 16, (millis << 16) + 9)
+        );
+
+        // 超出48位的时间戳被截断
+        assert_eq!(advance_state(0, (1 << 48) | millis, 1).0, millis << 16);
+    }
+
+    #[test]
+    fn test_machine_id_configuration() {
+        assert_eq!(parse_machine_id(Some("42")), Some(42));
+        assert_eq!(parse_machine_id(Some(" 65535 ")), Some(65535));
+        assert_eq!(parse_machine_id(Some("65536")), None);
+        assert_eq!(parse_machine_id(Some("pod-1")), None);
+        assert_eq!(parse_machine_id(None), None);
+
+        // 生成追踪ID后机器ID已确定，不能再修改
+        let trace_id = TraceId::new();
+        assert!(!set_machine_id(0));
+        let machine_id = u16::from_be_bytes([trace_id.as_bytes()[6], trace_id.as_bytes()[7]]);
+        assert_eq!(machine_id, TraceId::get_machine_id());
+    }
+
+    #[test]
+    fn test_new_with_rng() {
+        // 随机部分位于最低44位，超出部分被忽略，其上4位为分片编号
+        let trace_id = TraceId::new_with_rng(&|| 0xffff_0000_dead_beef);
+        assert_eq!(trace_id.to_u128() as u64 & RANDOM_MASK, 0xdead_beef);
+        assert!(trace_id.as_str().ends_with("000deadbeef"));
+        assert_eq!(trace_id.shard() as usize, current_shard());
+
+        // 序列号保证相同随机数下的ID仍然唯一
+        let other = TraceId::new_with_rng(&|| 0xffff_0000_dead_beef);
+        assert_ne!(trace_id, other);
+    }
+
+    #[test]
+    fn test_sharded_generation() {
+        let per_thread = 1_000;
+        let handles: Vec<_> = (0..SHARD_COUNT * 2)
+            .map(|_| {
+                std::thread::spawn(move || {
+                    (0..per_thread).map(|_| TraceId::new()).collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        let mut all = std::collections::HashSet::new();
+        for handle in handles {
+            let ids = handle.join().unwrap();
+
+            // 同一线程固定使用一个分片，分片内按生成顺序严格递增
+            let shard = ids[0].shard();
+            assert!((shard as usize) < SHARD_COUNT);
+            assert!(ids.iter().all(|id| id.shard() == shard));
+            assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+
+            all.extend(ids);
+        }
+
+        // 线程数超过分片数时共享分片，所有ID仍互不重复
+        assert_eq!(all.len(), SHARD_COUNT * 2 * per_thread);
+    }
+
+    #[test]
+    fn test_new_sortable() {
+        // 跨越毫秒边界持续生成，字符串顺序与生成顺序一致
+        let first = TraceId::new_sortable();
+        let mut ids = vec![first];
+        while ids.last().unwrap().timestamp_millis() < first.timestamp_millis().map(|t| t + 2) {
+            ids.push(TraceId::new_sortable());
+        }
+        assert!(ids
+            .windows(2)
+            .all(|pair| pair[0].as_str() < pair[1].as_str()));
+
+        // 多线程交替生成时同样按生成顺序递增（生成与记录在同一把锁内完成）
+        let ordered = std::sync::Mutex::new(Vec::new());
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..1_000 {
+                        let mut ordered = ordered.lock().unwrap();
+                        ordered.push(TraceId::new_sortable());
+                    }
+                });
+            }
+        });
+        let ordered = ordered.into_inner().unwrap();
+        assert!(ordered
+            .windows(2)
+            .all(|pair| pair[0].as_str() < pair[1].as_str()));
+        assert!(ordered.iter().all(|id| id.shard() == SORTABLE_SHARD as u8));
+    }
+
+    #[test]
+    fn test_new_random() {
+        let ids: Vec<_> = (0..1_000).map(|_| TraceId::new_random()).collect();
+        assert!(ids.iter().all(|id| TraceId::is_valid(id.as_str())));
+
+        let unique: std::collections::HashSet<_> = ids.iter().collect();
+        assert_eq!(unique.len(), ids.len());
+
+        // 没有固定的机器ID与时间戳前缀
+        let machine_ids: std::collections::HashSet<_> =
+            ids.iter().map(|id| id.machine_id()).collect();
+        assert!(machine_ids.len() > 1);
+        let prefixes: std::collections::HashSet<_> =
+            ids.iter().map(|id| id.to_u128() >> 80).collect();
+        assert!(prefixes.len() > 1);
+    }
+
+    #[test]
+    fn test_new_batch() {
+        let batch = TraceId::new_batch(10_000);
+        assert_eq!(batch.len(), 10_000);
+        assert!(TraceId::new_batch(0).is_empty());
+
+        // 批内ID互不重复，且格式与 new() 相同
+        let unique: std::collections::HashSet<_> = batch.iter().collect();
+        assert_eq!(unique.len(), batch.len());
+        for trace_id in &batch {
+            assert!(TraceId::from_string_validated(trace_id.as_str()).is_some());
+        }
+
+        // 整批共享时间戳与机器ID（高64位）
+        let high = batch[0].to_u128() >> 64;
+        assert!(batch.iter().all(|id| id.to_u128() >> 64 == high));
+    }
+
+    #[test]
+    fn test_new_from_timestamp() {
+        let timestamp = |id: TraceId| (id.to_u128() >> 80) as u64;
+
+        // 历史时间戳原样嵌入高48位，不受实时生成状态影响
+        let millis = 1_262_304_000_000; // 2010-01-01
+        TraceId::new();
+        let trace_id = TraceId::new_from_timestamp(millis);
+        assert_eq!(timestamp(trace_id), millis);
+        assert!(trace_id.as_str().starts_with(&format!("{millis:012x}")));
+
+        // 同一时间戳下的ID互不相同
+        let other = TraceId::new_from_timestamp(millis);
+        assert_eq!(timestamp(other), millis);
+        assert_ne!(trace_id, other);
+
+        // 48位上限可用
+        assert_eq!(
+            timestamp(TraceId::new_from_timestamp(TIMESTAMP_MASK)),
+            TIMESTAMP_MASK
+        );
+
+        // 超出48位时panic
+        assert!(std::panic::catch_unwind(|| TraceId::new_from_timestamp(1 << 48)).is_err());
+    }
+
+    #[test]
+    fn test_introspection() {
+        let millis = SystemClock.now_millis();
+        let trace_id = TraceId::new();
+        assert!(trace_id.timestamp_millis().unwrap() >= millis);
+        assert_eq!(trace_id.machine_id(), TraceId::get_machine_id());
+
+        // 回填ID的各部分可以原样取回
+        let backfilled = TraceId::new_from_timestamp(1_262_304_000_000);
+        assert_eq!(backfilled.timestamp_millis(), Some(1_262_304_000_000));
+
+        let value = 0x0123_4567_89ab_cdef_1234_5678_9abc_def0_u128;
+        let trace_id = TraceId::from_u128(value).unwrap();
+        assert_eq!(trace_id.machine_id(), 0xcdef);
+        assert_eq!(trace_id.counter(), 0x1234);
+        assert_eq!(trace_id.timestamp_millis(), Some(0x0123_4567_89ab));
+
+        // 高位随机的外部ID（时间戳远在未来）与时间戳为0的ID返回None
+        let external = TraceId::from_string_validated("fedcba0987654321fedcba0987654321").unwrap();
+        assert_eq!(external.timestamp_millis(), None);
+        let zero_timestamp = TraceId::from_u128(1).unwrap();
+        assert_eq!(zero_timestamp.timestamp_millis(), None);
+    }
+
+    #[test]
+    fn test_is_valid() {
+        assert!(TraceId::is_valid("0af7651916cd43dd8448eb211c80319c"));
+        assert!(TraceId::is_valid_bytes(b"0af7651916cd43dd8448eb211c80319c"));
+
+        for invalid in [
+            "",
+            "0af7651916cd43dd8448eb211c80319",
+            "0af7651916cd43dd8448eb211c80319c0",
+            "0AF7651916CD43DD8448EB211C80319C",
+            "0af7651916cd43dd8448eb211c80319g",
+            "00000000000000000000000000000000",
+        ] {
+            assert!(!TraceId::is_valid(invalid), "应拒绝: {invalid}");
+            assert_eq!(
+                TraceId::is_valid(invalid),
+                TraceId::from_string_validated(invalid).is_some()
+            );
+        }
+        assert!(!TraceId::is_valid_bytes(
+            b"0af7651916cd43dd8448eb211c80319\xff"
+        ));
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn test_new_uuid() {
+        let trace_id = TraceId::new_uuid();
+        assert_eq!(
+            TraceId::from_string_validated(trace_id.as_str()),
+            Some(trace_id)
+        );
+
+        // 与 UUID 的字节及去掉连字符的字符串表示一致
+        let uuid = uuid::Uuid::from_bytes(*trace_id.as_bytes());
+        assert_eq!(uuid.get_version_num(), 4);
+        assert_eq!(uuid.simple().to_string(), trace_id.as_str());
+        assert_ne!(TraceId::new_uuid(), trace_id);
+    }
+
+    #[test]
+    fn test_from_seed() {
+        // 标准 FNV-1a 128 测试向量，保证跨版本、跨平台稳定
+        assert_eq!(
+            TraceId::from_seed(b"").as_str(),
+            "6c62272e07bb014262b821756295c58d"
+        );
+        assert_eq!(
+            TraceId::from_seed(b"a").as_str(),
+            "d228cb696f1a8caf78912b704e4a8964"
+        );
+
+        let trace_id = TraceId::from_seed(b"batch-job-42");
+        assert_eq!(trace_id, TraceId::from_seed(b"batch-job-42"));
+        assert_ne!(trace_id, TraceId::from_seed(b"batch-job-43"));
+        assert_eq!(
+            TraceId::from_string_validated(trace_id.as_str()),
+            Some(trace_id)
+        );
+    }
+
+    #[test]
+    fn test_abbreviated_and_truncated() {
+        let trace_id = TraceId::from_string_validated("0af7651916cd43dd8448eb211c80319c").unwrap();
+        assert_eq!(trace_id.abbreviated(), "1c80319c");
+        assert_eq!(trace_id.truncated(4).as_str(), "319c");
+        assert_eq!(format!("{}", trace_id.truncated(0)), "");
+        assert_eq!(trace_id.truncated(64).as_str(), trace_id.as_str());
+
+        // 同一时刻生成的ID前缀相同，缩写格式仍可区分
+        let a = TraceId::new();
+        let b = TraceId::new();
+        assert_ne!(a.abbreviated(), b.abbreviated());
+
+        // 截断只影响显示，ID本身保持完整
+        assert_eq!(trace_id.as_str().len(), 32);
+    }
+
+    #[test]
+    fn test_short_ids() {
+        let trace_id = TraceId::from_string_validated_short("8448eb211c80319c").unwrap();
+        assert!(trace_id.is_short());
+        assert_eq!(trace_id.as_str(), "8448eb211c80319c");
+        assert_eq!(
+            trace_id.padded().to_string(),
+            "00000000000000008448eb211c80319c"
+        );
+        assert_eq!(trace_id.to_u128(), 0x8448eb211c80319c);
+        assert_eq!(format!("{trace_id:X}"), "8448EB211C80319C");
+        assert_eq!(trace_id.abbreviated(), "1c80319c");
+        assert_eq!(trace_id.truncated(64).as_str(), "8448eb211c80319c");
+
+        // W3C 严格校验拒绝短ID
+        assert!(!TraceId::is_valid("8448eb211c80319c"));
+        assert_eq!(
+            "8448eb211c80319c".parse::<TraceId>(),
+            Err(TraceIdParseError::WrongLength(16))
+        );
+
+        // 短ID校验
+        assert!(TraceId::is_valid_short("8448eb211c80319c"));
+        assert!(!TraceId::is_valid_short("0000000000000000"));
+        assert!(!TraceId::is_valid_short("8448EB211C80319C"));
+        assert!(!TraceId::is_valid_short("0af7651916cd43dd8448eb211c80319c"));
+
+        // 与补零后的标准ID不相等
+        let full = TraceId::from_string_validated(&trace_id.padded().to_string()).unwrap();
+        assert!(!full.is_short());
+        assert_ne!(full, trace_id);
+        assert_eq!(full.as_bytes(), trace_id.as_bytes());
+        assert!(full < trace_id);
+
+        // 位宽参与 Eq/Hash，短ID通过 to_u64/from_u64 往返
+        assert_ne!(TraceId::from_u64(0x1234), TraceId::from_u128(0x1234));
+        assert_eq!(TraceId::from_u128(trace_id.to_u128()), Some(full));
+        assert_eq!(
+            TraceId::from_u64(trace_id.to_u64().unwrap()),
+            Some(trace_id)
+        );
+        assert_eq!(full.to_u64(), None);
+        let set: std::collections::HashSet<TraceId> = [trace_id, full].into();
+        assert_eq!(set.len(), 2);
+        assert!(set.contains("8448eb211c80319c"));
+        assert!(!trace_id.is_nil());
+
+        let generated = TraceId::new_short();
+        assert_eq!(generated.as_str().len(), 16);
+        assert_ne!(generated, TraceId::new_short());
+    }
+
+    #[test]
+    fn test_write_hex() {
+        let mut buf = [0u8; 32];
+
+        let trace_id = TraceId::from_string_validated("0af7651916cd43dd8448eb211c80319c").unwrap();
+        assert_eq!(trace_id.write_hex(&mut buf), trace_id.as_str());
+
+        // 短ID写入补零后的形式
+        let short = TraceId::from_string_validated_short("8448eb211c80319c").unwrap();
+        assert_eq!(short.write_hex(&mut buf), short.padded().to_string());
+
+        // 生成的ID写入缓冲区后可以解析回来，且与之后生成的ID不同
+        let generated = TraceId::from_string_validated(TraceId::generate_into(&mut buf)).unwrap();
+        assert_eq!(&buf, generated.as_str().as_bytes());
+        assert_ne!(TraceId::generate_into(&mut buf), generated.as_str());
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        let lower = "0af7651916cd43dd8448eb211c80319c";
+        let upper = "0AF7651916CD43DD8448EB211C80319C";
+        let mixed = "0Af7651916cD43dd8448Eb211c80319C";
+
+        // 严格校验仍只接受小写
+        assert!(TraceId::from_string_validated(upper).is_none());
+
+        for id in [lower, upper, mixed] {
+            let trace_id = TraceId::from_string_validated_ci(id).unwrap();
+            assert_eq!(trace_id.as_str(), lower);
+        }
+        assert!(TraceId::from_string_validated_ci("0AF7651916CD43DD8448EB211C80319G").is_none());
+        assert!(TraceId::from_string_validated_ci("0AF7").is_none());
+        assert!(TraceId::from_string_validated_ci(&"0".repeat(32)).is_none());
+
+        let trace_id = TraceId::from_string_validated(lower).unwrap();
+        assert_eq!(trace_id.as_uppercase_hex(), upper);
+        assert_eq!(format!("{trace_id:X}"), upper);
+        assert_eq!(format!("{trace_id:x}"), lower);
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_to_header_value() {
+        let trace_id = TraceId::new();
+        let header_value = trace_id.to_header_value();
+        assert_eq!(header_value.to_str().unwrap(), trace_id.as_str());
+        assert_eq!(
+            header_value,
+            trace_id.as_str().parse::<http::HeaderValue>().unwrap()
+        );
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_from_header_value() {
+        let trace_id = TraceId::new();
+        assert_eq!(
+            TraceId::from_header_value(&trace_id.to_header_value()),
+            Some(trace_id)
+        );
+
+        // 与字符串路径的校验规则一致
+        for value in [
+            "0AF7651916CD43DD8448EB211C80319C",
+            "00000000000000000000000000000000",
+            " 0af7651916cd43dd8448eb211c80319c",
+            "0af7651916cd43dd",
+        ] {
+            let header_value = http::HeaderValue::from_static(value);
+            assert_eq!(TraceId::from_header_value(&header_value), None);
+        }
+
+        // 非ASCII字节无需UTF-8检查即被拒绝
+        let mut raw = *b"0af7651916cd43dd8448eb211c80319c";
+        raw[5] = 0xff;
+        let header_value = http::HeaderValue::from_bytes(&raw).unwrap();
+        assert_eq!(TraceId::from_header_value(&header_value), None);
     }
 }
@@ -2,6 +2,8 @@
 //!
 //! 验证 trace_id 模块在高并发场景下的线程安全性和稳定性
 
+#![cfg(feature = "std")]
+
 use std::collections::HashSet;
 use std::sync::{Arc, Mutex};
 use std::thread;
@@ -67,8 +69,8 @@ async fn test_concurrent_context_management() {
             let expected_trace_id_str = trace_id.as_str().to_string();
 
             // 在独立的上下文中执行
-            let result = with_trace_id(trace_id.clone(), async move {
-                let trace_id_clone = trace_id.clone();
+            let result = with_trace_id(trace_id, async move {
+                let trace_id_clone = trace_id;
 
                 // 验证上下文中的ID正确性
                 let current_id = get_trace_id();
@@ -230,3 +232,35 @@ fn test_atomic_counter_thread_safety() {
     // 验证生成的ID总数
     assert_eq!(all_ids.len(), THREAD_COUNT * IDS_PER_THREAD);
 }
+
+/// 压力测试：多线程紧密循环生成数百万个ID，验证零碰撞
+///
+/// 同一毫秒内生成的ID远超序列号容量（65536），覆盖序列号进位路径
+#[test]
+fn test_millions_of_ids_without_collision() {
+    const THREAD_COUNT: usize = 4;
+    const IDS_PER_THREAD: usize = 500_000;
+
+    let handles: Vec<_> = (0..THREAD_COUNT)
+        .map(|_| {
+            thread::spawn(|| {
+                (0..IDS_PER_THREAD)
+                    .map(|_| TraceId::new())
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect();
+
+    let mut all_ids = HashSet::with_capacity(THREAD_COUNT * IDS_PER_THREAD);
+    for handle in handles {
+        for id in handle.join().unwrap() {
+            assert!(all_ids.insert(id), "发现重复的trace_id: {id}");
+        }
+    }
+    assert_eq!(all_ids.len(), THREAD_COUNT * IDS_PER_THREAD);
+
+    // 批量生成同样不与逐个生成的ID冲突
+    for id in TraceId::new_batch(1_000_000) {
+        assert!(all_ids.insert(id), "发现重复的trace_id: {id}");
+    }
+}
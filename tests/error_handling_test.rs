@@ -2,6 +2,8 @@
 //!
 //! 验证 trace_id 模块在各种异常情况下的健壮性和错误处理能力
 
+#![cfg(feature = "std")]
+
 use trace_id::{get_trace_id, TraceId};
 
 #[cfg(feature = "axum")]
@@ -136,7 +138,7 @@ fn test_memory_safety() {
     // 创建大量TraceId实例并立即丢弃
     for _ in 0..10000 {
         let trace_id = TraceId::new();
-        let _cloned = trace_id.clone();
+        let _copied = trace_id;
         let _string_repr = trace_id.as_str();
         let _display = format!("{trace_id}");
         let _debug = format!("{trace_id:?}");
@@ -150,15 +152,15 @@ fn test_memory_safety() {
     // 测试完成
 }
 
-/// 测试TraceId的克隆和相等性
+/// 测试TraceId的复制和相等性
 ///
-/// 验证TraceId的Clone和PartialEq实现
+/// 验证TraceId的Copy和PartialEq实现
 #[test]
 fn test_trace_id_clone_and_equality() {
     let trace_id1 = TraceId::new();
-    let trace_id2 = trace_id1.clone();
+    let trace_id2 = trace_id1;
 
-    // 测试克隆后的相等性
+    // 测试复制后的相等性
     assert_eq!(trace_id1, trace_id2);
     assert_eq!(trace_id1.as_str(), trace_id2.as_str());
 
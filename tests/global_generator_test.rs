@@ -0,0 +1,45 @@
+//! 全局生成器测试
+//!
+//! 全局生成器对整个进程生效，因此放在独立的测试二进制中，且只包含一个测试
+
+#![cfg(feature = "std")]
+
+use trace_id::{set_global_generator, SystemClock, TraceId};
+
+/// 区域代码（高8位）
+const REGION: u128 = 0x42;
+
+#[tokio::test]
+async fn test_global_generator() {
+    assert!(set_global_generator(|| {
+        let value = TraceId::new_with_clock(&SystemClock).to_u128();
+        TraceId::from_u128((REGION << 120) | (value >> 8)).unwrap()
+    }));
+
+    // 只能设置一次
+    assert!(!set_global_generator(TraceId::default));
+
+    // TraceId::new() 与默认值使用全局生成器
+    assert!(TraceId::new().as_str().starts_with("42"));
+    assert!(TraceId::default().as_str().starts_with("42"));
+
+    // 显式指定来源的函数不受影响
+    let clock_id = TraceId::new_with_clock(&SystemClock);
+    assert_eq!(clock_id.machine_id(), TraceId::new_batch(1)[0].machine_id());
+
+    // 未配置生成器的中间件同样使用全局生成器
+    #[cfg(feature = "tower")]
+    {
+        use http::{Request, Response};
+        use std::convert::Infallible;
+        use tower::{service_fn, Layer, ServiceExt};
+        use trace_id::{TraceIdLayer, TRACE_ID_HEADER};
+
+        let service = TraceIdLayer::new().layer(service_fn(|_req: Request<()>| async {
+            Ok::<_, Infallible>(Response::new(()))
+        }));
+        let response = service.oneshot(Request::new(())).await.unwrap();
+        let header = response.headers().get(TRACE_ID_HEADER).unwrap();
+        assert!(header.to_str().unwrap().starts_with("42"));
+    }
+}
@@ -0,0 +1,39 @@
+//! panic hook 测试
+//!
+//! panic hook 对整个进程生效，因此放在独立的测试二进制中，且只包含一个测试
+
+#![cfg(feature = "std")]
+
+use std::panic::catch_unwind;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use trace_id::{install_panic_hook, with_trace_id_sync, TraceId};
+
+/// 原有 hook 被调用的次数
+static PREVIOUS_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[tokio::test]
+async fn test_panic_hook_chains_previous_hook() {
+    std::panic::set_hook(Box::new(|_| {
+        PREVIOUS_CALLS.fetch_add(1, Ordering::SeqCst);
+    }));
+    install_panic_hook();
+    // 重复安装不会多次串联
+    install_panic_hook();
+
+    // 不在追踪上下文中：原样调用原有 hook
+    assert!(catch_unwind(|| panic!("outside")).is_err());
+    assert_eq!(PREVIOUS_CALLS.load(Ordering::SeqCst), 1);
+
+    // 同步追踪上下文中：原有 hook 仍被调用且只调用一次
+    let trace_id = TraceId::new();
+    assert!(catch_unwind(|| with_trace_id_sync(trace_id, || panic!("inside"))).is_err());
+    assert_eq!(PREVIOUS_CALLS.load(Ordering::SeqCst), 2);
+
+    // 继承了上下文的异步任务中
+    let result = trace_id::with_trace_id(trace_id, async {
+        trace_id::spawn_traced(async { panic!("in task") }).await
+    })
+    .await;
+    assert!(result.unwrap_err().is_panic());
+    assert_eq!(PREVIOUS_CALLS.load(Ordering::SeqCst), 3);
+}
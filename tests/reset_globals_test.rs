@@ -0,0 +1,43 @@
+//! 全局配置重置测试
+//!
+//! 机器ID与全局生成器对整个进程生效，因此放在独立的测试二进制中，且只包含一个测试
+
+#![cfg(feature = "test-util")]
+
+use trace_id::{reset_globals, set_global_generator, set_machine_id, SystemClock, TraceId};
+
+#[test]
+fn test_reset_globals() {
+    // 第一组配置
+    assert!(set_machine_id(1));
+    assert!(set_global_generator(|| {
+        let value = TraceId::new_with_clock(&SystemClock).to_u128();
+        TraceId::from_u128((0x42 << 120) | (value >> 8)).unwrap()
+    }));
+    assert!(!set_machine_id(2));
+    assert!(TraceId::new().as_str().starts_with("42"));
+    assert_eq!(TraceId::new_with_clock(&SystemClock).machine_id(), 1);
+
+    // 重置后可以重新配置
+    reset_globals();
+    assert!(set_machine_id(2));
+    assert!(set_global_generator(|| {
+        let value = TraceId::new_with_clock(&SystemClock).to_u128();
+        TraceId::from_u128((0x77 << 120) | (value >> 8)).unwrap()
+    }));
+    assert!(TraceId::new().as_str().starts_with("77"));
+    assert_eq!(TraceId::new_with_clock(&SystemClock).machine_id(), 2);
+
+    // 重置后未设置时回到内置方案，首次生成即确定配置
+    reset_globals();
+    let trace_id = TraceId::new();
+    assert!(!set_global_generator(TraceId::default));
+    assert!(!set_machine_id(3));
+    assert_eq!(TraceId::new().machine_id(), trace_id.machine_id());
+
+    // 环境变量在重置后重新读取
+    reset_globals();
+    std::env::set_var(trace_id::MACHINE_ID_ENV, "4242");
+    assert_eq!(TraceId::new().machine_id(), 4242);
+    std::env::remove_var(trace_id::MACHINE_ID_ENV);
+}
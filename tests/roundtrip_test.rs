@@ -0,0 +1,187 @@
+//! 字节/十六进制/字符串转换的往返测试
+//!
+//! 以固定种子生成大量随机输入，检查各转换之间互为逆运算、校验无法被绕过。
+//! 覆盖更广的输入空间请使用 `fuzz/` 下的模糊测试（`cargo +nightly fuzz run conversions`）
+
+#![cfg(feature = "std")]
+
+use trace_id::TraceId;
+
+/// 每项检查的随机输入数量
+const ITERATIONS: usize = 10_000;
+
+/// 固定种子，失败时可以稳定复现
+const SEED: u64 = 0x7ace_1d00;
+
+/// 独立于实现的校验规则：32个小写十六进制字符且不全为零
+fn is_w3c_trace_id(input: &[u8]) -> bool {
+    input.len() == 32
+        && input
+            .iter()
+            .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(b))
+        && input.iter().any(|&b| b != b'0')
+}
+
+/// 检查一个合法ID在各种表示之间往返不变
+fn assert_round_trips(trace_id: TraceId) {
+    let hex = format!("{:032x}", trace_id.to_u128());
+    assert_eq!(trace_id.as_str(), hex);
+    assert_eq!(trace_id.to_string(), hex);
+
+    // 字节
+    assert_eq!(TraceId::from_bytes(*trace_id.as_bytes()), trace_id);
+    assert_eq!(
+        TraceId::from_bytes(*trace_id.as_bytes()).as_str(),
+        trace_id.as_str()
+    );
+
+    // 整数
+    assert_eq!(TraceId::from_u128(trace_id.to_u128()), Some(trace_id));
+    assert_eq!(trace_id.as_bytes(), &trace_id.to_u128().to_be_bytes());
+
+    // 字符串
+    assert_eq!(TraceId::from_string_validated(&hex), Some(trace_id));
+    assert_eq!(hex.parse::<TraceId>(), Ok(trace_id));
+    assert_eq!(
+        TraceId::from_string_validated_ci(&hex.to_ascii_uppercase()),
+        Some(trace_id)
+    );
+    assert_eq!(TraceId::from_string_unchecked(&hex), trace_id);
+
+    // 调用方缓冲区
+    let mut buf = [0u8; 32];
+    assert_eq!(trace_id.write_hex(&mut buf), hex);
+}
+
+#[test]
+fn test_random_values_round_trip() {
+    let mut rng = fastrand::Rng::with_seed(SEED);
+    for _ in 0..ITERATIONS {
+        let value = rng.u128(1..);
+        assert_round_trips(TraceId::from_u128(value).unwrap());
+    }
+
+    // 生成的ID同样往返不变
+    for trace_id in TraceId::new_batch(ITERATIONS) {
+        assert_round_trips(trace_id);
+    }
+}
+
+#[test]
+fn test_edge_values_round_trip() {
+    assert_round_trips(TraceId::from_u128(1).unwrap());
+    assert_round_trips(TraceId::from_u128(u128::MAX).unwrap());
+    for bit in 0..128 {
+        assert_round_trips(TraceId::from_u128(1 << bit).unwrap());
+        assert_round_trips(TraceId::from_u128(!(1 << bit)).unwrap());
+    }
+
+    // 全零不是合法ID，无法通过任何校验入口构造
+    assert_eq!(TraceId::from_u128(0), None);
+    assert_eq!(TraceId::from_string_validated(&"0".repeat(32)), None);
+    assert_eq!(TraceId::from_string_validated_ci(&"0".repeat(32)), None);
+}
+
+#[test]
+fn test_random_strings_cannot_bypass_validation() {
+    // 偏向接近合法ID的输入：合法字符中混入大写、越界字母、空白与多字节字符
+    const ALPHABET: &[&str] = &[
+        "0", "1", "7", "9", "a", "c", "f", "A", "F", "g", "z", " ", "-", "\0", "é", "零",
+    ];
+
+    let mut rng = fastrand::Rng::with_seed(SEED);
+    let mut accepted = 0;
+    for _ in 0..ITERATIONS {
+        let len = match rng.u8(..4) {
+            0 => rng.usize(..40),
+            _ => 32,
+        };
+        let input: String = (0..len)
+            .map(|_| match rng.u8(..8) {
+                // 大部分字符取自合法字符，使一部分输入能够通过校验
+                0..=5 => ALPHABET[rng.usize(..6)],
+                _ => ALPHABET[rng.usize(..ALPHABET.len())],
+            })
+            .collect();
+
+        let validated = TraceId::from_string_validated(&input);
+        assert_eq!(validated.is_some(), is_w3c_trace_id(input.as_bytes()));
+        assert_eq!(TraceId::is_valid(&input), validated.is_some());
+        assert_eq!(
+            TraceId::is_valid_bytes(input.as_bytes()),
+            validated.is_some()
+        );
+        assert_eq!(input.parse::<TraceId>().ok(), validated);
+
+        if let Some(trace_id) = validated {
+            accepted += 1;
+            assert_eq!(trace_id.as_str(), input);
+            assert_eq!(TraceId::from_bytes(*trace_id.as_bytes()).as_str(), input);
+            assert_round_trips(trace_id);
+        }
+    }
+
+    // 确认随机输入确实覆盖了通过校验的分支
+    assert!(accepted > 0);
+}
+
+#[test]
+fn test_random_bytes_round_trip() {
+    let mut rng = fastrand::Rng::with_seed(SEED);
+    for _ in 0..ITERATIONS {
+        let mut bytes = [0u8; 16];
+        rng.fill(&mut bytes);
+
+        let trace_id = TraceId::from_bytes(bytes);
+        assert_eq!(trace_id.as_bytes(), &bytes);
+        assert_eq!(trace_id.to_u128(), u128::from_be_bytes(bytes));
+        assert_eq!(
+            TraceId::from_string_validated(trace_id.as_str()),
+            TraceId::from_u128(trace_id.to_u128())
+        );
+    }
+}
+
+#[test]
+fn test_short_ids_round_trip() {
+    let mut rng = fastrand::Rng::with_seed(SEED);
+    for _ in 0..ITERATIONS {
+        let value = rng.u64(1..);
+        let short = TraceId::from_u64(value).unwrap();
+        let hex = format!("{value:016x}");
+        assert_eq!(short.as_str(), hex);
+        assert_eq!(TraceId::from_string_validated_short(&hex), Some(short));
+        assert_eq!(short.to_u128(), u128::from(value));
+
+        // 短ID补零后是另一个（标准）ID，字节相同
+        let padded = TraceId::from_string_validated(&short.padded().to_string()).unwrap();
+        assert_eq!(padded.as_bytes(), short.as_bytes());
+        assert!(!padded.is_short());
+    }
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_random_header_values_agree_with_string_parsing() {
+    let mut rng = fastrand::Rng::with_seed(SEED);
+    for _ in 0..ITERATIONS {
+        let mut input = TraceId::from_u128(rng.u128(1..))
+            .unwrap()
+            .to_string()
+            .into_bytes();
+        // 随机改写若干字节（可能仍然合法）
+        for _ in 0..rng.usize(..3) {
+            let index = rng.usize(..input.len());
+            input[index] = rng.u8(0x20..0x7f);
+        }
+
+        if let Ok(value) = http::HeaderValue::from_bytes(&input) {
+            assert_eq!(
+                TraceId::from_header_value(&value),
+                std::str::from_utf8(&input)
+                    .ok()
+                    .and_then(TraceId::from_string_validated)
+            );
+        }
+    }
+}